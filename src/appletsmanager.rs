@@ -0,0 +1,109 @@
+use classes::OBJECT_CLASS;
+use constants;
+use jcvmerrors::InterpreterError;
+use objects::JCVMObject;
+use objectsmanager::ObjectManager;
+use tearing::TearingSimulation;
+
+///
+/// Applet registered in the JCRE
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppletInstance {
+    pub aid: Vec<u8>,
+    // context in which the applet runs (the one owning the applet object)
+    pub context: i16,
+    // the applet object
+    pub instance: i16,
+    // JCRE owned AID object designating the applet (a JCRE entry point)
+    pub aid_object: i16,
+}
+
+pub struct AppletManager {
+    applets: Vec<AppletInstance>,
+}
+
+impl Default for AppletManager {
+    fn default() -> AppletManager {
+        AppletManager::new()
+    }
+}
+
+impl AppletManager {
+    pub fn new() -> AppletManager {
+        AppletManager {
+            applets: Vec::new(),
+        }
+    }
+
+    ///
+    /// Registers the applet object referenced by instance under the given AID, and returns its
+    /// AID object. The AID object has no class of its own: it is an instance of
+    /// java.lang.Object holding the bytes of the AID (persistent writes, accounted for by the
+    /// card tearing simulation)
+    ///
+    pub fn register(
+        &mut self,
+        object_manager: &mut ObjectManager,
+        tearing: &mut TearingSimulation,
+        aid: &[u8],
+        instance: i16,
+    ) -> Result<i16, InterpreterError> {
+        let context = object_manager
+            .get_object(instance as usize)
+            .map_err(|_| InterpreterError::InvalidObjectReference)?
+            .owner();
+
+        let mut aid_object = JCVMObject::new_instance(
+            constants::JCRE_CONTEXT,
+            constants::ObjectFlags::GLOBAL as u8,
+            OBJECT_CLASS,
+            aid.len() as i16,
+            true,
+        );
+        let bytes: Vec<i8> = aid.iter().map(|byte| *byte as i8).collect();
+        tearing.write(&mut aid_object, 0, &bytes)?;
+        let aid_object = object_manager.add_object(aid_object) as i16;
+
+        self.applets.push(AppletInstance {
+            aid: aid.to_vec(),
+            context,
+            instance,
+            aid_object,
+        });
+        Ok(aid_object)
+    }
+
+    pub fn applets(&self) -> &[AppletInstance] {
+        &self.applets
+    }
+
+    pub fn find(&self, aid: &[u8]) -> Option<&AppletInstance> {
+        self.applets.iter().find(|applet| applet.aid.as_slice() == aid)
+    }
+
+    /// applet designated by the given AID object
+    pub fn find_by_aid_object(&self, aid_object: i16) -> Option<&AppletInstance> {
+        self.applets
+            .iter()
+            .find(|applet| applet.aid_object == aid_object)
+    }
+
+    /// applet whose applet object is referenced by instance
+    pub fn find_by_instance(&self, instance: i16) -> Option<&AppletInstance> {
+        self.applets
+            .iter()
+            .find(|applet| applet.instance == instance)
+    }
+
+    ///
+    /// Applet instance becoming active when a method of the object referenced by objectref is
+    /// invoked from another context: the object itself when it is an applet object, otherwise
+    /// the applet recorded as owning the object when it was created (null for the objects of
+    /// the JCRE)
+    ///
+    pub fn applet_invoked(&self, objectref: i16, object: &JCVMObject) -> i16 {
+        self.find_by_instance(objectref)
+            .map_or(object.applet(), |applet| applet.instance)
+    }
+}
@@ -6,17 +6,55 @@ use interpreter::{BytecodeData, BytecodeType};
 pub struct BytecodeFetcher<'a> {
     bc_array: &'a BytecodeData,
     offset: usize,
+    // offset of the last opcode fetched
+    opcode_offset: usize,
+    // bounds of the method being executed (branches cannot leave it)
+    method_start: usize,
+    method_end: usize,
 }
 
 impl<'a> BytecodeFetcher<'a> {
     /// Initialization method
-    pub fn new(bc: &BytecodeData) -> BytecodeFetcher {
+    pub fn new(bc: &BytecodeData) -> BytecodeFetcher<'_> {
         BytecodeFetcher {
             bc_array: bc,
             offset: 0,
+            opcode_offset: 0,
+            method_start: 0,
+            method_end: bc.len(),
         }
     }
 
+    /// Restricts branches to the given range of the bytecode array (end excluded)
+    pub fn set_method_bounds(&mut self, start: usize, end: usize) -> Result<(), InterpreterError> {
+        if start > end || end > self.bc_array.len() {
+            return Err(InterpreterError::IndexOutOfBound);
+        }
+        self.method_start = start;
+        self.method_end = end;
+        Ok(())
+    }
+
+    /// Moves the current offset relatively to the given origin (which is the offset
+    /// of the branching opcode), making sure the target stays inside the current method
+    pub fn branch(&mut self, origin: usize, relative_offset: i16) -> Result<(), InterpreterError> {
+        let target = origin as isize + relative_offset as isize;
+        if target < 0 {
+            return Err(InterpreterError::InvalidBranchTarget);
+        }
+        self.branch_absolute(target as usize)
+    }
+
+    /// Moves the current offset to the given absolute offset, making sure the target
+    /// stays inside the current method (used by ret)
+    pub fn branch_absolute(&mut self, target: usize) -> Result<(), InterpreterError> {
+        if target < self.method_start || target >= self.method_end {
+            return Err(InterpreterError::InvalidBranchTarget);
+        }
+        self.offset = target;
+        Ok(())
+    }
+
     pub fn get(&self, i: usize) -> Result<BytecodeType, InterpreterError> {
         let res = self.bc_array.get(i).ok_or(InterpreterError::EndOfStream)?;
         Ok(*res)
@@ -26,8 +64,33 @@ impl<'a> BytecodeFetcher<'a> {
         self.offset
     }
 
+    /// offset of the opcode being executed (i.e. the last one fetched)
+    pub fn opcode_offset(&self) -> usize {
+        self.opcode_offset
+    }
+
+    /// size of the whole bytecode array
+    pub fn len(&self) -> usize {
+        self.bc_array.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bc_array.is_empty()
+    }
+
+    /// Moves the current offset to the given absolute offset (used when entering or
+    /// leaving a method, the method bounds have to be updated accordingly)
+    pub fn jump(&mut self, offset: usize) -> Result<(), InterpreterError> {
+        if offset >= self.bc_array.len() {
+            return Err(InterpreterError::InvalidBranchTarget);
+        }
+        self.offset = offset;
+        Ok(())
+    }
+
     /// fetches a bytecode and return its associated value
     pub fn fetch_bytecode(&mut self) -> Result<bytecode, InterpreterError> {
+        self.opcode_offset = self.offset;
         bytecode::from(self.fetch_b()? as u8)
     }
 
@@ -40,15 +103,18 @@ impl<'a> BytecodeFetcher<'a> {
 
     /// Fetches one short from the internal array at given index and return it (if any)
     pub fn fetch_s(&mut self) -> Result<i16, InterpreterError> {
-        let r = (self.get(self.offset)? as u16) << 8 | self.get(self.offset + 1)? as u16;
+        let r = u16::from(self.get(self.offset)? as u8) << 8
+            | u16::from(self.get(self.offset + 1)? as u8);
         self.offset += 2;
         Ok(r as i16)
     }
 
     /// Fetches one integer from the internal array at given index and return it (if any)
     pub fn fetch_i(&mut self) -> Result<i32, InterpreterError> {
-        let r1 = (self.get(self.offset)? as u16) << 8 | self.get(self.offset + 1)? as u16;
-        let r2 = (self.get(self.offset + 2)? as u16) << 8 | self.get(self.offset + 3)? as u16;
+        let r1 = u16::from(self.get(self.offset)? as u8) << 8
+            | u16::from(self.get(self.offset + 1)? as u8);
+        let r2 = u16::from(self.get(self.offset + 2)? as u8) << 8
+            | u16::from(self.get(self.offset + 3)? as u8);
         let r = (u32::from(r1) << 16) | u32::from(r2);
         self.offset += 4;
         Ok(r as i32)
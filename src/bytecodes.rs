@@ -1,7 +1,7 @@
 use jcvmerrors::InterpreterError;
 
 #[allow(non_camel_case_types)]
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 #[repr(u8)]
 pub enum bytecode {
     nop = 0x00,      // 0
@@ -195,6 +195,64 @@ pub enum bytecode {
 }
 
 impl bytecode {
+    /// Indicates whether the bytecode belongs to the optional 32-bit integer instruction set
+    pub fn is_integer_bytecode(&self) -> bool {
+        matches!(
+            *self,
+            bytecode::iconst_m1
+            | bytecode::iconst_0
+            | bytecode::iconst_1
+            | bytecode::iconst_2
+            | bytecode::iconst_3
+            | bytecode::iconst_4
+            | bytecode::iconst_5
+            | bytecode::bipush
+            | bytecode::sipush
+            | bytecode::iipush
+            | bytecode::iload
+            | bytecode::iload_0
+            | bytecode::iload_1
+            | bytecode::iload_2
+            | bytecode::iload_3
+            | bytecode::iaload
+            | bytecode::istore
+            | bytecode::istore_0
+            | bytecode::istore_1
+            | bytecode::istore_2
+            | bytecode::istore_3
+            | bytecode::iastore
+            | bytecode::iadd
+            | bytecode::isub
+            | bytecode::imul
+            | bytecode::idiv
+            | bytecode::irem
+            | bytecode::ineg
+            | bytecode::ishl
+            | bytecode::ishr
+            | bytecode::iushr
+            | bytecode::iand
+            | bytecode::ior
+            | bytecode::ixor
+            | bytecode::iinc
+            | bytecode::s2i
+            | bytecode::i2b
+            | bytecode::i2s
+            | bytecode::icmp
+            | bytecode::itableswitch
+            | bytecode::ilookupswitch
+            | bytecode::ireturn
+            | bytecode::getstatic_i
+            | bytecode::putstatic_i
+            | bytecode::getfield_i
+            | bytecode::putfield_i
+            | bytecode::iinc_w
+            | bytecode::getfield_i_w
+            | bytecode::getfield_i_this
+            | bytecode::putfield_i_w
+            | bytecode::putfield_i_this
+        )
+    }
+
     pub fn from(b: u8) -> Result<bytecode, InterpreterError> {
         match b {
             0 => Ok(bytecode::nop),
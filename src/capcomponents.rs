@@ -0,0 +1,1173 @@
+use constants;
+use exceptions::ExceptionHandler;
+use jcvmerrors::CapFileError;
+use staticfields::{ArrayInit, StaticFieldComponent};
+
+// tags of the standard components (see chapter 6 of the JCVM specification)
+pub const COMPONENT_HEADER: u8 = 1;
+pub const COMPONENT_DIRECTORY: u8 = 2;
+pub const COMPONENT_APPLET: u8 = 3;
+pub const COMPONENT_IMPORT: u8 = 4;
+pub const COMPONENT_CONSTANT_POOL: u8 = 5;
+pub const COMPONENT_CLASS: u8 = 6;
+pub const COMPONENT_METHOD: u8 = 7;
+pub const COMPONENT_STATIC_FIELD: u8 = 8;
+pub const COMPONENT_REFERENCE_LOCATION: u8 = 9;
+pub const COMPONENT_EXPORT: u8 = 10;
+pub const COMPONENT_DESCRIPTOR: u8 = 11;
+pub const COMPONENT_DEBUG: u8 = 12;
+// custom components use tags 128 to 255
+pub const COMPONENT_CUSTOM_FIRST: u8 = 128;
+
+pub const CAP_MAGIC: u32 = 0xDECA_FFED;
+
+// flags of the Header component
+pub const ACC_INT: u8 = 0x1;
+pub const ACC_EXPORT: u8 = 0x2;
+pub const ACC_APPLET: u8 = 0x4;
+
+// flags of the classes and interfaces of the Class component (ACC_INTERFACE is in classes)
+pub const ACC_SHAREABLE: u8 = 0x4;
+pub const ACC_REMOTE: u8 = 0x2;
+
+// access flag of the fields and methods of the Descriptor component
+pub const ACC_STATIC: u8 = 0x8;
+
+// external references have the high bit of their first byte set
+const EXTERNAL_REFERENCE: u8 = 0x80;
+// class_ref of the superclass of java.lang.Object
+const NO_SUPERCLASS: u16 = 0xFFFF;
+
+// tags of the constant pool entries
+const CONSTANT_CLASSREF: u8 = 1;
+const CONSTANT_INSTANCE_FIELDREF: u8 = 2;
+const CONSTANT_VIRTUAL_METHODREF: u8 = 3;
+const CONSTANT_SUPER_METHODREF: u8 = 4;
+const CONSTANT_STATIC_FIELDREF: u8 = 5;
+const CONSTANT_STATIC_METHODREF: u8 = 6;
+
+///
+/// Reads the big endian items of the info array of a component
+///
+pub struct ComponentReader<'a> {
+    tag: u8,
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> ComponentReader<'a> {
+    pub fn new(tag: u8, data: &'a [u8]) -> ComponentReader<'a> {
+        ComponentReader {
+            tag,
+            data,
+            offset: 0,
+        }
+    }
+
+    pub fn tag(&self) -> u8 {
+        self.tag
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offset >= self.data.len()
+    }
+
+    /// number of bytes left to read
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.offset
+    }
+
+    /// the whole info array of the component
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    pub fn bytes(&mut self, count: usize) -> Result<&'a [u8], CapFileError> {
+        if self.remaining() < count {
+            return Err(CapFileError::UnexpectedEnd(self.tag));
+        }
+        let result = &self.data[self.offset..self.offset + count];
+        self.offset += count;
+        Ok(result)
+    }
+
+    pub fn u1(&mut self) -> Result<u8, CapFileError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn u2(&mut self) -> Result<u16, CapFileError> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from(bytes[0]) << 8 | u16::from(bytes[1]))
+    }
+
+    pub fn u4(&mut self) -> Result<u32, CapFileError> {
+        let high = self.u2()?;
+        let low = self.u2()?;
+        Ok(u32::from(high) << 16 | u32::from(low))
+    }
+
+    /// reads a length byte followed by as many bytes (AIDs, names)
+    pub fn u1_prefixed(&mut self) -> Result<Vec<u8>, CapFileError> {
+        let length = self.u1()?;
+        Ok(self.bytes(length as usize)?.to_vec())
+    }
+
+    pub fn class_ref(&mut self) -> Result<CapClassRef, CapFileError> {
+        let first = self.u1()?;
+        let second = self.u1()?;
+        if first & EXTERNAL_REFERENCE != 0 {
+            Ok(CapClassRef::External {
+                package_token: first & !EXTERNAL_REFERENCE,
+                class_token: second,
+            })
+        } else {
+            Ok(CapClassRef::Internal(u16::from(first) << 8 | u16::from(second)))
+        }
+    }
+
+    /// reads a static field or static method reference (3 bytes)
+    pub fn static_ref(&mut self) -> Result<CapStaticRef, CapFileError> {
+        let first = self.u1()?;
+        if first & EXTERNAL_REFERENCE != 0 {
+            Ok(CapStaticRef::External {
+                package_token: first & !EXTERNAL_REFERENCE,
+                class_token: self.u1()?,
+                token: self.u1()?,
+            })
+        } else {
+            // the first byte is a padding byte
+            Ok(CapStaticRef::Internal(self.u2()?))
+        }
+    }
+
+    pub fn package_info(&mut self) -> Result<PackageInfo, CapFileError> {
+        Ok(PackageInfo {
+            minor_version: self.u1()?,
+            major_version: self.u1()?,
+            aid: self.u1_prefixed()?,
+        })
+    }
+
+    /// fails if some bytes of the component were not read
+    pub fn finish(&self) -> Result<(), CapFileError> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(CapFileError::InvalidComponentSize(self.tag))
+        }
+    }
+}
+
+///
+/// Reference to a class or an interface: offset of its class_info in the Class component,
+/// or tokens of the package (in the Import component) and of the class
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CapClassRef {
+    Internal(u16),
+    External { package_token: u8, class_token: u8 },
+}
+
+///
+/// Reference to a static field (offset in the static field image) or a static method
+/// (offset in the Method component), or tokens of an element of an imported package
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CapStaticRef {
+    Internal(u16),
+    External {
+        package_token: u8,
+        class_token: u8,
+        token: u8,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackageInfo {
+    pub minor_version: u8,
+    pub major_version: u8,
+    pub aid: Vec<u8>,
+}
+
+///
+/// Header component: version of the CAP file and identity of the package
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeaderComponent {
+    pub minor_version: u8,
+    pub major_version: u8,
+    pub flags: u8,
+    pub package: PackageInfo,
+    // only present from version 2.2 (empty if the package has no remote interface)
+    pub package_name: Option<Vec<u8>>,
+}
+
+impl HeaderComponent {
+    pub fn parse(reader: &mut ComponentReader) -> Result<HeaderComponent, CapFileError> {
+        let magic = reader.u4()?;
+        if magic != CAP_MAGIC {
+            return Err(CapFileError::InvalidMagic(magic));
+        }
+        let minor_version = reader.u1()?;
+        let major_version = reader.u1()?;
+        if major_version != 2 || !(1..=2).contains(&minor_version) {
+            return Err(CapFileError::UnsupportedVersion(major_version, minor_version));
+        }
+        let flags = reader.u1()?;
+        let package = reader.package_info()?;
+        let package_name = if reader.is_empty() {
+            None
+        } else {
+            Some(reader.u1_prefixed()?)
+        };
+        Ok(HeaderComponent {
+            minor_version,
+            major_version,
+            flags,
+            package,
+            package_name,
+        })
+    }
+
+    /// indicates whether the CAP file follows the format of version 2.2 or later
+    pub fn is_2_2(&self) -> bool {
+        self.minor_version >= 2
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomComponentInfo {
+    pub tag: u8,
+    pub size: u16,
+    pub aid: Vec<u8>,
+}
+
+///
+/// Directory component: sizes of the other components and of the static field image
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirectoryComponent {
+    // size of each standard component, indexed by tag - 1 (no Debug component before 2.2)
+    pub component_sizes: Vec<u16>,
+    pub image_size: u16,
+    pub array_init_count: u16,
+    pub array_init_size: u16,
+    pub import_count: u8,
+    pub applet_count: u8,
+    pub custom_components: Vec<CustomComponentInfo>,
+}
+
+impl DirectoryComponent {
+    pub fn parse(
+        reader: &mut ComponentReader,
+        header: &HeaderComponent,
+    ) -> Result<DirectoryComponent, CapFileError> {
+        let size_count = if header.is_2_2() {
+            COMPONENT_DEBUG
+        } else {
+            COMPONENT_DESCRIPTOR
+        };
+        let mut component_sizes = Vec::new();
+        for _ in 0..size_count {
+            component_sizes.push(reader.u2()?);
+        }
+        let image_size = reader.u2()?;
+        let array_init_count = reader.u2()?;
+        let array_init_size = reader.u2()?;
+        let import_count = reader.u1()?;
+        let applet_count = reader.u1()?;
+        let custom_count = reader.u1()?;
+        let mut custom_components = Vec::new();
+        for _ in 0..custom_count {
+            let tag = reader.u1()?;
+            if tag < COMPONENT_CUSTOM_FIRST {
+                return Err(CapFileError::InvalidValue(reader.tag()));
+            }
+            custom_components.push(CustomComponentInfo {
+                tag,
+                size: reader.u2()?,
+                aid: reader.u1_prefixed()?,
+            });
+        }
+        Ok(DirectoryComponent {
+            component_sizes,
+            image_size,
+            array_init_count,
+            array_init_size,
+            import_count,
+            applet_count,
+            custom_components,
+        })
+    }
+
+    /// size declared for the component with the given tag (0 for unknown components)
+    pub fn component_size(&self, tag: u8) -> u16 {
+        if tag >= COMPONENT_CUSTOM_FIRST {
+            return self.custom_components
+                .iter()
+                .find(|custom| custom.tag == tag)
+                .map_or(0, |custom| custom.size);
+        }
+        match tag {
+            0 => 0,
+            _ => self.component_sizes
+                .get(tag as usize - 1)
+                .cloned()
+                .unwrap_or(0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppletInfo {
+    pub aid: Vec<u8>,
+    // offset of the install method in the Method component
+    pub install_method_offset: u16,
+}
+
+///
+/// Applet component: applets defined by the package
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppletComponent {
+    pub applets: Vec<AppletInfo>,
+}
+
+impl AppletComponent {
+    pub fn parse(reader: &mut ComponentReader) -> Result<AppletComponent, CapFileError> {
+        let count = reader.u1()?;
+        let mut applets = Vec::new();
+        for _ in 0..count {
+            applets.push(AppletInfo {
+                aid: reader.u1_prefixed()?,
+                install_method_offset: reader.u2()?,
+            });
+        }
+        Ok(AppletComponent { applets })
+    }
+}
+
+///
+/// Import component: packages referenced by the package, external references use the
+/// index of the package in this list as package token
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportComponent {
+    pub packages: Vec<PackageInfo>,
+}
+
+impl ImportComponent {
+    pub fn parse(reader: &mut ComponentReader) -> Result<ImportComponent, CapFileError> {
+        let count = reader.u1()?;
+        let mut packages = Vec::new();
+        for _ in 0..count {
+            packages.push(reader.package_info()?);
+        }
+        Ok(ImportComponent { packages })
+    }
+}
+
+///
+/// Entry of the ConstantPool component, before resolution
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CapConstantPoolEntry {
+    ClassRef(CapClassRef),
+    InstanceFieldRef(CapClassRef, u8),
+    VirtualMethodRef(CapClassRef, u8),
+    SuperMethodRef(CapClassRef, u8),
+    StaticFieldRef(CapStaticRef),
+    StaticMethodRef(CapStaticRef),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstantPoolComponent {
+    pub entries: Vec<CapConstantPoolEntry>,
+}
+
+impl ConstantPoolComponent {
+    pub fn parse(reader: &mut ComponentReader) -> Result<ConstantPoolComponent, CapFileError> {
+        let count = reader.u2()?;
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            let entry = match reader.u1()? {
+                CONSTANT_CLASSREF => {
+                    let class_ref = reader.class_ref()?;
+                    // padding byte
+                    reader.u1()?;
+                    CapConstantPoolEntry::ClassRef(class_ref)
+                }
+                CONSTANT_INSTANCE_FIELDREF => {
+                    CapConstantPoolEntry::InstanceFieldRef(reader.class_ref()?, reader.u1()?)
+                }
+                CONSTANT_VIRTUAL_METHODREF => {
+                    CapConstantPoolEntry::VirtualMethodRef(reader.class_ref()?, reader.u1()?)
+                }
+                CONSTANT_SUPER_METHODREF => {
+                    CapConstantPoolEntry::SuperMethodRef(reader.class_ref()?, reader.u1()?)
+                }
+                CONSTANT_STATIC_FIELDREF => {
+                    CapConstantPoolEntry::StaticFieldRef(reader.static_ref()?)
+                }
+                CONSTANT_STATIC_METHODREF => {
+                    CapConstantPoolEntry::StaticMethodRef(reader.static_ref()?)
+                }
+                _ => return Err(CapFileError::InvalidValue(reader.tag())),
+            };
+            entries.push(entry);
+        }
+        Ok(ConstantPoolComponent { entries })
+    }
+}
+
+///
+/// Type of a method or a field, as a sequence of nibbles (see type_descriptor)
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeDescriptor {
+    pub nibble_count: u8,
+    pub types: Vec<u8>,
+}
+
+impl TypeDescriptor {
+    fn parse(reader: &mut ComponentReader) -> Result<TypeDescriptor, CapFileError> {
+        let nibble_count = reader.u1()?;
+        let types = reader
+            .bytes((nibble_count as usize).div_ceil(2))?
+            .to_vec();
+        Ok(TypeDescriptor {
+            nibble_count,
+            types,
+        })
+    }
+
+    /// size of the descriptor in the Descriptor component
+    pub fn size(&self) -> usize {
+        1 + self.types.len()
+    }
+
+    /// the nibbles of the descriptor, in order
+    pub fn nibbles(&self) -> Vec<u8> {
+        (0..self.nibble_count as usize)
+            .filter_map(|index| {
+                let byte = self.types.get(index / 2)?;
+                if index % 2 == 0 {
+                    Some(byte >> 4)
+                } else {
+                    Some(byte & 0x0F)
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteMethodInfo {
+    pub remote_method_hash: u16,
+    pub signature_offset: u16,
+    pub virtual_method_token: u8,
+}
+
+///
+/// Information about the remote methods of a remote class (version 2.2)
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteInterfaceInfo {
+    pub remote_methods: Vec<RemoteMethodInfo>,
+    pub hash_modifier: Vec<u8>,
+    pub class_name: Vec<u8>,
+    pub remote_interfaces: Vec<CapClassRef>,
+}
+
+impl RemoteInterfaceInfo {
+    fn parse(reader: &mut ComponentReader) -> Result<RemoteInterfaceInfo, CapFileError> {
+        let method_count = reader.u1()?;
+        let mut remote_methods = Vec::new();
+        for _ in 0..method_count {
+            remote_methods.push(RemoteMethodInfo {
+                remote_method_hash: reader.u2()?,
+                signature_offset: reader.u2()?,
+                virtual_method_token: reader.u1()?,
+            });
+        }
+        let hash_modifier = reader.u1_prefixed()?;
+        let class_name = reader.u1_prefixed()?;
+        let interface_count = reader.u1()?;
+        let mut remote_interfaces = Vec::new();
+        for _ in 0..interface_count {
+            remote_interfaces.push(reader.class_ref()?);
+        }
+        Ok(RemoteInterfaceInfo {
+            remote_methods,
+            hash_modifier,
+            class_name,
+            remote_interfaces,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapImplementedInterface {
+    pub interface: CapClassRef,
+    pub index: Vec<u8>,
+}
+
+///
+/// class_info or interface_info item of the Class component
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum CapClassInfo {
+    Interface {
+        // offset of the item in the Class component
+        offset: u16,
+        flags: u8,
+        super_interfaces: Vec<CapClassRef>,
+        // only present for remote interfaces
+        interface_name: Option<Vec<u8>>,
+    },
+    Class {
+        offset: u16,
+        flags: u8,
+        // None for java.lang.Object
+        super_class: Option<CapClassRef>,
+        declared_instance_size: u8,
+        first_reference_token: u8,
+        reference_count: u8,
+        public_method_table_base: u8,
+        package_method_table_base: u8,
+        public_virtual_methods: Vec<u16>,
+        package_virtual_methods: Vec<u16>,
+        interfaces: Vec<CapImplementedInterface>,
+        // only present for remote classes
+        remote_interfaces: Option<RemoteInterfaceInfo>,
+    },
+}
+
+impl CapClassInfo {
+    pub fn offset(&self) -> u16 {
+        match *self {
+            CapClassInfo::Interface { offset, .. } | CapClassInfo::Class { offset, .. } => offset,
+        }
+    }
+
+    pub fn flags(&self) -> u8 {
+        match *self {
+            CapClassInfo::Interface { flags, .. } | CapClassInfo::Class { flags, .. } => flags,
+        }
+    }
+}
+
+///
+/// Class component: classes and interfaces defined by the package
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassComponent {
+    // only present from version 2.2
+    pub signature_pool: Vec<TypeDescriptor>,
+    pub classes: Vec<CapClassInfo>,
+}
+
+impl ClassComponent {
+    pub fn parse(
+        reader: &mut ComponentReader,
+        header: &HeaderComponent,
+    ) -> Result<ClassComponent, CapFileError> {
+        let mut signature_pool = Vec::new();
+        if header.is_2_2() {
+            let pool_end = reader.u2()? as usize + reader.offset();
+            while reader.offset() < pool_end {
+                signature_pool.push(TypeDescriptor::parse(reader)?);
+            }
+            if reader.offset() != pool_end {
+                return Err(CapFileError::InvalidComponentSize(reader.tag()));
+            }
+        }
+
+        let mut classes = Vec::new();
+        while !reader.is_empty() {
+            classes.push(ClassComponent::parse_class_info(reader)?);
+        }
+        Ok(ClassComponent {
+            signature_pool,
+            classes,
+        })
+    }
+
+    fn parse_class_info(reader: &mut ComponentReader) -> Result<CapClassInfo, CapFileError> {
+        let offset = reader.offset() as u16;
+        let bitfield = reader.u1()?;
+        let flags = bitfield >> 4;
+        let interface_count = bitfield & 0xF;
+
+        if flags & ::classes::ACC_INTERFACE != 0 {
+            let mut super_interfaces = Vec::new();
+            for _ in 0..interface_count {
+                super_interfaces.push(reader.class_ref()?);
+            }
+            let interface_name = if flags & ACC_REMOTE != 0 {
+                Some(reader.u1_prefixed()?)
+            } else {
+                None
+            };
+            return Ok(CapClassInfo::Interface {
+                offset,
+                flags,
+                super_interfaces,
+                interface_name,
+            });
+        }
+
+        let super_class = match reader.class_ref()? {
+            CapClassRef::Internal(NO_SUPERCLASS) => None,
+            class_ref => Some(class_ref),
+        };
+        let declared_instance_size = reader.u1()?;
+        let first_reference_token = reader.u1()?;
+        let reference_count = reader.u1()?;
+        let public_method_table_base = reader.u1()?;
+        let public_method_table_count = reader.u1()?;
+        let package_method_table_base = reader.u1()?;
+        let package_method_table_count = reader.u1()?;
+        let mut public_virtual_methods = Vec::new();
+        for _ in 0..public_method_table_count {
+            public_virtual_methods.push(reader.u2()?);
+        }
+        let mut package_virtual_methods = Vec::new();
+        for _ in 0..package_method_table_count {
+            package_virtual_methods.push(reader.u2()?);
+        }
+        let mut interfaces = Vec::new();
+        for _ in 0..interface_count {
+            let interface = reader.class_ref()?;
+            let count = reader.u1()?;
+            interfaces.push(CapImplementedInterface {
+                interface,
+                index: reader.bytes(count as usize)?.to_vec(),
+            });
+        }
+        let remote_interfaces = if flags & ACC_REMOTE != 0 {
+            Some(RemoteInterfaceInfo::parse(reader)?)
+        } else {
+            None
+        };
+
+        Ok(CapClassInfo::Class {
+            offset,
+            flags,
+            super_class,
+            declared_instance_size,
+            first_reference_token,
+            reference_count,
+            public_method_table_base,
+            package_method_table_base,
+            public_virtual_methods,
+            package_virtual_methods,
+            interfaces,
+            remote_interfaces,
+        })
+    }
+}
+
+///
+/// Method component: exception handlers and methods of the package.
+/// The offsets found in the other components (methods, handlers) are relative to the start of
+/// the component info, which is kept as a whole in methods
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodComponent {
+    pub exception_handlers: Vec<ExceptionHandler>,
+    // whether the handler at the same index is the last one protecting its try block
+    pub stop_bits: Vec<bool>,
+    // info of the component (handler table included)
+    pub methods: Vec<u8>,
+}
+
+impl MethodComponent {
+    pub fn parse(reader: &mut ComponentReader) -> Result<MethodComponent, CapFileError> {
+        let handler_count = reader.u1()?;
+        let mut exception_handlers = Vec::new();
+        let mut stop_bits = Vec::new();
+        for _ in 0..handler_count {
+            let start_offset = reader.u2()? as usize;
+            let bitfield = reader.u2()?;
+            let handler_offset = reader.u2()? as usize;
+            let catch_type_index = match reader.u2()? {
+                // handlers of finally blocks catch everything
+                0 => None,
+                index => Some(index),
+            };
+            stop_bits.push(bitfield & 0x8000 != 0);
+            exception_handlers.push(ExceptionHandler {
+                start_offset,
+                active_length: (bitfield & 0x7FFF) as usize,
+                handler_offset,
+                catch_type_index,
+            });
+        }
+        // the methods follow the handlers, up to the end of the component
+        let remaining = reader.remaining();
+        reader.bytes(remaining)?;
+        Ok(MethodComponent {
+            exception_handlers,
+            stop_bits,
+            methods: reader.data().to_vec(),
+        })
+    }
+}
+
+// types of the arrays initialized by the StaticField component
+const ARRAY_INIT_BOOLEAN: u8 = 2;
+const ARRAY_INIT_BYTE: u8 = 3;
+const ARRAY_INIT_SHORT: u8 = 4;
+const ARRAY_INIT_INT: u8 = 5;
+
+impl StaticFieldComponent {
+    pub fn parse(reader: &mut ComponentReader) -> Result<StaticFieldComponent, CapFileError> {
+        let image_size = reader.u2()?;
+        let reference_count = reader.u2()?;
+        let array_init_count = reader.u2()?;
+        let mut array_init = Vec::new();
+        for _ in 0..array_init_count {
+            let type_ = match reader.u1()? {
+                ARRAY_INIT_BOOLEAN | ARRAY_INIT_BYTE => constants::PrimitiveType::BYTE,
+                ARRAY_INIT_SHORT => constants::PrimitiveType::SHORT,
+                ARRAY_INIT_INT => constants::PrimitiveType::INTEGER,
+                _ => return Err(CapFileError::InvalidValue(reader.tag())),
+            };
+            let count = reader.u2()?;
+            let values = reader
+                .bytes(count as usize)?
+                .iter()
+                .map(|value| *value as i8)
+                .collect();
+            array_init.push(ArrayInit { type_, values });
+        }
+        let default_value_count = reader.u2()?;
+        let non_default_value_count = reader.u2()?;
+        let non_default_values = reader
+            .bytes(non_default_value_count as usize)?
+            .iter()
+            .map(|value| *value as i8)
+            .collect();
+
+        // the image holds the references followed by the primitive fields
+        let expected_size = reference_count as usize * constants::REFERENCE_SIZE
+            + default_value_count as usize
+            + non_default_value_count as usize;
+        if array_init_count > reference_count || expected_size != image_size as usize {
+            return Err(CapFileError::InvalidValue(reader.tag()));
+        }
+
+        Ok(StaticFieldComponent {
+            image_size,
+            reference_count,
+            array_init,
+            default_value_count,
+            non_default_values,
+        })
+    }
+}
+
+///
+/// ReferenceLocation component: offsets in the Method component of the operands of the
+/// instructions referencing the constant pool (decoded from the offset deltas)
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReferenceLocationComponent {
+    // one byte constant pool indexes
+    pub byte_indices: Vec<u16>,
+    // two bytes constant pool indexes
+    pub byte2_indices: Vec<u16>,
+}
+
+impl ReferenceLocationComponent {
+    pub fn parse(reader: &mut ComponentReader) -> Result<ReferenceLocationComponent, CapFileError> {
+        let byte_indices = ReferenceLocationComponent::parse_offsets(reader)?;
+        let byte2_indices = ReferenceLocationComponent::parse_offsets(reader)?;
+        Ok(ReferenceLocationComponent {
+            byte_indices,
+            byte2_indices,
+        })
+    }
+
+    /// each offset is relative to the previous one, 255 meaning "add 255 and keep reading"
+    fn parse_offsets(reader: &mut ComponentReader) -> Result<Vec<u16>, CapFileError> {
+        let count = reader.u2()?;
+        let mut result = Vec::new();
+        let mut offset: u32 = 0;
+        for delta in reader.bytes(count as usize)? {
+            offset += u32::from(*delta);
+            if offset > u32::from(u16::MAX) {
+                return Err(CapFileError::InvalidValue(reader.tag()));
+            }
+            if *delta != 0xFF {
+                result.push(offset as u16);
+            }
+        }
+        Ok(result)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassExportInfo {
+    // offset of the class in the Class component
+    pub class_offset: u16,
+    // offsets in the static field image, indexed by static field token
+    pub static_field_offsets: Vec<u16>,
+    // offsets in the Method component, indexed by static method token
+    pub static_method_offsets: Vec<u16>,
+}
+
+///
+/// Export component: public elements of the package accessible from other packages
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportComponent {
+    // indexed by class token
+    pub classes: Vec<ClassExportInfo>,
+}
+
+impl ExportComponent {
+    pub fn parse(reader: &mut ComponentReader) -> Result<ExportComponent, CapFileError> {
+        let class_count = reader.u1()?;
+        let mut classes = Vec::new();
+        for _ in 0..class_count {
+            let class_offset = reader.u2()?;
+            let static_field_count = reader.u1()?;
+            let static_method_count = reader.u1()?;
+            let mut static_field_offsets = Vec::new();
+            for _ in 0..static_field_count {
+                static_field_offsets.push(reader.u2()?);
+            }
+            let mut static_method_offsets = Vec::new();
+            for _ in 0..static_method_count {
+                static_method_offsets.push(reader.u2()?);
+            }
+            classes.push(ClassExportInfo {
+                class_offset,
+                static_field_offsets,
+                static_method_offsets,
+            });
+        }
+        Ok(ExportComponent { classes })
+    }
+}
+
+///
+/// Reference to the field described by a field_descriptor_info
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CapFieldRef {
+    Static(CapStaticRef),
+    Instance(CapClassRef, u8),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDescriptor {
+    pub token: u8,
+    pub access_flags: u8,
+    pub field_ref: CapFieldRef,
+    // primitive type (high bit set) or offset of a type descriptor in the Descriptor component
+    pub type_: u16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodDescriptor {
+    pub token: u8,
+    pub access_flags: u8,
+    // offset of the method header in the Method component (0 for abstract methods)
+    pub method_offset: u16,
+    pub type_offset: u16,
+    pub bytecode_count: u16,
+    pub exception_handler_count: u16,
+    pub exception_handler_index: u16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassDescriptor {
+    pub token: u8,
+    pub access_flags: u8,
+    pub this_class_ref: CapClassRef,
+    pub interfaces: Vec<CapClassRef>,
+    pub fields: Vec<FieldDescriptor>,
+    pub methods: Vec<MethodDescriptor>,
+}
+
+///
+/// Descriptor component: description of the classes, fields and methods of the package,
+/// along with the types of the constant pool entries
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct DescriptorComponent {
+    pub classes: Vec<ClassDescriptor>,
+    // offset of the type of each constant pool entry (0xFFFF for classes)
+    pub constant_pool_types: Vec<u16>,
+    // offset of the first type descriptor, types are referenced by their component offset
+    pub type_desc_offset: u16,
+    pub type_desc: Vec<TypeDescriptor>,
+}
+
+impl DescriptorComponent {
+    pub fn parse(reader: &mut ComponentReader) -> Result<DescriptorComponent, CapFileError> {
+        let class_count = reader.u1()?;
+        let mut classes = Vec::new();
+        for _ in 0..class_count {
+            classes.push(DescriptorComponent::parse_class(reader)?);
+        }
+
+        let constant_pool_count = reader.u2()?;
+        let mut constant_pool_types = Vec::new();
+        for _ in 0..constant_pool_count {
+            constant_pool_types.push(reader.u2()?);
+        }
+        let type_desc_offset = reader.offset() as u16;
+        let mut type_desc = Vec::new();
+        while !reader.is_empty() {
+            type_desc.push(TypeDescriptor::parse(reader)?);
+        }
+
+        Ok(DescriptorComponent {
+            classes,
+            constant_pool_types,
+            type_desc_offset,
+            type_desc,
+        })
+    }
+
+    /// Returns the type descriptor at the given offset of the component (see type_offset)
+    pub fn type_descriptor(&self, offset: u16) -> Option<&TypeDescriptor> {
+        let mut current = self.type_desc_offset as usize;
+        for descriptor in &self.type_desc {
+            if current == offset as usize {
+                return Some(descriptor);
+            }
+            current += descriptor.size();
+        }
+        None
+    }
+
+    fn parse_class(reader: &mut ComponentReader) -> Result<ClassDescriptor, CapFileError> {
+        let token = reader.u1()?;
+        let access_flags = reader.u1()?;
+        let this_class_ref = reader.class_ref()?;
+        let interface_count = reader.u1()?;
+        let field_count = reader.u2()?;
+        let method_count = reader.u2()?;
+
+        let mut interfaces = Vec::new();
+        for _ in 0..interface_count {
+            interfaces.push(reader.class_ref()?);
+        }
+        let mut fields = Vec::new();
+        for _ in 0..field_count {
+            let token = reader.u1()?;
+            let access_flags = reader.u1()?;
+            let field_ref = if access_flags & ACC_STATIC != 0 {
+                CapFieldRef::Static(reader.static_ref()?)
+            } else {
+                CapFieldRef::Instance(reader.class_ref()?, reader.u1()?)
+            };
+            fields.push(FieldDescriptor {
+                token,
+                access_flags,
+                field_ref,
+                type_: reader.u2()?,
+            });
+        }
+        let mut methods = Vec::new();
+        for _ in 0..method_count {
+            methods.push(MethodDescriptor {
+                token: reader.u1()?,
+                access_flags: reader.u1()?,
+                method_offset: reader.u2()?,
+                type_offset: reader.u2()?,
+                bytecode_count: reader.u2()?,
+                exception_handler_count: reader.u2()?,
+                exception_handler_index: reader.u2()?,
+            });
+        }
+
+        Ok(ClassDescriptor {
+            token,
+            access_flags,
+            this_class_ref,
+            interfaces,
+            fields,
+            methods,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDebugInfo {
+    pub name_index: u16,
+    pub descriptor_index: u16,
+    pub access_flags: u16,
+    // token of the field, or value of the static final primitive fields
+    pub contents: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariableInfo {
+    pub index: u8,
+    pub name_index: u16,
+    pub descriptor_index: u16,
+    pub start_pc: u16,
+    pub length: u16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineInfo {
+    pub start_pc: u16,
+    pub end_pc: u16,
+    pub source_line: u16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodDebugInfo {
+    pub name_index: u16,
+    pub descriptor_index: u16,
+    pub access_flags: u16,
+    // offset of the method in the Method component
+    pub location: u16,
+    pub header_size: u8,
+    pub body_size: u16,
+    pub variable_table: Vec<VariableInfo>,
+    pub line_table: Vec<LineInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassDebugInfo {
+    pub name_index: u16,
+    pub access_flags: u16,
+    // offset of the class in the Class component
+    pub location: u16,
+    pub superclass_name_index: u16,
+    pub source_file_index: u16,
+    pub interface_name_indexes: Vec<u16>,
+    pub fields: Vec<FieldDebugInfo>,
+    pub methods: Vec<MethodDebugInfo>,
+}
+
+///
+/// Debug component: names, source lines and local variables of the package, the names
+/// being indexes in the strings table
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugComponent {
+    pub strings: Vec<Vec<u8>>,
+    pub package_name_index: u16,
+    pub classes: Vec<ClassDebugInfo>,
+}
+
+impl DebugComponent {
+    pub fn parse(reader: &mut ComponentReader) -> Result<DebugComponent, CapFileError> {
+        let string_count = reader.u2()?;
+        let mut strings = Vec::new();
+        for _ in 0..string_count {
+            let length = reader.u2()?;
+            strings.push(reader.bytes(length as usize)?.to_vec());
+        }
+        let package_name_index = reader.u2()?;
+        let class_count = reader.u2()?;
+        let mut classes = Vec::new();
+        for _ in 0..class_count {
+            classes.push(DebugComponent::parse_class(reader)?);
+        }
+
+        let result = DebugComponent {
+            strings,
+            package_name_index,
+            classes,
+        };
+        if result.package_name_index >= string_count {
+            return Err(CapFileError::InvalidValue(reader.tag()));
+        }
+        Ok(result)
+    }
+
+    fn parse_class(reader: &mut ComponentReader) -> Result<ClassDebugInfo, CapFileError> {
+        let name_index = reader.u2()?;
+        let access_flags = reader.u2()?;
+        let location = reader.u2()?;
+        let superclass_name_index = reader.u2()?;
+        let source_file_index = reader.u2()?;
+        let interface_count = reader.u1()?;
+        let field_count = reader.u2()?;
+        let method_count = reader.u2()?;
+
+        let mut interface_name_indexes = Vec::new();
+        for _ in 0..interface_count {
+            interface_name_indexes.push(reader.u2()?);
+        }
+        let mut fields = Vec::new();
+        for _ in 0..field_count {
+            fields.push(FieldDebugInfo {
+                name_index: reader.u2()?,
+                descriptor_index: reader.u2()?,
+                access_flags: reader.u2()?,
+                contents: reader.u4()?,
+            });
+        }
+        let mut methods = Vec::new();
+        for _ in 0..method_count {
+            methods.push(DebugComponent::parse_method(reader)?);
+        }
+
+        Ok(ClassDebugInfo {
+            name_index,
+            access_flags,
+            location,
+            superclass_name_index,
+            source_file_index,
+            interface_name_indexes,
+            fields,
+            methods,
+        })
+    }
+
+    fn parse_method(reader: &mut ComponentReader) -> Result<MethodDebugInfo, CapFileError> {
+        let name_index = reader.u2()?;
+        let descriptor_index = reader.u2()?;
+        let access_flags = reader.u2()?;
+        let location = reader.u2()?;
+        let header_size = reader.u1()?;
+        let body_size = reader.u2()?;
+        let variable_count = reader.u2()?;
+        let line_count = reader.u2()?;
+
+        let mut variable_table = Vec::new();
+        for _ in 0..variable_count {
+            variable_table.push(VariableInfo {
+                index: reader.u1()?,
+                name_index: reader.u2()?,
+                descriptor_index: reader.u2()?,
+                start_pc: reader.u2()?,
+                length: reader.u2()?,
+            });
+        }
+        let mut line_table = Vec::new();
+        for _ in 0..line_count {
+            line_table.push(LineInfo {
+                start_pc: reader.u2()?,
+                end_pc: reader.u2()?,
+                source_line: reader.u2()?,
+            });
+        }
+
+        Ok(MethodDebugInfo {
+            name_index,
+            descriptor_index,
+            access_flags,
+            location,
+            header_size,
+            body_size,
+            variable_table,
+            line_table,
+        })
+    }
+}
@@ -0,0 +1,292 @@
+use miniz_oxide::inflate;
+
+use capcomponents::*;
+use jcvmerrors::CapFileError;
+use staticfields::StaticFieldComponent;
+
+// signatures of the zip records used to read the JAR container
+const ZIP_LOCAL_HEADER: u32 = 0x0403_4B50;
+const ZIP_CENTRAL_HEADER: u32 = 0x0201_4B50;
+const ZIP_END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4B50;
+// sizes of the fixed part of the zip records
+const ZIP_LOCAL_HEADER_SIZE: usize = 30;
+const ZIP_CENTRAL_HEADER_SIZE: usize = 46;
+const ZIP_END_OF_CENTRAL_DIRECTORY_SIZE: usize = 22;
+// compression methods supported for the entries of the container
+const ZIP_STORED: u16 = 0;
+const ZIP_DEFLATED: u16 = 8;
+
+// extension of the component files in the JAR container
+const COMPONENT_FILE_EXTENSION: &str = ".cap";
+// size of the tag and size items preceding the info of a component
+const COMPONENT_HEADER_SIZE: usize = 3;
+
+///
+/// Content of a CAP file, each component being parsed and checked against the Directory
+/// component
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapFile {
+    pub header: HeaderComponent,
+    pub directory: DirectoryComponent,
+    // only present in packages defining applets
+    pub applet: Option<AppletComponent>,
+    pub import: ImportComponent,
+    pub constant_pool: ConstantPoolComponent,
+    pub class: ClassComponent,
+    pub method: MethodComponent,
+    pub static_field: StaticFieldComponent,
+    pub reference_location: ReferenceLocationComponent,
+    // only present in library packages (or applet packages with shareable interfaces)
+    pub export: Option<ExportComponent>,
+    // optional here as the on-card format (IJC) usually strips it
+    pub descriptor: Option<DescriptorComponent>,
+    pub debug: Option<DebugComponent>,
+    // tag and info of the custom components declared in the Directory component
+    pub custom_components: Vec<(u8, Vec<u8>)>,
+}
+
+impl CapFile {
+    ///
+    /// Parses a CAP file, either a JAR container or the components concatenated one after
+    /// the other (IJC format)
+    ///
+    pub fn from_bytes(data: &[u8]) -> Result<CapFile, CapFileError> {
+        if data.len() >= 4 && read_u4_le(data, 0)? == ZIP_LOCAL_HEADER {
+            let mut components = Vec::new();
+            for (name, content) in read_zip_entries(data)? {
+                if name.ends_with(COMPONENT_FILE_EXTENSION) {
+                    components.push(content);
+                }
+            }
+            CapFile::from_component_files(&components)
+        } else {
+            let mut components = Vec::new();
+            let mut offset = 0;
+            while offset < data.len() {
+                let (tag, info) = split_component(&data[offset..])?;
+                components.push((tag, info));
+                offset += COMPONENT_HEADER_SIZE + info.len();
+            }
+            CapFile::from_components(&components)
+        }
+    }
+
+    ///
+    /// Parses a CAP file given as separate component files (Header.cap, Directory.cap...),
+    /// each one holding exactly one component
+    ///
+    pub fn from_component_files(files: &[Vec<u8>]) -> Result<CapFile, CapFileError> {
+        let mut components = Vec::new();
+        for file in files {
+            let (tag, info) = split_component(file)?;
+            if COMPONENT_HEADER_SIZE + info.len() != file.len() {
+                return Err(CapFileError::InvalidComponentSize(tag));
+            }
+            components.push((tag, info));
+        }
+        CapFile::from_components(&components)
+    }
+
+    /// parses the components given as their tag and info
+    fn from_components(components: &[(u8, &[u8])]) -> Result<CapFile, CapFileError> {
+        let mut infos: Vec<Option<&[u8]>> = vec![None; 256];
+        for &(tag, info) in components {
+            if infos[tag as usize].is_some() {
+                return Err(CapFileError::DuplicateComponent(tag));
+            }
+            infos[tag as usize] = Some(info);
+        }
+        let info = |tag: u8| -> Result<ComponentReader, CapFileError> {
+            infos[tag as usize]
+                .map(|info| ComponentReader::new(tag, info))
+                .ok_or(CapFileError::MissingComponent(tag))
+        };
+
+        let header = parse_with(info(COMPONENT_HEADER)?, HeaderComponent::parse)?;
+        let directory = parse_with(info(COMPONENT_DIRECTORY)?, |reader| {
+            DirectoryComponent::parse(reader, &header)
+        })?;
+
+        // all the components shall be declared, with their actual size, in the Directory
+        let mut custom_components = Vec::new();
+        for &(tag, info) in components {
+            let known = match tag {
+                COMPONENT_HEADER..=COMPONENT_DESCRIPTOR => true,
+                COMPONENT_DEBUG => header.is_2_2(),
+                _ => directory
+                    .custom_components
+                    .iter()
+                    .any(|custom| custom.tag == tag),
+            };
+            if !known {
+                return Err(CapFileError::UnknownComponent(tag));
+            }
+            if info.len() != directory.component_size(tag) as usize {
+                return Err(CapFileError::InvalidComponentSize(tag));
+            }
+            if tag >= COMPONENT_CUSTOM_FIRST {
+                custom_components.push((tag, info.to_vec()));
+            }
+        }
+
+        let optional = |tag: u8| infos[tag as usize].map(|info| ComponentReader::new(tag, info));
+        let result = CapFile {
+            applet: match optional(COMPONENT_APPLET) {
+                Some(reader) => Some(parse_with(reader, AppletComponent::parse)?),
+                None => None,
+            },
+            import: parse_with(info(COMPONENT_IMPORT)?, ImportComponent::parse)?,
+            constant_pool: parse_with(
+                info(COMPONENT_CONSTANT_POOL)?,
+                ConstantPoolComponent::parse,
+            )?,
+            class: parse_with(info(COMPONENT_CLASS)?, |reader| {
+                ClassComponent::parse(reader, &header)
+            })?,
+            method: parse_with(info(COMPONENT_METHOD)?, MethodComponent::parse)?,
+            static_field: parse_with(info(COMPONENT_STATIC_FIELD)?, StaticFieldComponent::parse)?,
+            reference_location: parse_with(
+                info(COMPONENT_REFERENCE_LOCATION)?,
+                ReferenceLocationComponent::parse,
+            )?,
+            export: match optional(COMPONENT_EXPORT) {
+                Some(reader) => Some(parse_with(reader, ExportComponent::parse)?),
+                None => None,
+            },
+            descriptor: match optional(COMPONENT_DESCRIPTOR) {
+                Some(reader) => Some(parse_with(reader, DescriptorComponent::parse)?),
+                None => None,
+            },
+            debug: match optional(COMPONENT_DEBUG) {
+                Some(reader) => Some(parse_with(reader, DebugComponent::parse)?),
+                None => None,
+            },
+            header,
+            directory,
+            custom_components,
+        };
+        result.check_directory()?;
+        Ok(result)
+    }
+
+    /// checks the counts of the Directory component against the other components
+    fn check_directory(&self) -> Result<(), CapFileError> {
+        let applet_count = self.applet
+            .as_ref()
+            .map_or(0, |applet| applet.applets.len());
+        let array_init_size: usize = self.static_field
+            .array_init
+            .iter()
+            .map(|array| array.values.len())
+            .sum();
+
+        if self.directory.import_count as usize != self.import.packages.len()
+            || self.directory.applet_count as usize != applet_count
+            || self.directory.image_size != self.static_field.image_size
+            || self.directory.array_init_count as usize != self.static_field.array_init.len()
+            || self.directory.array_init_size as usize != array_init_size
+        {
+            return Err(CapFileError::InvalidValue(COMPONENT_DIRECTORY));
+        }
+        Ok(())
+    }
+}
+
+/// parses a component, which shall be read entirely
+fn parse_with<'a, T, F>(mut reader: ComponentReader<'a>, parse: F) -> Result<T, CapFileError>
+where
+    F: FnOnce(&mut ComponentReader<'a>) -> Result<T, CapFileError>,
+{
+    let result = parse(&mut reader)?;
+    reader.finish()?;
+    Ok(result)
+}
+
+/// splits the first component of data into its tag and info
+fn split_component(data: &[u8]) -> Result<(u8, &[u8]), CapFileError> {
+    if data.len() < COMPONENT_HEADER_SIZE {
+        return Err(CapFileError::UnexpectedEnd(data.first().cloned().unwrap_or(0)));
+    }
+    let tag = data[0];
+    let size = (data[1] as usize) << 8 | data[2] as usize;
+    if data.len() < COMPONENT_HEADER_SIZE + size {
+        return Err(CapFileError::UnexpectedEnd(tag));
+    }
+    Ok((tag, &data[COMPONENT_HEADER_SIZE..COMPONENT_HEADER_SIZE + size]))
+}
+
+fn read_u2_le(data: &[u8], offset: usize) -> Result<u16, CapFileError> {
+    match data.get(offset..offset + 2) {
+        Some(bytes) => Ok(u16::from(bytes[1]) << 8 | u16::from(bytes[0])),
+        None => Err(CapFileError::InvalidArchive),
+    }
+}
+
+fn read_u4_le(data: &[u8], offset: usize) -> Result<u32, CapFileError> {
+    let low = read_u2_le(data, offset)?;
+    let high = read_u2_le(data, offset + 2)?;
+    Ok(u32::from(high) << 16 | u32::from(low))
+}
+
+///
+/// Reads the name and the uncompressed content of the files of a zip archive, using the
+/// central directory found at the end of the archive
+///
+fn read_zip_entries(data: &[u8]) -> Result<Vec<(String, Vec<u8>)>, CapFileError> {
+    // the end of central directory record is followed by a comment of up to 64KB
+    if data.len() < ZIP_END_OF_CENTRAL_DIRECTORY_SIZE {
+        return Err(CapFileError::InvalidArchive);
+    }
+    let mut end_offset = data.len() - ZIP_END_OF_CENTRAL_DIRECTORY_SIZE;
+    while read_u4_le(data, end_offset)? != ZIP_END_OF_CENTRAL_DIRECTORY {
+        if end_offset == 0 {
+            return Err(CapFileError::InvalidArchive);
+        }
+        end_offset -= 1;
+    }
+    let entry_count = read_u2_le(data, end_offset + 10)?;
+    let mut offset = read_u4_le(data, end_offset + 16)? as usize;
+
+    let mut result = Vec::new();
+    for _ in 0..entry_count {
+        if read_u4_le(data, offset)? != ZIP_CENTRAL_HEADER {
+            return Err(CapFileError::InvalidArchive);
+        }
+        let method = read_u2_le(data, offset + 10)?;
+        let compressed_size = read_u4_le(data, offset + 20)? as usize;
+        let uncompressed_size = read_u4_le(data, offset + 24)? as usize;
+        let name_length = read_u2_le(data, offset + 28)? as usize;
+        let extra_length = read_u2_le(data, offset + 30)? as usize;
+        let comment_length = read_u2_le(data, offset + 32)? as usize;
+        let local_offset = read_u4_le(data, offset + 42)? as usize;
+        let name_offset = offset + ZIP_CENTRAL_HEADER_SIZE;
+        let name = data.get(name_offset..name_offset + name_length)
+            .ok_or(CapFileError::InvalidArchive)?;
+        let name = String::from_utf8_lossy(name).into_owned();
+        offset = name_offset + name_length + extra_length + comment_length;
+
+        // the local header may have its own extra field
+        if read_u4_le(data, local_offset)? != ZIP_LOCAL_HEADER {
+            return Err(CapFileError::InvalidArchive);
+        }
+        let content_offset = local_offset
+            + ZIP_LOCAL_HEADER_SIZE
+            + read_u2_le(data, local_offset + 26)? as usize
+            + read_u2_le(data, local_offset + 28)? as usize;
+        let content = data.get(content_offset..content_offset + compressed_size)
+            .ok_or(CapFileError::InvalidArchive)?;
+
+        let content = match method {
+            ZIP_STORED => content.to_vec(),
+            ZIP_DEFLATED => inflate::decompress_to_vec_with_limit(content, uncompressed_size)
+                .map_err(|_| CapFileError::DecompressionFailed)?,
+            _ => return Err(CapFileError::UnsupportedCompression(method)),
+        };
+        if content.len() != uncompressed_size {
+            return Err(CapFileError::DecompressionFailed);
+        }
+        result.push((name, content));
+    }
+    Ok(result)
+}
@@ -0,0 +1,150 @@
+use capcomponents::ACC_SHAREABLE;
+
+// index of a class in the class manager
+pub type ClassRef = usize;
+
+// java.lang.Object, root of the class hierarchy (always the first class of the class manager)
+pub const OBJECT_CLASS: ClassRef = 0;
+
+// flags of the classes (see class_info in the Class component)
+pub const ACC_INTERFACE: u8 = 0x8;
+
+// virtual method tokens with this bit set refer to package visible methods
+pub const PACKAGE_METHOD_TOKEN: u8 = 0x80;
+
+///
+/// Table of virtual methods (public or package visible) introduced or overridden by a class
+/// Tokens lower than base are inherited from the superclass
+///
+#[derive(Debug, Clone, Default)]
+pub struct VirtualMethodTable {
+    base: u8,
+    methods: Vec<usize>,
+}
+
+impl VirtualMethodTable {
+    pub fn new(base: u8, methods: Vec<usize>) -> VirtualMethodTable {
+        VirtualMethodTable { base, methods }
+    }
+
+    /// returns the offset of the method associated to the given token, if defined here
+    pub fn get(&self, token: u8) -> Option<usize> {
+        if token < self.base {
+            return None;
+        }
+        self.methods.get((token - self.base) as usize).cloned()
+    }
+}
+
+///
+/// Interface implemented by a class, with the virtual method token associated
+/// to each of the interface method tokens
+///
+#[derive(Debug, Clone)]
+pub struct ImplementedInterface {
+    pub interface: ClassRef,
+    pub method_tokens: Vec<u8>,
+}
+
+// a structure representing a class (or an interface)
+#[derive(Debug, Clone)]
+pub struct JCVMClass {
+    flags: u8,
+    super_class: Option<ClassRef>,
+    public_methods: VirtualMethodTable,
+    package_methods: VirtualMethodTable,
+    interfaces: Vec<ImplementedInterface>,
+    // number of field slots taken by the instance fields declared by this class
+    // (instance field tokens of the class range from 0 to this size excluded)
+    declared_instance_size: u8,
+}
+
+impl JCVMClass {
+    pub fn new(super_class: Option<ClassRef>) -> JCVMClass {
+        JCVMClass {
+            flags: 0,
+            super_class,
+            public_methods: VirtualMethodTable::default(),
+            package_methods: VirtualMethodTable::default(),
+            interfaces: Vec::new(),
+            declared_instance_size: 0,
+        }
+    }
+
+    ///
+    /// Creates an interface, extending the given superinterfaces
+    ///
+    pub fn new_interface(super_interfaces: &[ClassRef]) -> JCVMClass {
+        let mut result = JCVMClass::new(None);
+        result.flags = ACC_INTERFACE;
+        for super_interface in super_interfaces {
+            result.add_interface(ImplementedInterface {
+                interface: *super_interface,
+                method_tokens: Vec::new(),
+            });
+        }
+        result
+    }
+
+    pub fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    pub fn set_flags(&mut self, flags: u8) {
+        self.flags = flags;
+    }
+
+    pub fn is_interface(&self) -> bool {
+        self.flags & ACC_INTERFACE != 0
+    }
+
+    /// Indicates whether the interface is javacard.framework.Shareable or extends it
+    pub fn is_shareable(&self) -> bool {
+        self.flags & ACC_SHAREABLE != 0
+    }
+
+    pub fn super_class(&self) -> Option<ClassRef> {
+        self.super_class
+    }
+
+    pub fn declared_instance_size(&self) -> u8 {
+        self.declared_instance_size
+    }
+
+    pub fn set_declared_instance_size(&mut self, size: u8) {
+        self.declared_instance_size = size;
+    }
+
+    pub fn set_public_methods(&mut self, table: VirtualMethodTable) {
+        self.public_methods = table;
+    }
+
+    pub fn set_package_methods(&mut self, table: VirtualMethodTable) {
+        self.package_methods = table;
+    }
+
+    pub fn add_interface(&mut self, interface: ImplementedInterface) {
+        self.interfaces.push(interface);
+    }
+
+    pub fn interfaces(&self) -> &[ImplementedInterface] {
+        &self.interfaces
+    }
+
+    /// returns the offset of the method associated to the token if this class defines it
+    pub fn get_virtual_method(&self, token: u8) -> Option<usize> {
+        if token & PACKAGE_METHOD_TOKEN != 0 {
+            self.package_methods.get(token & !PACKAGE_METHOD_TOKEN)
+        } else {
+            self.public_methods.get(token)
+        }
+    }
+
+    /// returns the virtual method token implementing the given interface method, if any
+    pub fn get_interface_method_token(&self, interface: ClassRef, token: u8) -> Option<u8> {
+        self.interfaces
+            .iter()
+            .find(|implemented| implemented.interface == interface)
+            .and_then(|implemented| implemented.method_tokens.get(token as usize).cloned())
+    }
+}
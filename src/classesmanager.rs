@@ -0,0 +1,180 @@
+use classes::{ClassRef, JCVMClass, OBJECT_CLASS};
+use jcvmerrors::InterpreterError;
+use constants;
+use exceptions;
+
+pub struct ClassManager {
+    classes_container: Vec<JCVMClass>,
+}
+
+impl Default for ClassManager {
+    fn default() -> ClassManager {
+        ClassManager::new()
+    }
+}
+
+impl ClassManager {
+    pub fn new() -> ClassManager {
+        let mut result = ClassManager {
+            classes_container: Vec::new(),
+        };
+        result.add_class(JCVMClass::new(None));
+        exceptions::register_exception_classes(&mut result);
+        result
+    }
+
+    pub fn get_class(&self, class: ClassRef) -> Result<&JCVMClass, InterpreterError> {
+        self.classes_container
+            .get(class)
+            .ok_or(InterpreterError::InvalidClassReference)
+    }
+
+    /// number of classes registered (the built-in ones included)
+    pub fn len(&self) -> usize {
+        self.classes_container.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.classes_container.is_empty()
+    }
+
+    pub fn add_class(&mut self, entry: JCVMClass) -> ClassRef {
+        self.classes_container.push(entry);
+        self.classes_container.len() - 1
+    }
+
+    ///
+    /// Indicates whether class is the given ancestor or one of its subclasses
+    /// (all the classes are subclasses of java.lang.Object)
+    ///
+    pub fn is_subclass_of(
+        &self,
+        class: ClassRef,
+        ancestor: ClassRef,
+    ) -> Result<bool, InterpreterError> {
+        if ancestor == OBJECT_CLASS {
+            return Ok(true);
+        }
+        let mut current = Some(class);
+        while let Some(class_ref) = current {
+            if class_ref == ancestor {
+                return Ok(true);
+            }
+            current = self.get_class(class_ref)?.super_class();
+        }
+        Ok(false)
+    }
+
+    ///
+    /// Indicates whether class (or one of its superclasses) implements the interface,
+    /// either directly or through the superinterfaces of the interfaces it implements
+    /// For interfaces, indicates whether class is the interface or one of its subinterfaces
+    ///
+    pub fn implements_interface(
+        &self,
+        class: ClassRef,
+        interface: ClassRef,
+    ) -> Result<bool, InterpreterError> {
+        let mut current = Some(class);
+        while let Some(class_ref) = current {
+            if class_ref == interface {
+                return Ok(true);
+            }
+            let current_class = self.get_class(class_ref)?;
+            for implemented in current_class.interfaces() {
+                if self.implements_interface(implemented.interface, interface)? {
+                    return Ok(true);
+                }
+            }
+            current = current_class.super_class();
+        }
+        Ok(false)
+    }
+
+    ///
+    /// Indicates whether an instance of class can be assigned to a variable of type target,
+    /// target being a class or an interface
+    ///
+    pub fn is_assignable(&self, class: ClassRef, target: ClassRef) -> Result<bool, InterpreterError> {
+        if self.get_class(target)?.is_interface() {
+            self.implements_interface(class, target)
+        } else {
+            self.is_subclass_of(class, target)
+        }
+    }
+
+    /// Indicates whether the class is a shareable interface (see is_shareable)
+    pub fn is_shareable_interface(&self, class: ClassRef) -> Result<bool, InterpreterError> {
+        let class = self.get_class(class)?;
+        Ok(class.is_interface() && class.is_shareable())
+    }
+
+    /// size in bytes of the instances of the class (fields of the superclasses included)
+    pub fn instance_size(&self, class: ClassRef) -> Result<usize, InterpreterError> {
+        let mut slots = 0;
+        let mut current = Some(class);
+        while let Some(class_ref) = current {
+            let current_class = self.get_class(class_ref)?;
+            slots += current_class.declared_instance_size() as usize;
+            current = current_class.super_class();
+        }
+        Ok(slots * constants::FIELD_SLOT_SIZE)
+    }
+
+    ///
+    /// Returns the offset in bytes of the instance field declared by the given class with the
+    /// given token. Fields of the superclasses come first in the instances.
+    ///
+    pub fn instance_field_offset(
+        &self,
+        class: ClassRef,
+        token: u8,
+    ) -> Result<usize, InterpreterError> {
+        let declaring_class = self.get_class(class)?;
+        if token >= declaring_class.declared_instance_size() {
+            return Err(InterpreterError::UnresolvedField);
+        }
+        let inherited_size = match declaring_class.super_class() {
+            Some(super_class) => self.instance_size(super_class)?,
+            None => 0,
+        };
+        Ok(inherited_size + token as usize * constants::FIELD_SLOT_SIZE)
+    }
+
+    ///
+    /// Looks for the method associated to the virtual method token, starting from the
+    /// given class and going up through its superclasses
+    ///
+    pub fn resolve_virtual_method(&self, class: ClassRef, token: u8) -> Result<usize, InterpreterError> {
+        let mut current = Some(class);
+        while let Some(class_ref) = current {
+            let current_class = self.get_class(class_ref)?;
+            if let Some(method_offset) = current_class.get_virtual_method(token) {
+                return Ok(method_offset);
+            }
+            current = current_class.super_class();
+        }
+        Err(InterpreterError::UnresolvedMethod)
+    }
+
+    ///
+    /// Looks for the virtual method implementing the interface method, starting from the
+    /// given class and going up through its superclasses
+    ///
+    pub fn resolve_interface_method(
+        &self,
+        class: ClassRef,
+        interface: ClassRef,
+        token: u8,
+    ) -> Result<usize, InterpreterError> {
+        let mut current = Some(class);
+        while let Some(class_ref) = current {
+            let current_class = self.get_class(class_ref)?;
+            if let Some(virtual_token) = current_class.get_interface_method_token(interface, token) {
+                return self.resolve_virtual_method(class, virtual_token);
+            }
+            current = current_class.super_class();
+        }
+        Err(InterpreterError::UnresolvedMethod)
+    }
+}
@@ -0,0 +1,119 @@
+use std::ops::Range;
+
+use classes::ClassRef;
+use staticfields::PackageRef;
+use jcvmerrors::InterpreterError;
+
+///
+/// Entries of the constant pool once resolved, so that the interpreter can use them directly
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConstantPoolEntry {
+    // a class or an interface registered in the class manager
+    ClassRef(ClassRef),
+    // offset of the header of a static method (or a constructor/private method)
+    StaticMethodRef(usize),
+    // virtual method identified by the class it is declared in and its token
+    VirtualMethodRef(ClassRef, u8),
+    // virtual method of the superclass of the given class (invokespecial on super.method())
+    SuperMethodRef(ClassRef, u8),
+    // instance field identified by the class it is declared in and its token
+    InstanceFieldRef(ClassRef, u8),
+    // static field identified by its package and its offset in the static field image
+    StaticFieldRef(PackageRef, u16),
+}
+
+pub struct ConstantPool {
+    entries: Vec<ConstantPoolEntry>,
+}
+
+impl Default for ConstantPool {
+    fn default() -> ConstantPool {
+        ConstantPool::new()
+    }
+}
+
+impl ConstantPool {
+    pub fn new() -> ConstantPool {
+        ConstantPool {
+            entries: Vec::new(),
+        }
+    }
+
+    /// adds an entry and returns its index
+    pub fn add_entry(&mut self, entry: ConstantPoolEntry) -> u16 {
+        self.entries.push(entry);
+        (self.entries.len() - 1) as u16
+    }
+
+    /// number of entries of the constant pool
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get_entry(&self, index: u16) -> Result<ConstantPoolEntry, InterpreterError> {
+        match self.entries.get(index as usize) {
+            Some(entry) => Ok(*entry),
+            None => Err(InterpreterError::InvalidConstantPoolEntry),
+        }
+    }
+}
+
+///
+/// Constant pool of a loaded package, used by the code of its Method component
+///
+struct PackageConstantPool {
+    // range of the bytecode array holding the code of the package
+    code: Range<usize>,
+    constant_pool: ConstantPool,
+}
+
+///
+/// Constant pools of the loaded packages: the indexes used by the code of a package refer to
+/// the constant pool of this package. The code which does not belong to a loaded package
+/// (such as the code registered without a CAP file) uses the default constant pool
+///
+pub struct ConstantPoolManager {
+    default_pool: ConstantPool,
+    packages: Vec<PackageConstantPool>,
+}
+
+impl Default for ConstantPoolManager {
+    fn default() -> ConstantPoolManager {
+        ConstantPoolManager::new()
+    }
+}
+
+impl ConstantPoolManager {
+    pub fn new() -> ConstantPoolManager {
+        ConstantPoolManager {
+            default_pool: ConstantPool::new(),
+            packages: Vec::new(),
+        }
+    }
+
+    /// adds an entry to the default constant pool and returns its index
+    pub fn add_entry(&mut self, entry: ConstantPoolEntry) -> u16 {
+        self.default_pool.add_entry(entry)
+    }
+
+    /// registers the constant pool of the package whose code is in the given range
+    pub fn add_package(&mut self, code: Range<usize>, constant_pool: ConstantPool) {
+        self.packages.push(PackageConstantPool {
+            code,
+            constant_pool,
+        });
+    }
+
+    /// constant pool used by the code at the given offset of the bytecode array
+    pub fn pool_at(&self, offset: usize) -> &ConstantPool {
+        self.packages
+            .iter()
+            .find(|package| package.code.contains(&offset))
+            .map_or(&self.default_pool, |package| &package.constant_pool)
+    }
+}
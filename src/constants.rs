@@ -1,18 +1,34 @@
 pub const NULL_HANDLE: i16 = 0;
 
+// context of the Java Card runtime environment (owner of the objects it creates)
+pub const JCRE_CONTEXT: i16 = 0;
+
+// types of the arrays created by newarray (atype operand)
+pub const T_BOOLEAN: u8 = 10;
+pub const T_BYTE: u8 = 11;
+pub const T_SHORT: u8 = 12;
+pub const T_INT: u8 = 13;
+// array of references, only used by checkcast and instanceof (atype 0 denotes a class)
+pub const T_REFERENCE: u8 = 14;
+
 // some sizes...
 pub const REFERENCE_SIZE: usize = 2;
 pub const SHORT_SIZE: usize = 2;
 pub const INTEGER_SIZE: usize = 4;
 pub const BYTE_SIZE: usize = 1;
+// instance fields take one slot each (two for integers), whatever their type
+pub const FIELD_SLOT_SIZE: usize = 2;
 
 // constants
 // visibility mask : global or not global (this will impact firewall rules)
+// global arrays can be accessed from any context, global instances are JCRE entry points
 #[derive(Debug)]
 #[repr(u8)]
 pub enum ObjectFlags {
     GLOBAL = 0x1,
     ARRAY = 0x2,
+    // JCRE entry point whose references cannot be stored (global arrays are always temporary)
+    TEMPORARY = 0x4,
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -23,4 +39,19 @@ pub enum PrimitiveType {
     BYTE = 0x2,
     SHORT = 0x4,
     INTEGER = 0x8,
+    // offset of the opcode following a jsr, only found on the operand stack and in locals
+    RETURNADDRESS = 0x10,
+}
+
+impl PrimitiveType {
+    /// size in bytes of an array element of this type
+    pub fn size(self) -> usize {
+        match self {
+            PrimitiveType::UNKNOWN | PrimitiveType::RETURNADDRESS => 0,
+            PrimitiveType::REFERENCE => REFERENCE_SIZE,
+            PrimitiveType::BYTE => BYTE_SIZE,
+            PrimitiveType::SHORT => SHORT_SIZE,
+            PrimitiveType::INTEGER => INTEGER_SIZE,
+        }
+    }
 }
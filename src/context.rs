@@ -4,25 +4,145 @@ use framestack::FrameStack;
 use jcvmerrors::InterpreterError;
 use bcutils::BytecodeFetcher;
 use objectsmanager::ObjectManager;
+use classesmanager::ClassManager;
+use appletsmanager::AppletManager;
+use jcre::JcreObjects;
+use transactions::TransactionManager;
+use tearing::TearingSimulation;
+use constantpool::{ConstantPoolEntry, ConstantPoolManager};
+use exceptions::ExceptionHandler;
+use faultreport::FaultReport;
+use methods::{MethodExtents, MethodHeader};
+use staticfields::StaticFieldManager;
 use interpreter::BytecodeData;
+use constants;
 
 pub struct Context<'a> {
     pub bytecode_fetcher: BytecodeFetcher<'a>,
     pub operand_stack: Stack,
     pub frame_stack: FrameStack,
     pub object_manager: ObjectManager,
+    pub class_manager: ClassManager,
+    // constant pools of the packages
+    pub constant_pools: ConstantPoolManager,
+    pub static_fields: StaticFieldManager,
+    pub applet_manager: AppletManager,
+    // APDU object and buffer, exception singletons
+    pub jcre: JcreObjects,
+    // transaction in progress and commit buffer
+    pub transaction: TransactionManager,
+    // card tearing simulation (disarmed by default)
+    pub tearing: TearingSimulation,
+    // exception handlers, in the order they are looked up
+    pub exception_handlers: Vec<ExceptionHandler>,
+    // end of the code of the methods of the loaded packages
+    pub method_extents: MethodExtents,
+    // state of the virtual machine where the last error stopping the interpreter occurred
+    pub fault_report: Option<FaultReport>,
+    // context of the code being executed, owner of the objects it creates
+    pub current_context: i16,
+    // applet instance on behalf of which the code is executed (null for the JCRE)
+    pub current_applet: i16,
+    // optional 32-bit integer support (enabled by default)
+    pub int_support: bool,
+    // full type and bounds checks on every opcode (enabled by default)
+    defensive: bool,
 }
 
 impl<'a> Context<'a> {
-    pub fn new(bc: &BytecodeData) -> Context {
+    pub fn new(bc: &BytecodeData) -> Context<'_> {
         Context {
             bytecode_fetcher: BytecodeFetcher::new(bc),
             operand_stack: Stack::new(256),
             frame_stack: FrameStack::new(),
             object_manager: ObjectManager::new(),
+            class_manager: ClassManager::new(),
+            constant_pools: ConstantPoolManager::new(),
+            static_fields: StaticFieldManager::new(),
+            applet_manager: AppletManager::new(),
+            jcre: JcreObjects::new(),
+            transaction: TransactionManager::default(),
+            tearing: TearingSimulation::new(),
+            exception_handlers: Vec::new(),
+            method_extents: MethodExtents::new(),
+            fault_report: None,
+            current_context: constants::JCRE_CONTEXT,
+            current_applet: constants::NULL_HANDLE,
+            int_support: true,
+            defensive: true,
         }
     }
 
+    ///
+    /// Switches between the defensive mode, where the type of every operand and local is
+    /// checked and each frame only accesses its own words of the operand stack, and the fast
+    /// mode, meant for code that went through the bytecode verifier, where these checks are
+    /// skipped. Frames already in the frame stack are switched as well
+    ///
+    pub fn set_defensive(&mut self, enabled: bool) {
+        self.defensive = enabled;
+        self.operand_stack.set_type_checks(enabled);
+        for frame in self.frame_stack.iter_mut() {
+            frame.set_type_checks(enabled);
+        }
+        if !enabled {
+            self.operand_stack.clear_bounds();
+        }
+    }
+
+    pub fn is_defensive(&self) -> bool {
+        self.defensive
+    }
+
+    ///
+    /// Restricts the operand stack to the words of the current frame: from its base up to
+    /// max_stack words above it. Frames created without a method access the whole stack
+    ///
+    pub fn restrict_operand_stack(&mut self) {
+        let bounds = self.frame_stack.top().ok().and_then(|frame| {
+            frame.method().map(|method| {
+                let base = frame.operand_stack_base();
+                (base, base + method.max_stack as usize)
+            })
+        });
+        match bounds {
+            Some((floor, ceiling)) => self.operand_stack.set_bounds(floor, ceiling),
+            None => self.operand_stack.clear_bounds(),
+        }
+    }
+
+    ///
+    /// Restricts the branches to the code of the given method, which ends at the end of the
+    /// bytecode array if its extent is unknown. Frames created without a method can branch
+    /// anywhere
+    ///
+    pub fn set_method_bounds(
+        &mut self,
+        method: Option<MethodHeader>,
+    ) -> Result<(), InterpreterError> {
+        let code_end = self.bytecode_fetcher.len();
+        let (start, end) = match method {
+            Some(header) => (
+                header.code_offset,
+                self.method_extents
+                    .code_end(header.method_offset)
+                    .unwrap_or(code_end),
+            ),
+            None => (0, code_end),
+        };
+        self.bytecode_fetcher.set_method_bounds(start, end)
+    }
+
+    ///
+    /// Returns the entry of the constant pool of the package of the executing method (the one
+    /// whose code holds the opcode being executed)
+    ///
+    pub fn constant_pool_entry(&self, index: u16) -> Result<ConstantPoolEntry, InterpreterError> {
+        self.constant_pools
+            .pool_at(self.bytecode_fetcher.opcode_offset())
+            .get_entry(index)
+    }
+
     pub fn current_frame(&mut self) -> Result<&Frame, InterpreterError> {
         self.frame_stack.top()
     }
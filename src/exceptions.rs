@@ -1,28 +1,277 @@
 use context::Context;
+use faultreport::FaultReport;
 use jcvmerrors::InterpreterError;
-use exceptions;
+use classes::{ClassRef, JCVMClass, OBJECT_CLASS};
+use classesmanager::ClassManager;
+use constantpool::ConstantPoolEntry;
+use objects::JCVMObject;
+use traits::BufferAccessor;
 
-#[derive(Debug)]
+// classes of the exceptions thrown by the virtual machine itself. They are always
+// registered right after java.lang.Object in the class manager, so their references
+// are known in advance
+pub const THROWABLE_CLASS: ClassRef = 1;
+pub const EXCEPTION_CLASS: ClassRef = 2;
+pub const RUNTIME_EXCEPTION_CLASS: ClassRef = 3;
+pub const INDEX_OUT_OF_BOUNDS_EXCEPTION_CLASS: ClassRef = 4;
+pub const ARITHMETIC_EXCEPTION_CLASS: ClassRef = 5;
+pub const ARRAY_INDEX_OUT_OF_BOUNDS_EXCEPTION_CLASS: ClassRef = 6;
+pub const NULL_POINTER_EXCEPTION_CLASS: ClassRef = 7;
+pub const SECURITY_EXCEPTION_CLASS: ClassRef = 8;
+pub const NEGATIVE_ARRAY_SIZE_EXCEPTION_CLASS: ClassRef = 9;
+pub const CLASS_CAST_EXCEPTION_CLASS: ClassRef = 10;
+pub const ARRAY_STORE_EXCEPTION_CLASS: ClassRef = 11;
+// javacard.framework.CardException and CardRuntimeException, declaring the reason code
+pub const CARD_EXCEPTION_CLASS: ClassRef = 12;
+pub const CARD_RUNTIME_EXCEPTION_CLASS: ClassRef = 13;
+// javacard.framework.TransactionException
+pub const TRANSACTION_EXCEPTION_CLASS: ClassRef = 14;
+// javacard.framework.SystemException
+pub const SYSTEM_EXCEPTION_CLASS: ClassRef = 15;
+
+// instance field token of the reason code in CardException and CardRuntimeException
+pub const REASON_FIELD_TOKEN: u8 = 0;
+
+// reason code of SystemException raised when there are not enough resources
+pub const NO_RESOURCE: i16 = 5;
+
+// superclass of each of the classes above and number of field slots it declares,
+// in the same order
+const BUILTIN_EXCEPTION_CLASSES: [(ClassRef, u8); 15] = [
+    (OBJECT_CLASS, 0),
+    (THROWABLE_CLASS, 0),
+    (EXCEPTION_CLASS, 0),
+    (RUNTIME_EXCEPTION_CLASS, 0),
+    (RUNTIME_EXCEPTION_CLASS, 0),
+    (INDEX_OUT_OF_BOUNDS_EXCEPTION_CLASS, 0),
+    (RUNTIME_EXCEPTION_CLASS, 0),
+    (RUNTIME_EXCEPTION_CLASS, 0),
+    (RUNTIME_EXCEPTION_CLASS, 0),
+    (RUNTIME_EXCEPTION_CLASS, 0),
+    (RUNTIME_EXCEPTION_CLASS, 0),
+    (EXCEPTION_CLASS, 1),
+    (RUNTIME_EXCEPTION_CLASS, 1),
+    (CARD_RUNTIME_EXCEPTION_CLASS, 0),
+    (CARD_RUNTIME_EXCEPTION_CLASS, 0),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum InterpreterException {
+    ArithmeticException,
     NullPointerException,
     ArrayIndexOutOfBoundsException,
     SecurityException,
+    NegativeArraySizeException,
+    ClassCastException,
+    ArrayStoreException,
+    // with its reason code
+    TransactionException(i16),
+    SystemException(i16),
+}
+
+impl InterpreterException {
+    /// class of the objects thrown for this exception
+    pub fn class_ref(self) -> ClassRef {
+        match self {
+            InterpreterException::ArithmeticException => ARITHMETIC_EXCEPTION_CLASS,
+            InterpreterException::NullPointerException => NULL_POINTER_EXCEPTION_CLASS,
+            InterpreterException::ArrayIndexOutOfBoundsException => {
+                ARRAY_INDEX_OUT_OF_BOUNDS_EXCEPTION_CLASS
+            }
+            InterpreterException::SecurityException => SECURITY_EXCEPTION_CLASS,
+            InterpreterException::NegativeArraySizeException => {
+                NEGATIVE_ARRAY_SIZE_EXCEPTION_CLASS
+            }
+            InterpreterException::ClassCastException => CLASS_CAST_EXCEPTION_CLASS,
+            InterpreterException::ArrayStoreException => ARRAY_STORE_EXCEPTION_CLASS,
+            InterpreterException::TransactionException(_) => TRANSACTION_EXCEPTION_CLASS,
+            InterpreterException::SystemException(_) => SYSTEM_EXCEPTION_CLASS,
+        }
+    }
+
+    /// reason code of the objects thrown for this exception
+    pub fn reason(self) -> i16 {
+        match self {
+            InterpreterException::TransactionException(reason)
+            | InterpreterException::SystemException(reason) => reason,
+            _ => 0,
+        }
+    }
+}
+
+///
+/// Entry of the exception handlers table (see exception_handler_info in the Method component
+/// of the JCVM specification). Offsets are absolute offsets in the bytecode array.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExceptionHandler {
+    pub start_offset: usize,
+    pub active_length: usize,
+    pub handler_offset: usize,
+    // constant pool index of the class caught, None for handlers catching everything (finally)
+    pub catch_type_index: Option<u16>,
+}
+
+impl ExceptionHandler {
+    /// Indicates whether the opcode at the given offset is protected by this handler
+    pub fn covers(&self, offset: usize) -> bool {
+        offset >= self.start_offset && offset < self.start_offset + self.active_length
+    }
+}
+
+///
+/// Registers the classes of the exceptions thrown by the virtual machine
+/// (this has to be done right after java.lang.Object is added, so that they get the
+/// references above)
+///
+pub fn register_exception_classes(class_manager: &mut ClassManager) {
+    for (super_class, declared_instance_size) in BUILTIN_EXCEPTION_CLASSES.iter() {
+        let mut class = JCVMClass::new(Some(*super_class));
+        class.set_declared_instance_size(*declared_instance_size);
+        class_manager.add_class(class);
+    }
+}
+
+///
+/// Reason code of a thrown object: the reason field of CardException or CardRuntimeException
+/// when it is an instance of one of them, 0 otherwise
+///
+fn reason_code(
+    class_manager: &ClassManager,
+    object: &JCVMObject,
+    class: ClassRef,
+) -> Result<i16, InterpreterError> {
+    for declaring_class in &[CARD_EXCEPTION_CLASS, CARD_RUNTIME_EXCEPTION_CLASS] {
+        if class_manager.is_subclass_of(class, *declaring_class)? {
+            let offset = class_manager.instance_field_offset(*declaring_class, REASON_FIELD_TOKEN)?;
+            return object.read_s(offset);
+        }
+    }
+    Ok(0)
+}
+
+///
+/// Returns the offset of the first handler protecting the opcode at the given offset
+/// and catching exceptions of the given class
+///
+fn find_handler(
+    ctx: &Context,
+    offset: usize,
+    class: ClassRef,
+) -> Result<Option<usize>, InterpreterError> {
+    for handler in ctx.exception_handlers.iter().filter(|h| h.covers(offset)) {
+        let caught = match handler.catch_type_index {
+            None => true,
+            // the constant pool of the package of the handler
+            Some(index) => match ctx.constant_pools
+                .pool_at(handler.handler_offset)
+                .get_entry(index)?
+            {
+                ConstantPoolEntry::ClassRef(catch_type) => {
+                    ctx.class_manager.is_subclass_of(class, catch_type)?
+                }
+                _ => return Err(InterpreterError::InvalidConstantPoolEntry),
+            },
+        };
+        if caught {
+            return Ok(Some(handler.handler_offset));
+        }
+    }
+    Ok(None)
+}
+
+///
+/// Throws the exception object referenced by objectref (athrow): the handlers of the current
+/// method are looked up first, then frames are discarded until a handler is found.
+/// When a handler is found, the execution continues there with the operand stack holding
+/// only objectref. Otherwise, all the frames are discarded and the exception is returned
+/// as an UncaughtException error along with its reason code, the fault report of the context
+/// recording the state of the virtual machine where it was thrown.
+/// Note: the reason code is the one of CardException and CardRuntimeException, 0 for the
+/// other exceptions (see reason_code)
+///
+pub fn throw_object(ctx: &mut Context, objectref: i16) -> Result<(), InterpreterError> {
+    let (class, reason) = {
+        let object = ctx.object_manager
+            .get_object(objectref as usize)
+            .map_err(|_| InterpreterError::InvalidObjectReference)?;
+        let class = object
+            .class_ref()
+            .ok_or(InterpreterError::InvalidClassReference)?;
+        if !ctx.class_manager.is_subclass_of(class, THROWABLE_CLASS)? {
+            return Err(InterpreterError::InvalidClassReference);
+        }
+        (class, reason_code(&ctx.class_manager, object, class)?)
+    };
+
+    let mut offset = ctx.bytecode_fetcher.opcode_offset();
+    let mut report = None;
+    loop {
+        if let Some(handler_offset) = find_handler(ctx, offset, class)? {
+            let operand_stack_base = ctx.frame_stack
+                .top()
+                .map_or(0, |frame| frame.operand_stack_base());
+            ctx.operand_stack.truncate(operand_stack_base);
+            ctx.operand_stack.apush(objectref)?;
+            return ctx.bytecode_fetcher.jump(handler_offset);
+        }
+
+        // the exception leaves the method where it was thrown: the fault is recorded
+        // before its frame is discarded, in case no caller catches it
+        if report.is_none() {
+            report = Some(FaultReport::new(
+                ctx,
+                InterpreterError::UncaughtException(class, reason),
+            ));
+        }
+
+        // no handler in this method, look for one in the caller
+        let finished_frame = match ctx.frame_stack.pop() {
+            Ok(frame) => frame,
+            Err(_) => break,
+        };
+        ctx.current_context = finished_frame.caller_context();
+        ctx.current_applet = finished_frame.caller_applet();
+        ctx.operand_stack
+            .truncate(finished_frame.operand_stack_base());
+        let caller = match ctx.frame_stack.top() {
+            Ok(caller) => caller.method(),
+            Err(_) => break,
+        };
+        ctx.set_method_bounds(caller)?;
+        // the invocation opcode is right before the return address
+        offset = finished_frame.return_address().saturating_sub(1);
+    }
+
+    ctx.fault_report = report;
+    Err(InterpreterError::UncaughtException(class, reason))
 }
 
+///
+/// Throws the JCRE owned instance of the given exception (a permanent entry point, so that
+/// applets can catch and rethrow it)
+///
 pub fn throw_exception(
-    _ctx: &Context,
-    _except: exceptions::InterpreterException,
-) -> Result<(), InterpreterException> {
-    println!("JC Exception raised !");
-    panic!("WIP");
-    Ok(())
+    ctx: &mut Context,
+    except: InterpreterException,
+) -> Result<(), InterpreterError> {
+    let objectref = ctx.jcre
+        .exception(&mut ctx.object_manager, except.class_ref(), except.reason())?;
+    throw_object(ctx, objectref)
 }
 
+///
+/// Throws the Java Card exception corresponding to the given error, if any
+/// (other errors are returned as is)
+///
 pub fn throw_exception_from_interpretererror(
-    _ctx: &Context,
-    _except: InterpreterError,
-) -> Result<(), InterpreterException> {
-    println!("JC Exception raised !");
-    panic!("WIP");
-    Ok(())
+    ctx: &mut Context,
+    except: InterpreterError,
+) -> Result<(), InterpreterError> {
+    match except {
+        InterpreterError::IndexOutOfBound => {
+            throw_exception(ctx, InterpreterException::ArrayIndexOutOfBoundsException)
+        }
+        _ => Err(except),
+    }
 }
@@ -0,0 +1,666 @@
+use capcomponents::{CapClassRef, CapFieldRef, CapStaticRef, ClassDebugInfo, ClassDescriptor,
+                    ComponentReader, DebugComponent, FieldDebugInfo, FieldDescriptor,
+                    PackageInfo, COMPONENT_DEBUG, COMPONENT_DESCRIPTOR, COMPONENT_EXPORT};
+use capfile::CapFile;
+use jcvmerrors::ExportFileError;
+use linker::{ExportTable, ExportedClass};
+
+pub const EXPORT_MAGIC: u32 = 0x00FA_CADE;
+const EXPORT_MINOR_VERSION: u8 = 1;
+const EXPORT_MAJOR_VERSION: u8 = 2;
+
+// tags of the constant pool entries of export files
+const CONSTANT_UTF8: u8 = 1;
+const CONSTANT_INTEGER: u8 = 3;
+const CONSTANT_CLASSREF: u8 = 7;
+const CONSTANT_PACKAGE: u8 = 13;
+
+// flag of the CONSTANT_Package entry
+pub const ACC_LIBRARY: u8 = 0x01;
+
+// access flags of the Descriptor component
+const DESCRIPTOR_ACC_PUBLIC: u8 = 0x01;
+const DESCRIPTOR_ACC_PROTECTED: u8 = 0x04;
+const DESCRIPTOR_ACC_STATIC: u8 = 0x08;
+const DESCRIPTOR_ACC_FINAL: u8 = 0x10;
+const DESCRIPTOR_ACC_INTERFACE: u8 = 0x40;
+
+// access flags of the fields of the Debug component
+const DEBUG_ACC_STATIC: u16 = 0x0008;
+const DEBUG_ACC_FINAL: u16 = 0x0010;
+
+// high bit of the primitive types of the Descriptor component
+const DESCRIPTOR_PRIMITIVE_TYPE: u16 = 0x8000;
+
+const CONSTANT_VALUE_ATTRIBUTE: &str = "ConstantValue";
+
+///
+/// Field exported by a class. Primitive static final fields carry their value, as they
+/// are compiled as constants by the importing packages.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportField {
+    pub token: u8,
+    pub access_flags: u16,
+    pub name: String,
+    pub descriptor: String,
+    pub constant_value: Option<i32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportMethod {
+    pub token: u8,
+    pub access_flags: u16,
+    pub name: String,
+    pub descriptor: String,
+}
+
+///
+/// Public class or interface of a package. Class names are fully qualified with '/' as
+/// separator (java/lang/Object).
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportClass {
+    pub token: u8,
+    pub access_flags: u16,
+    pub name: String,
+    // public superclasses, the direct superclass first
+    pub supers: Vec<String>,
+    pub interfaces: Vec<String>,
+    pub fields: Vec<ExportField>,
+    pub methods: Vec<ExportMethod>,
+}
+
+impl ExportClass {
+    pub fn field_token(&self, name: &str) -> Option<u8> {
+        self.fields
+            .iter()
+            .find(|field| field.name == name)
+            .map(|field| field.token)
+    }
+
+    pub fn method_token(&self, name: &str, descriptor: &str) -> Option<u8> {
+        self.methods
+            .iter()
+            .find(|method| method.name == name && method.descriptor == descriptor)
+            .map(|method| method.token)
+    }
+}
+
+///
+/// Content of an export file: tokens assigned to the public elements of a package, by name
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportFile {
+    // minor version of the export file format (1 or 2), written back as read
+    pub minor_version: u8,
+    // flags of the package (ACC_LIBRARY for packages without applets)
+    pub flags: u8,
+    pub package_name: String,
+    pub package: PackageInfo,
+    pub classes: Vec<ExportClass>,
+}
+
+impl ExportFile {
+    pub fn read(data: &[u8]) -> Result<ExportFile, ExportFileError> {
+        let mut reader = ComponentReader::new(0, data);
+        let magic = reader.u4()?;
+        if magic != EXPORT_MAGIC {
+            return Err(ExportFileError::InvalidMagic(magic));
+        }
+        let minor_version = reader.u1()?;
+        let major_version = reader.u1()?;
+        if major_version != EXPORT_MAJOR_VERSION || !(1..=2).contains(&minor_version) {
+            return Err(ExportFileError::UnsupportedVersion(major_version, minor_version));
+        }
+
+        let count = reader.u2()?;
+        let mut constant_pool = Vec::new();
+        for _ in 0..count {
+            constant_pool.push(ExportConstant::read(&mut reader)?);
+        }
+        let pool = ExportConstantPool {
+            entries: constant_pool,
+        };
+
+        let (flags, package_name, package) = pool.package(reader.u2()?)?;
+        let class_count = reader.u1()?;
+        let mut classes = Vec::new();
+        for _ in 0..class_count {
+            classes.push(pool.read_class(&mut reader)?);
+        }
+        reader
+            .finish()
+            .map_err(|_| ExportFileError::TrailingData)?;
+
+        Ok(ExportFile {
+            minor_version,
+            flags,
+            package_name,
+            package,
+            classes,
+        })
+    }
+
+    pub fn write(&self) -> Vec<u8> {
+        let mut pool = ExportConstantPool {
+            entries: Vec::new(),
+        };
+        let package_name = pool.utf8(&self.package_name);
+        pool.entries.push(ExportConstant::Package {
+            flags: self.flags,
+            name_index: package_name,
+            minor_version: self.package.minor_version,
+            major_version: self.package.major_version,
+            aid: self.package.aid.clone(),
+        });
+        let this_package = pool.entries.len() as u16 - 1;
+
+        let mut classes = Vec::new();
+        for class in &self.classes {
+            pool.write_class(class, &mut classes);
+        }
+
+        let mut result = Vec::new();
+        push_u4(&mut result, EXPORT_MAGIC);
+        result.push(self.minor_version);
+        result.push(EXPORT_MAJOR_VERSION);
+        push_u2(&mut result, pool.entries.len() as u16);
+        for entry in &pool.entries {
+            entry.write(&mut result);
+        }
+        push_u2(&mut result, this_package);
+        result.push(self.classes.len() as u8);
+        result.extend_from_slice(&classes);
+        result
+    }
+
+    pub fn find_class(&self, name: &str) -> Option<&ExportClass> {
+        self.classes.iter().find(|class| class.name == name)
+    }
+
+    ///
+    /// Builds the export file of the package of a CAP file, the tokens being the ones of its
+    /// Export and Descriptor components (used by the linker) and the names coming from its
+    /// Debug component. The export files of the imported packages give the superclasses
+    /// defined outside of the package
+    ///
+    pub fn from_cap(cap: &CapFile, imports: &[ExportFile]) -> Result<ExportFile, ExportFileError> {
+        let export = cap.export
+            .as_ref()
+            .ok_or(ExportFileError::MissingComponent(COMPONENT_EXPORT))?;
+        let descriptor = cap.descriptor
+            .as_ref()
+            .ok_or(ExportFileError::MissingComponent(COMPONENT_DESCRIPTOR))?;
+        let debug = cap.debug
+            .as_ref()
+            .ok_or(ExportFileError::MissingComponent(COMPONENT_DEBUG))?;
+
+        let mut classes = Vec::new();
+        for (class_token, class_export) in export.classes.iter().enumerate() {
+            let this_class = CapClassRef::Internal(class_export.class_offset);
+            let class_descriptor = descriptor
+                .classes
+                .iter()
+                .find(|class| class.this_class_ref == this_class)
+                .ok_or(ExportFileError::InvalidReference(class_export.class_offset))?;
+            let class_debug = find_class_debug(debug, class_export.class_offset)?;
+            classes.push(export_class(
+                debug,
+                imports,
+                class_token as u8,
+                class_descriptor,
+                class_debug,
+            )?);
+        }
+
+        Ok(ExportFile {
+            minor_version: EXPORT_MINOR_VERSION,
+            flags: if cap.applet.is_some() { 0 } else { ACC_LIBRARY },
+            package_name: debug_string(debug, debug.package_name_index)?,
+            package: cap.header.package.clone(),
+            classes,
+        })
+    }
+
+    ///
+    /// Builds the export table used by the linker for a package implemented by the virtual
+    /// machine, resolve_class returning the runtime references of each exported class
+    ///
+    pub fn export_table<F>(&self, mut resolve_class: F) -> Result<ExportTable, ExportFileError>
+    where
+        F: FnMut(&ExportClass) -> Option<ExportedClass>,
+    {
+        let mut classes = vec![None; self.classes.len()];
+        for class in &self.classes {
+            let exported = resolve_class(class)
+                .ok_or_else(|| ExportFileError::UnresolvedClass(class.name.clone()))?;
+            match classes.get_mut(class.token as usize) {
+                Some(entry) if entry.is_none() => *entry = Some(exported),
+                _ => return Err(ExportFileError::InvalidToken(class.token)),
+            }
+        }
+        // the tokens being unique and lower than the class count, all the classes are set
+        Ok(ExportTable {
+            classes: classes.into_iter().map(Option::unwrap_or_default).collect(),
+        })
+    }
+}
+
+fn push_u2(data: &mut Vec<u8>, value: u16) {
+    data.extend_from_slice(&[(value >> 8) as u8, value as u8]);
+}
+
+fn push_u4(data: &mut Vec<u8>, value: u32) {
+    push_u2(data, (value >> 16) as u16);
+    push_u2(data, value as u16);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ExportConstant {
+    Utf8(String),
+    Integer(i32),
+    ClassRef(u16),
+    Package {
+        flags: u8,
+        name_index: u16,
+        minor_version: u8,
+        major_version: u8,
+        aid: Vec<u8>,
+    },
+}
+
+impl ExportConstant {
+    fn read(reader: &mut ComponentReader) -> Result<ExportConstant, ExportFileError> {
+        let tag = reader.u1()?;
+        Ok(match tag {
+            CONSTANT_UTF8 => {
+                let length = reader.u2()?;
+                let bytes = reader.bytes(length as usize)?;
+                ExportConstant::Utf8(String::from_utf8(bytes.to_vec())
+                    .map_err(|_| ExportFileError::InvalidConstant(tag))?)
+            }
+            CONSTANT_INTEGER => ExportConstant::Integer(reader.u4()? as i32),
+            CONSTANT_CLASSREF => ExportConstant::ClassRef(reader.u2()?),
+            CONSTANT_PACKAGE => ExportConstant::Package {
+                flags: reader.u1()?,
+                name_index: reader.u2()?,
+                minor_version: reader.u1()?,
+                major_version: reader.u1()?,
+                aid: reader.u1_prefixed()?,
+            },
+            _ => return Err(ExportFileError::InvalidConstant(tag)),
+        })
+    }
+
+    fn write(&self, data: &mut Vec<u8>) {
+        match *self {
+            ExportConstant::Utf8(ref value) => {
+                data.push(CONSTANT_UTF8);
+                push_u2(data, value.len() as u16);
+                data.extend_from_slice(value.as_bytes());
+            }
+            ExportConstant::Integer(value) => {
+                data.push(CONSTANT_INTEGER);
+                push_u4(data, value as u32);
+            }
+            ExportConstant::ClassRef(name_index) => {
+                data.push(CONSTANT_CLASSREF);
+                push_u2(data, name_index);
+            }
+            ExportConstant::Package {
+                flags,
+                name_index,
+                minor_version,
+                major_version,
+                ref aid,
+            } => {
+                data.push(CONSTANT_PACKAGE);
+                data.push(flags);
+                push_u2(data, name_index);
+                data.push(minor_version);
+                data.push(major_version);
+                data.push(aid.len() as u8);
+                data.extend_from_slice(aid);
+            }
+        }
+    }
+}
+
+struct ExportConstantPool {
+    entries: Vec<ExportConstant>,
+}
+
+impl ExportConstantPool {
+    fn get(&self, index: u16) -> Result<&ExportConstant, ExportFileError> {
+        self.entries
+            .get(index as usize)
+            .ok_or(ExportFileError::InvalidConstantPoolIndex(index))
+    }
+
+    fn get_utf8(&self, index: u16) -> Result<String, ExportFileError> {
+        match *self.get(index)? {
+            ExportConstant::Utf8(ref value) => Ok(value.clone()),
+            _ => Err(ExportFileError::InvalidConstantPoolIndex(index)),
+        }
+    }
+
+    fn get_class_name(&self, index: u16) -> Result<String, ExportFileError> {
+        match *self.get(index)? {
+            ExportConstant::ClassRef(name_index) => self.get_utf8(name_index),
+            _ => Err(ExportFileError::InvalidConstantPoolIndex(index)),
+        }
+    }
+
+    fn package(&self, index: u16) -> Result<(u8, String, PackageInfo), ExportFileError> {
+        match *self.get(index)? {
+            ExportConstant::Package {
+                flags,
+                name_index,
+                minor_version,
+                major_version,
+                ref aid,
+            } => Ok((
+                flags,
+                self.get_utf8(name_index)?,
+                PackageInfo {
+                    minor_version,
+                    major_version,
+                    aid: aid.clone(),
+                },
+            )),
+            _ => Err(ExportFileError::InvalidConstantPoolIndex(index)),
+        }
+    }
+
+    fn read_class_names(
+        &self,
+        reader: &mut ComponentReader,
+        count: usize,
+    ) -> Result<Vec<String>, ExportFileError> {
+        let mut result = Vec::new();
+        for _ in 0..count {
+            result.push(self.get_class_name(reader.u2()?)?);
+        }
+        Ok(result)
+    }
+
+    fn read_class(&self, reader: &mut ComponentReader) -> Result<ExportClass, ExportFileError> {
+        let token = reader.u1()?;
+        let access_flags = reader.u2()?;
+        let name = self.get_class_name(reader.u2()?)?;
+        let supers_count = reader.u2()? as usize;
+        let supers = self.read_class_names(reader, supers_count)?;
+        let interfaces_count = reader.u1()? as usize;
+        let interfaces = self.read_class_names(reader, interfaces_count)?;
+
+        let field_count = reader.u2()?;
+        let mut fields = Vec::new();
+        for _ in 0..field_count {
+            let mut field = ExportField {
+                token: reader.u1()?,
+                access_flags: reader.u2()?,
+                name: self.get_utf8(reader.u2()?)?,
+                descriptor: self.get_utf8(reader.u2()?)?,
+                constant_value: None,
+            };
+            let attribute_count = reader.u2()?;
+            for _ in 0..attribute_count {
+                let attribute_name = self.get_utf8(reader.u2()?)?;
+                let length = reader.u4()? as usize;
+                if attribute_name != CONSTANT_VALUE_ATTRIBUTE {
+                    // unknown attributes are skipped
+                    reader.bytes(length)?;
+                    continue;
+                }
+                let index = reader.u2()?;
+                field.constant_value = match *self.get(index)? {
+                    ExportConstant::Integer(value) => Some(value),
+                    _ => return Err(ExportFileError::InvalidConstantPoolIndex(index)),
+                };
+            }
+            fields.push(field);
+        }
+
+        let method_count = reader.u2()?;
+        let mut methods = Vec::new();
+        for _ in 0..method_count {
+            methods.push(ExportMethod {
+                token: reader.u1()?,
+                access_flags: reader.u2()?,
+                name: self.get_utf8(reader.u2()?)?,
+                descriptor: self.get_utf8(reader.u2()?)?,
+            });
+        }
+
+        Ok(ExportClass {
+            token,
+            access_flags,
+            name,
+            supers,
+            interfaces,
+            fields,
+            methods,
+        })
+    }
+
+    /// returns the index of the entry, which is added if needed
+    fn add(&mut self, entry: ExportConstant) -> u16 {
+        match self.entries.iter().position(|existing| *existing == entry) {
+            Some(index) => index as u16,
+            None => {
+                self.entries.push(entry);
+                self.entries.len() as u16 - 1
+            }
+        }
+    }
+
+    fn utf8(&mut self, value: &str) -> u16 {
+        self.add(ExportConstant::Utf8(value.to_string()))
+    }
+
+    fn class_ref(&mut self, name: &str) -> u16 {
+        let name_index = self.utf8(name);
+        self.add(ExportConstant::ClassRef(name_index))
+    }
+
+    fn write_class(&mut self, class: &ExportClass, data: &mut Vec<u8>) {
+        data.push(class.token);
+        push_u2(data, class.access_flags);
+        let name = self.class_ref(&class.name);
+        push_u2(data, name);
+        push_u2(data, class.supers.len() as u16);
+        for super_class in &class.supers {
+            let index = self.class_ref(super_class);
+            push_u2(data, index);
+        }
+        data.push(class.interfaces.len() as u8);
+        for interface in &class.interfaces {
+            let index = self.class_ref(interface);
+            push_u2(data, index);
+        }
+
+        push_u2(data, class.fields.len() as u16);
+        for field in &class.fields {
+            data.push(field.token);
+            push_u2(data, field.access_flags);
+            let name = self.utf8(&field.name);
+            push_u2(data, name);
+            let descriptor = self.utf8(&field.descriptor);
+            push_u2(data, descriptor);
+            match field.constant_value {
+                Some(value) => {
+                    push_u2(data, 1);
+                    let attribute_name = self.utf8(CONSTANT_VALUE_ATTRIBUTE);
+                    push_u2(data, attribute_name);
+                    push_u4(data, 2);
+                    let value = self.add(ExportConstant::Integer(value));
+                    push_u2(data, value);
+                }
+                None => push_u2(data, 0),
+            }
+        }
+
+        push_u2(data, class.methods.len() as u16);
+        for method in &class.methods {
+            data.push(method.token);
+            push_u2(data, method.access_flags);
+            let name = self.utf8(&method.name);
+            push_u2(data, name);
+            let descriptor = self.utf8(&method.descriptor);
+            push_u2(data, descriptor);
+        }
+    }
+}
+
+fn debug_string(debug: &DebugComponent, index: u16) -> Result<String, ExportFileError> {
+    debug
+        .strings
+        .get(index as usize)
+        .and_then(|string| String::from_utf8(string.clone()).ok())
+        .ok_or(ExportFileError::InvalidDebugString(index))
+}
+
+/// debug information of the class at the given offset of the Class component
+fn find_class_debug(
+    debug: &DebugComponent,
+    offset: u16,
+) -> Result<&ClassDebugInfo, ExportFileError> {
+    debug
+        .classes
+        .iter()
+        .find(|class| class.location == offset)
+        .ok_or(ExportFileError::InvalidReference(offset))
+}
+
+/// compile-time constants: static final fields of primitive types, whose value is in the Debug
+/// component instead of the static field image
+fn is_descriptor_constant(field: &FieldDescriptor) -> bool {
+    let flags = DESCRIPTOR_ACC_STATIC | DESCRIPTOR_ACC_FINAL;
+    field.access_flags & flags == flags && field.type_ & DESCRIPTOR_PRIMITIVE_TYPE != 0
+}
+
+fn is_debug_constant(debug: &DebugComponent, field: &FieldDebugInfo) -> bool {
+    let flags = DEBUG_ACC_STATIC | DEBUG_ACC_FINAL;
+    field.access_flags & flags == flags
+        && debug_string(debug, field.descriptor_index)
+            .map(|descriptor| ["Z", "B", "S", "I"].contains(&descriptor.as_str()))
+            .unwrap_or(false)
+}
+
+///
+/// Builds the exported class from its descriptor (tokens) and debug information (names),
+/// only public and protected members being exported
+///
+fn export_class(
+    debug: &DebugComponent,
+    imports: &[ExportFile],
+    token: u8,
+    descriptor: &ClassDescriptor,
+    class_debug: &ClassDebugInfo,
+) -> Result<ExportClass, ExportFileError> {
+    let exported = |flags: u8| flags & (DESCRIPTOR_ACC_PUBLIC | DESCRIPTOR_ACC_PROTECTED) != 0;
+
+    // superclasses defined in the package are followed up to the first external one, whose
+    // superclasses are given by the export file of its package (interfaces have no superclass)
+    let mut supers: Vec<String> = Vec::new();
+    let mut current = if descriptor.access_flags & DESCRIPTOR_ACC_INTERFACE != 0 {
+        None
+    } else {
+        Some(class_debug)
+    };
+    while let Some(class) = current {
+        let name = debug_string(debug, class.superclass_name_index)?;
+        if supers.contains(&name) {
+            return Err(ExportFileError::InvalidDebugString(class.superclass_name_index));
+        }
+        current = debug.classes.iter().find(|candidate| {
+            debug_string(debug, candidate.name_index).ok().as_ref() == Some(&name)
+        });
+        if current.is_none() {
+            let external = imports
+                .iter()
+                .filter_map(|import| import.find_class(&name))
+                .next()
+                .ok_or_else(|| ExportFileError::UnresolvedClass(name.clone()))?;
+            supers.push(name);
+            supers.extend(external.supers.iter().cloned());
+            break;
+        }
+        supers.push(name);
+    }
+
+    let mut interfaces = Vec::new();
+    for index in &class_debug.interface_name_indexes {
+        interfaces.push(debug_string(debug, *index)?);
+    }
+
+    // constants have neither token nor offset in the Debug component, the descriptors and the
+    // debug information of the constants of a class being in the same order
+    let descriptor_constants: Vec<&FieldDescriptor> = descriptor
+        .fields
+        .iter()
+        .filter(|field| is_descriptor_constant(field))
+        .collect();
+    let debug_constants: Vec<&FieldDebugInfo> = class_debug
+        .fields
+        .iter()
+        .filter(|field| is_debug_constant(debug, field))
+        .collect();
+
+    let mut fields = Vec::new();
+    for field in descriptor.fields.iter().filter(|field| exported(field.access_flags)) {
+        // instance fields are identified by their token, static fields by their offset
+        let constant_index = descriptor_constants
+            .iter()
+            .position(|constant| *constant == field);
+        let field_debug = match constant_index {
+            Some(index) => debug_constants.get(index).cloned(),
+            None => class_debug.fields.iter().find(|candidate| {
+                let is_static = candidate.access_flags & DEBUG_ACC_STATIC != 0;
+                !is_debug_constant(debug, candidate) && match field.field_ref {
+                    CapFieldRef::Instance(_, token) => {
+                        !is_static && candidate.contents & 0xFF == u32::from(token)
+                    }
+                    CapFieldRef::Static(CapStaticRef::Internal(offset)) => {
+                        is_static && candidate.contents == u32::from(offset)
+                    }
+                    CapFieldRef::Static(_) => false,
+                }
+            }),
+        }.ok_or(ExportFileError::InvalidReference(u16::from(field.token)))?;
+        fields.push(ExportField {
+            token: field.token,
+            access_flags: field_debug.access_flags,
+            name: debug_string(debug, field_debug.name_index)?,
+            descriptor: debug_string(debug, field_debug.descriptor_index)?,
+            // the value of constants is in the contents of their debug information
+            constant_value: constant_index.map(|_| field_debug.contents as i32),
+        });
+    }
+
+    let mut methods = Vec::new();
+    for method in descriptor.methods.iter().filter(|method| exported(method.access_flags)) {
+        let method_debug = class_debug
+            .methods
+            .iter()
+            .find(|candidate| candidate.location == method.method_offset)
+            .ok_or(ExportFileError::InvalidReference(method.method_offset))?;
+        methods.push(ExportMethod {
+            token: method.token,
+            access_flags: method_debug.access_flags,
+            name: debug_string(debug, method_debug.name_index)?,
+            descriptor: debug_string(debug, method_debug.descriptor_index)?,
+        });
+    }
+
+    Ok(ExportClass {
+        token,
+        access_flags: class_debug.access_flags,
+        name: debug_string(debug, class_debug.name_index)?,
+        supers,
+        interfaces,
+        fields,
+        methods,
+    })
+}
@@ -0,0 +1,81 @@
+use std::error::Error;
+use std::fmt;
+
+use bytecodes::bytecode;
+use context::Context;
+use jcvmerrors::InterpreterError;
+use methods::MethodHeader;
+use stack::StackEntry;
+
+// maximum number of operand stack entries recorded in a fault report
+pub const STACK_SNAPSHOT_SIZE: usize = 8;
+
+///
+/// Location and state of the virtual machine when the interpreter stopped on an error,
+/// captured where the error occurred (see Context::fault_report)
+///
+#[derive(Debug)]
+pub struct FaultReport {
+    pub error: InterpreterError,
+    // opcode being executed (None if no valid opcode was fetched)
+    pub opcode: Option<bytecode>,
+    pub opcode_offset: usize,
+    // offset of the bytecode fetcher when the error occurred (operands included)
+    pub current_offset: usize,
+    pub frame_depth: usize,
+    // method of the active frame, if any
+    pub method: Option<MethodHeader>,
+    // top entries of the operand stack, the top one first
+    pub stack: Vec<StackEntry>,
+}
+
+impl FaultReport {
+    pub fn new(ctx: &Context, error: InterpreterError) -> FaultReport {
+        let opcode_offset = ctx.bytecode_fetcher.opcode_offset();
+        let opcode = ctx.bytecode_fetcher
+            .get(opcode_offset)
+            .ok()
+            .and_then(|value| bytecode::from(value as u8).ok());
+        let stack_size = ctx.operand_stack.len().min(STACK_SNAPSHOT_SIZE);
+
+        FaultReport {
+            error,
+            opcode,
+            opcode_offset,
+            current_offset: ctx.bytecode_fetcher.current_offset(),
+            frame_depth: ctx.frame_stack.depth(),
+            method: ctx.frame_stack.top().ok().and_then(|frame| frame.method()),
+            stack: (0..stack_size)
+                .filter_map(|index| ctx.operand_stack.peek_index(index as i16).ok())
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for FaultReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at offset 0x{:04X}", self.error, self.current_offset)?;
+        match self.opcode {
+            Some(opcode) => write!(f, " ({:?} at 0x{:04X})", opcode, self.opcode_offset)?,
+            None => write!(f, " (no opcode at 0x{:04X})", self.opcode_offset)?,
+        }
+        write!(f, ", frame depth {}", self.frame_depth)?;
+        if let Some(method) = self.method {
+            write!(f, ", method at 0x{:04X}", method.method_offset)?;
+        }
+        write!(f, ", operand stack (top first): [")?;
+        for (index, entry) in self.stack.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{:?} 0x{:04X}", entry.entry_type(), entry.value as u16)?;
+        }
+        write!(f, "]")
+    }
+}
+
+impl Error for FaultReport {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
@@ -0,0 +1,65 @@
+use classes::ClassRef;
+use constants;
+use context::Context;
+use jcvmerrors::InterpreterError;
+use objects::JCVMObject;
+
+///
+/// Kind of access to an object, each of them having its own firewall rules
+/// (see chapter 6.2.8 of the JCRE specification)
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ObjectAccess {
+    // getfield, putfield
+    Field,
+    // xaload, xastore, arraylength
+    Array,
+    // invokevirtual, invokespecial
+    Method,
+    // invokeinterface, with the interface declaring the invoked method
+    Interface(ClassRef),
+    // athrow
+    Throw,
+    // checkcast and instanceof, with the target class or interface (None for array types)
+    Cast(Option<ClassRef>),
+}
+
+///
+/// Indicates whether the firewall allows the current context to access the given object.
+/// The JCRE accesses any object and applets access the objects they own. Otherwise:
+/// - global arrays (arrays with the GLOBAL flag) can be accessed in any way
+/// - the methods of JCRE entry point objects (instances with the GLOBAL flag) can be invoked,
+///   and they can be thrown and cast, but their fields cannot be accessed
+/// - the methods of shareable interfaces can be invoked, and objects can be cast to them
+///
+pub fn is_accessible(
+    ctx: &Context,
+    object: &JCVMObject,
+    access: ObjectAccess,
+) -> Result<bool, InterpreterError> {
+    if ctx.current_context == constants::JCRE_CONTEXT || object.owner() == ctx.current_context {
+        return Ok(true);
+    }
+
+    let global = object.is_global();
+    if object.is_array() {
+        return Ok(global);
+    }
+
+    match access {
+        ObjectAccess::Field | ObjectAccess::Array => Ok(false),
+        ObjectAccess::Method | ObjectAccess::Throw | ObjectAccess::Cast(None) => Ok(global),
+        ObjectAccess::Interface(interface) | ObjectAccess::Cast(Some(interface)) => {
+            Ok(global || ctx.class_manager.is_shareable_interface(interface)?)
+        }
+    }
+}
+
+///
+/// Indicates whether a reference to the object can be stored in a field, a static field or an
+/// array component: references to global arrays and temporary JCRE entry points cannot be
+/// stored
+///
+pub fn is_storable(object: &JCVMObject) -> bool {
+    !object.is_temporary()
+}
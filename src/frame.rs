@@ -1,31 +1,97 @@
 use stack::{Stack, StackEntry};
 use jcvmerrors::InterpreterError;
 use constants;
+use methods::MethodHeader;
 
 // structure representing one frame
 pub struct Frame {
     locals_stack: Stack,
+    // method being executed in this frame (None for frames created by hand)
+    method: Option<MethodHeader>,
+    // offset of the next opcode to execute in the caller once this frame returns
+    return_address: usize,
+    // size of the operand stack when the frame was created
+    operand_stack_base: usize,
+    // context that was active when the method was invoked, restored when it returns
+    caller_context: i16,
+    // applet instance that was active when the method was invoked, restored when it returns
+    caller_applet: i16,
 }
 
 // methods/functions associated to the Frame struct
 impl Frame {
     pub fn new(max_stack: u8) -> Frame {
+        Frame::with_locals(max_stack as usize)
+    }
+
+    fn with_locals(max_stack: usize) -> Frame {
         let mut result = Frame {
-            locals_stack: Stack::new(max_stack as usize),
+            locals_stack: Stack::new(max_stack),
+            method: None,
+            return_address: 0,
+            operand_stack_base: 0,
+            caller_context: constants::JCRE_CONTEXT,
+            caller_applet: constants::NULL_HANDLE,
         };
 
         {
             // initialize the locals stack with StackEntry so that we can
             // access and modify them later
             let to_be_initialised = &mut result;
+            // this cannot overflow, the capacity of the locals stack being max_stack
             for _ in 0..max_stack {
-                to_be_initialised.locals_stack.push(StackEntry::new());
+                let _ = to_be_initialised.locals_stack.push(StackEntry::new());
             }
         }
 
         result
     }
 
+    ///
+    /// Creates the frame of an invoked method, with room for its arguments and locals
+    ///
+    pub fn from_method(
+        method: MethodHeader,
+        return_address: usize,
+        operand_stack_base: usize,
+        caller_context: i16,
+        caller_applet: i16,
+    ) -> Frame {
+        let mut result =
+            Frame::with_locals(method.nargs as usize + method.max_locals as usize);
+        result.method = Some(method);
+        result.return_address = return_address;
+        result.operand_stack_base = operand_stack_base;
+        result.caller_context = caller_context;
+        result.caller_applet = caller_applet;
+        result
+    }
+
+    pub fn method(&self) -> Option<MethodHeader> {
+        self.method
+    }
+
+    pub fn return_address(&self) -> usize {
+        self.return_address
+    }
+
+    pub fn operand_stack_base(&self) -> usize {
+        self.operand_stack_base
+    }
+
+    pub fn caller_context(&self) -> i16 {
+        self.caller_context
+    }
+
+    pub fn caller_applet(&self) -> i16 {
+        self.caller_applet
+    }
+
+    /// enables or disables the type checks of the locals (enabled by default)
+    pub fn set_type_checks(&mut self, enabled: bool) {
+        self.locals_stack.set_type_checks(enabled);
+    }
+
     pub fn get_local(&self, index: i16) -> Result<StackEntry, InterpreterError> {
         self.locals_stack.peek_index(index)
     }
@@ -5,6 +5,12 @@ pub struct FrameStack {
     internal_stack: Vec<Frame>,
 }
 
+impl Default for FrameStack {
+    fn default() -> FrameStack {
+        FrameStack::new()
+    }
+}
+
 impl FrameStack {
     pub fn new() -> FrameStack {
         FrameStack {
@@ -16,6 +22,37 @@ impl FrameStack {
         self.internal_stack.push(new_frame);
     }
 
+    pub fn pop(&mut self) -> Result<Frame, InterpreterError> {
+        match self.internal_stack.pop() {
+            Some(result) => Ok(result),
+            None => Err(InterpreterError::StackUnderflowError),
+        }
+    }
+
+    /// discards all the frames
+    pub fn clear(&mut self) {
+        self.internal_stack.clear();
+    }
+
+    /// number of frames currently in the stack
+    pub fn depth(&self) -> usize {
+        self.internal_stack.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.internal_stack.is_empty()
+    }
+
+    /// frames of the stack, from the bottom one to the top one
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Frame> {
+        self.internal_stack.iter()
+    }
+
+    /// frames of the stack, from the bottom one to the top one
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Frame> {
+        self.internal_stack.iter_mut()
+    }
+
     pub fn top(&self) -> Result<&Frame, InterpreterError> {
         match self.internal_stack.last() {
             Some(result) => Ok(result),
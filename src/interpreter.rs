@@ -1,86 +1,128 @@
+use std::cmp::Ordering;
+
 use bytecodes::bytecode;
 use context::Context;
-use stack::StackEntry;
+use faultreport::FaultReport;
 use jcvmerrors::InterpreterError;
 use constants;
-use interpreterutils::{xaload, xastore, xstore};
+use natives::invoke_native;
+use interpreterutils::{anewarray, array_type, arraylength, athrow, checkcast, conditional_branch,
+                       dup_x, field_type, getfield, getstatic, ibinop, iinc, instanceof,
+                       invoke_method, invokeinterface, invokestatic_special, invokevirtual, jsr,
+                       new_instance, newarray, putfield, putstatic, ret, sbinop, sinc, swap_x,
+                       xaload, xastore, xlookupswitch, xreturn, xstore, xtableswitch};
 
 pub type BytecodeType = i8;
 // pub type BytecodeData = Vec<BytecodeType>;
 pub type BytecodeData = [BytecodeType];
 
+///
+/// Invokes the method whose header is located at method_offset and runs it until it returns.
+/// Its arguments have to be pushed on the operand stack beforehand, and its result (if any)
+/// is left on the operand stack.
+///
+pub fn invoke(execution_context: &mut Context, method_offset: usize) -> Result<(), InterpreterError> {
+    invoke_method(execution_context, method_offset)?;
+    interpreter(execution_context)
+}
+
+///
+/// Runs the bytecode from the current offset until the outermost method returns.
+/// When an error stops the execution, the state of the virtual machine where it occurred
+/// is recorded in the fault report of the context
+///
 pub fn interpreter(execution_context: &mut Context) -> Result<(), InterpreterError> {
+    execution_context.fault_report = None;
+    run(execution_context).map_err(|error| {
+        // uncaught exceptions are reported where they were thrown, before the frames
+        // were discarded (see exceptions::throw_object)
+        let report = match execution_context.fault_report.take() {
+            Some(report) if report.error == error => report,
+            _ => FaultReport::new(execution_context, error.clone()),
+        };
+        execution_context.fault_report = Some(report);
+        error
+    })
+}
+
+fn run(execution_context: &mut Context) -> Result<(), InterpreterError> {
     loop {
+        // offset of the opcode being executed (branches are relative to it)
+        let opcode_offset = execution_context.bytecode_fetcher.current_offset();
         let current_opcode = execution_context.bytecode_fetcher.fetch_bytecode()?;
 
+        // the integer instruction set is optional
+        if !execution_context.int_support && current_opcode.is_integer_bytecode() {
+            return Err(InterpreterError::UnsupportedBytecode);
+        }
+
+        // in defensive mode, the opcode only accesses the words of the current frame
+        if execution_context.is_defensive() {
+            execution_context.restrict_operand_stack();
+        }
+
         //println!("Found bytecode : {:02X}", current_opcode.unwrap() as u8);
         match current_opcode {
             // bytecode 0 : NOP
-            bytecode::nop => {
-                println!(
-                    "NOP bytecode reached at offset 0x{:X}",
-                    execution_context.bytecode_fetcher.current_offset()
-                );
-                panic!("Unexpected NOP bytecode !");
-            }
+            bytecode::nop => {}
             // bytecode 1 : ACONST_NULL
             bytecode::aconst_null => {
                 execution_context
                     .operand_stack
-                    .apush(constants::NULL_HANDLE);
+                    .apush(constants::NULL_HANDLE)?;
             }
 
             // bytecode 2 : SCONST_M1
             bytecode::sconst_m1 => {
-                execution_context.operand_stack.spush(-1 as i16);
+                execution_context.operand_stack.spush(-1)?;
             }
 
             // bytecode 3: SCONST_0
-            bytecode::sconst_0 => execution_context.operand_stack.spush(0),
+            bytecode::sconst_0 => execution_context.operand_stack.spush(0)?,
             // bytecode 4: SCONST_1
-            bytecode::sconst_1 => execution_context.operand_stack.spush(1),
+            bytecode::sconst_1 => execution_context.operand_stack.spush(1)?,
             // bytecode 5: SCONST_2
-            bytecode::sconst_2 => execution_context.operand_stack.spush(2),
+            bytecode::sconst_2 => execution_context.operand_stack.spush(2)?,
             // bytecode 6: SCONST_3
-            bytecode::sconst_3 => execution_context.operand_stack.spush(3),
+            bytecode::sconst_3 => execution_context.operand_stack.spush(3)?,
             // bytecode 7: SCONST_4
-            bytecode::sconst_4 => execution_context.operand_stack.spush(4),
+            bytecode::sconst_4 => execution_context.operand_stack.spush(4)?,
             // bytecode 8: SCONST_5
-            bytecode::sconst_5 => execution_context.operand_stack.spush(5),
+            bytecode::sconst_5 => execution_context.operand_stack.spush(5)?,
             // bytecode 9: ICONST_M1
-            bytecode::iconst_m1 => execution_context.operand_stack.ipush(-1),
+            bytecode::iconst_m1 => execution_context.operand_stack.ipush(-1)?,
             // bytecode 10: ICONST_0
-            bytecode::iconst_0 => execution_context.operand_stack.ipush(0),
+            bytecode::iconst_0 => execution_context.operand_stack.ipush(0)?,
             // bytecode 11: ICONST_1
-            bytecode::iconst_1 => execution_context.operand_stack.ipush(1),
+            bytecode::iconst_1 => execution_context.operand_stack.ipush(1)?,
             // bytecode 12: ICONST_2
-            bytecode::iconst_2 => execution_context.operand_stack.ipush(2),
+            bytecode::iconst_2 => execution_context.operand_stack.ipush(2)?,
             // bytecode 13: ICONST_3
-            bytecode::iconst_3 => execution_context.operand_stack.ipush(3),
+            bytecode::iconst_3 => execution_context.operand_stack.ipush(3)?,
             // bytecode 14: ICONST_4
-            bytecode::iconst_4 => execution_context.operand_stack.ipush(4),
+            bytecode::iconst_4 => execution_context.operand_stack.ipush(4)?,
             // bytecode 15: ICONST_5
-            bytecode::iconst_5 => execution_context.operand_stack.ipush(5),
+            bytecode::iconst_5 => execution_context.operand_stack.ipush(5)?,
             // bytecode 16: BSPUSH
             bytecode::bspush => execution_context
                 .operand_stack
-                .spush(i16::from(execution_context.bytecode_fetcher.fetch_b()?)),
+                .spush(i16::from(execution_context.bytecode_fetcher.fetch_b()?))?,
             // bytecode 17: SSPUSH
             bytecode::sspush => execution_context
                 .operand_stack
-                .spush(execution_context.bytecode_fetcher.fetch_s()?),
+                .spush(execution_context.bytecode_fetcher.fetch_s()?)?,
             // bytecode 18: BIPUSH
             bytecode::bipush => execution_context
                 .operand_stack
-                .ipush(execution_context.bytecode_fetcher.fetch_b()? as i32),
+                .ipush(execution_context.bytecode_fetcher.fetch_b()? as i32)?,
             // bytecode 19: SIPUSH
             bytecode::sipush => execution_context
                 .operand_stack
-                .ipush(execution_context.bytecode_fetcher.fetch_s()? as i32),
+                .ipush(execution_context.bytecode_fetcher.fetch_s()? as i32)?,
             // bytecode 20: IIPUSH
             bytecode::iipush => execution_context
                 .operand_stack
-                .ipush(execution_context.bytecode_fetcher.fetch_i()? as i32),
+                .ipush(execution_context.bytecode_fetcher.fetch_i()?)?,
             // bytecode 21: ALOAD
             bytecode::aload => {
                 let index = execution_context.bytecode_fetcher.fetch_b()?;
@@ -89,7 +131,7 @@ pub fn interpreter(execution_context: &mut Context) -> Result<(), InterpreterErr
                     .current_frame()?
                     .get_local_check_type(i16::from(index), constants::PrimitiveType::REFERENCE)?;
 
-                execution_context.operand_stack.push(current_local);
+                execution_context.operand_stack.push(current_local)?;
             }
 
             // bytecode 22: SLOAD
@@ -100,7 +142,7 @@ pub fn interpreter(execution_context: &mut Context) -> Result<(), InterpreterErr
                     .current_frame()?
                     .get_local_check_type(i16::from(index), constants::PrimitiveType::SHORT)?;
 
-                execution_context.operand_stack.push(current_local);
+                execution_context.operand_stack.push(current_local)?;
             }
 
             // bytecode 23: ILOAD
@@ -114,11 +156,11 @@ pub fn interpreter(execution_context: &mut Context) -> Result<(), InterpreterErr
                     .get_local_check_type(i16::from(index), constants::PrimitiveType::INTEGER)?;
                 let current_local2 = execution_context
                     .current_frame()?
-                    .get_local_check_type(i16::from(index + 1), constants::PrimitiveType::INTEGER)?;
+                    .get_local_check_type(i16::from(index) + 1, constants::PrimitiveType::INTEGER)?;
 
                 // push variables in reverse order to keep the original order
-                execution_context.operand_stack.push(current_local2);
-                execution_context.operand_stack.push(current_local1);
+                execution_context.operand_stack.push(current_local2)?;
+                execution_context.operand_stack.push(current_local1)?;
             }
 
             // bytecode 24...27: ALOAD_0...ALOAD_3
@@ -129,7 +171,7 @@ pub fn interpreter(execution_context: &mut Context) -> Result<(), InterpreterErr
                     constants::PrimitiveType::REFERENCE,
                 )?;
 
-                execution_context.operand_stack.push(current_local);
+                execution_context.operand_stack.push(current_local)?;
             }
 
             // bytecode 28...31: SLOAD_0...SLOAD_3
@@ -140,7 +182,7 @@ pub fn interpreter(execution_context: &mut Context) -> Result<(), InterpreterErr
                     constants::PrimitiveType::SHORT,
                 )?;
 
-                execution_context.operand_stack.push(current_local);
+                execution_context.operand_stack.push(current_local)?;
             }
 
             // bytecode 32...35: ILOAD_0...ILOAD_3
@@ -152,52 +194,52 @@ pub fn interpreter(execution_context: &mut Context) -> Result<(), InterpreterErr
                     .current_frame()?
                     .get_local_check_type(current_idx + 1, constants::PrimitiveType::INTEGER)?;
 
-                execution_context.operand_stack.push(current_local);
+                execution_context.operand_stack.push(current_local)?;
 
                 current_local = execution_context
                     .current_frame()?
                     .get_local_check_type(current_idx, constants::PrimitiveType::INTEGER)?;
 
-                execution_context.operand_stack.push(current_local);
+                execution_context.operand_stack.push(current_local)?;
             }
 
             bytecode::aaload => {
-                xaload(execution_context, constants::PrimitiveType::REFERENCE);
+                xaload(execution_context, constants::PrimitiveType::REFERENCE)?;
             }
             bytecode::baload => {
-                xaload(execution_context, constants::PrimitiveType::BYTE);
+                xaload(execution_context, constants::PrimitiveType::BYTE)?;
             }
             bytecode::saload => {
-                xaload(execution_context, constants::PrimitiveType::SHORT);
+                xaload(execution_context, constants::PrimitiveType::SHORT)?;
             }
             bytecode::iaload => {
-                xaload(execution_context, constants::PrimitiveType::INTEGER);
+                xaload(execution_context, constants::PrimitiveType::INTEGER)?;
             }
             bytecode::astore => {
                 let idx: u8 = execution_context.bytecode_fetcher.fetch_b()? as u8;
-                xstore(execution_context, idx, constants::PrimitiveType::REFERENCE);
+                xstore(execution_context, idx, constants::PrimitiveType::REFERENCE)?;
             }
             bytecode::sstore => {
                 let idx: u8 = execution_context.bytecode_fetcher.fetch_b()? as u8;
-                xstore(execution_context, idx, constants::PrimitiveType::SHORT);
+                xstore(execution_context, idx, constants::PrimitiveType::SHORT)?;
             }
             bytecode::istore => {
                 let idx: u8 = execution_context.bytecode_fetcher.fetch_b()? as u8;
-                xstore(execution_context, idx, constants::PrimitiveType::INTEGER);
+                xstore(execution_context, idx, constants::PrimitiveType::INTEGER)?;
             }
             bytecode::astore_0 | bytecode::astore_1 | bytecode::astore_2 | bytecode::astore_3 => {
                 xstore(
                     execution_context,
                     current_opcode as u8 - bytecode::astore_0 as u8,
                     constants::PrimitiveType::REFERENCE,
-                );
+                )?;
             }
             bytecode::sstore_0 | bytecode::sstore_1 | bytecode::sstore_2 | bytecode::sstore_3 => {
                 xstore(
                     execution_context,
                     current_opcode as u8 - bytecode::sstore_0 as u8,
                     constants::PrimitiveType::SHORT,
-                );
+                )?;
             }
 
             bytecode::istore_0 | bytecode::istore_1 | bytecode::istore_2 | bytecode::istore_3 => {
@@ -205,163 +247,376 @@ pub fn interpreter(execution_context: &mut Context) -> Result<(), InterpreterErr
                     execution_context,
                     current_opcode as u8 - bytecode::istore_0 as u8,
                     constants::PrimitiveType::INTEGER,
-                );
+                )?;
             }
             bytecode::aastore => {
-                xastore(execution_context, constants::PrimitiveType::REFERENCE);
+                xastore(execution_context, constants::PrimitiveType::REFERENCE)?;
             }
             bytecode::bastore => {
-                xastore(execution_context, constants::PrimitiveType::BYTE);
+                xastore(execution_context, constants::PrimitiveType::BYTE)?;
             }
             bytecode::sastore => {
-                xastore(execution_context, constants::PrimitiveType::SHORT);
+                xastore(execution_context, constants::PrimitiveType::SHORT)?;
             }
             bytecode::iastore => {
-                xastore(execution_context, constants::PrimitiveType::INTEGER);
-            }
-            //bytecode::pop,             // 59
-            //bytecode::pop2,            // 60
-            //bytecode::dup,             // 61
-            //bytecode::dup2,            // 62
-            //bytecode::dup_x,           // 63
-            //bytecode::swap_x,          // 64
-            bytecode::sadd => {
-                let value1 = execution_context
-                    .operand_stack
-                    .pop_check_type(constants::PrimitiveType::SHORT)
-                    .unwrap();
-                let value2 = execution_context
-                    .operand_stack
-                    .pop_check_type(constants::PrimitiveType::SHORT)
-                    .unwrap();
-                let res = value1.value + value2.value;
+                xastore(execution_context, constants::PrimitiveType::INTEGER)?;
+            }
+            // bytecode 59: POP
+            bytecode::pop => {
+                execution_context.operand_stack.pop()?;
+            }
+            // bytecode 60: POP2
+            bytecode::pop2 => {
+                execution_context.operand_stack.pop()?;
+                execution_context.operand_stack.pop()?;
+            }
+            // bytecode 61: DUP
+            bytecode::dup => dup_x(execution_context, 1, 0)?,
+            // bytecode 62: DUP2
+            bytecode::dup2 => dup_x(execution_context, 2, 0)?,
+            // bytecode 63: DUP_X
+            bytecode::dup_x => {
+                let mn = execution_context.bytecode_fetcher.fetch_b()? as u8;
+                dup_x(execution_context, mn >> 4, mn & 0x0F)?;
+            }
+            // bytecode 64: SWAP_X
+            bytecode::swap_x => {
+                let mn = execution_context.bytecode_fetcher.fetch_b()? as u8;
+                swap_x(execution_context, mn >> 4, mn & 0x0F)?;
+            }
+            // bytecode 65...87: arithmetic and logic operations on shorts
+            bytecode::sadd
+            | bytecode::ssub
+            | bytecode::smul
+            | bytecode::sdiv
+            | bytecode::srem
+            | bytecode::sshl
+            | bytecode::sshr
+            | bytecode::sushr
+            | bytecode::sand
+            | bytecode::sor
+            | bytecode::sxor => {
+                sbinop(execution_context, current_opcode)?;
+            }
+            // bytecode 66...88: arithmetic and logic operations on integers
+            bytecode::iadd
+            | bytecode::isub
+            | bytecode::imul
+            | bytecode::idiv
+            | bytecode::irem
+            | bytecode::ishl
+            | bytecode::ishr
+            | bytecode::iushr
+            | bytecode::iand
+            | bytecode::ior
+            | bytecode::ixor => {
+                ibinop(execution_context, current_opcode)?;
+            }
+            // bytecode 75: SNEG
+            bytecode::sneg => {
+                let value = execution_context.operand_stack.spop()?;
+                execution_context.operand_stack.spush(value.wrapping_neg())?;
+            }
+            // bytecode 76: INEG
+            bytecode::ineg => {
+                let value = execution_context.operand_stack.ipop()?;
+                execution_context.operand_stack.ipush(value.wrapping_neg())?;
+            }
+            // bytecode 89: SINC
+            bytecode::sinc => {
+                let index = execution_context.bytecode_fetcher.fetch_b()? as u8;
+                let constant = i16::from(execution_context.bytecode_fetcher.fetch_b()?);
+                sinc(execution_context, index, constant)?;
+            }
+            // bytecode 90: IINC
+            bytecode::iinc => {
+                let index = execution_context.bytecode_fetcher.fetch_b()? as u8;
+                let constant = i16::from(execution_context.bytecode_fetcher.fetch_b()?);
+                iinc(execution_context, index, constant)?;
+            }
+            // bytecode 91: S2B
+            bytecode::s2b => {
+                let value = execution_context.operand_stack.spop()?;
+                execution_context.operand_stack.spush(i16::from(value as i8))?;
+            }
+            // bytecode 92: S2I
+            bytecode::s2i => {
+                let value = execution_context.operand_stack.spop()?;
+                execution_context.operand_stack.ipush(i32::from(value))?;
+            }
+            // bytecode 93: I2B
+            bytecode::i2b => {
+                let value = execution_context.operand_stack.ipop()?;
+                execution_context.operand_stack.spush(i16::from(value as i8))?;
+            }
+            // bytecode 94: I2S
+            bytecode::i2s => {
+                let value = execution_context.operand_stack.ipop()?;
+                execution_context.operand_stack.spush(value as i16)?;
+            }
+            // bytecode 95: ICMP
+            bytecode::icmp => {
+                let value2 = execution_context.operand_stack.ipop()?;
+                let value1 = execution_context.operand_stack.ipop()?;
+                let res = match value1.cmp(&value2) {
+                    Ordering::Greater => 1,
+                    Ordering::Equal => 0,
+                    Ordering::Less => -1,
+                };
+                execution_context.operand_stack.spush(res)?;
+            }
+            // bytecode 96...111: conditional branches
+            bytecode::ifeq
+            | bytecode::ifne
+            | bytecode::iflt
+            | bytecode::ifge
+            | bytecode::ifgt
+            | bytecode::ifle
+            | bytecode::ifnull
+            | bytecode::ifnonnull
+            | bytecode::if_acmpeq
+            | bytecode::if_acmpne
+            | bytecode::if_scmpeq
+            | bytecode::if_scmpne
+            | bytecode::if_scmplt
+            | bytecode::if_scmpge
+            | bytecode::if_scmpgt
+            | bytecode::if_scmple => {
+                conditional_branch(execution_context, current_opcode, opcode_offset)?;
+            }
+            // bytecode 112: GOTO
+            bytecode::goto => {
+                let branch = i16::from(execution_context.bytecode_fetcher.fetch_b()?);
                 execution_context
-                    .operand_stack
-                    .push(StackEntry::from_values(
-                        res,
-                        constants::PrimitiveType::SHORT,
-                    ));
-            }
-            // bytecode::iadd,            // 66
-            // bytecode::ssub,            // 67
-            // bytecode::isub,            // 68
-            // bytecode::smul,            // 69
-            // bytecode::imul,            // 70
-            // bytecode::sdiv,            // 71
-            // bytecode::idiv,            // 72
-            // bytecode::srem,            // 73
-            // bytecode::irem,            // 74
-            // bytecode::sneg,            // 75
-            // bytecode::ineg,            // 76
-            // bytecode::sshl,            // 77
-            // bytecode::ishl,            // 78
-            // bytecode::sshr,            // 79
-            // bytecode::ishr,            // 80
-            // bytecode::sushr,           // 81
-            // bytecode::iushr,           // 82
-            // bytecode::sand,            // 83
-            // bytecode::iand,            // 84
-            // bytecode::sor,             // 85
-            // bytecode::ior,             // 86
-            // bytecode::sxor,            // 87
-            // bytecode::ixor,            // 88
-            // bytecode::sinc,            // 89
-            // bytecode::iinc,            // 90
-            // bytecode::s2b,             // 91
-            // bytecode::s2i,             // 92
-            // bytecode::i2b,             // 93
-            // bytecode::i2s,             // 94
-            // bytecode::icmp,            // 95
-            // bytecode::ifeq,            // 96
-            // bytecode::ifne,            // 97
-            // bytecode::iflt,            // 98
-            // bytecode::ifge,            // 99
-            // bytecode::ifgt,            // 100
-            // bytecode::ifle,            // 101
-            // bytecode::ifnull,          // 102
-            // bytecode::ifnonnull,       // 103
-            // bytecode::if_acmpeq,       // 104
-            // bytecode::if_acmpne,       // 105
-            // bytecode::if_scmpeq,       // 106
-            // bytecode::if_scmpne,       // 107
-            // bytecode::if_scmplt,       // 108
-            // bytecode::if_scmpge,       // 109
-            // bytecode::if_scmpgt,       // 110
-            // bytecode::if_scmple,       // 111
-            // bytecode::goto,            // 112
-            // bytecode::jsr,             // 113
-            // bytecode::ret,             // 114
-            // bytecode::stableswitch,    // 115
-            // bytecode::itableswitch,    // 116
-            // bytecode::slookupswitch,   // 117
-            // bytecode::ilookupswitch,   // 118
-            // bytecode::areturn,         // 119
-            // bytecode::sreturn,         // 120
-            // bytecode::ireturn,         // 121
-            // bytecode::return_,         // 122
-            // bytecode::getstatic_a,     // 123
-            // bytecode::getstatic_b,     // 124
-            // bytecode::getstatic_s,     // 125
-            // bytecode::getstatic_i,     // 126
-            // bytecode::putstatic_a,     // 127
-            // bytecode::putstatic_b,     // 128
-            // bytecode::putstatic_s,     // 129
-            // bytecode::putstatic_i,     // 130
-            // bytecode::getfield_a,      // 131
-            // bytecode::getfield_b,      // 132
-            // bytecode::getfield_s,      // 133
-            // bytecode::getfield_i,      // 134
-            // bytecode::putfield_a,      // 135
-            // bytecode::putfield_b,      // 136
-            // bytecode::putfield_s,      // 137
-            // bytecode::putfield_i,      // 138
-            // bytecode::invokevirtual,   // 139
-            // bytecode::invokespecial,   // 140
-            // bytecode::invokestatic,    // 141
-            // bytecode::invokeinterface, // 142
-            // bytecode::new,             // 143
-            // bytecode::newarray,        // 144
-            // bytecode::anewarray,       // 145
-            // bytecode::arraylength,     // 146
-            // bytecode::athrow,          // 147
-            // bytecode::checkcast,       // 148
-            // bytecode::instanceof,      // 149
-            // bytecode::sinc_w,          // 150
-            // bytecode::iinc_w,          // 151
-            // bytecode::ifeq_w,          // 152
-            // bytecode::ifne_w,          // 153
-            // bytecode::iflt_w,          // 154
-            // bytecode::ifge_w,          // 155
-            // bytecode::ifgt_w,          // 156
-            // bytecode::ifle_w,          // 157
-            // bytecode::ifnull_w,        // 158
-            // bytecode::ifnonnull_w,     // 159
-            // bytecode::if_acmpeq_w,     // 160
-            // bytecode::if_acmpne_w,     // 161
-            // bytecode::if_scmpeq_w,     // 162
-            // bytecode::if_scmpne_w,     // 163
-            // bytecode::if_scmplt_w,     // 164
-            // bytecode::if_scmpge_w,     // 165
-            // bytecode::if_scmpgt_w,     // 166
-            // bytecode::if_scmple_w,     // 167
-            // bytecode::goto_w,          // 168
-            // bytecode::getfield_a_w,    // 169
-            // bytecode::getfield_b_w,    // 170
-            // bytecode::getfield_s_w,    // 171
-            // bytecode::getfield_i_w,    // 172
-            // bytecode::getfield_a_this, // 173
-            // bytecode::getfield_b_this, // 174
-            // bytecode::getfield_s_this, // 175
-            // bytecode::getfield_i_this, // 176
-            // bytecode::putfield_a_w,    // 177
-            // bytecode::putfield_b_w,    // 178
-            // bytecode::putfield_s_w,    // 179
-            // bytecode::putfield_i_w,    // 180
-            // bytecode::putfield_a_this, // 181
-            // bytecode::putfield_b_this, // 182
-            // bytecode::putfield_s_this, // 183
-            // bytecode::putfield_i_this, // 184
-            _ => break,
+                    .bytecode_fetcher
+                    .branch(opcode_offset, branch)?;
+            }
+            // bytecode 113: JSR
+            bytecode::jsr => jsr(execution_context, opcode_offset)?,
+            // bytecode 114: RET
+            bytecode::ret => {
+                let index = execution_context.bytecode_fetcher.fetch_b()? as u8;
+                ret(execution_context, index)?;
+            }
+            // bytecode 115: STABLESWITCH
+            bytecode::stableswitch => {
+                xtableswitch(
+                    execution_context,
+                    constants::PrimitiveType::SHORT,
+                    opcode_offset,
+                )?;
+            }
+            // bytecode 116: ITABLESWITCH
+            bytecode::itableswitch => {
+                xtableswitch(
+                    execution_context,
+                    constants::PrimitiveType::INTEGER,
+                    opcode_offset,
+                )?;
+            }
+            // bytecode 117: SLOOKUPSWITCH
+            bytecode::slookupswitch => {
+                xlookupswitch(
+                    execution_context,
+                    constants::PrimitiveType::SHORT,
+                    opcode_offset,
+                )?;
+            }
+            // bytecode 118: ILOOKUPSWITCH
+            bytecode::ilookupswitch => {
+                xlookupswitch(
+                    execution_context,
+                    constants::PrimitiveType::INTEGER,
+                    opcode_offset,
+                )?;
+            }
+            // bytecode 119...122: ARETURN, SRETURN, IRETURN, RETURN
+            bytecode::areturn | bytecode::sreturn | bytecode::ireturn | bytecode::return_ => {
+                let type_ = match current_opcode {
+                    bytecode::areturn => Some(constants::PrimitiveType::REFERENCE),
+                    bytecode::sreturn => Some(constants::PrimitiveType::SHORT),
+                    bytecode::ireturn => Some(constants::PrimitiveType::INTEGER),
+                    _ => None,
+                };
+                xreturn(execution_context, type_)?;
+
+                // the outermost method returned, the execution is over
+                if execution_context.frame_stack.is_empty() {
+                    break;
+                }
+            }
+            // bytecode 123...126: GETSTATIC_A, GETSTATIC_B, GETSTATIC_S, GETSTATIC_I
+            bytecode::getstatic_a
+            | bytecode::getstatic_b
+            | bytecode::getstatic_s
+            | bytecode::getstatic_i => {
+                getstatic(
+                    execution_context,
+                    field_type(current_opcode as u8 - bytecode::getstatic_a as u8),
+                )?;
+            }
+            // bytecode 127...130: PUTSTATIC_A, PUTSTATIC_B, PUTSTATIC_S, PUTSTATIC_I
+            bytecode::putstatic_a
+            | bytecode::putstatic_b
+            | bytecode::putstatic_s
+            | bytecode::putstatic_i => {
+                putstatic(
+                    execution_context,
+                    field_type(current_opcode as u8 - bytecode::putstatic_a as u8),
+                )?;
+            }
+            // bytecode 131...134: GETFIELD_A, GETFIELD_B, GETFIELD_S, GETFIELD_I
+            bytecode::getfield_a
+            | bytecode::getfield_b
+            | bytecode::getfield_s
+            | bytecode::getfield_i => {
+                let index = execution_context.bytecode_fetcher.fetch_b()? as u8;
+                getfield(
+                    execution_context,
+                    field_type(current_opcode as u8 - bytecode::getfield_a as u8),
+                    u16::from(index),
+                    false,
+                )?;
+            }
+            // bytecode 135...138: PUTFIELD_A, PUTFIELD_B, PUTFIELD_S, PUTFIELD_I
+            bytecode::putfield_a
+            | bytecode::putfield_b
+            | bytecode::putfield_s
+            | bytecode::putfield_i => {
+                let index = execution_context.bytecode_fetcher.fetch_b()? as u8;
+                putfield(
+                    execution_context,
+                    field_type(current_opcode as u8 - bytecode::putfield_a as u8),
+                    u16::from(index),
+                    false,
+                )?;
+            }
+            // bytecode 139: INVOKEVIRTUAL
+            bytecode::invokevirtual => invokevirtual(execution_context)?,
+            // bytecode 140...141: INVOKESPECIAL, INVOKESTATIC
+            bytecode::invokespecial | bytecode::invokestatic => {
+                invokestatic_special(execution_context, current_opcode)?;
+            }
+            // bytecode 142: INVOKEINTERFACE
+            bytecode::invokeinterface => invokeinterface(execution_context)?,
+            // bytecode 143: NEW
+            bytecode::new => new_instance(execution_context)?,
+            // bytecode 144: NEWARRAY
+            bytecode::newarray => {
+                let atype = execution_context.bytecode_fetcher.fetch_b()? as u8;
+                let type_ = array_type(atype)?;
+                // arrays of integers are only available with the integer instruction set
+                if type_ == constants::PrimitiveType::INTEGER && !execution_context.int_support {
+                    return Err(InterpreterError::UnsupportedBytecode);
+                }
+                newarray(execution_context, type_, None)?;
+            }
+            // bytecode 145: ANEWARRAY
+            bytecode::anewarray => anewarray(execution_context)?,
+            // bytecode 146: ARRAYLENGTH
+            bytecode::arraylength => arraylength(execution_context)?,
+            // bytecode 147: ATHROW
+            bytecode::athrow => athrow(execution_context)?,
+            // bytecode 148: CHECKCAST
+            bytecode::checkcast => checkcast(execution_context)?,
+            // bytecode 149: INSTANCEOF
+            bytecode::instanceof => instanceof(execution_context)?,
+            // bytecode 150: SINC_W
+            bytecode::sinc_w => {
+                let index = execution_context.bytecode_fetcher.fetch_b()? as u8;
+                let constant = execution_context.bytecode_fetcher.fetch_s()?;
+                sinc(execution_context, index, constant)?;
+            }
+            // bytecode 151: IINC_W
+            bytecode::iinc_w => {
+                let index = execution_context.bytecode_fetcher.fetch_b()? as u8;
+                let constant = execution_context.bytecode_fetcher.fetch_s()?;
+                iinc(execution_context, index, constant)?;
+            }
+            // bytecode 152...167: wide conditional branches
+            bytecode::ifeq_w
+            | bytecode::ifne_w
+            | bytecode::iflt_w
+            | bytecode::ifge_w
+            | bytecode::ifgt_w
+            | bytecode::ifle_w
+            | bytecode::ifnull_w
+            | bytecode::ifnonnull_w
+            | bytecode::if_acmpeq_w
+            | bytecode::if_acmpne_w
+            | bytecode::if_scmpeq_w
+            | bytecode::if_scmpne_w
+            | bytecode::if_scmplt_w
+            | bytecode::if_scmpge_w
+            | bytecode::if_scmpgt_w
+            | bytecode::if_scmple_w => {
+                conditional_branch(execution_context, current_opcode, opcode_offset)?;
+            }
+            // bytecode 168: GOTO_W
+            bytecode::goto_w => {
+                let branch = execution_context.bytecode_fetcher.fetch_s()?;
+                execution_context
+                    .bytecode_fetcher
+                    .branch(opcode_offset, branch)?;
+            }
+            // bytecode 169...172: GETFIELD_A_W, GETFIELD_B_W, GETFIELD_S_W, GETFIELD_I_W
+            bytecode::getfield_a_w
+            | bytecode::getfield_b_w
+            | bytecode::getfield_s_w
+            | bytecode::getfield_i_w => {
+                let index = execution_context.bytecode_fetcher.fetch_s()? as u16;
+                getfield(
+                    execution_context,
+                    field_type(current_opcode as u8 - bytecode::getfield_a_w as u8),
+                    index,
+                    false,
+                )?;
+            }
+            // bytecode 173...176: GETFIELD_A_THIS, GETFIELD_B_THIS, GETFIELD_S_THIS, GETFIELD_I_THIS
+            bytecode::getfield_a_this
+            | bytecode::getfield_b_this
+            | bytecode::getfield_s_this
+            | bytecode::getfield_i_this => {
+                let index = execution_context.bytecode_fetcher.fetch_b()? as u8;
+                getfield(
+                    execution_context,
+                    field_type(current_opcode as u8 - bytecode::getfield_a_this as u8),
+                    u16::from(index),
+                    true,
+                )?;
+            }
+            // bytecode 177...180: PUTFIELD_A_W, PUTFIELD_B_W, PUTFIELD_S_W, PUTFIELD_I_W
+            bytecode::putfield_a_w
+            | bytecode::putfield_b_w
+            | bytecode::putfield_s_w
+            | bytecode::putfield_i_w => {
+                let index = execution_context.bytecode_fetcher.fetch_s()? as u16;
+                putfield(
+                    execution_context,
+                    field_type(current_opcode as u8 - bytecode::putfield_a_w as u8),
+                    index,
+                    false,
+                )?;
+            }
+            // bytecode 181...184: PUTFIELD_A_THIS, PUTFIELD_B_THIS, PUTFIELD_S_THIS, PUTFIELD_I_THIS
+            bytecode::putfield_a_this
+            | bytecode::putfield_b_this
+            | bytecode::putfield_s_this
+            | bytecode::putfield_i_this => {
+                let index = execution_context.bytecode_fetcher.fetch_b()? as u8;
+                putfield(
+                    execution_context,
+                    field_type(current_opcode as u8 - bytecode::putfield_a_this as u8),
+                    u16::from(index),
+                    true,
+                )?;
+            }
+            // bytecode 186: IMPDEP1, followed by the identifier of a native method
+            bytecode::impdep1 => {
+                let id = execution_context.bytecode_fetcher.fetch_b()? as u8;
+                invoke_native(execution_context, id)?;
+            }
+            _ => return Err(InterpreterError::UnsupportedBytecode),
         }
     }
 
@@ -2,7 +2,17 @@ use bytecodes::bytecode;
 use context::Context;
 use stack::StackEntry;
 use constants;
-use exceptions::{throw_exception, throw_exception_from_interpretererror, InterpreterException};
+use jcvmerrors::InterpreterError;
+use constantpool::ConstantPoolEntry;
+use classes::{ClassRef, OBJECT_CLASS};
+use methods::MethodHeader;
+use objects::JCVMObject;
+use frame::Frame;
+use staticfields::PackageRef;
+use firewall::{self, ObjectAccess};
+use transactions::{self, Location};
+use exceptions::{self, throw_exception, throw_exception_from_interpretererror,
+                 InterpreterException};
 use traits::{BufferAccessor, HasType};
 
 // macro allowing to simplify null reference check
@@ -17,34 +27,76 @@ macro_rules! check_null_reference {
     };
 }
 
+// macro returning the object designated by the (non null) reference, or throwing the
+// exception raised by the object manager when the reference designates no object
+macro_rules! get_object {
+    ($reference: expr, $ctx: ident) => {
+        match $ctx.object_manager.get_object($reference as usize) {
+            Ok(object) => object,
+            Err(e) => return throw_exception($ctx, e),
+        }
+    };
+}
+
+// macro throwing a SecurityException when the firewall denies the access to the (non null)
+// object referenced by the variable
+macro_rules! check_firewall {
+    ($variable: ident, $access: expr, $ctx: ident) => {
+        let object = get_object!($variable.value, $ctx);
+        if !firewall::is_accessible($ctx, object, $access)? {
+            return throw_exception($ctx, InterpreterException::SecurityException);
+        }
+    };
+}
+
+// macro throwing a SecurityException when the reference about to be stored in a field, a
+// static field or an array component designates a global array or a temporary entry point
+macro_rules! check_storable {
+    ($type_: expr, $value: expr, $ctx: ident) => {
+        if $type_ == constants::PrimitiveType::REFERENCE && $value as i16 != constants::NULL_HANDLE
+        {
+            let object = get_object!($value, $ctx);
+            if !firewall::is_storable(object) {
+                return throw_exception($ctx, InterpreterException::SecurityException);
+            }
+        }
+    };
+}
+
 ///
 /// Manages aaload, baload, saload, iaload
 ///
 pub fn xaload(
     execution_context: &mut Context,
     type_: constants::PrimitiveType,
-) -> Result<(), InterpreterException> {
-    let arrayref = execution_context
-        .operand_stack
-        .pop_check_type(constants::PrimitiveType::REFERENCE)
-        .unwrap();
+) -> Result<(), InterpreterError> {
+    // ..., arrayref, index
     let index = execution_context
         .operand_stack
-        .pop_check_type(constants::PrimitiveType::SHORT)
-        .unwrap();
+        .pop_check_type(constants::PrimitiveType::SHORT)?;
+    let arrayref = execution_context
+        .operand_stack
+        .pop_check_type(constants::PrimitiveType::REFERENCE)?;
 
     check_null_reference!(arrayref, execution_context);
+    check_firewall!(arrayref, ObjectAccess::Array, execution_context);
 
     let associated_reference = execution_context
         .object_manager
         .get_object(arrayref.value as usize);
 
     if let Ok(e) = associated_reference {
-        // consistency check to make sure it is an array
-        assert!(e.is_array());
-
-        // in case of arrays, the primitive type represents the type of its elements
-        assert!(e.is_of_type(type_));
+        // consistency check to make sure it is an array, and in case of arrays, the
+        // primitive type represents the type of its elements
+        if !e.is_array() || !e.is_of_type(type_) {
+            return Err(InterpreterError::InvalidOperand);
+        }
+        if index.value < 0 || index.value >= e.array_length() {
+            return throw_exception(
+                execution_context,
+                InterpreterException::ArrayIndexOutOfBoundsException,
+            );
+        }
 
         match type_ {
             // for short and references, we perform thee same type of checks and
@@ -56,7 +108,7 @@ pub fn xaload(
                     Ok(res) => {
                         execution_context
                             .operand_stack
-                            .push(StackEntry::from_values(res, type_));
+                            .push(StackEntry::from_values(res, type_))?;
                     }
                     Err(e) => {
                         return throw_exception_from_interpretererror(execution_context, e);
@@ -67,9 +119,9 @@ pub fn xaload(
             constants::PrimitiveType::BYTE => {
                 let size_one_entry = constants::BYTE_SIZE;
                 match e.read_b((index.value as usize) * size_one_entry) {
-                    // retrieve v(alue of the reference of the array
+                    // the byte is sign-extended to a short
                     Ok(res) => {
-                        execution_context.operand_stack.bpush(res);
+                        execution_context.operand_stack.spush(i16::from(res))?;
                     }
                     Err(e) => return throw_exception_from_interpretererror(execution_context, e),
                 }
@@ -81,18 +133,18 @@ pub fn xaload(
                 match e.read_i((index.value as usize) * size_one_entry) {
                     // retrieve value of the reference of the array
                     Ok(res) => {
-                        execution_context.operand_stack.ipush(res);
+                        execution_context.operand_stack.ipush(res)?;
                     }
                     Err(e) => return throw_exception_from_interpretererror(execution_context, e),
                 }
             }
 
-            constants::PrimitiveType::UNKNOWN => {
-                panic!("Unknown type !");
+            constants::PrimitiveType::UNKNOWN | constants::PrimitiveType::RETURNADDRESS => {
+                return Err(InterpreterError::InvalidOperand);
             }
         }
-    } else {
-        return throw_exception(execution_context, associated_reference.err().unwrap());
+    } else if let Err(e) = associated_reference {
+        return throw_exception(execution_context, e);
     }
 
     Ok(())
@@ -101,92 +153,1265 @@ pub fn xaload(
 ///
 /// Manages astore, sstore, istore and assoiated xstore_x (because index is passed as parameter)
 ///
-pub fn xstore(execution_context: &mut Context, index: u8, type_: constants::PrimitiveType) {
+pub fn xstore(
+    execution_context: &mut Context,
+    index: u8,
+    type_: constants::PrimitiveType,
+) -> Result<(), InterpreterError> {
     match type_ {
         // storing shorts and references follow the same pattern
         constants::PrimitiveType::SHORT | constants::PrimitiveType::REFERENCE => {
+            // astore also stores the return addresses pushed by jsr
+            let stored_type = match execution_context.operand_stack.top() {
+                Ok(ref entry)
+                    if type_ == constants::PrimitiveType::REFERENCE
+                        && entry.is_of_type(constants::PrimitiveType::RETURNADDRESS) =>
+                {
+                    constants::PrimitiveType::RETURNADDRESS
+                }
+                _ => type_,
+            };
             // pop and check the type loaded from stack
             let value_to_put = execution_context
                 .operand_stack
-                .pop_check_type(type_)
-                .unwrap();
+                .pop_check_type(stored_type)?;
             //update local variable
             execution_context
-                .current_frame_mut()
-                .unwrap()
+                .current_frame_mut()?
                 .set_local(index as i16, value_to_put)
-                .unwrap();
         }
         // for integers, we pop and check 2 times on the stack
         constants::PrimitiveType::INTEGER => {
             let value_to_put1 = execution_context
                 .operand_stack
-                .pop_check_type(type_)
-                .unwrap();
+                .pop_check_type(type_)?;
 
             let value_to_put2 = execution_context
                 .operand_stack
-                .pop_check_type(type_)
-                .unwrap();
+                .pop_check_type(type_)?;
             // ... and we update 2 indexes in local variables stack
-            execution_context
-                .current_frame_mut()
-                .unwrap()
-                .set_local(index as i16, value_to_put1)
-                .unwrap();
-
-            execution_context
-                .current_frame_mut()
-                .unwrap()
-                .set_local((index + 1) as i16, value_to_put2)
-                .unwrap();
+            let frame = execution_context.current_frame_mut()?;
+            frame.set_local(index as i16, value_to_put1)?;
+            frame.set_local(i16::from(index) + 1, value_to_put2)
         }
 
-        _ => panic!("Unknown type"),
-    };
+        _ => Err(InterpreterError::InvalidOperand),
+    }
 }
 
 ///
-/// Manages astore, sstore, istore and assoiated xstore_x (because index is passed as parameter)
-/// Note: for aaload, some supplementary checks are performed to ensure consistency of the operaton
+/// Manages jsr: pushes the offset of the next opcode as a return address and jumps to the
+/// subroutine
+///
+pub fn jsr(execution_context: &mut Context, opcode_offset: usize) -> Result<(), InterpreterError> {
+    let branch = execution_context.bytecode_fetcher.fetch_s()?;
+    let return_address = execution_context.bytecode_fetcher.current_offset();
+    if return_address > u16::MAX as usize {
+        return Err(InterpreterError::InvalidBranchTarget);
+    }
+    execution_context.operand_stack.push(StackEntry::from_values(
+        return_address as i16,
+        constants::PrimitiveType::RETURNADDRESS,
+    ))?;
+    execution_context
+        .bytecode_fetcher
+        .branch(opcode_offset, branch)
+}
+
+///
+/// Manages ret: returns from a subroutine to the address stored in the given local.
+/// Only values pushed by jsr are accepted, so that shorts cannot be used as jump targets
+///
+pub fn ret(execution_context: &mut Context, index: u8) -> Result<(), InterpreterError> {
+    let return_address = execution_context
+        .current_frame()?
+        .get_local_check_type(index as i16, constants::PrimitiveType::RETURNADDRESS)?;
+    execution_context
+        .bytecode_fetcher
+        .branch_absolute(return_address.value as u16 as usize)
+}
+
+///
+/// Manages aastore, bastore, sastore and iastore
+/// Note: for aastore, some supplementary checks are performed to ensure consistency of the operaton
 /// See chapter 7.5.2 from JCVM specification for more details
 ///
 pub fn xastore(
     execution_context: &mut Context,
     type_: constants::PrimitiveType,
-) -> Result<(), InterpreterException> {
+) -> Result<(), InterpreterError> {
     // in stack:
-    // array ref
-    // index
     // value
-    // first, pop the array reference and check it is not null
+    // index
+    // array ref
+    let value = match type_ {
+        constants::PrimitiveType::REFERENCE => i32::from(
+            execution_context
+                .operand_stack
+                .pop_check_type(constants::PrimitiveType::REFERENCE)?
+                .value,
+        ),
+        constants::PrimitiveType::INTEGER => execution_context.operand_stack.ipop()?,
+        // bytes and shorts are truncated to the size of the elements
+        _ => i32::from(execution_context.operand_stack.spop()?),
+    };
+    let index = execution_context.operand_stack.spop()?;
     let array_ref = execution_context
         .operand_stack
-        .pop_check_type(constants::PrimitiveType::REFERENCE)
-        .unwrap();
+        .pop_check_type(constants::PrimitiveType::REFERENCE)?;
 
     check_null_reference!(array_ref, execution_context);
 
-    // make sure it is an array of the correct type
-    let array = execution_context
+    let (accessible, array_length, component_class) = match execution_context
         .object_manager
         .get_object(array_ref.value as usize)
-        .unwrap();
+    {
+        Ok(array) => {
+            // make sure it is an array of the correct type
+            if !array.is_array() || !array.is_of_type(type_) {
+                return Err(InterpreterError::InvalidOperand);
+            }
+            (
+                firewall::is_accessible(execution_context, array, ObjectAccess::Array)?,
+                array.array_length(),
+                array.component_class(),
+            )
+        }
+        Err(e) => return throw_exception(execution_context, e),
+    };
 
-    // check that is is really an array
-    if !array.is_array() || !array.is_of_type(type_) {
-        return Err(InterpreterException::SecurityException);
+    if !accessible {
+        return throw_exception(execution_context, InterpreterException::SecurityException);
+    }
+    if index < 0 || index >= array_length {
+        return throw_exception(
+            execution_context,
+            InterpreterException::ArrayIndexOutOfBoundsException,
+        );
     }
 
-    let index = execution_context
+    if type_ == constants::PrimitiveType::REFERENCE && value as i16 != constants::NULL_HANDLE {
+        let stored = match execution_context
+            .object_manager
+            .get_object(value as usize)
+        {
+            Ok(object) => object,
+            Err(e) => return throw_exception(execution_context, e),
+        };
+        // references to global arrays and temporary entry points cannot be stored
+        if stored.is_temporary() {
+            return throw_exception(execution_context, InterpreterException::SecurityException);
+        }
+        if !is_assignment_compatible(execution_context, stored, component_class)? {
+            return throw_exception(execution_context, InterpreterException::ArrayStoreException);
+        }
+    }
+
+    let offset = index as usize * type_.size();
+    transactions::write(
+        execution_context,
+        Location::Object(array_ref.value),
+        offset,
+        type_,
+        value,
+    )
+}
+
+///
+/// Indicates whether the given object can be stored in an array whose elements are
+/// instances of component_class (see aastore in chapter 7.5.2 of the JCVM specification)
+///
+fn is_assignment_compatible(
+    execution_context: &Context,
+    object: &JCVMObject,
+    component_class: Option<ClassRef>,
+) -> Result<bool, InterpreterError> {
+    let target = component_class.unwrap_or(OBJECT_CLASS);
+    match object.class_ref() {
+        Some(class_ref) => execution_context
+            .class_manager
+            .is_assignable(class_ref, target),
+        // arrays are only compatible with java.lang.Object
+        None => Ok(target == OBJECT_CLASS),
+    }
+}
+
+///
+/// Manages sadd, ssub, smul, sdiv, srem, sshl, sshr, sushr, sand, sor and sxor
+/// Note: all operations wrap around on overflow, as required by the JCVM specification
+///
+pub fn sbinop(
+    execution_context: &mut Context,
+    operation: bytecode,
+) -> Result<(), InterpreterError> {
+    // in stack:
+    // value2
+    // value1
+    let value2 = execution_context.operand_stack.spop()?;
+    let value1 = execution_context.operand_stack.spop()?;
+
+    // only the 5 lowest bits are used by shift operations
+    let shift = (value2 & 0x1F) as u32;
+
+    let res = match operation {
+        bytecode::sadd => value1.wrapping_add(value2),
+        bytecode::ssub => value1.wrapping_sub(value2),
+        bytecode::smul => value1.wrapping_mul(value2),
+        bytecode::sdiv | bytecode::srem => {
+            if value2 == 0 {
+                return throw_exception(
+                    execution_context,
+                    InterpreterException::ArithmeticException,
+                );
+            }
+
+            if operation == bytecode::sdiv {
+                value1.wrapping_div(value2)
+            } else {
+                value1.wrapping_rem(value2)
+            }
+        }
+        // shifts are performed on the int representation of value1 then truncated to a short
+        bytecode::sshl => (i32::from(value1) << shift) as i16,
+        bytecode::sshr => (i32::from(value1) >> shift) as i16,
+        bytecode::sushr => ((i32::from(value1) as u32) >> shift) as i16,
+        bytecode::sand => value1 & value2,
+        bytecode::sor => value1 | value2,
+        bytecode::sxor => value1 ^ value2,
+        _ => return Err(InterpreterError::UnrecognizedBytecode),
+    };
+
+    execution_context.operand_stack.spush(res)?;
+    Ok(())
+}
+
+///
+/// Manages sinc and sinc_w (the constant is fetched by the caller as it depends on the opcode)
+///
+pub fn sinc(
+    execution_context: &mut Context,
+    index: u8,
+    constant: i16,
+) -> Result<(), InterpreterError> {
+    let local = execution_context
+        .current_frame()?
+        .get_local_check_type(i16::from(index), constants::PrimitiveType::SHORT)?;
+
+    execution_context.current_frame_mut()?.set_local(
+        i16::from(index),
+        StackEntry::from_values(
+            local.value.wrapping_add(constant),
+            constants::PrimitiveType::SHORT,
+        ),
+    )
+}
+
+///
+/// Manages iadd, isub, imul, idiv, irem, ishl, ishr, iushr, iand, ior and ixor
+/// Note: all operations wrap around on overflow, as required by the JCVM specification
+///
+pub fn ibinop(
+    execution_context: &mut Context,
+    operation: bytecode,
+) -> Result<(), InterpreterError> {
+    // in stack:
+    // value2 (2 slots)
+    // value1 (2 slots)
+    let value2 = execution_context.operand_stack.ipop()?;
+    let value1 = execution_context.operand_stack.ipop()?;
+
+    // only the 5 lowest bits are used by shift operations
+    let shift = (value2 & 0x1F) as u32;
+
+    let res = match operation {
+        bytecode::iadd => value1.wrapping_add(value2),
+        bytecode::isub => value1.wrapping_sub(value2),
+        bytecode::imul => value1.wrapping_mul(value2),
+        bytecode::idiv | bytecode::irem => {
+            if value2 == 0 {
+                return throw_exception(
+                    execution_context,
+                    InterpreterException::ArithmeticException,
+                );
+            }
+
+            if operation == bytecode::idiv {
+                value1.wrapping_div(value2)
+            } else {
+                value1.wrapping_rem(value2)
+            }
+        }
+        bytecode::ishl => value1 << shift,
+        bytecode::ishr => value1 >> shift,
+        bytecode::iushr => ((value1 as u32) >> shift) as i32,
+        bytecode::iand => value1 & value2,
+        bytecode::ior => value1 | value2,
+        bytecode::ixor => value1 ^ value2,
+        _ => return Err(InterpreterError::UnrecognizedBytecode),
+    };
+
+    execution_context.operand_stack.ipush(res)?;
+    Ok(())
+}
+
+///
+/// Manages iinc and iinc_w (the constant is fetched by the caller as it depends on the opcode)
+///
+pub fn iinc(
+    execution_context: &mut Context,
+    index: u8,
+    constant: i16,
+) -> Result<(), InterpreterError> {
+    // the 2 MSB are stored at index, the 2 LSB at index + 1
+    let high = execution_context
+        .current_frame()?
+        .get_local_check_type(i16::from(index), constants::PrimitiveType::INTEGER)?;
+    let low = execution_context
+        .current_frame()?
+        .get_local_check_type(i16::from(index) + 1, constants::PrimitiveType::INTEGER)?;
+
+    let value = ((u32::from(high.value as u16) << 16) | u32::from(low.value as u16)) as i32;
+    let res = value.wrapping_add(i32::from(constant));
+
+    let frame = execution_context.current_frame_mut()?;
+    frame.set_local(
+        i16::from(index),
+        StackEntry::from_values((res >> 16) as i16, constants::PrimitiveType::INTEGER),
+    )?;
+    frame.set_local(
+        i16::from(index) + 1,
+        StackEntry::from_values((res & 0xFFFF) as i16, constants::PrimitiveType::INTEGER),
+    )
+}
+
+///
+/// Manages all the conditional branches (ifxx, ifnull, ifnonnull, if_acmpxx, if_scmpxx)
+/// and their wide counterparts. The branch offset is relative to the opcode offset.
+///
+pub fn conditional_branch(
+    execution_context: &mut Context,
+    operation: bytecode,
+    opcode_offset: usize,
+) -> Result<(), InterpreterError> {
+    // wide variants only differ by the size of their branch offset
+    let wide = operation as u8 >= bytecode::ifeq_w as u8;
+    let branch = if wide {
+        execution_context.bytecode_fetcher.fetch_s()?
+    } else {
+        i16::from(execution_context.bytecode_fetcher.fetch_b()?)
+    };
+    let narrow_operation = if wide {
+        bytecode::from(operation as u8 - bytecode::ifeq_w as u8 + bytecode::ifeq as u8)?
+    } else {
+        operation
+    };
+
+    let condition = match narrow_operation {
+        // comparisons with zero
+        bytecode::ifeq => execution_context.operand_stack.spop()? == 0,
+        bytecode::ifne => execution_context.operand_stack.spop()? != 0,
+        bytecode::iflt => execution_context.operand_stack.spop()? < 0,
+        bytecode::ifge => execution_context.operand_stack.spop()? >= 0,
+        bytecode::ifgt => execution_context.operand_stack.spop()? > 0,
+        bytecode::ifle => execution_context.operand_stack.spop()? <= 0,
+        // comparisons with null
+        bytecode::ifnull | bytecode::ifnonnull => {
+            let reference = execution_context
+                .operand_stack
+                .pop_check_type(constants::PrimitiveType::REFERENCE)?;
+            (reference.value == constants::NULL_HANDLE) == (narrow_operation == bytecode::ifnull)
+        }
+        // comparisons of references
+        bytecode::if_acmpeq | bytecode::if_acmpne => {
+            let value2 = execution_context
+                .operand_stack
+                .pop_check_type(constants::PrimitiveType::REFERENCE)?;
+            let value1 = execution_context
+                .operand_stack
+                .pop_check_type(constants::PrimitiveType::REFERENCE)?;
+            (value1.value == value2.value) == (narrow_operation == bytecode::if_acmpeq)
+        }
+        // comparisons of shorts
+        _ => {
+            let value2 = execution_context.operand_stack.spop()?;
+            let value1 = execution_context.operand_stack.spop()?;
+            match narrow_operation {
+                bytecode::if_scmpeq => value1 == value2,
+                bytecode::if_scmpne => value1 != value2,
+                bytecode::if_scmplt => value1 < value2,
+                bytecode::if_scmpge => value1 >= value2,
+                bytecode::if_scmpgt => value1 > value2,
+                bytecode::if_scmple => value1 <= value2,
+                _ => return Err(InterpreterError::UnrecognizedBytecode),
+            }
+        }
+    };
+
+    if condition {
+        execution_context
+            .bytecode_fetcher
+            .branch(opcode_offset, branch)?;
+    }
+
+    Ok(())
+}
+
+///
+/// fetches one switch key (short or integer) from the bytecode
+///
+fn fetch_switch_key(
+    execution_context: &mut Context,
+    type_: constants::PrimitiveType,
+) -> Result<i32, InterpreterError> {
+    match type_ {
+        constants::PrimitiveType::INTEGER => execution_context.bytecode_fetcher.fetch_i(),
+        _ => Ok(i32::from(execution_context.bytecode_fetcher.fetch_s()?)),
+    }
+}
+
+///
+/// pops the value switched on (short or integer) from the operand stack
+///
+fn pop_switch_key(
+    execution_context: &mut Context,
+    type_: constants::PrimitiveType,
+) -> Result<i32, InterpreterError> {
+    match type_ {
+        constants::PrimitiveType::INTEGER => execution_context.operand_stack.ipop(),
+        _ => Ok(i32::from(execution_context.operand_stack.spop()?)),
+    }
+}
+
+///
+/// Manages stableswitch and itableswitch
+/// Layout: default (2 bytes), low and high (2 or 4 bytes each), high - low + 1 branch offsets
+///
+pub fn xtableswitch(
+    execution_context: &mut Context,
+    type_: constants::PrimitiveType,
+    opcode_offset: usize,
+) -> Result<(), InterpreterError> {
+    let index = pop_switch_key(execution_context, type_)?;
+
+    let default = execution_context.bytecode_fetcher.fetch_s()?;
+    let low = fetch_switch_key(execution_context, type_)?;
+    let high = fetch_switch_key(execution_context, type_)?;
+    if low > high {
+        return Err(InterpreterError::MalformedSwitchTable);
+    }
+
+    // the whole table is read so that truncated tables are always rejected
+    let mut branch = default;
+    for current in i64::from(low)..=i64::from(high) {
+        let offset = execution_context.bytecode_fetcher.fetch_s()?;
+        if current == i64::from(index) {
+            branch = offset;
+        }
+    }
+
+    execution_context
+        .bytecode_fetcher
+        .branch(opcode_offset, branch)
+}
+
+///
+/// Manages slookupswitch and ilookupswitch
+/// Layout: default (2 bytes), npairs (2 bytes), npairs (match, offset) sorted by match
+///
+pub fn xlookupswitch(
+    execution_context: &mut Context,
+    type_: constants::PrimitiveType,
+    opcode_offset: usize,
+) -> Result<(), InterpreterError> {
+    let key = pop_switch_key(execution_context, type_)?;
+
+    let default = execution_context.bytecode_fetcher.fetch_s()?;
+    let npairs = execution_context.bytecode_fetcher.fetch_s()? as u16;
+
+    let mut branch = default;
+    let mut previous_match: Option<i32> = None;
+    for _ in 0..npairs {
+        let current_match = fetch_switch_key(execution_context, type_)?;
+        let offset = execution_context.bytecode_fetcher.fetch_s()?;
+
+        // the matches shall be sorted in increasing order
+        if let Some(previous) = previous_match {
+            if current_match <= previous {
+                return Err(InterpreterError::MalformedSwitchTable);
+            }
+        }
+        previous_match = Some(current_match);
+
+        if current_match == key {
+            branch = offset;
+        }
+    }
+
+    execution_context
+        .bytecode_fetcher
+        .branch(opcode_offset, branch)
+}
+
+///
+/// Manages dup, dup2 and dup_x: duplicates the top m words and inserts them n words down
+/// in the operand stack (n = 0 simply pushes them). Types of the words are preserved.
+///
+pub fn dup_x(execution_context: &mut Context, m: u8, n: u8) -> Result<(), InterpreterError> {
+    if !(1..=4).contains(&m) || (n != 0 && !(m..=m + 4).contains(&n)) {
+        return Err(InterpreterError::InvalidOperand);
+    }
+
+    let mut words = Vec::with_capacity(m as usize);
+    for index in 0..m {
+        words.push(execution_context.operand_stack.peek_index(i16::from(index))?);
+    }
+
+    // the copy of the deepest word is inserted first so that the order is kept
+    for word in words.into_iter().rev() {
+        execution_context
+            .operand_stack
+            .insert_index(i16::from(n), word)?;
+    }
+    Ok(())
+}
+
+///
+/// Manages swap_x: swaps the top m words with the n words immediately beneath them.
+/// Types of the words are preserved.
+///
+pub fn swap_x(execution_context: &mut Context, m: u8, n: u8) -> Result<(), InterpreterError> {
+    if !(1..=2).contains(&m) || !(1..=2).contains(&n) {
+        return Err(InterpreterError::InvalidOperand);
+    }
+
+    let total = i16::from(m + n);
+    let mut words = Vec::with_capacity(total as usize);
+    for index in 0..total {
+        words.push(execution_context.operand_stack.peek_index(index)?);
+    }
+
+    // the n words end up on top, followed by the m words
+    for index in 0..total {
+        let source = (index + i16::from(m)) % total;
+        execution_context
+            .operand_stack
+            .update_index(index, words[source as usize])?;
+    }
+    Ok(())
+}
+
+///
+/// Returns the reference nargs - 1 words down in the operand stack
+/// (i.e. the 'this' argument of an instance method)
+///
+fn peek_this(execution_context: &Context, nargs: u8) -> Result<StackEntry, InterpreterError> {
+    if nargs == 0 {
+        return Err(InterpreterError::InvalidOperand);
+    }
+    execution_context
+        .operand_stack
+        .peek_index_check_type(i16::from(nargs) - 1, constants::PrimitiveType::REFERENCE)
+}
+
+///
+/// Invokes the method whose header is located at method_offset: the arguments are moved
+/// from the operand stack to the locals of a new frame, then the execution continues
+/// with the first opcode of the method
+///
+pub fn invoke_method(
+    execution_context: &mut Context,
+    method_offset: usize,
+) -> Result<(), InterpreterError> {
+    let context = execution_context.current_context;
+    let applet = execution_context.current_applet;
+    invoke_method_in_context(execution_context, method_offset, context, applet)
+}
+
+///
+/// Invokes the method as invoke_method does, the method being executed in the given context
+/// on behalf of the given applet instance (the context and the applet of the caller are
+/// restored when the method returns)
+///
+pub fn invoke_method_in_context(
+    execution_context: &mut Context,
+    method_offset: usize,
+    context: i16,
+    applet: i16,
+) -> Result<(), InterpreterError> {
+    let header = MethodHeader::read(&execution_context.bytecode_fetcher, method_offset)?;
+    if header.is_abstract() {
+        return Err(InterpreterError::UnresolvedMethod);
+    }
+
+    // the deepest argument becomes local 0
+    let nargs = header.nargs as usize;
+    let operand_stack_base = execution_context
+        .operand_stack
+        .len()
+        .checked_sub(nargs)
+        .ok_or(InterpreterError::StackUnderflowError)?;
+    let mut args = Vec::with_capacity(nargs);
+    for index in (0..nargs).rev() {
+        args.push(execution_context.operand_stack.peek_index(index as i16)?);
+    }
+    execution_context.operand_stack.truncate(operand_stack_base);
+
+    // integers have their 2 MSB on top of the operand stack, but at the lowest index
+    // in locals (see xstore), so both halves are swapped
+    let mut index = 0;
+    while index < nargs {
+        if index + 1 < nargs && args[index].is_of_type(constants::PrimitiveType::INTEGER) {
+            args.swap(index, index + 1);
+            index += 2;
+        } else {
+            index += 1;
+        }
+    }
+
+    let mut new_frame = Frame::from_method(
+        header,
+        execution_context.bytecode_fetcher.current_offset(),
+        operand_stack_base,
+        execution_context.current_context,
+        execution_context.current_applet,
+    );
+    new_frame.set_type_checks(execution_context.is_defensive());
+    for (index, arg) in args.into_iter().enumerate() {
+        new_frame.set_local(index as i16, arg)?;
+    }
+    execution_context.frame_stack.push(new_frame);
+    execution_context.current_context = context;
+    execution_context.current_applet = applet;
+
+    execution_context.set_method_bounds(Some(header))?;
+    execution_context
+        .bytecode_fetcher
+        .jump(header.code_offset)
+}
+
+///
+/// Invokes an instance method of the (non null) object referenced by objectref. The method is
+/// executed in the context owning the object, which is another context for the methods of
+/// shareable interface objects and JCRE entry points (methods of arrays never switch context)
+///
+fn invoke_instance_method(
+    execution_context: &mut Context,
+    method_offset: usize,
+    objectref: StackEntry,
+) -> Result<(), InterpreterError> {
+    let (context, applet) = {
+        let object = get_object!(objectref.value, execution_context);
+        if object.is_array() || object.owner() == execution_context.current_context {
+            // the active applet only changes along with the context
+            (
+                execution_context.current_context,
+                execution_context.current_applet,
+            )
+        } else {
+            (
+                object.owner(),
+                execution_context
+                    .applet_manager
+                    .applet_invoked(objectref.value, object),
+            )
+        }
+    };
+    invoke_method_in_context(execution_context, method_offset, context, applet)
+}
+
+///
+/// Manages invokestatic and invokespecial (the constant pool index is fetched here)
+///
+pub fn invokestatic_special(
+    execution_context: &mut Context,
+    operation: bytecode,
+) -> Result<(), InterpreterError> {
+    let index = execution_context.bytecode_fetcher.fetch_s()? as u16;
+
+    let method_offset = match execution_context.constant_pool_entry(index)? {
+        ConstantPoolEntry::StaticMethodRef(method_offset) => method_offset,
+        // super.method(): resolution starts from the superclass
+        ConstantPoolEntry::SuperMethodRef(class_ref, token) if operation == bytecode::invokespecial => {
+            let super_class = execution_context
+                .class_manager
+                .get_class(class_ref)?
+                .super_class()
+                .ok_or(InterpreterError::UnresolvedMethod)?;
+            execution_context
+                .class_manager
+                .resolve_virtual_method(super_class, token)?
+        }
+        _ => return Err(InterpreterError::InvalidConstantPoolEntry),
+    };
+
+    if operation == bytecode::invokespecial {
+        // instance methods (constructors, private and super methods) need a valid 'this'
+        let header = MethodHeader::read(&execution_context.bytecode_fetcher, method_offset)?;
+        let objectref = peek_this(execution_context, header.nargs)?;
+        check_null_reference!(objectref, execution_context);
+        check_firewall!(objectref, ObjectAccess::Method, execution_context);
+        return invoke_instance_method(execution_context, method_offset, objectref);
+    }
+
+    invoke_method(execution_context, method_offset)
+}
+
+///
+/// Manages invokevirtual: the method is selected from the class of the object
+///
+pub fn invokevirtual(execution_context: &mut Context) -> Result<(), InterpreterError> {
+    let index = execution_context.bytecode_fetcher.fetch_s()? as u16;
+
+    let (class_ref, token) = match execution_context.constant_pool_entry(index)? {
+        ConstantPoolEntry::VirtualMethodRef(class_ref, token) => (class_ref, token),
+        _ => return Err(InterpreterError::InvalidConstantPoolEntry),
+    };
+
+    // the declared method gives the number of arguments (and thus the position of 'this')
+    let declared_method = execution_context
+        .class_manager
+        .resolve_virtual_method(class_ref, token)?;
+    let header = MethodHeader::read(&execution_context.bytecode_fetcher, declared_method)?;
+
+    let objectref = peek_this(execution_context, header.nargs)?;
+    check_null_reference!(objectref, execution_context);
+    check_firewall!(objectref, ObjectAccess::Method, execution_context);
+
+    // arrays have no class
+    let method_offset = match get_object!(objectref.value, execution_context).class_ref() {
+        Some(object_class) => execution_context
+            .class_manager
+            .resolve_virtual_method(object_class, token)?,
+        None => declared_method,
+    };
+
+    invoke_instance_method(execution_context, method_offset, objectref)
+}
+
+///
+/// Manages invokeinterface: operands are nargs, the constant pool index of the interface
+/// and the interface method token
+///
+pub fn invokeinterface(execution_context: &mut Context) -> Result<(), InterpreterError> {
+    let nargs = execution_context.bytecode_fetcher.fetch_b()? as u8;
+    let index = execution_context.bytecode_fetcher.fetch_s()? as u16;
+    let token = execution_context.bytecode_fetcher.fetch_b()? as u8;
+
+    let interface = match execution_context.constant_pool_entry(index)? {
+        ConstantPoolEntry::ClassRef(interface) => interface,
+        _ => return Err(InterpreterError::InvalidConstantPoolEntry),
+    };
+
+    let objectref = peek_this(execution_context, nargs)?;
+    check_null_reference!(objectref, execution_context);
+    check_firewall!(objectref, ObjectAccess::Interface(interface), execution_context);
+
+    let object_class = get_object!(objectref.value, execution_context)
+        .class_ref()
+        .ok_or(InterpreterError::UnresolvedMethod)?;
+    let method_offset = execution_context
+        .class_manager
+        .resolve_interface_method(object_class, interface, token)?;
+
+    invoke_instance_method(execution_context, method_offset, objectref)
+}
+
+///
+/// Manages areturn, sreturn, ireturn and return (type_ is None in the latter case)
+/// The frame is removed, the returned value is pushed on the operand stack of the
+/// caller and the execution continues in the caller (if any)
+///
+pub fn xreturn(
+    execution_context: &mut Context,
+    type_: Option<constants::PrimitiveType>,
+) -> Result<(), InterpreterError> {
+    // returned words, top of stack first
+    let mut result = Vec::new();
+    match type_ {
+        Some(constants::PrimitiveType::INTEGER) => {
+            for _ in 0..2 {
+                result.push(execution_context
+                    .operand_stack
+                    .pop_check_type(constants::PrimitiveType::INTEGER)?);
+            }
+        }
+        Some(constants::PrimitiveType::SHORT) => {
+            let value = execution_context.operand_stack.spop()?;
+            result.push(StackEntry::from_values(
+                value,
+                constants::PrimitiveType::SHORT,
+            ));
+        }
+        Some(type_) => {
+            result.push(execution_context.operand_stack.pop_check_type(type_)?);
+        }
+        None => {}
+    }
+
+    let finished_frame = execution_context.frame_stack.pop()?;
+    execution_context.current_context = finished_frame.caller_context();
+    execution_context.current_applet = finished_frame.caller_applet();
+    execution_context
+        .operand_stack
+        .truncate(finished_frame.operand_stack_base());
+    for word in result.into_iter().rev() {
+        execution_context.operand_stack.push(word)?;
+    }
+
+    // go back to the caller, if any
+    let caller = match execution_context.frame_stack.top() {
+        Ok(caller) => caller.method(),
+        Err(_) => return Ok(()),
+    };
+    execution_context.set_method_bounds(caller)?;
+    execution_context
+        .bytecode_fetcher
+        .jump(finished_frame.return_address())
+}
+
+///
+/// Type of the field accessed by getfield/putfield opcodes, given the position of the opcode
+/// in its group (opcodes are always ordered reference, byte, short, int)
+///
+pub fn field_type(position: u8) -> constants::PrimitiveType {
+    match position {
+        0 => constants::PrimitiveType::REFERENCE,
+        1 => constants::PrimitiveType::BYTE,
+        2 => constants::PrimitiveType::SHORT,
+        _ => constants::PrimitiveType::INTEGER,
+    }
+}
+
+///
+/// Returns the reference the field is accessed through: local 0 for the _this variants,
+/// the reference popped from the operand stack otherwise
+///
+fn pop_field_objectref(
+    execution_context: &mut Context,
+    this: bool,
+) -> Result<StackEntry, InterpreterError> {
+    if this {
+        execution_context
+            .current_frame()?
+            .get_local_check_type(0, constants::PrimitiveType::REFERENCE)
+    } else {
+        execution_context
+            .operand_stack
+            .pop_check_type(constants::PrimitiveType::REFERENCE)
+    }
+}
+
+///
+/// Resolves the instance field at the given constant pool index and returns its offset in the
+/// object, which has to be an instance of the class declaring the field
+///
+fn resolve_instance_field(
+    execution_context: &Context,
+    index: u16,
+    object: &JCVMObject,
+) -> Result<usize, InterpreterError> {
+    let (class_ref, token) = match execution_context.constant_pool_entry(index)? {
+        ConstantPoolEntry::InstanceFieldRef(class_ref, token) => (class_ref, token),
+        _ => return Err(InterpreterError::InvalidConstantPoolEntry),
+    };
+
+    let object_class = object
+        .class_ref()
+        .ok_or(InterpreterError::InvalidClassReference)?;
+    if !execution_context
+        .class_manager
+        .is_subclass_of(object_class, class_ref)?
+    {
+        return Err(InterpreterError::InvalidClassReference);
+    }
+
+    execution_context
+        .class_manager
+        .instance_field_offset(class_ref, token)
+}
+
+///
+/// Manages getfield_<t>, getfield_<t>_w and getfield_<t>_this
+/// (the constant pool index is fetched by the caller as its size depends on the opcode)
+///
+pub fn getfield(
+    execution_context: &mut Context,
+    type_: constants::PrimitiveType,
+    index: u16,
+    this: bool,
+) -> Result<(), InterpreterError> {
+    let objectref = pop_field_objectref(execution_context, this)?;
+    check_null_reference!(objectref, execution_context);
+    check_firewall!(objectref, ObjectAccess::Field, execution_context);
+
+    let object = get_object!(objectref.value, execution_context);
+    let offset = resolve_instance_field(execution_context, index, object)?;
+
+    match type_ {
+        // bytes are sign-extended to shorts
+        constants::PrimitiveType::BYTE => {
+            execution_context
+                .operand_stack
+                .spush(i16::from(object.read_b(offset)?))?;
+        }
+        constants::PrimitiveType::INTEGER => {
+            execution_context.operand_stack.ipush(object.read_i(offset)?)?;
+        }
+        _ => {
+            execution_context
+                .operand_stack
+                .push(StackEntry::from_values(object.read_s(offset)?, type_))?;
+        }
+    }
+    Ok(())
+}
+
+///
+/// Manages putfield_<t>, putfield_<t>_w and putfield_<t>_this
+/// (the constant pool index is fetched by the caller as its size depends on the opcode)
+///
+pub fn putfield(
+    execution_context: &mut Context,
+    type_: constants::PrimitiveType,
+    index: u16,
+    this: bool,
+) -> Result<(), InterpreterError> {
+    // in stack:
+    // value (1 or 2 slots)
+    // objectref (except for the _this variants)
+    let value = match type_ {
+        constants::PrimitiveType::INTEGER => execution_context.operand_stack.ipop()?,
+        constants::PrimitiveType::REFERENCE => i32::from(
+            execution_context
+                .operand_stack
+                .pop_check_type(constants::PrimitiveType::REFERENCE)?
+                .value,
+        ),
+        _ => i32::from(execution_context.operand_stack.spop()?),
+    };
+    let objectref = pop_field_objectref(execution_context, this)?;
+    check_null_reference!(objectref, execution_context);
+    check_firewall!(objectref, ObjectAccess::Field, execution_context);
+    check_storable!(type_, value, execution_context);
+
+    let offset = {
+        let object = get_object!(objectref.value, execution_context);
+        resolve_instance_field(execution_context, index, object)?
+    };
+    // values are truncated to the size of the field
+    transactions::write(
+        execution_context,
+        Location::Object(objectref.value),
+        offset,
+        type_,
+        value,
+    )
+}
+
+///
+/// Resolves the static field at the given constant pool index
+/// Returns the package it belongs to and its offset in the static field image
+///
+fn resolve_static_field(
+    execution_context: &Context,
+    index: u16,
+) -> Result<(PackageRef, usize), InterpreterError> {
+    match execution_context.constant_pool_entry(index)? {
+        ConstantPoolEntry::StaticFieldRef(package, offset) => Ok((package, offset as usize)),
+        _ => Err(InterpreterError::InvalidConstantPoolEntry),
+    }
+}
+
+///
+/// Manages getstatic_a, getstatic_b, getstatic_s and getstatic_i
+///
+pub fn getstatic(
+    execution_context: &mut Context,
+    type_: constants::PrimitiveType,
+) -> Result<(), InterpreterError> {
+    let index = execution_context.bytecode_fetcher.fetch_s()? as u16;
+    let (package, offset) = resolve_static_field(execution_context, index)?;
+    let image = execution_context.static_fields.get_image(package)?;
+
+    match type_ {
+        // bytes are sign-extended to shorts
+        constants::PrimitiveType::BYTE => {
+            execution_context
+                .operand_stack
+                .spush(i16::from(image.read_b(offset)?))?;
+        }
+        constants::PrimitiveType::INTEGER => {
+            execution_context.operand_stack.ipush(image.read_i(offset)?)?;
+        }
+        _ => {
+            execution_context
+                .operand_stack
+                .push(StackEntry::from_values(image.read_s(offset)?, type_))?;
+        }
+    }
+    Ok(())
+}
+
+///
+/// Manages putstatic_a, putstatic_b, putstatic_s and putstatic_i
+///
+pub fn putstatic(
+    execution_context: &mut Context,
+    type_: constants::PrimitiveType,
+) -> Result<(), InterpreterError> {
+    let index = execution_context.bytecode_fetcher.fetch_s()? as u16;
+    let (package, offset) = resolve_static_field(execution_context, index)?;
+
+    let value = match type_ {
+        constants::PrimitiveType::INTEGER => execution_context.operand_stack.ipop()?,
+        constants::PrimitiveType::REFERENCE => i32::from(
+            execution_context
+                .operand_stack
+                .pop_check_type(constants::PrimitiveType::REFERENCE)?
+                .value,
+        ),
+        _ => i32::from(execution_context.operand_stack.spop()?),
+    };
+    check_storable!(type_, value, execution_context);
+    // values are truncated to the size of the field
+    transactions::write(
+        execution_context,
+        Location::Static(package),
+        offset,
+        type_,
+        value,
+    )
+}
+
+///
+/// Resolves the class at the given constant pool index
+///
+fn resolve_class(execution_context: &Context, index: u16) -> Result<ClassRef, InterpreterError> {
+    match execution_context.constant_pool_entry(index)? {
+        ConstantPoolEntry::ClassRef(class_ref) => Ok(class_ref),
+        _ => Err(InterpreterError::InvalidConstantPoolEntry),
+    }
+}
+
+///
+/// Manages new: creates an instance of the class at the given constant pool index, owned by
+/// the current context and the active applet, and pushes its reference
+///
+pub fn new_instance(execution_context: &mut Context) -> Result<(), InterpreterError> {
+    let index = execution_context.bytecode_fetcher.fetch_s()? as u16;
+    let class_ref = resolve_class(execution_context, index)?;
+    let size = execution_context.class_manager.instance_size(class_ref)?;
+    if size > i16::MAX as usize {
+        return throw_exception(
+            execution_context,
+            InterpreterException::SystemException(exceptions::NO_RESOURCE),
+        );
+    }
+
+    let mut instance = JCVMObject::new_instance(
+        execution_context.current_context,
+        0,
+        class_ref,
+        size as i16,
+        true,
+    );
+    instance.set_applet(execution_context.current_applet);
+    let objectref = execution_context.object_manager.add_object(instance);
+    execution_context.operand_stack.apush(objectref as i16)?;
+    Ok(())
+}
+
+///
+/// Type of the elements of the arrays created by newarray with the given atype
+///
+pub fn array_type(atype: u8) -> Result<constants::PrimitiveType, InterpreterError> {
+    match atype {
+        constants::T_BOOLEAN | constants::T_BYTE => Ok(constants::PrimitiveType::BYTE),
+        constants::T_SHORT => Ok(constants::PrimitiveType::SHORT),
+        constants::T_INT => Ok(constants::PrimitiveType::INTEGER),
+        _ => Err(InterpreterError::InvalidOperand),
+    }
+}
+
+///
+/// Manages newarray and anewarray (component_class is the class of the elements in the latter
+/// case): pops the number of elements, then creates the array owned by the current context
+/// and the active applet
+///
+pub fn newarray(
+    execution_context: &mut Context,
+    type_: constants::PrimitiveType,
+    component_class: Option<ClassRef>,
+) -> Result<(), InterpreterError> {
+    let count = execution_context.operand_stack.spop()?;
+    if count < 0 {
+        return throw_exception(
+            execution_context,
+            InterpreterException::NegativeArraySizeException,
+        );
+    }
+
+    let size = count as usize * type_.size();
+    if size > i16::MAX as usize {
+        return throw_exception(
+            execution_context,
+            InterpreterException::SystemException(exceptions::NO_RESOURCE),
+        );
+    }
+
+    let owner = execution_context.current_context;
+    let mut array = match component_class {
+        Some(class_ref) => JCVMObject::new_reference_array(owner, 0, class_ref, size as i16, true),
+        None => JCVMObject::new_array(owner, 0, type_, size as i16, true),
+    };
+    array.set_applet(execution_context.current_applet);
+    let arrayref = execution_context.object_manager.add_object(array);
+    execution_context.operand_stack.apush(arrayref as i16)?;
+    Ok(())
+}
+
+///
+/// Manages anewarray: the class of the elements is at the given constant pool index
+///
+pub fn anewarray(execution_context: &mut Context) -> Result<(), InterpreterError> {
+    let index = execution_context.bytecode_fetcher.fetch_s()? as u16;
+    let component_class = resolve_class(execution_context, index)?;
+    newarray(
+        execution_context,
+        constants::PrimitiveType::REFERENCE,
+        Some(component_class),
+    )
+}
+
+///
+/// Manages athrow: the exception object is thrown, unless it is null or the firewall denies
+/// the access to it
+///
+pub fn athrow(execution_context: &mut Context) -> Result<(), InterpreterError> {
+    let objectref = execution_context
+        .operand_stack
+        .pop_check_type(constants::PrimitiveType::REFERENCE)?;
+    check_null_reference!(objectref, execution_context);
+    check_firewall!(objectref, ObjectAccess::Throw, execution_context);
+    exceptions::throw_object(execution_context, objectref.value)
+}
+
+///
+/// Manages arraylength: pushes the number of elements of the array
+///
+pub fn arraylength(execution_context: &mut Context) -> Result<(), InterpreterError> {
+    let arrayref = execution_context
+        .operand_stack
+        .pop_check_type(constants::PrimitiveType::REFERENCE)?;
+    check_null_reference!(arrayref, execution_context);
+    check_firewall!(arrayref, ObjectAccess::Array, execution_context);
+
+    let array = get_object!(arrayref.value, execution_context);
+    if !array.is_array() {
+        return Err(InterpreterError::InvalidOperand);
+    }
+    execution_context.operand_stack.spush(array.array_length())?;
+    Ok(())
+}
+
+///
+/// Indicates whether the object is an instance of the type given by the operands of checkcast
+/// and instanceof: atype is 0 for classes and interfaces, the type of the elements for arrays
+/// (the class of the elements of arrays of references being at the given constant pool index)
+///
+fn is_instance_of(
+    execution_context: &Context,
+    object: &JCVMObject,
+    atype: u8,
+    index: u16,
+) -> Result<bool, InterpreterError> {
+    match atype {
+        0 => {
+            let target = resolve_class(execution_context, index)?;
+            match object.class_ref() {
+                Some(class_ref) => execution_context
+                    .class_manager
+                    .is_assignable(class_ref, target),
+                // arrays are only instances of java.lang.Object
+                None => Ok(target == OBJECT_CLASS),
+            }
+        }
+        constants::T_REFERENCE => {
+            let target = resolve_class(execution_context, index)?;
+            if !object.is_array() || !object.is_of_type(constants::PrimitiveType::REFERENCE) {
+                return Ok(false);
+            }
+            match object.component_class() {
+                Some(component_class) => execution_context
+                    .class_manager
+                    .is_assignable(component_class, target),
+                None => Ok(target == OBJECT_CLASS),
+            }
+        }
+        _ => {
+            let type_ = array_type(atype)?;
+            Ok(object.is_array() && object.is_of_type(type_))
+        }
+    }
+}
+
+///
+/// Firewall access of checkcast and instanceof: the target type is only needed for classes
+/// and interfaces, as objects can be cast to shareable interfaces
+///
+fn cast_access(
+    execution_context: &Context,
+    atype: u8,
+    index: u16,
+) -> Result<ObjectAccess, InterpreterError> {
+    match atype {
+        0 => Ok(ObjectAccess::Cast(Some(resolve_class(execution_context, index)?))),
+        _ => Ok(ObjectAccess::Cast(None)),
+    }
+}
+
+///
+/// Manages checkcast: throws a ClassCastException if the object on top of the operand stack
+/// cannot be cast to the given type. The operand stack is left unchanged.
+///
+pub fn checkcast(execution_context: &mut Context) -> Result<(), InterpreterError> {
+    let atype = execution_context.bytecode_fetcher.fetch_b()? as u8;
+    let index = execution_context.bytecode_fetcher.fetch_s()? as u16;
+    let objectref = execution_context
         .operand_stack
-        .pop_check_type(constants::PrimitiveType::SHORT)
-        .unwrap();
+        .peek_index_check_type(0, constants::PrimitiveType::REFERENCE)?;
 
-    /*let value = execution_context
+    // null can be cast to any type
+    if objectref.value == constants::NULL_HANDLE {
+        return Ok(());
+    }
+    check_firewall!(objectref, cast_access(execution_context, atype, index)?, execution_context);
+    let object = get_object!(objectref.value, execution_context);
+    if !is_instance_of(execution_context, object, atype, index)? {
+        return throw_exception(execution_context, InterpreterException::ClassCastException);
+    }
+    Ok(())
+}
+
+///
+/// Manages instanceof: pushes 1 if the object is an instance of the given type, 0 otherwise
+/// (including for null references)
+///
+pub fn instanceof(execution_context: &mut Context) -> Result<(), InterpreterError> {
+    let atype = execution_context.bytecode_fetcher.fetch_b()? as u8;
+    let index = execution_context.bytecode_fetcher.fetch_s()? as u16;
+    let objectref = execution_context
         .operand_stack
-        .pop_check_type(type_)
-        .unwrap_or(return Err(InterpreterException::SecurityException));*/
+        .pop_check_type(constants::PrimitiveType::REFERENCE)?;
 
+    if objectref.value == constants::NULL_HANDLE {
+        return execution_context.operand_stack.spush(0);
+    }
+    check_firewall!(objectref, cast_access(execution_context, atype, index)?, execution_context);
+    let result = {
+        let object = get_object!(objectref.value, execution_context);
+        is_instance_of(execution_context, object, atype, index)?
+    };
+    execution_context.operand_stack.spush(result as i16)?;
     Ok(())
 }
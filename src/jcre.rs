@@ -0,0 +1,109 @@
+use classes::ClassRef;
+use constants;
+use jcvmerrors::InterpreterError;
+use objects::JCVMObject;
+use objectsmanager::ObjectManager;
+use traits::BufferAccessor;
+
+// size of the APDU buffer (5 bytes of header and 256 bytes of data, plus 2 bytes for Le)
+pub const APDU_BUFFER_LENGTH: i16 = 263;
+
+///
+/// Objects owned by the JCRE and shared with the applets (see chapter 6.2.1 of the JCRE
+/// specification):
+/// - the APDU object, a temporary entry point, and the APDU buffer, a global array
+/// - the exception objects thrown by the virtual machine, which are permanent entry points
+///   allocated once per class and reused
+///
+pub struct JcreObjects {
+    apdu: i16,
+    apdu_buffer: i16,
+    // (class, object) of the exception singletons
+    exceptions: Vec<(ClassRef, i16)>,
+}
+
+impl Default for JcreObjects {
+    fn default() -> JcreObjects {
+        JcreObjects::new()
+    }
+}
+
+impl JcreObjects {
+    pub fn new() -> JcreObjects {
+        JcreObjects {
+            apdu: constants::NULL_HANDLE,
+            apdu_buffer: constants::NULL_HANDLE,
+            exceptions: Vec::new(),
+        }
+    }
+
+    ///
+    /// Allocates the APDU object, an instance of the given class (javacard.framework.APDU),
+    /// and the APDU buffer. Returns the reference to the APDU object
+    ///
+    pub fn create_apdu(
+        &mut self,
+        object_manager: &mut ObjectManager,
+        apdu_class: ClassRef,
+        instance_length: i16,
+    ) -> i16 {
+        let apdu = JCVMObject::new_entry_point(apdu_class, instance_length, true);
+        self.apdu = object_manager.add_object(apdu) as i16;
+        let buffer =
+            JCVMObject::new_global_array(constants::PrimitiveType::BYTE, APDU_BUFFER_LENGTH);
+        self.apdu_buffer = object_manager.add_object(buffer) as i16;
+        self.apdu
+    }
+
+    /// the APDU object, null if it has not been created
+    pub fn apdu(&self) -> i16 {
+        self.apdu
+    }
+
+    /// the APDU buffer, null if the APDU object has not been created
+    pub fn apdu_buffer(&self) -> i16 {
+        self.apdu_buffer
+    }
+
+    ///
+    /// Returns the exception singleton of the given class, which is allocated on first use.
+    /// Its reason code is set to the given one
+    ///
+    pub fn exception(
+        &mut self,
+        object_manager: &mut ObjectManager,
+        class_ref: ClassRef,
+        reason: i16,
+    ) -> Result<i16, InterpreterError> {
+        let objectref = match self.exceptions.iter().find(|(class, _)| *class == class_ref) {
+            Some((_, objectref)) => *objectref,
+            None => {
+                let exception = JCVMObject::new_entry_point(class_ref, 2, false);
+                let objectref = object_manager.add_object(exception) as i16;
+                self.exceptions.push((class_ref, objectref));
+                objectref
+            }
+        };
+        object_manager
+            .get_object_mut(objectref as usize)
+            .map_err(|_| InterpreterError::InvalidObjectReference)?
+            .write_s(0, reason)?;
+        Ok(objectref)
+    }
+}
+
+///
+/// Allocates the bArray parameter of Applet.install: a global byte array holding the
+/// installation parameters (instance AID, control information and applet data)
+///
+pub fn create_install_parameters(
+    object_manager: &mut ObjectManager,
+    parameters: &[u8],
+) -> Result<i16, InterpreterError> {
+    let mut array =
+        JCVMObject::new_global_array(constants::PrimitiveType::BYTE, parameters.len() as i16);
+    for (offset, byte) in parameters.iter().enumerate() {
+        array.write_b(offset, *byte as i8)?;
+    }
+    Ok(object_manager.add_object(array) as i16)
+}
@@ -1,6 +1,11 @@
+use std::error::Error;
+use std::fmt;
+
 use constants;
+use classes::ClassRef;
+use verifier::VerificationError;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum InterpreterError {
     // stack errors
     StackOverflowError,
@@ -9,6 +14,188 @@ pub enum InterpreterError {
     EndOfStream,
     IndexOutOfBound,
     UnrecognizedBytecode,
+    // bytecode belonging to an optional feature that is disabled
+    UnsupportedBytecode,
     NoBytecodeToFetch,
+    InvalidBranchTarget,
+    MalformedSwitchTable,
+    InvalidOperand,
+    // errors associated to the resolution of methods and classes
+    InvalidConstantPoolEntry,
+    InvalidClassReference,
+    UnresolvedMethod,
+    UnresolvedField,
+    InvalidPackageReference,
     InvalidVariableType(constants::PrimitiveType, constants::PrimitiveType),
+    // reference held by the virtual machine (not by the bytecode) which designates no object
+    InvalidObjectReference,
+    // java card exception that left the interpreter without being handled
+    // (class of the exception and its reason code)
+    UncaughtException(ClassRef, i16),
+    // the card was torn by the tearing simulation (see tearing.rs)
+    CardTorn,
+}
+
+///
+/// Errors raised while loading a CAP file. Component related errors carry the tag of the
+/// component being parsed
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum CapFileError {
+    // errors associated to the JAR container
+    InvalidArchive,
+    UnsupportedCompression(u16),
+    DecompressionFailed,
+    // the component ended before all its items were read
+    UnexpectedEnd(u8),
+    // the size of the component does not match its content or the Directory component
+    InvalidComponentSize(u8),
+    // an item of the component has a value that is not allowed
+    InvalidValue(u8),
+    InvalidMagic(u32),
+    UnsupportedVersion(u8, u8),
+    UnknownComponent(u8),
+    DuplicateComponent(u8),
+    MissingComponent(u8),
+}
+
+impl fmt::Display for CapFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Error for CapFileError {}
+
+///
+/// Errors raised while linking a package against the packages already loaded
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkError {
+    // a package with the same AID is already loaded
+    DuplicatePackage(Vec<u8>),
+    // no loaded package has the AID of an imported package
+    UnresolvedImport(Vec<u8>),
+    // the loaded package is not binary compatible with the imported version
+    // (AID, major and minor versions of the import)
+    VersionMismatch(Vec<u8>, u8, u8),
+    // an external reference uses a token not exported by the imported package
+    // (package and class tokens)
+    UnresolvedToken(u8, u8),
+    // an internal reference does not designate an element of the package
+    InvalidReference(u16),
+    InvalidStaticFieldImage,
+    // the package was rejected by the bytecode verifier
+    Verification(VerificationError),
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Error for LinkError {}
+
+///
+/// Errors raised while reading an export file, or building one from a CAP file
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExportFileError {
+    UnexpectedEnd,
+    // bytes left after the last class
+    TrailingData,
+    InvalidMagic(u32),
+    UnsupportedVersion(u8, u8),
+    // unknown constant pool tag, or invalid UTF-8 string
+    InvalidConstant(u8),
+    // the constant pool index does not designate an entry of the expected type
+    InvalidConstantPoolIndex(u16),
+    // the CAP file does not have the component (Export, Descriptor or Debug)
+    MissingComponent(u8),
+    // no descriptor or debug information for the element at this offset (or with this token)
+    InvalidReference(u16),
+    InvalidDebugString(u16),
+    // no runtime class for an exported class, or no export file for a superclass
+    UnresolvedClass(String),
+    // the token is used twice, or leaves a gap in the class tokens
+    InvalidToken(u8),
+    // error raised by the CAP file reader
+    Cap(CapFileError),
+}
+
+impl fmt::Display for ExportFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Error for ExportFileError {}
+
+///
+/// Errors raised by the bytecode verifier. The location of the error (method and opcode)
+/// is given by the VerificationError wrapping it
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerifyError {
+    // the CAP file does not have the component needed to verify the package (Descriptor)
+    MissingComponent(u8),
+    // the type of a method or a constant pool entry cannot be found or decoded
+    InvalidDescriptor(u16),
+    // the header does not fit in the Method component, or nargs does not match the signature
+    InvalidMethodHeader,
+    UnrecognizedBytecode(u8),
+    // integer bytecode in a package that does not use the integer instruction set
+    UnsupportedBytecode,
+    // the operands of the instruction go past the end of the method
+    TruncatedInstruction,
+    // the target is not the first byte of an instruction of the method
+    InvalidBranchTarget,
+    MalformedSwitchTable,
+    InvalidOperand,
+    // the operand stack goes above max_stack or below zero
+    StackOverflow,
+    StackUnderflow,
+    // found and expected types
+    InvalidType(constants::PrimitiveType, constants::PrimitiveType),
+    // the two halves of an integer are separated, or swapped
+    SplitInteger,
+    InvalidLocalIndex(u8),
+    // different operand stack heights on the paths reaching the same instruction
+    StackHeightMismatch(usize, usize),
+    // the constant pool entry does not exist, or is not of the expected kind
+    InvalidConstantPoolIndex(u16),
+    // the method cannot be found in the package or in the export files of its imports
+    UnresolvedMethod,
+    // the handler range or its target is not made of instructions of the method
+    InvalidExceptionHandler(u16),
+    // the execution can continue after the last instruction of the method
+    FallOffEnd,
+    // the returned type does not match the signature of the method
+    InvalidReturn,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Error for VerifyError {}
+
+impl fmt::Display for InterpreterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Error for InterpreterError {}
+
+impl From<CapFileError> for ExportFileError {
+    fn from(error: CapFileError) -> ExportFileError {
+        match error {
+            CapFileError::UnexpectedEnd(_) => ExportFileError::UnexpectedEnd,
+            _ => ExportFileError::Cap(error),
+        }
+    }
 }
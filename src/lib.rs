@@ -1,3 +1,5 @@
+extern crate miniz_oxide;
+
 pub mod bytecodes;
 pub mod context;
 
@@ -8,9 +10,26 @@ pub mod traits;
 pub mod frame;
 pub mod framestack;
 pub mod jcvmerrors;
+pub mod faultreport;
 pub mod constants;
 pub mod objects;
 pub mod objectsmanager;
+pub mod firewall;
+pub mod appletsmanager;
+pub mod jcre;
+pub mod transactions;
+pub mod tearing;
+pub mod classes;
+pub mod classesmanager;
+pub mod constantpool;
+pub mod methods;
+pub mod staticfields;
+pub mod capcomponents;
+pub mod capfile;
+pub mod linker;
+pub mod exportfile;
+pub mod verifier;
+pub mod natives;
 #[macro_use]
 mod interpreterutils;
 
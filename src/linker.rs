@@ -0,0 +1,575 @@
+use std::collections::{BTreeSet, HashMap};
+use std::mem;
+
+use capcomponents::{CapClassInfo, CapClassRef, CapConstantPoolEntry, CapStaticRef, PackageInfo};
+use bcutils::BytecodeFetcher;
+use capfile::CapFile;
+use classes::{ClassRef, ImplementedInterface, JCVMClass, VirtualMethodTable};
+use classesmanager::ClassManager;
+use constantpool::{ConstantPool, ConstantPoolEntry, ConstantPoolManager};
+use context::Context;
+use exportfile::ExportFile;
+use exceptions::ExceptionHandler;
+use interpreter::{BytecodeData, BytecodeType};
+use jcvmerrors::LinkError;
+use methods::{MethodExtents, MethodHeader};
+use objectsmanager::ObjectManager;
+use staticfields::{PackageRef, StaticFieldImage, StaticFieldManager};
+use tearing::TearingSimulation;
+use verifier;
+
+///
+/// Elements exported by a class, indexed by their token
+///
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExportedClass {
+    pub class_ref: ClassRef,
+    // package and offset in its static field image of each static field
+    pub static_fields: Vec<(PackageRef, u16)>,
+    // offset of the header of each static method
+    pub static_methods: Vec<usize>,
+}
+
+///
+/// Runtime references of the elements of a package that other packages can import,
+/// indexed by class token
+///
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExportTable {
+    pub classes: Vec<ExportedClass>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkedApplet {
+    pub aid: Vec<u8>,
+    // offset of the header of the install method
+    pub install_method: usize,
+}
+
+///
+/// Package available to the packages loaded after it
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkedPackage {
+    pub info: PackageInfo,
+    // context owning the objects of the package (its static arrays)
+    pub context: i16,
+    // None if the package exports nothing (no Export component)
+    pub exports: Option<ExportTable>,
+    // None for the packages registered without a CAP file
+    pub static_fields: Option<PackageRef>,
+    pub applets: Vec<LinkedApplet>,
+}
+
+///
+/// Loads packages one after the other: the code of their Method components is appended to a
+/// single bytecode array, their classes are registered in the class manager and their
+/// constant pool entries are resolved into the constant pool of the package, used by its code.
+/// The constant pool indexes used by the bytecode (listed by the ReferenceLocation component)
+/// are checked against it, and the extents of its methods are registered to bound their
+/// branches.
+///
+pub struct Linker {
+    code: Vec<BytecodeType>,
+    class_manager: ClassManager,
+    constant_pools: ConstantPoolManager,
+    static_fields: StaticFieldManager,
+    object_manager: ObjectManager,
+    exception_handlers: Vec<ExceptionHandler>,
+    method_extents: MethodExtents,
+    // persistent writes done while loading (static field images)
+    tearing: TearingSimulation,
+    packages: Vec<LinkedPackage>,
+}
+
+impl Default for Linker {
+    fn default() -> Linker {
+        Linker::new()
+    }
+}
+
+impl Linker {
+    pub fn new() -> Linker {
+        Linker {
+            code: Vec::new(),
+            class_manager: ClassManager::new(),
+            constant_pools: ConstantPoolManager::new(),
+            static_fields: StaticFieldManager::new(),
+            object_manager: ObjectManager::new(),
+            exception_handlers: Vec::new(),
+            method_extents: MethodExtents::new(),
+            tearing: TearingSimulation::new(),
+            packages: Vec::new(),
+        }
+    }
+
+    pub fn packages(&self) -> &[LinkedPackage] {
+        &self.packages
+    }
+
+    pub fn find_package(&self, aid: &[u8]) -> Option<&LinkedPackage> {
+        self.packages
+            .iter()
+            .find(|package| package.info.aid.as_slice() == aid)
+    }
+
+    ///
+    /// Appends code that does not come from a CAP file (such as the methods of the packages
+    /// registered with add_package) and returns its offset
+    ///
+    pub fn add_code(&mut self, code: &BytecodeData) -> usize {
+        self.code.extend_from_slice(code);
+        self.code.len() - code.len()
+    }
+
+    ///
+    /// Registers a package implemented by the virtual machine (such as java.lang, whose
+    /// classes are the built-in ones), so that loaded packages can import it
+    ///
+    pub fn add_package(
+        &mut self,
+        info: PackageInfo,
+        exports: ExportTable,
+    ) -> Result<&LinkedPackage, LinkError> {
+        self.check_new_package(&info)?;
+        let context = self.next_context();
+        self.packages.push(LinkedPackage {
+            info,
+            context,
+            exports: Some(exports),
+            static_fields: None,
+            applets: Vec::new(),
+        });
+        Ok(&self.packages[self.packages.len() - 1])
+    }
+
+    ///
+    /// Verifies the package of the CAP file (see verifier::verify) against the export files
+    /// of its imports, then loads it. Packages rejected by the verifier are not loaded
+    ///
+    pub fn load_verified(
+        &mut self,
+        cap: &CapFile,
+        imports: &[ExportFile],
+    ) -> Result<&LinkedPackage, LinkError> {
+        verifier::verify(cap, imports).map_err(LinkError::Verification)?;
+        self.load(cap)
+    }
+
+    ///
+    /// Links the package of the CAP file against the packages already loaded. Nothing is
+    /// added to the runtime structures if an error is returned.
+    ///
+    pub fn load(&mut self, cap: &CapFile) -> Result<&LinkedPackage, LinkError> {
+        self.check_new_package(&cap.header.package)?;
+        let imports = self.resolve_imports(cap)?;
+
+        let mut package = PackageLinker {
+            cap,
+            imports,
+            method_base: self.code.len(),
+            classes: HashMap::new(),
+            package: self.static_fields.len(),
+        };
+        let code: Vec<BytecodeType> = cap.method
+            .methods
+            .iter()
+            .map(|byte| *byte as BytecodeType)
+            .collect();
+        package.check_references(&code)?;
+        let method_extents = package.method_extents(&code);
+        let classes = package.link_classes(self.class_manager.len())?;
+        let mut constant_pool = ConstantPool::new();
+        for entry in &cap.constant_pool.entries {
+            constant_pool.add_entry(package.resolve_entry(entry)?);
+        }
+        let exports = match cap.export {
+            Some(ref export) => Some(package.export_table(export)?),
+            None => None,
+        };
+
+        // from here on, the runtime structures are modified
+        let context = self.next_context();
+        let image = StaticFieldImage::from_component(
+            &cap.static_field,
+            context,
+            &mut self.object_manager,
+            &mut self.tearing,
+        ).map_err(|_| LinkError::InvalidStaticFieldImage)?;
+        self.static_fields.add_image(image);
+        for class in classes {
+            self.class_manager.add_class(class);
+        }
+        for handler in &cap.method.exception_handlers {
+            self.exception_handlers.push(package.relocate_handler(handler));
+        }
+        for (method_offset, code_end) in method_extents {
+            self.method_extents.add_method(method_offset, code_end);
+        }
+        self.code.extend_from_slice(&code);
+        self.constant_pools
+            .add_package(package.method_base..self.code.len(), constant_pool);
+
+        let applets = cap.applet.as_ref().map_or(Vec::new(), |applet| {
+            applet
+                .applets
+                .iter()
+                .map(|applet| LinkedApplet {
+                    aid: applet.aid.clone(),
+                    install_method: package.method_base + applet.install_method_offset as usize,
+                })
+                .collect()
+        });
+        self.packages.push(LinkedPackage {
+            info: cap.header.package.clone(),
+            context,
+            exports,
+            static_fields: Some(package.package),
+            applets,
+        });
+        Ok(&self.packages[self.packages.len() - 1])
+    }
+
+    ///
+    /// Moves the linked packages into the runtime structures of the context, which has to
+    /// run a copy of the code returned by code() (the linker keeps the package list only)
+    ///
+    pub fn install(&mut self, execution_context: &mut Context) {
+        execution_context.class_manager = mem::take(&mut self.class_manager);
+        execution_context.constant_pools = mem::take(&mut self.constant_pools);
+        execution_context.static_fields = mem::take(&mut self.static_fields);
+        execution_context.object_manager = mem::take(&mut self.object_manager);
+        execution_context.exception_handlers = mem::take(&mut self.exception_handlers);
+        execution_context.method_extents = mem::take(&mut self.method_extents);
+        execution_context.tearing = mem::take(&mut self.tearing);
+    }
+
+    pub fn code(&self) -> &BytecodeData {
+        &self.code
+    }
+
+    fn check_new_package(&self, info: &PackageInfo) -> Result<(), LinkError> {
+        if self.find_package(&info.aid).is_some() {
+            return Err(LinkError::DuplicatePackage(info.aid.clone()));
+        }
+        Ok(())
+    }
+
+    /// each package has its own context, the JCRE context being 0
+    fn next_context(&self) -> i16 {
+        self.packages.len() as i16 + 1
+    }
+
+    ///
+    /// Returns the export tables of the imported packages, indexed by package token. The
+    /// loaded version has to have the same major version and at least the minor version
+    /// of the import.
+    ///
+    fn resolve_imports(&self, cap: &CapFile) -> Result<Vec<ExportTable>, LinkError> {
+        let mut result = Vec::new();
+        for import in &cap.import.packages {
+            let package = self.find_package(&import.aid)
+                .ok_or_else(|| LinkError::UnresolvedImport(import.aid.clone()))?;
+            if package.info.major_version != import.major_version
+                || package.info.minor_version < import.minor_version
+            {
+                return Err(LinkError::VersionMismatch(
+                    import.aid.clone(),
+                    import.major_version,
+                    import.minor_version,
+                ));
+            }
+            result.push(package.exports.clone().unwrap_or_default());
+        }
+        Ok(result)
+    }
+}
+
+///
+/// State of the package being loaded
+///
+struct PackageLinker<'a> {
+    cap: &'a CapFile,
+    // export tables indexed by package token
+    imports: Vec<ExportTable>,
+    // offset of the Method component in the bytecode array
+    method_base: usize,
+    // classes of the package, by offset in the Class component
+    classes: HashMap<u16, ClassRef>,
+    // static field image of the package
+    package: PackageRef,
+}
+
+impl<'a> PackageLinker<'a> {
+    /// checks that the indexes used by the code are entries of the constant pool of the package
+    fn check_references(&self, code: &[BytecodeType]) -> Result<(), LinkError> {
+        let count = self.cap.constant_pool.entries.len();
+        for &offset in &self.cap.reference_location.byte_indices {
+            let operand = code.get(offset as usize)
+                .ok_or(LinkError::InvalidReference(offset))?;
+            if *operand as u8 as usize >= count {
+                return Err(LinkError::InvalidReference(offset));
+            }
+        }
+        for &offset in &self.cap.reference_location.byte2_indices {
+            let offset = offset as usize;
+            if offset + 1 >= code.len() {
+                return Err(LinkError::InvalidReference(offset as u16));
+            }
+            let index = (code[offset] as u8 as usize) << 8 | code[offset + 1] as u8 as usize;
+            if index >= count {
+                return Err(LinkError::InvalidReference(offset as u16));
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// Returns the offset of the header and the end of the code of the methods of the package,
+    /// relocated. The methods listed by the Descriptor component end after their bytecode
+    /// count, the other methods known to the linker (virtual methods, static methods of the
+    /// Constant Pool and Export components, install methods) at the next known method
+    ///
+    fn method_extents(&self, code: &[BytecodeType]) -> Vec<(usize, usize)> {
+        let mut starts = BTreeSet::new();
+        let mut bytecode_counts = HashMap::new();
+        if let Some(ref descriptor) = self.cap.descriptor {
+            for method in descriptor.classes.iter().flat_map(|class| class.methods.iter()) {
+                // abstract methods have no code
+                if method.method_offset != 0 {
+                    starts.insert(method.method_offset);
+                    bytecode_counts.insert(method.method_offset, method.bytecode_count);
+                }
+            }
+        }
+        for class_info in &self.cap.class.classes {
+            if let CapClassInfo::Class {
+                ref public_virtual_methods,
+                ref package_virtual_methods,
+                ..
+            } = *class_info
+            {
+                starts.extend(public_virtual_methods.iter().chain(package_virtual_methods));
+            }
+        }
+        for entry in &self.cap.constant_pool.entries {
+            if let CapConstantPoolEntry::StaticMethodRef(CapStaticRef::Internal(offset)) = *entry {
+                starts.insert(offset);
+            }
+        }
+        if let Some(ref export) = self.cap.export {
+            for class in &export.classes {
+                starts.extend(&class.static_method_offsets);
+            }
+        }
+        if let Some(ref applet) = self.cap.applet {
+            starts.extend(applet.applets.iter().map(|applet| applet.install_method_offset));
+        }
+
+        let starts: Vec<usize> = starts
+            .into_iter()
+            .map(|offset| offset as usize)
+            .filter(|offset| *offset < code.len())
+            .collect();
+        let fetcher = BytecodeFetcher::new(code);
+        let mut result = Vec::new();
+        for (index, &start) in starts.iter().enumerate() {
+            let next = starts.get(index + 1).cloned().unwrap_or(code.len());
+            let header = MethodHeader::read(&fetcher, start);
+            let end = match (bytecode_counts.get(&(start as u16)), header) {
+                (Some(count), Ok(header)) => (header.code_offset + *count as usize).min(code.len()),
+                _ => next,
+            };
+            result.push((self.method_base + start, self.method_base + end));
+        }
+        result
+    }
+
+    ///
+    /// Builds the classes and interfaces of the Class component, which are going to be
+    /// registered in the class manager from the given class reference on
+    ///
+    fn link_classes(&mut self, first_class: ClassRef) -> Result<Vec<JCVMClass>, LinkError> {
+        for (index, class_info) in self.cap.class.classes.iter().enumerate() {
+            self.classes.insert(class_info.offset(), first_class + index);
+        }
+
+        let mut result = Vec::new();
+        for class_info in &self.cap.class.classes {
+            let mut class = match *class_info {
+                CapClassInfo::Interface {
+                    ref super_interfaces,
+                    ..
+                } => {
+                    let mut super_refs = Vec::new();
+                    for super_interface in super_interfaces {
+                        super_refs.push(self.resolve_class(*super_interface)?);
+                    }
+                    JCVMClass::new_interface(&super_refs)
+                }
+                CapClassInfo::Class {
+                    super_class,
+                    declared_instance_size,
+                    public_method_table_base,
+                    package_method_table_base,
+                    ref public_virtual_methods,
+                    ref package_virtual_methods,
+                    ref interfaces,
+                    ..
+                } => {
+                    let super_class = match super_class {
+                        Some(class_ref) => Some(self.resolve_class(class_ref)?),
+                        None => None,
+                    };
+                    let mut class = JCVMClass::new(super_class);
+                    class.set_declared_instance_size(declared_instance_size);
+                    class.set_public_methods(VirtualMethodTable::new(
+                        public_method_table_base,
+                        self.relocate_methods(public_virtual_methods),
+                    ));
+                    class.set_package_methods(VirtualMethodTable::new(
+                        package_method_table_base,
+                        self.relocate_methods(package_virtual_methods),
+                    ));
+                    for interface in interfaces {
+                        class.add_interface(ImplementedInterface {
+                            interface: self.resolve_class(interface.interface)?,
+                            method_tokens: interface.index.clone(),
+                        });
+                    }
+                    class
+                }
+            };
+            class.set_flags(class_info.flags());
+            result.push(class);
+        }
+        Ok(result)
+    }
+
+    fn relocate_methods(&self, offsets: &[u16]) -> Vec<usize> {
+        offsets
+            .iter()
+            .map(|offset| self.method_base + *offset as usize)
+            .collect()
+    }
+
+    fn relocate_handler(&self, handler: &ExceptionHandler) -> ExceptionHandler {
+        ExceptionHandler {
+            start_offset: self.method_base + handler.start_offset,
+            active_length: handler.active_length,
+            handler_offset: self.method_base + handler.handler_offset,
+            catch_type_index: handler.catch_type_index,
+        }
+    }
+
+    fn exported_class(
+        &self,
+        package_token: u8,
+        class_token: u8,
+    ) -> Result<&ExportedClass, LinkError> {
+        self.imports
+            .get(package_token as usize)
+            .and_then(|exports| exports.classes.get(class_token as usize))
+            .ok_or(LinkError::UnresolvedToken(package_token, class_token))
+    }
+
+    fn resolve_class(&self, class_ref: CapClassRef) -> Result<ClassRef, LinkError> {
+        match class_ref {
+            CapClassRef::Internal(offset) => self.classes
+                .get(&offset)
+                .cloned()
+                .ok_or(LinkError::InvalidReference(offset)),
+            CapClassRef::External {
+                package_token,
+                class_token,
+            } => Ok(self.exported_class(package_token, class_token)?.class_ref),
+        }
+    }
+
+    fn resolve_static_method(&self, method_ref: CapStaticRef) -> Result<usize, LinkError> {
+        match method_ref {
+            CapStaticRef::Internal(offset) => {
+                if offset as usize >= self.cap.method.methods.len() {
+                    return Err(LinkError::InvalidReference(offset));
+                }
+                Ok(self.method_base + offset as usize)
+            }
+            CapStaticRef::External {
+                package_token,
+                class_token,
+                token,
+            } => self.exported_class(package_token, class_token)?
+                .static_methods
+                .get(token as usize)
+                .cloned()
+                .ok_or(LinkError::UnresolvedToken(package_token, class_token)),
+        }
+    }
+
+    fn resolve_static_field(
+        &self,
+        field_ref: CapStaticRef,
+    ) -> Result<(PackageRef, u16), LinkError> {
+        match field_ref {
+            CapStaticRef::Internal(offset) => {
+                if offset >= self.cap.static_field.image_size {
+                    return Err(LinkError::InvalidReference(offset));
+                }
+                Ok((self.package, offset))
+            }
+            CapStaticRef::External {
+                package_token,
+                class_token,
+                token,
+            } => self.exported_class(package_token, class_token)?
+                .static_fields
+                .get(token as usize)
+                .cloned()
+                .ok_or(LinkError::UnresolvedToken(package_token, class_token)),
+        }
+    }
+
+    fn resolve_entry(&self, entry: &CapConstantPoolEntry) -> Result<ConstantPoolEntry, LinkError> {
+        Ok(match *entry {
+            CapConstantPoolEntry::ClassRef(class_ref) => {
+                ConstantPoolEntry::ClassRef(self.resolve_class(class_ref)?)
+            }
+            CapConstantPoolEntry::InstanceFieldRef(class_ref, token) => {
+                ConstantPoolEntry::InstanceFieldRef(self.resolve_class(class_ref)?, token)
+            }
+            CapConstantPoolEntry::VirtualMethodRef(class_ref, token) => {
+                ConstantPoolEntry::VirtualMethodRef(self.resolve_class(class_ref)?, token)
+            }
+            CapConstantPoolEntry::SuperMethodRef(class_ref, token) => {
+                ConstantPoolEntry::SuperMethodRef(self.resolve_class(class_ref)?, token)
+            }
+            CapConstantPoolEntry::StaticFieldRef(field_ref) => {
+                let (package, offset) = self.resolve_static_field(field_ref)?;
+                ConstantPoolEntry::StaticFieldRef(package, offset)
+            }
+            CapConstantPoolEntry::StaticMethodRef(method_ref) => {
+                ConstantPoolEntry::StaticMethodRef(self.resolve_static_method(method_ref)?)
+            }
+        })
+    }
+
+    /// builds the export table of the package from its Export component
+    fn export_table(
+        &self,
+        export: &::capcomponents::ExportComponent,
+    ) -> Result<ExportTable, LinkError> {
+        let mut classes = Vec::new();
+        for class in &export.classes {
+            classes.push(ExportedClass {
+                class_ref: self.resolve_class(CapClassRef::Internal(class.class_offset))?,
+                static_fields: class
+                    .static_field_offsets
+                    .iter()
+                    .map(|offset| (self.package, *offset))
+                    .collect(),
+                static_methods: self.relocate_methods(&class.static_method_offsets),
+            });
+        }
+        Ok(ExportTable { classes })
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::BTreeMap;
+
+use bcutils::BytecodeFetcher;
+use jcvmerrors::InterpreterError;
+
+// flags of the method header
+pub const ACC_EXTENDED: u8 = 0x8;
+pub const ACC_ABSTRACT: u8 = 0x4;
+
+///
+/// Header preceding the bytecode of every method (see method_header_info and
+/// extended_method_header_info in the Method component of the JCVM specification)
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MethodHeader {
+    // offset of the header in the bytecode array
+    pub method_offset: usize,
+    // offset of the first opcode of the method
+    pub code_offset: usize,
+    pub flags: u8,
+    pub max_stack: u8,
+    // number of words taken by the arguments (including 'this' for instance methods)
+    pub nargs: u8,
+    // number of locals, arguments excluded
+    pub max_locals: u8,
+}
+
+impl MethodHeader {
+    /// reads the header of the method located at the given offset
+    pub fn read(fetcher: &BytecodeFetcher, offset: usize) -> Result<MethodHeader, InterpreterError> {
+        let first = fetcher.get(offset)? as u8;
+        let flags = first >> 4;
+
+        if flags & ACC_EXTENDED != 0 {
+            // extended header: flags, max_stack, nargs and max_locals take one byte each
+            Ok(MethodHeader {
+                method_offset: offset,
+                code_offset: offset + 4,
+                flags,
+                max_stack: fetcher.get(offset + 1)? as u8,
+                nargs: fetcher.get(offset + 2)? as u8,
+                max_locals: fetcher.get(offset + 3)? as u8,
+            })
+        } else {
+            let second = fetcher.get(offset + 1)? as u8;
+            Ok(MethodHeader {
+                method_offset: offset,
+                code_offset: offset + 2,
+                flags,
+                max_stack: first & 0x0F,
+                nargs: second >> 4,
+                max_locals: second & 0x0F,
+            })
+        }
+    }
+
+    pub fn is_abstract(&self) -> bool {
+        self.flags & ACC_ABSTRACT != 0
+    }
+}
+
+///
+/// End of the code of the methods of the bytecode array, which bounds their branches. The
+/// extents are registered by the linker for the methods of the loaded packages, the methods
+/// of the code registered without a CAP file extending up to the end of the bytecode array
+///
+#[derive(Debug, Default)]
+pub struct MethodExtents {
+    // end of the code (excluded) of the methods, by offset of their header
+    code_ends: BTreeMap<usize, usize>,
+}
+
+impl MethodExtents {
+    pub fn new() -> MethodExtents {
+        MethodExtents::default()
+    }
+
+    /// registers the end of the code (excluded) of the method whose header is at the offset
+    pub fn add_method(&mut self, method_offset: usize, code_end: usize) {
+        self.code_ends.insert(method_offset, code_end);
+    }
+
+    pub fn code_end(&self, method_offset: usize) -> Option<usize> {
+        self.code_ends.get(&method_offset).cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.code_ends.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.code_ends.is_empty()
+    }
+}
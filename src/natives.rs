@@ -0,0 +1,134 @@
+use bytecodes::bytecode;
+use constants;
+use context::Context;
+use exceptions::throw_exception;
+use interpreter::BytecodeType;
+use interpreterutils::invoke_method_in_context;
+use jcvmerrors::InterpreterError;
+use transactions;
+
+// identifiers of the native methods (operand of impdep1 in their code)
+// JCSystem.getAppletShareableInterfaceObject(AID serverAID, byte parameter)
+pub const GET_APPLET_SHAREABLE_INTERFACE_OBJECT: u8 = 0;
+// JCSystem.getPreviousContextAID()
+pub const GET_PREVIOUS_CONTEXT_AID: u8 = 1;
+// JCSystem.beginTransaction()
+pub const BEGIN_TRANSACTION: u8 = 2;
+// JCSystem.commitTransaction()
+pub const COMMIT_TRANSACTION: u8 = 3;
+// JCSystem.abortTransaction()
+pub const ABORT_TRANSACTION: u8 = 4;
+// JCSystem.getTransactionDepth()
+pub const GET_TRANSACTION_DEPTH: u8 = 5;
+// JCSystem.getUnusedCommitCapacity()
+pub const GET_UNUSED_COMMIT_CAPACITY: u8 = 6;
+
+// virtual method token of Applet.getShareableInterfaceObject(AID clientAID, byte parameter)
+pub const GET_SHAREABLE_INTERFACE_OBJECT_TOKEN: u8 = 2;
+
+///
+/// Code (header included) of the given native method, to be added to the code of the package
+/// implementing it (see Linker::add_code). The native method finds its arguments in its
+/// locals, and leaves the value it returns, if any, on top of the operand stack
+///
+pub fn native_method(id: u8) -> Result<Vec<BytecodeType>, InterpreterError> {
+    let (max_stack, nargs, return_opcode): (u8, u8, bytecode) = match id {
+        // the arguments of getShareableInterfaceObject are pushed by the native method
+        GET_APPLET_SHAREABLE_INTERFACE_OBJECT => (3, 2, bytecode::areturn),
+        GET_PREVIOUS_CONTEXT_AID => (1, 0, bytecode::areturn),
+        BEGIN_TRANSACTION | COMMIT_TRANSACTION | ABORT_TRANSACTION => (0, 0, bytecode::return_),
+        GET_TRANSACTION_DEPTH | GET_UNUSED_COMMIT_CAPACITY => (1, 0, bytecode::sreturn),
+        _ => return Err(InterpreterError::UnresolvedMethod),
+    };
+    Ok(vec![
+        max_stack as BytecodeType,
+        (nargs << 4) as BytecodeType,
+        bytecode::impdep1 as BytecodeType,
+        id as BytecodeType,
+        return_opcode as BytecodeType,
+    ])
+}
+
+///
+/// Executes the native method with the given identifier (impdep1 opcode)
+///
+pub fn invoke_native(ctx: &mut Context, id: u8) -> Result<(), InterpreterError> {
+    match id {
+        GET_APPLET_SHAREABLE_INTERFACE_OBJECT => get_applet_shareable_interface_object(ctx),
+        GET_PREVIOUS_CONTEXT_AID => {
+            let aid_object = previous_context_aid(ctx);
+            ctx.operand_stack.apush(aid_object)
+        }
+        BEGIN_TRANSACTION => match ctx.transaction.begin() {
+            Ok(()) => Ok(()),
+            Err(e) => throw_exception(ctx, e),
+        },
+        COMMIT_TRANSACTION => match ctx.transaction.commit() {
+            Ok(()) => Ok(()),
+            Err(e) => throw_exception(ctx, e),
+        },
+        ABORT_TRANSACTION => transactions::abort(ctx),
+        GET_TRANSACTION_DEPTH => {
+            // the byte returned is sign-extended to a short, as any byte on the operand stack
+            let depth = ctx.transaction.depth();
+            ctx.operand_stack.spush(i16::from(depth))
+        }
+        GET_UNUSED_COMMIT_CAPACITY => {
+            let unused = ctx.transaction.unused_capacity();
+            ctx.operand_stack.spush(unused)
+        }
+        _ => Err(InterpreterError::UnresolvedMethod),
+    }
+}
+
+///
+/// JCSystem.getAppletShareableInterfaceObject: getShareableInterfaceObject is invoked on the
+/// server applet, in its context, with the AID object of the client applet (the active applet)
+/// and the parameter. null is returned if the server is not registered
+///
+fn get_applet_shareable_interface_object(ctx: &mut Context) -> Result<(), InterpreterError> {
+    let server_aid = ctx.current_frame()?
+        .get_local_check_type(0, constants::PrimitiveType::REFERENCE)?
+        .value;
+    // the byte parameter is held by a short, as any byte on the operand stack
+    let parameter = ctx.current_frame()?
+        .get_local_check_type(1, constants::PrimitiveType::SHORT)?
+        .value;
+
+    let server = match ctx.applet_manager.find_by_aid_object(server_aid) {
+        Some(applet) => applet.clone(),
+        None => return ctx.operand_stack.apush(constants::NULL_HANDLE),
+    };
+    let client_aid = ctx.applet_manager
+        .find_by_instance(ctx.current_applet)
+        .map_or(constants::NULL_HANDLE, |applet| applet.aid_object);
+
+    let server_class = ctx.object_manager
+        .get_object(server.instance as usize)
+        .map_err(|_| InterpreterError::InvalidObjectReference)?
+        .class_ref()
+        .ok_or(InterpreterError::InvalidClassReference)?;
+    let method_offset = ctx.class_manager
+        .resolve_virtual_method(server_class, GET_SHAREABLE_INTERFACE_OBJECT_TOKEN)?;
+
+    ctx.operand_stack.apush(server.instance)?;
+    ctx.operand_stack.apush(client_aid)?;
+    ctx.operand_stack.spush(parameter)?;
+    invoke_method_in_context(ctx, method_offset, server.context, server.instance)
+}
+
+///
+/// JCSystem.getPreviousContextAID: AID object of the applet that was active before the last
+/// context switch, null if it was the JCRE. The contexts and applets of the callers are the
+/// ones recorded in the frames
+///
+fn previous_context_aid(ctx: &Context) -> i16 {
+    let previous_applet = ctx.frame_stack
+        .iter()
+        .rev()
+        .find(|frame| frame.caller_context() != ctx.current_context)
+        .map_or(constants::NULL_HANDLE, |frame| frame.caller_applet());
+    ctx.applet_manager
+        .find_by_instance(previous_applet)
+        .map_or(constants::NULL_HANDLE, |applet| applet.aid_object)
+}
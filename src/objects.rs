@@ -2,12 +2,17 @@ use constants;
 use jcvmerrors::InterpreterError;
 use traits::{BufferAccessor, HasType};
 use interpreter::BytecodeType;
+use classes::ClassRef;
 
 type InternalBuffer = Vec<i8>;
 
 // a structure representing an object
 pub struct JCVMObject {
     owner: i16,
+    // applet instance active in the owner context when the object was created
+    applet: i16,
+    // class of the instance, or class of the elements for arrays of references
+    class_ref: Option<ClassRef>,
     object_flags: u8,
     primitive_type: constants::PrimitiveType,
     object_length: i16, // length in terms of raw length (not in terms of items etc)
@@ -30,13 +35,16 @@ impl BufferAccessor for JCVMObject {
     }
 
     fn read_s(&self, offset: usize) -> Result<i16, InterpreterError> {
-        let r = (self.get(offset)? as i16) << 8 | self.get(offset + 1)? as i16;
-        Ok(r)
+        let r = u16::from(self.get(offset)? as u8) << 8
+            | u16::from(self.get(offset + 1)? as u8);
+        Ok(r as i16)
     }
 
     fn read_i(&self, offset: usize) -> Result<i32, InterpreterError> {
-        let r1 = (self.get(offset)? as u16) << 8 | self.get(offset + 1)? as u16;
-        let r2 = (self.get(offset + 2)? as u16) << 8 | self.get(offset + 3)? as u16;
+        let r1 = u16::from(self.get(offset)? as u8) << 8
+            | u16::from(self.get(offset + 1)? as u8);
+        let r2 = u16::from(self.get(offset + 2)? as u8) << 8
+            | u16::from(self.get(offset + 3)? as u8);
         let r = (u32::from(r1) << 16) | u32::from(r2);
         Ok(r as i32)
     }
@@ -47,7 +55,7 @@ impl BufferAccessor for JCVMObject {
 
     fn write_s(&mut self, offset: usize, val: i16) -> Result<(), InterpreterError> {
         self.put(offset, (val >> 8) as i8)?;
-        self.put(offset + 1, i8::from(val as i8))
+        self.put(offset + 1, val as i8)
     }
 
     fn write_i(&mut self, offset: usize, val: i32) -> Result<(), InterpreterError> {
@@ -67,11 +75,13 @@ impl JCVMObject {
         persistent: bool,
     ) -> JCVMObject {
         JCVMObject {
-            owner: owner,
+            owner,
+            applet: constants::NULL_HANDLE,
+            class_ref: None,
             object_flags: flags_,
             primitive_type: ptype,
             object_length: length,
-            persistent: persistent,
+            persistent,
             content: vec![0; length as usize],
         }
     }
@@ -84,19 +94,112 @@ impl JCVMObject {
         persistent: bool,
     ) -> JCVMObject {
         JCVMObject {
-            owner: owner,
+            owner,
+            applet: constants::NULL_HANDLE,
+            class_ref: None,
             object_flags: flags_ | (constants::ObjectFlags::ARRAY as u8),
             primitive_type: ptype,
             object_length: length,
-            persistent: persistent,
+            persistent,
             content: vec![0; length as usize],
         }
     }
 
+    pub fn new_reference_array(
+        owner: i16,
+        flags_: u8,
+        component_class: ClassRef,
+        length: i16,
+        persistent: bool,
+    ) -> JCVMObject {
+        let mut result = JCVMObject::new_array(
+            owner,
+            flags_,
+            constants::PrimitiveType::REFERENCE,
+            length,
+            persistent,
+        );
+        result.class_ref = Some(component_class);
+        result
+    }
+
+    pub fn new_instance(
+        owner: i16,
+        flags_: u8,
+        class_ref: ClassRef,
+        length: i16,
+        persistent: bool,
+    ) -> JCVMObject {
+        JCVMObject {
+            owner,
+            applet: constants::NULL_HANDLE,
+            class_ref: Some(class_ref),
+            object_flags: flags_,
+            primitive_type: constants::PrimitiveType::UNKNOWN,
+            object_length: length,
+            persistent,
+            content: vec![0; length as usize],
+        }
+    }
+
+    ///
+    /// Creates a JCRE entry point: an instance owned by the JCRE whose methods can be invoked
+    /// from any context. References to temporary entry points cannot be stored in fields,
+    /// static fields or arrays
+    ///
+    pub fn new_entry_point(class_ref: ClassRef, length: i16, temporary: bool) -> JCVMObject {
+        let mut flags = constants::ObjectFlags::GLOBAL as u8;
+        if temporary {
+            flags |= constants::ObjectFlags::TEMPORARY as u8;
+        }
+        JCVMObject::new_instance(constants::JCRE_CONTEXT, flags, class_ref, length, false)
+    }
+
+    ///
+    /// Creates a global array: an array owned by the JCRE which can be accessed from any
+    /// context, but whose references cannot be stored in fields, static fields or arrays
+    ///
+    pub fn new_global_array(ptype: constants::PrimitiveType, length: i16) -> JCVMObject {
+        JCVMObject::new_array(
+            constants::JCRE_CONTEXT,
+            constants::ObjectFlags::GLOBAL as u8,
+            ptype,
+            length,
+            false,
+        )
+    }
+
     pub fn owner(&self) -> i16 {
         self.owner
     }
 
+    /// applet instance owning the object (null for the objects of the JCRE)
+    pub fn applet(&self) -> i16 {
+        self.applet
+    }
+
+    pub fn set_applet(&mut self, applet: i16) {
+        self.applet = applet;
+    }
+
+    /// class of the instance (None for arrays)
+    pub fn class_ref(&self) -> Option<ClassRef> {
+        if self.is_array() {
+            None
+        } else {
+            self.class_ref
+        }
+    }
+
+    /// class of the elements of an array of references (None for other objects)
+    pub fn component_class(&self) -> Option<ClassRef> {
+        if self.is_array() {
+            self.class_ref
+        } else {
+            None
+        }
+    }
+
     pub fn flags(&self) -> u8 {
         self.object_flags
     }
@@ -105,6 +208,10 @@ impl JCVMObject {
         self.object_length
     }
 
+    pub fn is_persistent(&self) -> bool {
+        self.persistent
+    }
+
     pub fn get(&self, offset: usize) -> Result<BytecodeType, InterpreterError> {
         let res = self.content
             .get(offset)
@@ -121,7 +228,38 @@ impl JCVMObject {
         }
     }
 
+    /// resets the content of the object to 0
+    pub fn clear(&mut self) {
+        for byte in self.content.iter_mut() {
+            *byte = 0;
+        }
+    }
+
     pub fn is_array(&self) -> bool {
-        ((self.flags() as u8) & (constants::ObjectFlags::ARRAY as u8)) != 0
+        (self.flags() & (constants::ObjectFlags::ARRAY as u8)) != 0
+    }
+
+    /// Indicates whether the object is a global array or a JCRE entry point
+    pub fn is_global(&self) -> bool {
+        (self.flags() & (constants::ObjectFlags::GLOBAL as u8)) != 0
+    }
+
+    pub fn is_entry_point(&self) -> bool {
+        self.is_global() && !self.is_array()
+    }
+
+    /// Indicates whether references to the object cannot be stored (global arrays and
+    /// temporary JCRE entry points)
+    pub fn is_temporary(&self) -> bool {
+        self.is_global()
+            && (self.is_array() || (self.flags() & (constants::ObjectFlags::TEMPORARY as u8)) != 0)
+    }
+
+    /// number of elements of an array
+    pub fn array_length(&self) -> i16 {
+        match self.primitive_type.size() {
+            0 => self.object_length,
+            size => self.object_length / size as i16,
+        }
     }
 }
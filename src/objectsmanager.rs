@@ -5,6 +5,12 @@ pub struct ObjectManager {
     objects_container: Vec<JCVMObject>,
 }
 
+impl Default for ObjectManager {
+    fn default() -> ObjectManager {
+        ObjectManager::new()
+    }
+}
+
 impl ObjectManager {
     pub fn new() -> ObjectManager {
         ObjectManager {
@@ -13,10 +19,15 @@ impl ObjectManager {
     }
 
     pub fn get_object(&self, index: usize) -> Result<&JCVMObject, InterpreterException> {
-        if index >= 1 {
-            if index <= self.objects_container.len() {
-                return Ok(&self.objects_container[index - 1]);
-            }
+        if index >= 1 && index <= self.objects_container.len() {
+            return Ok(&self.objects_container[index - 1]);
+        }
+        Err(InterpreterException::ArrayIndexOutOfBoundsException)
+    }
+
+    pub fn get_object_mut(&mut self, index: usize) -> Result<&mut JCVMObject, InterpreterException> {
+        if index >= 1 && index <= self.objects_container.len() {
+            return Ok(&mut self.objects_container[index - 1]);
         }
         Err(InterpreterException::ArrayIndexOutOfBoundsException)
     }
@@ -25,4 +36,15 @@ impl ObjectManager {
         self.objects_container.push(entry);
         self.objects_container.len()
     }
+
+    /// clears the content of the objects that are not persistent (on card reset)
+    pub fn clear_transient_objects(&mut self) {
+        for object in self
+            .objects_container
+            .iter_mut()
+            .filter(|object| !object.is_persistent())
+        {
+            object.clear();
+        }
+    }
 }
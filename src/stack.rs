@@ -6,7 +6,7 @@ use constants;
 pub type StackElementType = i16;
 
 // one entry in the stack
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone)]
 pub struct StackEntry {
     pub value: StackElementType,
     entry_type: constants::PrimitiveType,
@@ -18,6 +18,12 @@ impl HasType for StackEntry {
     }
 }
 
+impl Default for StackEntry {
+    fn default() -> StackEntry {
+        StackEntry::new()
+    }
+}
+
 // implementation associated to the StackEntry
 impl StackEntry {
     pub fn new() -> StackEntry {
@@ -34,58 +40,112 @@ impl StackEntry {
     }
 
     pub fn is_of_type(&self, type_checked: constants::PrimitiveType) -> bool {
-        return self.entry_type == type_checked;
+        self.entry_type == type_checked
+    }
+
+    pub fn entry_type(&self) -> constants::PrimitiveType {
+        self.entry_type
     }
 }
 
 // basic implementation of a stack
 pub struct Stack {
     internal_stack: Vec<StackEntry>,
+    // number of entries the stack was created for
+    size: usize,
+    // entries below floor cannot be accessed, and no entry can be pushed above ceiling
+    floor: usize,
+    ceiling: usize,
+    // the type of the entries is checked when they are popped or peeked with an expected type
+    type_checks: bool,
 }
 
 // associated methods/functions
 impl Stack {
-    pub fn new(capacity: usize) -> Stack {
+    pub fn new(size: usize) -> Stack {
         Stack {
-            internal_stack: Vec::with_capacity(capacity),
+            internal_stack: Vec::with_capacity(size),
+            size,
+            floor: 0,
+            ceiling: size,
+            type_checks: true,
         }
     }
 
-    // push a value on the stack
-    pub fn push(&mut self, value: StackEntry) {
-        if self.internal_stack.len() < self.internal_stack.capacity() {
-            self.internal_stack.push(value);
-        } else {
-            panic!("Stack overflow error, is it a malformed code that is run ?");
-        }
+    /// number of entries currently in the stack
+    pub fn len(&self) -> usize {
+        self.internal_stack.len()
     }
 
-    pub fn bpush(&mut self, value: i8) {
-        self.push(StackEntry::from_values(
-            value as i16,
-            constants::PrimitiveType::BYTE,
-        ))
+    pub fn is_empty(&self) -> bool {
+        self.internal_stack.is_empty()
+    }
+
+    /// restricts the accessible entries to the ones between floor (included) and ceiling
+    /// (excluded). The ceiling cannot go above the size of the stack
+    pub fn set_bounds(&mut self, floor: usize, ceiling: usize) {
+        self.floor = floor;
+        self.ceiling = ceiling.min(self.size);
+    }
+
+    /// makes the whole stack accessible again
+    pub fn clear_bounds(&mut self) {
+        self.floor = 0;
+        self.ceiling = self.size;
+    }
+
+    /// enables or disables the type checks of the entries (enabled by default)
+    pub fn set_type_checks(&mut self, enabled: bool) {
+        self.type_checks = enabled;
+    }
+
+    pub fn type_checks(&self) -> bool {
+        self.type_checks
+    }
+
+    /// number of entries between the floor and the top of the stack
+    fn accessible_len(&self) -> usize {
+        self.internal_stack.len().saturating_sub(self.floor)
     }
 
-    pub fn apush(&mut self, value: i16) {
+    /// drops the entries above the given length
+    pub fn truncate(&mut self, len: usize) {
+        self.internal_stack.truncate(len);
+    }
+
+    // push a value on the stack
+    pub fn push(&mut self, value: StackEntry) -> Result<(), InterpreterError> {
+        if self.internal_stack.len() >= self.ceiling {
+            return Err(InterpreterError::StackOverflowError);
+        }
+        self.internal_stack.push(value);
+        Ok(())
+    }
+
+    pub fn apush(&mut self, value: i16) -> Result<(), InterpreterError> {
         self.push(StackEntry::from_values(
             value,
             constants::PrimitiveType::REFERENCE,
         ))
     }
 
-    pub fn spush(&mut self, value: i16) {
+    pub fn spush(&mut self, value: i16) -> Result<(), InterpreterError> {
         self.push(StackEntry::from_values(
             value,
             constants::PrimitiveType::SHORT,
-        ));
+        ))
     }
 
-    pub fn ipush(&mut self, value: i32) {
+    /// pushes an integer on the stack, the 2 MSB being on top of the 2 LSB.
+    /// Nothing is pushed if there is no room for both halves
+    pub fn ipush(&mut self, value: i32) -> Result<(), InterpreterError> {
+        if self.internal_stack.len() + 2 > self.ceiling {
+            return Err(InterpreterError::StackOverflowError);
+        }
         self.push(StackEntry::from_values(
             (value & 0xFFFF) as i16,
             constants::PrimitiveType::INTEGER,
-        ));
+        ))?;
 
         self.push(StackEntry::from_values(
             (value >> 16) as i16,
@@ -94,32 +154,40 @@ impl Stack {
     }
 
     // removes top item and returns its value
-    pub fn pop(&mut self) -> Option<StackEntry> {
-        match self.internal_stack.pop() {
-            Some(res) => Some(res),
-            None => panic!("Stack underflow error ! Is it a malformed applet ?"),
+    pub fn pop(&mut self) -> Result<StackEntry, InterpreterError> {
+        if self.accessible_len() == 0 {
+            return Err(InterpreterError::StackUnderflowError);
         }
+        self.internal_stack
+            .pop()
+            .ok_or(InterpreterError::StackUnderflowError)
     }
+
     pub fn pop_check_type(
         &mut self,
         type_: constants::PrimitiveType,
     ) -> Result<StackEntry, InterpreterError> {
-        let result = match self.pop() {
-            Some(entry) => {
-                if !entry.is_of_type(type_) {
-                    return Err(InterpreterError::InvalidVariableType(
-                        entry.entry_type,
-                        type_,
-                    ));
-                }
-
-                return Ok(entry);
-            }
+        // the entry is left on the stack when its type does not match
+        let entry = self.top()?;
+        if self.type_checks && !entry.is_of_type(type_) {
+            return Err(InterpreterError::InvalidVariableType(
+                entry.entry_type,
+                type_,
+            ));
+        }
+        self.pop()
+    }
 
-            None => {
-                return Err(InterpreterError::StackUnderflowError);
-            }
-        };
+    /// pops a short value from the stack
+    pub fn spop(&mut self) -> Result<i16, InterpreterError> {
+        Ok(self.pop_check_type(constants::PrimitiveType::SHORT)?.value)
+    }
+
+    /// pops an integer from the stack (the 2 MSB are on top of the 2 LSB)
+    pub fn ipop(&mut self) -> Result<i32, InterpreterError> {
+        let high = self.pop_check_type(constants::PrimitiveType::INTEGER)?;
+        let low = self.pop_check_type(constants::PrimitiveType::INTEGER)?;
+        Ok(((u32::from(high.value as u16) << 16) | u32::from(low.value as u16)) as i32)
     }
 
     /// returns the top element of the stack without removing it from the stack
@@ -128,7 +196,7 @@ impl Stack {
     }
 
     pub fn peek_index(&self, index: i16) -> Result<StackEntry, InterpreterError> {
-        if self.internal_stack.len() > index as usize {
+        if self.accessible_len() > index as usize {
             Ok(self.internal_stack[self.internal_stack.len() - 1 - index as usize])
         } else {
             Err(InterpreterError::StackUnderflowError)
@@ -140,9 +208,9 @@ impl Stack {
         index: i16,
         type_: constants::PrimitiveType,
     ) -> Result<StackEntry, InterpreterError> {
-        if self.internal_stack.len() > index as usize {
+        if self.accessible_len() > index as usize {
             let current_val = self.internal_stack[self.internal_stack.len() - 1 - index as usize];
-            if current_val.entry_type == type_ {
+            if !self.type_checks || current_val.entry_type == type_ {
                 return Ok(current_val);
             }
             Err(InterpreterError::InvalidVariableType(
@@ -154,14 +222,28 @@ impl Stack {
         }
     }
 
+    /// inserts an entry so that it can be peeked afterwards at the given index
+    /// (index 0 pushes the entry on top of the stack)
+    pub fn insert_index(&mut self, index: i16, newval: StackEntry) -> Result<(), InterpreterError> {
+        if index < 0 || (index as usize) > self.accessible_len() {
+            return Err(InterpreterError::IndexOutOfBound);
+        }
+        if self.internal_stack.len() >= self.ceiling {
+            return Err(InterpreterError::StackOverflowError);
+        }
+        let position = self.internal_stack.len() - index as usize;
+        self.internal_stack.insert(position, newval);
+        Ok(())
+    }
+
     pub fn update_index(&mut self, index: i16, newval: StackEntry) -> Result<(), InterpreterError> {
-        if (index as usize) < self.internal_stack.len() {
+        if (index as usize) < self.accessible_len() {
             let maxlen = self.internal_stack.len();
             if let Some(value_to_update) =
                 self.internal_stack.get_mut(maxlen - (index as usize) - 1)
             {
-                (*value_to_update).value = newval.value;
-                (*value_to_update).entry_type = newval.entry_type;
+                value_to_update.value = newval.value;
+                value_to_update.entry_type = newval.entry_type;
                 return Ok(());
             }
         }
@@ -0,0 +1,191 @@
+use constants;
+use jcvmerrors::InterpreterError;
+use traits::BufferAccessor;
+use objects::JCVMObject;
+use objectsmanager::ObjectManager;
+use tearing::TearingSimulation;
+
+// index of a package static field image in the static field manager
+pub type PackageRef = usize;
+
+///
+/// Initial value of a static array field (see array_init_info in the Static Field component)
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArrayInit {
+    // type of the elements (booleans are bytes)
+    pub type_: constants::PrimitiveType,
+    // raw content of the array
+    pub values: Vec<i8>,
+}
+
+///
+/// Content of the Static Field component of a package. The image is laid out as follows:
+/// - references to the arrays initialized by array_init (2 bytes each)
+/// - the other references (2 bytes each)
+/// - primitive fields initialized to their default value (default_value_count bytes)
+/// - primitive fields with a non default value (non_default_values)
+///
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StaticFieldComponent {
+    pub image_size: u16,
+    pub reference_count: u16,
+    pub array_init: Vec<ArrayInit>,
+    pub default_value_count: u16,
+    pub non_default_values: Vec<i8>,
+}
+
+///
+/// Static fields of a package
+///
+pub struct StaticFieldImage {
+    content: Vec<i8>,
+}
+
+impl BufferAccessor for StaticFieldImage {
+    fn read_b(&self, offset: usize) -> Result<i8, InterpreterError> {
+        let res = self.content
+            .get(offset)
+            .ok_or(InterpreterError::IndexOutOfBound)?;
+        Ok(*res)
+    }
+
+    fn read_s(&self, offset: usize) -> Result<i16, InterpreterError> {
+        let r = u16::from(self.read_b(offset)? as u8) << 8
+            | u16::from(self.read_b(offset + 1)? as u8);
+        Ok(r as i16)
+    }
+
+    fn read_i(&self, offset: usize) -> Result<i32, InterpreterError> {
+        let r1 = self.read_s(offset)? as u16;
+        let r2 = self.read_s(offset + 2)? as u16;
+        Ok(((u32::from(r1) << 16) | u32::from(r2)) as i32)
+    }
+
+    fn write_b(&mut self, offset: usize, val: i8) -> Result<(), InterpreterError> {
+        match self.content.get_mut(offset) {
+            Some(entry) => {
+                *entry = val;
+                Ok(())
+            }
+            None => Err(InterpreterError::IndexOutOfBound),
+        }
+    }
+
+    fn write_s(&mut self, offset: usize, val: i16) -> Result<(), InterpreterError> {
+        self.write_b(offset, (val >> 8) as i8)?;
+        self.write_b(offset + 1, val as i8)
+    }
+
+    fn write_i(&mut self, offset: usize, val: i32) -> Result<(), InterpreterError> {
+        self.write_s(offset, (val >> 16) as i16)?;
+        self.write_s(offset + 2, val as i16)
+    }
+}
+
+impl StaticFieldImage {
+    ///
+    /// Builds the static field image described by the component. The arrays initialized by
+    /// array_init are allocated in the object manager, on behalf of the given owner. The initial
+    /// values are persistent writes, accounted for by the card tearing simulation
+    ///
+    pub fn from_component(
+        component: &StaticFieldComponent,
+        owner: i16,
+        object_manager: &mut ObjectManager,
+        tearing: &mut TearingSimulation,
+    ) -> Result<StaticFieldImage, InterpreterError> {
+        let mut result = StaticFieldImage {
+            content: vec![0; component.image_size as usize],
+        };
+
+        if component.array_init.len() > component.reference_count as usize {
+            return Err(InterpreterError::InvalidOperand);
+        }
+        for (index, array_init) in component.array_init.iter().enumerate() {
+            let array = JCVMObject::new_array(
+                owner,
+                0,
+                array_init.type_,
+                array_init.values.len() as i16,
+                true,
+            );
+            let arrayref = object_manager.add_object(array);
+            {
+                let array = object_manager
+                    .get_object_mut(arrayref)
+                    .map_err(|_| InterpreterError::IndexOutOfBound)?;
+                tearing.write(array, 0, &array_init.values)?;
+            }
+            tearing.write(
+                &mut result,
+                index * constants::REFERENCE_SIZE,
+                &[(arrayref >> 8) as i8, arrayref as i8],
+            )?;
+        }
+
+        let non_default_offset = component.reference_count as usize * constants::REFERENCE_SIZE
+            + component.default_value_count as usize;
+        tearing.write(
+            &mut result,
+            non_default_offset,
+            &component.non_default_values,
+        )?;
+
+        Ok(result)
+    }
+
+    pub fn len(&self) -> usize {
+        self.content.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.content.is_empty()
+    }
+}
+
+pub struct StaticFieldManager {
+    images: Vec<StaticFieldImage>,
+}
+
+impl Default for StaticFieldManager {
+    fn default() -> StaticFieldManager {
+        StaticFieldManager::new()
+    }
+}
+
+impl StaticFieldManager {
+    pub fn new() -> StaticFieldManager {
+        StaticFieldManager { images: Vec::new() }
+    }
+
+    /// number of static field images
+    pub fn len(&self) -> usize {
+        self.images.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty()
+    }
+
+    /// adds the static field image of a package and returns its reference
+    pub fn add_image(&mut self, image: StaticFieldImage) -> PackageRef {
+        self.images.push(image);
+        self.images.len() - 1
+    }
+
+    pub fn get_image(&self, package: PackageRef) -> Result<&StaticFieldImage, InterpreterError> {
+        self.images
+            .get(package)
+            .ok_or(InterpreterError::InvalidPackageReference)
+    }
+
+    pub fn get_image_mut(
+        &mut self,
+        package: PackageRef,
+    ) -> Result<&mut StaticFieldImage, InterpreterError> {
+        self.images
+            .get_mut(package)
+            .ok_or(InterpreterError::InvalidPackageReference)
+    }
+}
@@ -0,0 +1,103 @@
+use constants;
+use context::Context;
+use jcvmerrors::InterpreterError;
+use traits::BufferAccessor;
+use transactions;
+
+///
+/// Card tearing simulation, meant for tests: once armed, the card is torn (powered off) after
+/// a given number of persistent writes, i.e. bytes written to persistent objects or static
+/// fields (see TearingSimulation::write). From then on, every persistent write fails with a
+/// CardTorn error, which leaves the interpreter, until the card is reset
+///
+#[derive(Debug, Default)]
+pub struct TearingSimulation {
+    // persistent writes left before the card is torn, None when the simulation is disarmed
+    remaining: Option<usize>,
+    // persistent writes done since the simulation was armed or disarmed
+    writes: usize,
+    torn: bool,
+}
+
+impl TearingSimulation {
+    pub fn new() -> TearingSimulation {
+        TearingSimulation::default()
+    }
+
+    ///
+    /// Tears the card after the given number of persistent writes (0 tears it on the first one)
+    ///
+    pub fn arm(&mut self, writes: usize) {
+        self.remaining = Some(writes);
+        self.writes = 0;
+        self.torn = false;
+    }
+
+    ///
+    /// Stops the simulation. The persistent writes keep being counted, so that the number of
+    /// writes done by some code can be known before sweeping it
+    ///
+    pub fn disarm(&mut self) {
+        self.remaining = None;
+        self.writes = 0;
+        self.torn = false;
+    }
+
+    pub fn is_torn(&self) -> bool {
+        self.torn
+    }
+
+    /// persistent writes done since the simulation was armed or disarmed
+    pub fn persistent_writes(&self) -> usize {
+        self.writes
+    }
+
+    ///
+    /// Accounts for a persistent write about to be done, failing if the card is torn
+    ///
+    pub fn persistent_write(&mut self) -> Result<(), InterpreterError> {
+        if self.remaining == Some(0) {
+            self.torn = true;
+        }
+        if self.torn {
+            return Err(InterpreterError::CardTorn);
+        }
+        self.remaining = self.remaining.map(|remaining| remaining - 1);
+        self.writes += 1;
+        Ok(())
+    }
+
+    ///
+    /// Writes the bytes at the offset of a persistent buffer, each of them being accounted for
+    /// before it is written. Persistent storage (persistent objects and static field images)
+    /// is only written through this method, so that no persistent write escapes the simulation
+    ///
+    pub fn write(
+        &mut self,
+        buffer: &mut dyn BufferAccessor,
+        offset: usize,
+        bytes: &[i8],
+    ) -> Result<(), InterpreterError> {
+        for (index, byte) in bytes.iter().enumerate() {
+            self.persistent_write()?;
+            buffer.write_b(offset + index, *byte)?;
+        }
+        Ok(())
+    }
+}
+
+///
+/// Simulates a reset of the card once it is torn: the simulation is disarmed, the execution
+/// state and the transient objects are cleared, and the interrupted updates of the persistent
+/// storage are undone (see transactions::recover)
+///
+pub fn reset(ctx: &mut Context) -> Result<(), InterpreterError> {
+    ctx.tearing.disarm();
+    ctx.operand_stack.truncate(0);
+    ctx.operand_stack.clear_bounds();
+    ctx.frame_stack.clear();
+    ctx.current_context = constants::JCRE_CONTEXT;
+    ctx.current_applet = constants::NULL_HANDLE;
+    ctx.object_manager.clear_transient_objects();
+    transactions::recover(ctx)
+}
@@ -0,0 +1,287 @@
+use constants;
+use context::Context;
+use exceptions::{throw_exception, InterpreterException};
+use jcvmerrors::InterpreterError;
+use objectsmanager::ObjectManager;
+use staticfields::{PackageRef, StaticFieldManager};
+use traits::BufferAccessor;
+
+// reason codes of TransactionException
+pub const IN_PROGRESS: i16 = 1;
+pub const NOT_IN_PROGRESS: i16 = 2;
+pub const BUFFER_FULL: i16 = 3;
+pub const INTERNAL_FAILURE: i16 = 4;
+
+// default size of the commit buffer, in bytes
+pub const DEFAULT_COMMIT_CAPACITY: usize = 512;
+// bytes used in the commit buffer by each entry besides its values (location and offset)
+pub const ENTRY_OVERHEAD: usize = 4;
+
+///
+/// Persistent storage updated by a write
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Location {
+    // field or array element of the referenced object
+    Object(i16),
+    // static field of the package
+    Static(PackageRef),
+}
+
+///
+/// Entry of the commit buffer: the value of the bytes of a location before (undo) and after
+/// (redo) a write
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogEntry {
+    pub location: Location,
+    pub offset: usize,
+    pub undo: Vec<i8>,
+    pub redo: Vec<i8>,
+}
+
+impl LogEntry {
+    /// bytes used by the entry in the commit buffer
+    pub fn size(&self) -> usize {
+        ENTRY_OVERHEAD + self.undo.len()
+    }
+}
+
+///
+/// State of the transaction of the JCRE (see chapter 7 of the JCRE specification). Writes to
+/// persistent storage are applied in place and recorded in the commit buffer while a
+/// transaction is in progress: commit discards the entries, abort restores the values they
+/// recorded, most recent first. Transactions cannot be nested.
+/// Outside of transactions, each write to persistent storage is recorded as well until it is
+/// done, so that single updates are atomic
+///
+pub struct TransactionManager {
+    capacity: usize,
+    in_progress: bool,
+    commit_buffer: Vec<LogEntry>,
+    // write to persistent storage in progress outside of a transaction
+    atomic_update: Option<LogEntry>,
+}
+
+impl Default for TransactionManager {
+    fn default() -> TransactionManager {
+        TransactionManager::new(DEFAULT_COMMIT_CAPACITY)
+    }
+}
+
+impl TransactionManager {
+    pub fn new(capacity: usize) -> TransactionManager {
+        TransactionManager {
+            capacity,
+            in_progress: false,
+            commit_buffer: Vec::new(),
+            atomic_update: None,
+        }
+    }
+
+    /// size of the commit buffer, in bytes
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+    }
+
+    pub fn in_progress(&self) -> bool {
+        self.in_progress
+    }
+
+    /// JCSystem.getTransactionDepth: 1 while a transaction is in progress, 0 otherwise
+    pub fn depth(&self) -> i8 {
+        if self.in_progress {
+            1
+        } else {
+            0
+        }
+    }
+
+    /// bytes of the commit buffer used by the transaction in progress
+    pub fn used_capacity(&self) -> usize {
+        self.commit_buffer.iter().map(LogEntry::size).sum()
+    }
+
+    /// JCSystem.getUnusedCommitCapacity (saturated to the range of a short)
+    pub fn unused_capacity(&self) -> i16 {
+        let unused = self.capacity.saturating_sub(self.used_capacity());
+        unused.min(i16::MAX as usize) as i16
+    }
+
+    pub fn commit_buffer(&self) -> &[LogEntry] {
+        &self.commit_buffer
+    }
+
+    pub fn begin(&mut self) -> Result<(), InterpreterException> {
+        if self.in_progress {
+            return Err(InterpreterException::TransactionException(IN_PROGRESS));
+        }
+        self.in_progress = true;
+        Ok(())
+    }
+
+    /// the values written by the transaction become permanent
+    pub fn commit(&mut self) -> Result<(), InterpreterException> {
+        if !self.in_progress {
+            return Err(InterpreterException::TransactionException(NOT_IN_PROGRESS));
+        }
+        self.commit_buffer.clear();
+        self.in_progress = false;
+        Ok(())
+    }
+
+    ///
+    /// Records the entry in the commit buffer. A full commit buffer leaves the transaction in
+    /// progress, the write being rejected
+    ///
+    fn record(&mut self, entry: LogEntry) -> Result<(), InterpreterException> {
+        if self.used_capacity() + entry.size() > self.capacity {
+            return Err(InterpreterException::TransactionException(BUFFER_FULL));
+        }
+        self.commit_buffer.push(entry);
+        Ok(())
+    }
+}
+
+///
+/// Storage designated by the location, along with its persistence
+///
+fn storage<'b>(
+    object_manager: &'b mut ObjectManager,
+    static_fields: &'b mut StaticFieldManager,
+    location: Location,
+) -> Result<(&'b mut dyn BufferAccessor, bool), InterpreterError> {
+    match location {
+        Location::Object(objectref) => {
+            // the objects written by the opcodes have been checked by them
+            let object = object_manager
+                .get_object_mut(objectref as usize)
+                .map_err(|_| InterpreterError::InvalidObjectReference)?;
+            let persistent = object.is_persistent();
+            Ok((object, persistent))
+        }
+        Location::Static(package) => Ok((static_fields.get_image_mut(package)?, true)),
+    }
+}
+
+///
+/// Writes the bytes at the offset of the location. Each byte written to persistent storage is
+/// accounted for by the card tearing simulation, the write failing once the card is torn
+///
+fn write_bytes(
+    ctx: &mut Context,
+    location: Location,
+    offset: usize,
+    bytes: &[i8],
+) -> Result<(), InterpreterError> {
+    let (buffer, persistent) =
+        storage(&mut ctx.object_manager, &mut ctx.static_fields, location)?;
+    if persistent {
+        return ctx.tearing.write(buffer, offset, bytes);
+    }
+    for (index, byte) in bytes.iter().enumerate() {
+        buffer.write_b(offset + index, *byte)?;
+    }
+    Ok(())
+}
+
+///
+/// Writes the value of the given type (truncated to its size) at the offset of the location.
+/// While a transaction is in progress, writes to persistent storage are recorded in the commit
+/// buffer first, and a TransactionException is thrown if it is full
+///
+pub fn write(
+    ctx: &mut Context,
+    location: Location,
+    offset: usize,
+    type_: constants::PrimitiveType,
+    value: i32,
+) -> Result<(), InterpreterError> {
+    // big endian, as the BufferAccessor implementations
+    let size = type_.size();
+    let redo: Vec<i8> = (0..size)
+        .map(|index| (value >> (8 * (size - 1 - index))) as i8)
+        .collect();
+
+    let undo = {
+        let (buffer, persistent) =
+            storage(&mut ctx.object_manager, &mut ctx.static_fields, location)?;
+        if !persistent {
+            None
+        } else {
+            Some(
+                (0..size)
+                    .map(|index| buffer.read_b(offset + index))
+                    .collect::<Result<Vec<i8>, InterpreterError>>()?,
+            )
+        }
+    };
+    let entry = match undo {
+        Some(undo) => LogEntry {
+            location,
+            offset,
+            undo,
+            redo: redo.clone(),
+        },
+        None => return write_bytes(ctx, location, offset, &redo),
+    };
+
+    if ctx.transaction.in_progress() {
+        if let Err(e) = ctx.transaction.record(entry) {
+            return throw_exception(ctx, e);
+        }
+        write_bytes(ctx, location, offset, &redo)
+    } else {
+        ctx.transaction.atomic_update = Some(entry);
+        write_bytes(ctx, location, offset, &redo)?;
+        ctx.transaction.atomic_update = None;
+        Ok(())
+    }
+}
+
+///
+/// Restores the values recorded in the commit buffer, most recent first, and ends the
+/// transaction. The entries are only discarded once all of them are restored, so that an
+/// interrupted rollback can be resumed
+///
+fn rollback(ctx: &mut Context) -> Result<(), InterpreterError> {
+    let entries = ctx.transaction.commit_buffer.clone();
+    for entry in entries.iter().rev() {
+        write_bytes(ctx, entry.location, entry.offset, &entry.undo)?;
+    }
+    ctx.transaction.commit_buffer.clear();
+    ctx.transaction.in_progress = false;
+    Ok(())
+}
+
+///
+/// JCSystem.abortTransaction: restores the values recorded in the commit buffer
+///
+pub fn abort(ctx: &mut Context) -> Result<(), InterpreterError> {
+    if !ctx.transaction.in_progress() {
+        return throw_exception(
+            ctx,
+            InterpreterException::TransactionException(NOT_IN_PROGRESS),
+        );
+    }
+    rollback(ctx)
+}
+
+///
+/// Recovery of the persistent storage when the card is powered up: the write interrupted
+/// outside of a transaction, if any, and the transaction in progress, if any, are undone
+///
+pub fn recover(ctx: &mut Context) -> Result<(), InterpreterError> {
+    if let Some(entry) = ctx.transaction.atomic_update.clone() {
+        write_bytes(ctx, entry.location, entry.offset, &entry.undo)?;
+        ctx.transaction.atomic_update = None;
+    }
+    if ctx.transaction.in_progress() {
+        rollback(ctx)?;
+    }
+    Ok(())
+}
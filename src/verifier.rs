@@ -0,0 +1,1509 @@
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+use std::fmt;
+
+use bcutils::BytecodeFetcher;
+use bytecodes::bytecode;
+use capcomponents::{CapClassRef, CapConstantPoolEntry, DescriptorComponent, MethodDescriptor,
+                    ACC_INT, ACC_STATIC, COMPONENT_DESCRIPTOR};
+use capfile::CapFile;
+use constants::{PrimitiveType, T_BOOLEAN, T_INT, T_REFERENCE};
+use exceptions::ExceptionHandler;
+use exportfile::ExportFile;
+use interpreter::BytecodeType;
+use interpreterutils::{array_type, field_type};
+use jcvmerrors::VerifyError;
+use methods::MethodHeader;
+
+// values of the nibbles of the type descriptors
+const TYPE_VOID: u8 = 0x1;
+const TYPE_BOOLEAN: u8 = 0x2;
+const TYPE_BYTE: u8 = 0x3;
+const TYPE_SHORT: u8 = 0x4;
+const TYPE_INT: u8 = 0x5;
+const TYPE_REFERENCE: u8 = 0x6;
+const TYPE_BOOLEAN_ARRAY: u8 = 0xA;
+const TYPE_INT_ARRAY: u8 = 0xD;
+const TYPE_REFERENCE_ARRAY: u8 = 0xE;
+// class references of the type descriptors take 4 nibbles
+const CLASS_REF_NIBBLES: usize = 4;
+
+///
+/// Error found by the verifier, along with its location: offsets are relative to the
+/// Method component, the method being None for the errors concerning the whole package
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerificationError {
+    pub error: VerifyError,
+    // offset of the header of the method being verified
+    pub method_offset: Option<u16>,
+    // offset of the opcode being verified (None for the errors found in the method header
+    // or in its exception handlers)
+    pub opcode_offset: Option<usize>,
+    pub opcode: Option<bytecode>,
+}
+
+impl VerificationError {
+    fn new(error: VerifyError, method_offset: Option<u16>) -> VerificationError {
+        VerificationError {
+            error,
+            method_offset,
+            opcode_offset: None,
+            opcode: None,
+        }
+    }
+}
+
+impl fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.error)?;
+        if let Some(method_offset) = self.method_offset {
+            write!(f, " in method at 0x{:04X}", method_offset)?;
+        }
+        match (self.opcode, self.opcode_offset) {
+            (Some(opcode), Some(offset)) => write!(f, " ({:?} at 0x{:04X})", opcode, offset),
+            (None, Some(offset)) => write!(f, " (at 0x{:04X})", offset),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Error for VerificationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+///
+/// Verifies the methods of a package before it is loaded: the types of the operand stack
+/// words and of the locals are inferred for every instruction, so that the runtime type
+/// checks of the interpreter cannot fail on verified code.
+/// The Descriptor component gives the methods and the types of the constant pool entries,
+/// the export files of the imported packages give the signatures of the methods of
+/// external interfaces.
+///
+pub fn verify(cap: &CapFile, imports: &[ExportFile]) -> Result<(), VerificationError> {
+    let descriptor = cap.descriptor.as_ref().ok_or_else(|| {
+        VerificationError::new(VerifyError::MissingComponent(COMPONENT_DESCRIPTOR), None)
+    })?;
+    let package = PackageVerifier {
+        cap,
+        descriptor,
+        imports,
+        code: cap.method
+            .methods
+            .iter()
+            .map(|byte| *byte as BytecodeType)
+            .collect(),
+        int_support: cap.header.flags & ACC_INT != 0,
+    };
+
+    for class in &descriptor.classes {
+        for method in &class.methods {
+            package.verify_method(method)?;
+        }
+    }
+    Ok(())
+}
+
+///
+/// Type of an operand stack word or of a local variable, as inferred by the verifier
+/// (bytes and booleans are represented as shorts)
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum WordType {
+    // uninitialized local, or words of different types merged together
+    Unusable,
+    Short,
+    Reference,
+    // 2 MSB of an integer, on top of its 2 LSB on the operand stack and before them in locals
+    IntegerHigh,
+    IntegerLow,
+    ReturnAddress,
+}
+
+impl WordType {
+    fn primitive_type(self) -> PrimitiveType {
+        match self {
+            WordType::Unusable => PrimitiveType::UNKNOWN,
+            WordType::Short => PrimitiveType::SHORT,
+            WordType::Reference => PrimitiveType::REFERENCE,
+            WordType::IntegerHigh | WordType::IntegerLow => PrimitiveType::INTEGER,
+            WordType::ReturnAddress => PrimitiveType::RETURNADDRESS,
+        }
+    }
+
+    fn merge(self, other: WordType) -> WordType {
+        if self == other {
+            self
+        } else {
+            WordType::Unusable
+        }
+    }
+}
+
+/// words of a value of the given type, the deepest one first
+fn words_of(type_: PrimitiveType) -> Vec<WordType> {
+    match type_ {
+        PrimitiveType::INTEGER => vec![WordType::IntegerLow, WordType::IntegerHigh],
+        PrimitiveType::REFERENCE => vec![WordType::Reference],
+        PrimitiveType::RETURNADDRESS => vec![WordType::ReturnAddress],
+        _ => vec![WordType::Short],
+    }
+}
+
+///
+/// Types of the arguments and of the result of a method (BYTE for booleans and bytes,
+/// REFERENCE for classes and arrays)
+///
+#[derive(Debug, Clone, PartialEq)]
+struct Signature {
+    arguments: Vec<PrimitiveType>,
+    result: Option<PrimitiveType>,
+}
+
+impl Signature {
+    fn from_types(mut types: Vec<Option<PrimitiveType>>) -> Option<Signature> {
+        let result = types.pop()?;
+        let arguments = types.into_iter().collect::<Option<Vec<_>>>()?;
+        Some(Signature { arguments, result })
+    }
+
+    ///
+    /// Decodes a method descriptor of an export file, such as (SLjava/lang/Object;)[B
+    ///
+    fn from_java_descriptor(descriptor: &str) -> Option<Signature> {
+        let bytes = descriptor.as_bytes();
+        if bytes.first() != Some(&b'(') {
+            return None;
+        }
+        let mut index = 1;
+        let mut arguments = Vec::new();
+        while *bytes.get(index)? != b')' {
+            let (type_, next) = java_type(bytes, index)?;
+            arguments.push(type_?);
+            index = next;
+        }
+        let (result, next) = java_type(bytes, index + 1)?;
+        if next != bytes.len() {
+            return None;
+        }
+        Some(Signature { arguments, result })
+    }
+
+    /// number of words taken by the arguments
+    fn words(&self) -> usize {
+        self.arguments
+            .iter()
+            .map(|type_| words_of(*type_).len())
+            .sum()
+    }
+}
+
+///
+/// Decodes the type of a Java descriptor starting at the given index (None for void),
+/// and returns it with the index following it
+///
+fn java_type(bytes: &[u8], index: usize) -> Option<(Option<PrimitiveType>, usize)> {
+    match *bytes.get(index)? {
+        b'V' => Some((None, index + 1)),
+        b'Z' | b'B' => Some((Some(PrimitiveType::BYTE), index + 1)),
+        b'S' => Some((Some(PrimitiveType::SHORT), index + 1)),
+        b'I' => Some((Some(PrimitiveType::INTEGER), index + 1)),
+        b'L' => {
+            let length = bytes[index..].iter().position(|byte| *byte == b';')?;
+            Some((Some(PrimitiveType::REFERENCE), index + length + 1))
+        }
+        b'[' => {
+            let (element, next) = java_type(bytes, index + 1)?;
+            element?;
+            Some((Some(PrimitiveType::REFERENCE), next))
+        }
+        _ => None,
+    }
+}
+
+///
+/// Decodes the types of the nibbles of a type descriptor (None for void)
+///
+fn decode_types(nibbles: &[u8]) -> Option<Vec<Option<PrimitiveType>>> {
+    let mut types = Vec::new();
+    let mut index = 0;
+    while index < nibbles.len() {
+        let type_ = match nibbles[index] {
+            TYPE_VOID => None,
+            TYPE_BOOLEAN | TYPE_BYTE => Some(PrimitiveType::BYTE),
+            TYPE_SHORT => Some(PrimitiveType::SHORT),
+            TYPE_INT => Some(PrimitiveType::INTEGER),
+            TYPE_REFERENCE | TYPE_REFERENCE_ARRAY => {
+                index += CLASS_REF_NIBBLES;
+                Some(PrimitiveType::REFERENCE)
+            }
+            TYPE_BOOLEAN_ARRAY..=TYPE_INT_ARRAY => Some(PrimitiveType::REFERENCE),
+            _ => return None,
+        };
+        types.push(type_);
+        index += 1;
+    }
+    if index != nibbles.len() {
+        return None;
+    }
+    Some(types)
+}
+
+///
+/// Types of the locals and of the operand stack words before an instruction
+///
+#[derive(Debug, Clone, PartialEq)]
+struct TypeState {
+    locals: Vec<WordType>,
+    // the top of the operand stack is the last word
+    stack: Vec<WordType>,
+}
+
+impl TypeState {
+    fn merge(&self, other: &TypeState) -> TypeState {
+        let merge_words = |words: &[WordType], others: &[WordType]| {
+            words
+                .iter()
+                .zip(others)
+                .map(|(word, other)| word.merge(*other))
+                .collect()
+        };
+        TypeState {
+            locals: merge_words(&self.locals, &other.locals),
+            stack: merge_words(&self.stack, &other.stack),
+        }
+    }
+
+    fn push(&mut self, word: WordType, max_stack: usize) -> Result<(), VerifyError> {
+        if self.stack.len() >= max_stack {
+            return Err(VerifyError::StackOverflow);
+        }
+        self.stack.push(word);
+        Ok(())
+    }
+
+    fn push_type(&mut self, type_: PrimitiveType, max_stack: usize) -> Result<(), VerifyError> {
+        for word in words_of(type_) {
+            self.push(word, max_stack)?;
+        }
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<WordType, VerifyError> {
+        self.stack.pop().ok_or(VerifyError::StackUnderflow)
+    }
+
+    /// pops a value of the given type (shorts for bytes), checking its words
+    fn pop_type(&mut self, type_: PrimitiveType) -> Result<(), VerifyError> {
+        for expected in words_of(type_).into_iter().rev() {
+            let word = self.pop()?;
+            if word != expected {
+                return Err(type_error(word, expected));
+            }
+        }
+        Ok(())
+    }
+
+    fn peek(&self) -> Result<WordType, VerifyError> {
+        self.stack.last().cloned().ok_or(VerifyError::StackUnderflow)
+    }
+
+    ///
+    /// Makes sure the top count words of the operand stack can be moved as a whole,
+    /// i.e. that the deepest of them is not the 2 MSB of an integer
+    ///
+    fn check_words(&self, count: usize) -> Result<(), VerifyError> {
+        if count > self.stack.len() {
+            return Err(VerifyError::StackUnderflow);
+        }
+        if count > 0 && self.stack[self.stack.len() - count] == WordType::IntegerHigh {
+            return Err(VerifyError::SplitInteger);
+        }
+        Ok(())
+    }
+
+    fn local(&self, index: usize) -> Result<WordType, VerifyError> {
+        self.locals
+            .get(index)
+            .cloned()
+            .ok_or(VerifyError::InvalidLocalIndex(index as u8))
+    }
+
+    /// checks that a value of the given type is stored at the given index
+    fn check_local(&self, index: u8, type_: PrimitiveType) -> Result<(), VerifyError> {
+        for (offset, expected) in locals_of(type_).into_iter().enumerate() {
+            let word = self.local(index as usize + offset)?;
+            if word != expected {
+                return Err(type_error(word, expected));
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// Stores words at the given index, the integers they overwrite half of becoming
+    /// unusable
+    ///
+    fn store(&mut self, index: u8, words: &[WordType]) -> Result<(), VerifyError> {
+        let index = index as usize;
+        if index + words.len() > self.locals.len() {
+            return Err(VerifyError::InvalidLocalIndex((index + words.len() - 1) as u8));
+        }
+        for current in index..index + words.len() {
+            match self.locals[current] {
+                WordType::IntegerHigh if current + 1 < self.locals.len() => {
+                    self.locals[current + 1] = WordType::Unusable;
+                }
+                WordType::IntegerLow if current > 0 => {
+                    self.locals[current - 1] = WordType::Unusable;
+                }
+                _ => {}
+            }
+        }
+        self.locals[index..index + words.len()].copy_from_slice(words);
+        Ok(())
+    }
+}
+
+/// locals taken by a value of the given type, in order
+fn locals_of(type_: PrimitiveType) -> Vec<WordType> {
+    let mut words = words_of(type_);
+    words.reverse();
+    words
+}
+
+/// error raised when a word is found instead of the expected one
+fn type_error(word: WordType, expected: WordType) -> VerifyError {
+    let integer = |word| word == WordType::IntegerHigh || word == WordType::IntegerLow;
+    if integer(word) && integer(expected) {
+        VerifyError::SplitInteger
+    } else {
+        VerifyError::InvalidType(word.primitive_type(), expected.primitive_type())
+    }
+}
+
+///
+/// Elements of the package shared by the verification of its methods
+///
+struct PackageVerifier<'a> {
+    cap: &'a CapFile,
+    descriptor: &'a DescriptorComponent,
+    imports: &'a [ExportFile],
+    // info of the Method component
+    code: Vec<BytecodeType>,
+    int_support: bool,
+}
+
+impl<'a> PackageVerifier<'a> {
+    fn verify_method(&self, method: &MethodDescriptor) -> Result<(), VerificationError> {
+        // abstract methods have no code
+        if method.method_offset == 0 {
+            return Ok(());
+        }
+        let method_error = |error| VerificationError::new(error, Some(method.method_offset));
+
+        let fetcher = BytecodeFetcher::new(&self.code);
+        let header = MethodHeader::read(&fetcher, method.method_offset as usize)
+            .map_err(|_| method_error(VerifyError::InvalidMethodHeader))?;
+        if header.is_abstract() {
+            return Ok(());
+        }
+        let signature = self.signature_at(method.type_offset)
+            .map_err(method_error)?;
+        let static_method = method.access_flags & ACC_STATIC != 0;
+        let this_words = if static_method { 0 } else { 1 };
+        let code_end = header.code_offset + method.bytecode_count as usize;
+        if header.nargs as usize != signature.words() + this_words
+            || code_end > self.code.len()
+        {
+            return Err(method_error(VerifyError::InvalidMethodHeader));
+        }
+        let first_handler = method.exception_handler_index as usize;
+        let handlers = self.cap
+            .method
+            .exception_handlers
+            .get(first_handler..first_handler + method.exception_handler_count as usize)
+            .ok_or_else(|| {
+                method_error(VerifyError::InvalidExceptionHandler(
+                    method.exception_handler_index,
+                ))
+            })?;
+
+        // the arguments are the first locals, 'this' being local 0 of instance methods
+        let mut locals = Vec::new();
+        if !static_method {
+            locals.push(WordType::Reference);
+        }
+        for argument in &signature.arguments {
+            locals.extend(locals_of(*argument));
+        }
+        locals.resize(
+            header.nargs as usize + header.max_locals as usize,
+            WordType::Unusable,
+        );
+
+        let mut verifier = MethodVerifier {
+            package: self,
+            fetcher,
+            header,
+            code_end,
+            first_handler: method.exception_handler_index,
+            handlers,
+            result: signature.result,
+            instructions: BTreeSet::new(),
+            return_points: Vec::new(),
+            states: HashMap::new(),
+            pending: Vec::new(),
+        };
+        verifier
+            .run(TypeState {
+                locals,
+                stack: Vec::new(),
+            })
+            .map_err(|mut error| {
+                error.method_offset = Some(method.method_offset);
+                error
+            })
+    }
+
+    fn constant(&self, index: u16) -> Result<CapConstantPoolEntry, VerifyError> {
+        self.cap
+            .constant_pool
+            .entries
+            .get(index as usize)
+            .cloned()
+            .ok_or(VerifyError::InvalidConstantPoolIndex(index))
+    }
+
+    fn types_at(&self, offset: u16) -> Result<Vec<Option<PrimitiveType>>, VerifyError> {
+        self.descriptor
+            .type_descriptor(offset)
+            .and_then(|descriptor| decode_types(&descriptor.nibbles()))
+            .ok_or(VerifyError::InvalidDescriptor(offset))
+    }
+
+    fn signature_at(&self, offset: u16) -> Result<Signature, VerifyError> {
+        Signature::from_types(self.types_at(offset)?).ok_or(VerifyError::InvalidDescriptor(offset))
+    }
+
+    /// offset of the type of the constant pool entry at the given index
+    fn constant_type_offset(&self, index: u16) -> Result<u16, VerifyError> {
+        self.descriptor
+            .constant_pool_types
+            .get(index as usize)
+            .cloned()
+            .ok_or(VerifyError::InvalidConstantPoolIndex(index))
+    }
+
+    /// type of the field referenced by the constant pool entry at the given index
+    fn field_type(&self, index: u16) -> Result<PrimitiveType, VerifyError> {
+        let offset = self.constant_type_offset(index)?;
+        match self.types_at(offset)?.as_slice() {
+            [Some(type_)] => Ok(*type_),
+            _ => Err(VerifyError::InvalidDescriptor(offset)),
+        }
+    }
+
+    /// signature of the method referenced by the constant pool entry at the given index
+    fn method_signature(&self, index: u16) -> Result<Signature, VerifyError> {
+        self.signature_at(self.constant_type_offset(index)?)
+    }
+
+    ///
+    /// Signature of the method of the given interface with the given token, found in the
+    /// Descriptor component or in the export file of the package of the interface
+    ///
+    fn interface_signature(
+        &self,
+        interface: CapClassRef,
+        token: u8,
+    ) -> Result<Signature, VerifyError> {
+        match interface {
+            CapClassRef::Internal(_) => {
+                let method = self.descriptor
+                    .classes
+                    .iter()
+                    .filter(|class| class.this_class_ref == interface)
+                    .flat_map(|class| class.methods.iter())
+                    .find(|method| method.token == token)
+                    .ok_or(VerifyError::UnresolvedMethod)?;
+                self.signature_at(method.type_offset)
+            }
+            CapClassRef::External {
+                package_token,
+                class_token,
+            } => {
+                let package = self.cap
+                    .import
+                    .packages
+                    .get(package_token as usize)
+                    .ok_or(VerifyError::UnresolvedMethod)?;
+                let method = self.imports
+                    .iter()
+                    .filter(|export| export.package.aid == package.aid)
+                    .flat_map(|export| export.classes.iter())
+                    .filter(|class| class.token == class_token)
+                    .flat_map(|class| class.methods.iter())
+                    .find(|method| method.token == token)
+                    .ok_or(VerifyError::UnresolvedMethod)?;
+                Signature::from_java_descriptor(&method.descriptor)
+                    .ok_or(VerifyError::UnresolvedMethod)
+            }
+        }
+    }
+}
+
+///
+/// Data flow analysis of one method: the type states of the instructions are merged until
+/// they no longer change
+///
+struct MethodVerifier<'a> {
+    package: &'a PackageVerifier<'a>,
+    fetcher: BytecodeFetcher<'a>,
+    header: MethodHeader,
+    // offset following the last instruction of the method
+    code_end: usize,
+    // index of the first handler of the method in the Method component
+    first_handler: u16,
+    handlers: &'a [ExceptionHandler],
+    result: Option<PrimitiveType>,
+    // offsets of the instructions of the method
+    instructions: BTreeSet<usize>,
+    // offsets of the instructions following the jsr instructions (where ret can go back)
+    return_points: Vec<usize>,
+    states: HashMap<usize, TypeState>,
+    // instructions whose type state changed since they were last verified
+    pending: Vec<usize>,
+}
+
+impl<'a> MethodVerifier<'a> {
+    fn run(&mut self, initial_state: TypeState) -> Result<(), VerificationError> {
+        self.decode_instructions()?;
+        self.check_handlers()?;
+
+        let code_offset = self.header.code_offset;
+        if !self.instructions.contains(&code_offset) {
+            return Err(VerificationError::new(VerifyError::FallOffEnd, None));
+        }
+        self.merge(code_offset, &initial_state)
+            .map_err(|error| VerificationError::new(error, None))?;
+
+        while let Some(offset) = self.pending.pop() {
+            let mut state = self.states[&offset].clone();
+            self.verify_instruction(offset, &mut state)
+                .map_err(|error| self.error_at(offset, error))?;
+        }
+        Ok(())
+    }
+
+    fn error_at(&self, offset: usize, error: VerifyError) -> VerificationError {
+        VerificationError {
+            error,
+            method_offset: None,
+            opcode_offset: Some(offset),
+            opcode: self.fetcher
+                .get(offset)
+                .ok()
+                .and_then(|value| bytecode::from(value as u8).ok()),
+        }
+    }
+
+    ///
+    /// Finds the instructions of the method, making sure their operands do not go past
+    /// the end of the method
+    ///
+    fn decode_instructions(&mut self) -> Result<(), VerificationError> {
+        let mut offset = self.header.code_offset;
+        while offset < self.code_end {
+            let opcode = self.fetch_opcode(offset)
+                .map_err(|error| self.error_at(offset, error))?;
+            self.skip_operands(offset, opcode)
+                .map_err(|error| self.error_at(offset, error))?;
+            let next = self.fetcher.current_offset();
+            if next > self.code_end {
+                return Err(self.error_at(offset, VerifyError::TruncatedInstruction));
+            }
+            if opcode == bytecode::jsr {
+                self.return_points.push(next);
+            }
+            self.instructions.insert(offset);
+            offset = next;
+        }
+        Ok(())
+    }
+
+    /// the ranges and targets of the handlers have to be instructions of the method
+    fn check_handlers(&self) -> Result<(), VerificationError> {
+        for (index, handler) in self.handlers.iter().enumerate() {
+            let end = handler.start_offset + handler.active_length;
+            let valid = handler.active_length > 0
+                && self.instructions.contains(&handler.start_offset)
+                && (end == self.code_end || self.instructions.contains(&end))
+                && self.instructions.contains(&handler.handler_offset)
+                && handler.catch_type_index.is_none_or(|catch_type| {
+                    matches!(
+                        self.package.constant(catch_type),
+                        Ok(CapConstantPoolEntry::ClassRef(_))
+                    )
+                });
+            if !valid {
+                return Err(VerificationError::new(
+                    VerifyError::InvalidExceptionHandler(self.first_handler + index as u16),
+                    None,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn fetch_opcode(&mut self, offset: usize) -> Result<bytecode, VerifyError> {
+        self.fetcher
+            .jump(offset)
+            .map_err(|_| VerifyError::TruncatedInstruction)?;
+        let value = self.fetch_b()? as u8;
+        let opcode = bytecode::from(value).map_err(|_| VerifyError::UnrecognizedBytecode(value))?;
+        if opcode == bytecode::END || opcode == bytecode::impdep1 {
+            return Err(VerifyError::UnrecognizedBytecode(value));
+        }
+        if !self.package.int_support && opcode.is_integer_bytecode() {
+            return Err(VerifyError::UnsupportedBytecode);
+        }
+        Ok(opcode)
+    }
+
+    fn fetch_b(&mut self) -> Result<BytecodeType, VerifyError> {
+        self.fetcher
+            .fetch_b()
+            .map_err(|_| VerifyError::TruncatedInstruction)
+    }
+
+    fn fetch_s(&mut self) -> Result<i16, VerifyError> {
+        self.fetcher
+            .fetch_s()
+            .map_err(|_| VerifyError::TruncatedInstruction)
+    }
+
+    fn fetch_index(&mut self) -> Result<u16, VerifyError> {
+        Ok(self.fetch_s()? as u16)
+    }
+
+    fn fetch_key(&mut self, type_: PrimitiveType) -> Result<i32, VerifyError> {
+        match type_ {
+            PrimitiveType::INTEGER => self.fetcher
+                .fetch_i()
+                .map_err(|_| VerifyError::TruncatedInstruction),
+            _ => Ok(i32::from(self.fetch_s()?)),
+        }
+    }
+
+    /// fetches the operands of the opcode at the given offset, without verifying them
+    fn skip_operands(&mut self, offset: usize, opcode: bytecode) -> Result<(), VerifyError> {
+        let size = match opcode {
+            bytecode::stableswitch
+            | bytecode::itableswitch
+            | bytecode::slookupswitch
+            | bytecode::ilookupswitch => {
+                self.switch_targets(offset, opcode)?;
+                return Ok(());
+            }
+            bytecode::bspush
+            | bytecode::bipush
+            | bytecode::aload
+            | bytecode::sload
+            | bytecode::iload
+            | bytecode::astore
+            | bytecode::sstore
+            | bytecode::istore
+            | bytecode::dup_x
+            | bytecode::swap_x
+            | bytecode::ifeq
+            | bytecode::ifne
+            | bytecode::iflt
+            | bytecode::ifge
+            | bytecode::ifgt
+            | bytecode::ifle
+            | bytecode::ifnull
+            | bytecode::ifnonnull
+            | bytecode::if_acmpeq
+            | bytecode::if_acmpne
+            | bytecode::if_scmpeq
+            | bytecode::if_scmpne
+            | bytecode::if_scmplt
+            | bytecode::if_scmpge
+            | bytecode::if_scmpgt
+            | bytecode::if_scmple
+            | bytecode::goto
+            | bytecode::ret
+            | bytecode::getfield_a
+            | bytecode::getfield_b
+            | bytecode::getfield_s
+            | bytecode::getfield_i
+            | bytecode::putfield_a
+            | bytecode::putfield_b
+            | bytecode::putfield_s
+            | bytecode::putfield_i
+            | bytecode::newarray
+            | bytecode::getfield_a_this
+            | bytecode::getfield_b_this
+            | bytecode::getfield_s_this
+            | bytecode::getfield_i_this
+            | bytecode::putfield_a_this
+            | bytecode::putfield_b_this
+            | bytecode::putfield_s_this
+            | bytecode::putfield_i_this => 1,
+            bytecode::sspush
+            | bytecode::sipush
+            | bytecode::sinc
+            | bytecode::iinc
+            | bytecode::jsr
+            | bytecode::getstatic_a
+            | bytecode::getstatic_b
+            | bytecode::getstatic_s
+            | bytecode::getstatic_i
+            | bytecode::putstatic_a
+            | bytecode::putstatic_b
+            | bytecode::putstatic_s
+            | bytecode::putstatic_i
+            | bytecode::invokevirtual
+            | bytecode::invokespecial
+            | bytecode::invokestatic
+            | bytecode::new
+            | bytecode::anewarray
+            | bytecode::ifeq_w
+            | bytecode::ifne_w
+            | bytecode::iflt_w
+            | bytecode::ifge_w
+            | bytecode::ifgt_w
+            | bytecode::ifle_w
+            | bytecode::ifnull_w
+            | bytecode::ifnonnull_w
+            | bytecode::if_acmpeq_w
+            | bytecode::if_acmpne_w
+            | bytecode::if_scmpeq_w
+            | bytecode::if_scmpne_w
+            | bytecode::if_scmplt_w
+            | bytecode::if_scmpge_w
+            | bytecode::if_scmpgt_w
+            | bytecode::if_scmple_w
+            | bytecode::goto_w
+            | bytecode::getfield_a_w
+            | bytecode::getfield_b_w
+            | bytecode::getfield_s_w
+            | bytecode::getfield_i_w
+            | bytecode::putfield_a_w
+            | bytecode::putfield_b_w
+            | bytecode::putfield_s_w
+            | bytecode::putfield_i_w => 2,
+            bytecode::checkcast | bytecode::instanceof | bytecode::sinc_w | bytecode::iinc_w => 3,
+            bytecode::iipush | bytecode::invokeinterface => 4,
+            _ => 0,
+        };
+        for _ in 0..size {
+            self.fetch_b()?;
+        }
+        Ok(())
+    }
+
+    ///
+    /// Reads the table of a switch instruction (the operand stack excepted) and returns
+    /// its targets, the default one first
+    ///
+    fn switch_targets(
+        &mut self,
+        offset: usize,
+        opcode: bytecode,
+    ) -> Result<Vec<usize>, VerifyError> {
+        let type_ = match opcode {
+            bytecode::itableswitch | bytecode::ilookupswitch => PrimitiveType::INTEGER,
+            _ => PrimitiveType::SHORT,
+        };
+        let mut branches = vec![self.fetch_s()?];
+        match opcode {
+            bytecode::stableswitch | bytecode::itableswitch => {
+                let low = self.fetch_key(type_)?;
+                let high = self.fetch_key(type_)?;
+                if low > high {
+                    return Err(VerifyError::MalformedSwitchTable);
+                }
+                for _ in i64::from(low)..=i64::from(high) {
+                    branches.push(self.fetch_s()?);
+                }
+            }
+            _ => {
+                let npairs = self.fetch_s()? as u16;
+                let mut previous_match: Option<i32> = None;
+                for _ in 0..npairs {
+                    let current_match = self.fetch_key(type_)?;
+                    // the matches shall be sorted in increasing order
+                    if let Some(previous) = previous_match {
+                        if current_match <= previous {
+                            return Err(VerifyError::MalformedSwitchTable);
+                        }
+                    }
+                    previous_match = Some(current_match);
+                    branches.push(self.fetch_s()?);
+                }
+            }
+        }
+        branches
+            .into_iter()
+            .map(|branch| branch_target(offset, branch))
+            .collect()
+    }
+
+    ///
+    /// Merges the given state into the state of the instruction at the given offset, which
+    /// has to be verified (again) if its state changed
+    ///
+    fn merge(&mut self, offset: usize, state: &TypeState) -> Result<(), VerifyError> {
+        if !self.instructions.contains(&offset) {
+            return Err(VerifyError::InvalidBranchTarget);
+        }
+        let changed = match self.states.get_mut(&offset) {
+            Some(current) => {
+                if current.stack.len() != state.stack.len() {
+                    return Err(VerifyError::StackHeightMismatch(
+                        current.stack.len(),
+                        state.stack.len(),
+                    ));
+                }
+                let merged = current.merge(state);
+                let changed = merged != *current;
+                *current = merged;
+                changed
+            }
+            None => {
+                self.states.insert(offset, state.clone());
+                true
+            }
+        };
+        if changed && !self.pending.contains(&offset) {
+            self.pending.push(offset);
+        }
+        Ok(())
+    }
+
+    /// the handlers protecting the instruction can be entered with the given locals
+    fn merge_handlers(&mut self, offset: usize, locals: &[WordType]) -> Result<(), VerifyError> {
+        let targets: Vec<usize> = self.handlers
+            .iter()
+            .filter(|handler| handler.covers(offset))
+            .map(|handler| handler.handler_offset)
+            .collect();
+        for target in targets {
+            // the operand stack only holds the exception when entering the handler
+            if self.header.max_stack == 0 {
+                return Err(VerifyError::StackOverflow);
+            }
+            let state = TypeState {
+                locals: locals.to_vec(),
+                stack: vec![WordType::Reference],
+            };
+            self.merge(target, &state)?;
+        }
+        Ok(())
+    }
+
+    fn verify_instruction(
+        &mut self,
+        offset: usize,
+        state: &mut TypeState,
+    ) -> Result<(), VerifyError> {
+        let locals = state.locals.clone();
+        self.merge_handlers(offset, &locals)?;
+
+        let opcode = self.fetch_opcode(offset)?;
+        let targets = self.execute(offset, opcode, state)?;
+
+        // locals may have been updated by the instruction before an exception is thrown
+        if state.locals != locals {
+            let locals = state.locals.clone();
+            self.merge_handlers(offset, &locals)?;
+        }
+        let falls_through = match targets {
+            Some(targets) => {
+                for target in targets {
+                    self.merge(target, state)?;
+                }
+                false
+            }
+            None => true,
+        };
+        if falls_through {
+            let next = self.fetcher.current_offset();
+            if next >= self.code_end {
+                return Err(VerifyError::FallOffEnd);
+            }
+            self.merge(next, state)?;
+        }
+        Ok(())
+    }
+
+    ///
+    /// Updates the state with the effect of the instruction, and returns its targets
+    /// (None if the execution continues with the next instruction)
+    ///
+    fn execute(
+        &mut self,
+        offset: usize,
+        opcode: bytecode,
+        state: &mut TypeState,
+    ) -> Result<Option<Vec<usize>>, VerifyError> {
+        let max_stack = self.header.max_stack as usize;
+        match opcode {
+            bytecode::nop => {}
+            bytecode::aconst_null => state.push(WordType::Reference, max_stack)?,
+            bytecode::sconst_m1
+            | bytecode::sconst_0
+            | bytecode::sconst_1
+            | bytecode::sconst_2
+            | bytecode::sconst_3
+            | bytecode::sconst_4
+            | bytecode::sconst_5 => state.push(WordType::Short, max_stack)?,
+            bytecode::iconst_m1
+            | bytecode::iconst_0
+            | bytecode::iconst_1
+            | bytecode::iconst_2
+            | bytecode::iconst_3
+            | bytecode::iconst_4
+            | bytecode::iconst_5 => state.push_type(PrimitiveType::INTEGER, max_stack)?,
+            bytecode::bspush | bytecode::sspush | bytecode::bipush | bytecode::sipush
+            | bytecode::iipush => {
+                self.skip_operands(offset, opcode)?;
+                let type_ = match opcode {
+                    bytecode::bspush | bytecode::sspush => PrimitiveType::SHORT,
+                    _ => PrimitiveType::INTEGER,
+                };
+                state.push_type(type_, max_stack)?;
+            }
+            bytecode::aload | bytecode::sload | bytecode::iload => {
+                let index = self.fetch_b()? as u8;
+                let type_ = load_store_type(opcode as u8 - bytecode::aload as u8);
+                self.load(state, index, type_)?;
+            }
+            bytecode::aload_0 | bytecode::aload_1 | bytecode::aload_2 | bytecode::aload_3 => {
+                let index = opcode as u8 - bytecode::aload_0 as u8;
+                self.load(state, index, PrimitiveType::REFERENCE)?;
+            }
+            bytecode::sload_0 | bytecode::sload_1 | bytecode::sload_2 | bytecode::sload_3 => {
+                let index = opcode as u8 - bytecode::sload_0 as u8;
+                self.load(state, index, PrimitiveType::SHORT)?;
+            }
+            bytecode::iload_0 | bytecode::iload_1 | bytecode::iload_2 | bytecode::iload_3 => {
+                let index = opcode as u8 - bytecode::iload_0 as u8;
+                self.load(state, index, PrimitiveType::INTEGER)?;
+            }
+            bytecode::aaload | bytecode::baload | bytecode::saload | bytecode::iaload => {
+                // ..., arrayref, index
+                state.pop_type(PrimitiveType::SHORT)?;
+                state.pop_type(PrimitiveType::REFERENCE)?;
+                let type_ = field_type(opcode as u8 - bytecode::aaload as u8);
+                state.push_type(type_, max_stack)?;
+            }
+            bytecode::astore | bytecode::sstore | bytecode::istore => {
+                let index = self.fetch_b()? as u8;
+                let type_ = load_store_type(opcode as u8 - bytecode::astore as u8);
+                store(state, index, type_)?;
+            }
+            bytecode::astore_0 | bytecode::astore_1 | bytecode::astore_2 | bytecode::astore_3 => {
+                let index = opcode as u8 - bytecode::astore_0 as u8;
+                store(state, index, PrimitiveType::REFERENCE)?;
+            }
+            bytecode::sstore_0 | bytecode::sstore_1 | bytecode::sstore_2 | bytecode::sstore_3 => {
+                let index = opcode as u8 - bytecode::sstore_0 as u8;
+                store(state, index, PrimitiveType::SHORT)?;
+            }
+            bytecode::istore_0 | bytecode::istore_1 | bytecode::istore_2 | bytecode::istore_3 => {
+                let index = opcode as u8 - bytecode::istore_0 as u8;
+                store(state, index, PrimitiveType::INTEGER)?;
+            }
+            bytecode::aastore | bytecode::bastore | bytecode::sastore | bytecode::iastore => {
+                state.pop_type(field_type(opcode as u8 - bytecode::aastore as u8))?;
+                state.pop_type(PrimitiveType::SHORT)?;
+                state.pop_type(PrimitiveType::REFERENCE)?;
+            }
+            bytecode::pop | bytecode::pop2 => {
+                let count = if opcode == bytecode::pop { 1 } else { 2 };
+                state.check_words(count)?;
+                for _ in 0..count {
+                    state.pop()?;
+                }
+            }
+            bytecode::dup => dup_x(state, 1, 0, max_stack)?,
+            bytecode::dup2 => dup_x(state, 2, 0, max_stack)?,
+            bytecode::dup_x => {
+                let mn = self.fetch_b()? as u8;
+                dup_x(state, mn >> 4, mn & 0x0F, max_stack)?;
+            }
+            bytecode::swap_x => {
+                let mn = self.fetch_b()? as u8;
+                swap_x(state, mn >> 4, mn & 0x0F)?;
+            }
+            bytecode::sadd
+            | bytecode::ssub
+            | bytecode::smul
+            | bytecode::sdiv
+            | bytecode::srem
+            | bytecode::sshl
+            | bytecode::sshr
+            | bytecode::sushr
+            | bytecode::sand
+            | bytecode::sor
+            | bytecode::sxor => {
+                state.pop_type(PrimitiveType::SHORT)?;
+                state.pop_type(PrimitiveType::SHORT)?;
+                state.push(WordType::Short, max_stack)?;
+            }
+            bytecode::iadd
+            | bytecode::isub
+            | bytecode::imul
+            | bytecode::idiv
+            | bytecode::irem
+            | bytecode::ishl
+            | bytecode::ishr
+            | bytecode::iushr
+            | bytecode::iand
+            | bytecode::ior
+            | bytecode::ixor => {
+                state.pop_type(PrimitiveType::INTEGER)?;
+                state.pop_type(PrimitiveType::INTEGER)?;
+                state.push_type(PrimitiveType::INTEGER, max_stack)?;
+            }
+            bytecode::sneg | bytecode::s2b => {
+                state.pop_type(PrimitiveType::SHORT)?;
+                state.push(WordType::Short, max_stack)?;
+            }
+            bytecode::ineg => {
+                state.pop_type(PrimitiveType::INTEGER)?;
+                state.push_type(PrimitiveType::INTEGER, max_stack)?;
+            }
+            bytecode::s2i => {
+                state.pop_type(PrimitiveType::SHORT)?;
+                state.push_type(PrimitiveType::INTEGER, max_stack)?;
+            }
+            bytecode::i2b | bytecode::i2s => {
+                state.pop_type(PrimitiveType::INTEGER)?;
+                state.push(WordType::Short, max_stack)?;
+            }
+            bytecode::icmp => {
+                state.pop_type(PrimitiveType::INTEGER)?;
+                state.pop_type(PrimitiveType::INTEGER)?;
+                state.push(WordType::Short, max_stack)?;
+            }
+            bytecode::sinc | bytecode::sinc_w | bytecode::iinc | bytecode::iinc_w => {
+                let index = self.fetch_b()? as u8;
+                let (type_, wide) = match opcode {
+                    bytecode::sinc => (PrimitiveType::SHORT, false),
+                    bytecode::sinc_w => (PrimitiveType::SHORT, true),
+                    bytecode::iinc => (PrimitiveType::INTEGER, false),
+                    _ => (PrimitiveType::INTEGER, true),
+                };
+                if wide {
+                    self.fetch_s()?;
+                } else {
+                    self.fetch_b()?;
+                }
+                state.check_local(index, type_)?;
+            }
+            bytecode::ifeq
+            | bytecode::ifne
+            | bytecode::iflt
+            | bytecode::ifge
+            | bytecode::ifgt
+            | bytecode::ifle
+            | bytecode::ifnull
+            | bytecode::ifnonnull
+            | bytecode::if_acmpeq
+            | bytecode::if_acmpne
+            | bytecode::if_scmpeq
+            | bytecode::if_scmpne
+            | bytecode::if_scmplt
+            | bytecode::if_scmpge
+            | bytecode::if_scmpgt
+            | bytecode::if_scmple
+            | bytecode::ifeq_w
+            | bytecode::ifne_w
+            | bytecode::iflt_w
+            | bytecode::ifge_w
+            | bytecode::ifgt_w
+            | bytecode::ifle_w
+            | bytecode::ifnull_w
+            | bytecode::ifnonnull_w
+            | bytecode::if_acmpeq_w
+            | bytecode::if_acmpne_w
+            | bytecode::if_scmpeq_w
+            | bytecode::if_scmpne_w
+            | bytecode::if_scmplt_w
+            | bytecode::if_scmpge_w
+            | bytecode::if_scmpgt_w
+            | bytecode::if_scmple_w => {
+                // wide variants only differ by the size of their branch offset
+                let wide = opcode as u8 >= bytecode::ifeq_w as u8;
+                let branch = if wide {
+                    self.fetch_s()?
+                } else {
+                    i16::from(self.fetch_b()?)
+                };
+                let position = if wide {
+                    opcode as u8 - bytecode::ifeq_w as u8
+                } else {
+                    opcode as u8 - bytecode::ifeq as u8
+                };
+                let (type_, count) = match position {
+                    // ifeq...ifle
+                    0..=5 => (PrimitiveType::SHORT, 1),
+                    // ifnull, ifnonnull
+                    6 | 7 => (PrimitiveType::REFERENCE, 1),
+                    // if_acmpeq, if_acmpne
+                    8 | 9 => (PrimitiveType::REFERENCE, 2),
+                    // if_scmpxx
+                    _ => (PrimitiveType::SHORT, 2),
+                };
+                for _ in 0..count {
+                    state.pop_type(type_)?;
+                }
+                let target = branch_target(offset, branch)?;
+                self.merge(target, state)?;
+            }
+            bytecode::goto | bytecode::goto_w => {
+                let branch = if opcode == bytecode::goto {
+                    i16::from(self.fetch_b()?)
+                } else {
+                    self.fetch_s()?
+                };
+                return Ok(Some(vec![branch_target(offset, branch)?]));
+            }
+            bytecode::jsr => {
+                let branch = self.fetch_s()?;
+                state.push(WordType::ReturnAddress, max_stack)?;
+                return Ok(Some(vec![branch_target(offset, branch)?]));
+            }
+            bytecode::ret => {
+                let index = self.fetch_b()? as u8;
+                state.check_local(index, PrimitiveType::RETURNADDRESS)?;
+                // the subroutine may have been called by any jsr of the method
+                for return_point in &self.return_points {
+                    if *return_point >= self.code_end {
+                        return Err(VerifyError::FallOffEnd);
+                    }
+                }
+                return Ok(Some(self.return_points.clone()));
+            }
+            bytecode::stableswitch
+            | bytecode::itableswitch
+            | bytecode::slookupswitch
+            | bytecode::ilookupswitch => {
+                let type_ = match opcode {
+                    bytecode::itableswitch | bytecode::ilookupswitch => PrimitiveType::INTEGER,
+                    _ => PrimitiveType::SHORT,
+                };
+                state.pop_type(type_)?;
+                return Ok(Some(self.switch_targets(offset, opcode)?));
+            }
+            bytecode::areturn | bytecode::sreturn | bytecode::ireturn | bytecode::return_ => {
+                let type_ = match opcode {
+                    bytecode::areturn => Some(PrimitiveType::REFERENCE),
+                    bytecode::sreturn => Some(PrimitiveType::SHORT),
+                    bytecode::ireturn => Some(PrimitiveType::INTEGER),
+                    _ => None,
+                };
+                // booleans and bytes are returned as shorts
+                let result = match self.result {
+                    Some(PrimitiveType::BYTE) => Some(PrimitiveType::SHORT),
+                    result => result,
+                };
+                if type_ != result {
+                    return Err(VerifyError::InvalidReturn);
+                }
+                if let Some(type_) = type_ {
+                    state.pop_type(type_)?;
+                }
+                return Ok(Some(Vec::new()));
+            }
+            bytecode::getstatic_a
+            | bytecode::getstatic_b
+            | bytecode::getstatic_s
+            | bytecode::getstatic_i => {
+                let type_ = field_type(opcode as u8 - bytecode::getstatic_a as u8);
+                self.check_static_field(type_)?;
+                state.push_type(type_, max_stack)?;
+            }
+            bytecode::putstatic_a
+            | bytecode::putstatic_b
+            | bytecode::putstatic_s
+            | bytecode::putstatic_i => {
+                let type_ = field_type(opcode as u8 - bytecode::putstatic_a as u8);
+                self.check_static_field(type_)?;
+                state.pop_type(type_)?;
+            }
+            bytecode::getfield_a
+            | bytecode::getfield_b
+            | bytecode::getfield_s
+            | bytecode::getfield_i
+            | bytecode::getfield_a_w
+            | bytecode::getfield_b_w
+            | bytecode::getfield_s_w
+            | bytecode::getfield_i_w
+            | bytecode::getfield_a_this
+            | bytecode::getfield_b_this
+            | bytecode::getfield_s_this
+            | bytecode::getfield_i_this => {
+                let (first, index) = match opcode as u8 {
+                    value if value >= bytecode::getfield_a_this as u8 => {
+                        (bytecode::getfield_a_this, u16::from(self.fetch_b()? as u8))
+                    }
+                    value if value >= bytecode::getfield_a_w as u8 => {
+                        (bytecode::getfield_a_w, self.fetch_index()?)
+                    }
+                    _ => (bytecode::getfield_a, u16::from(self.fetch_b()? as u8)),
+                };
+                let type_ = field_type(opcode as u8 - first as u8);
+                self.check_instance_field(index, type_)?;
+                if first == bytecode::getfield_a_this {
+                    state.check_local(0, PrimitiveType::REFERENCE)?;
+                } else {
+                    state.pop_type(PrimitiveType::REFERENCE)?;
+                }
+                state.push_type(type_, max_stack)?;
+            }
+            bytecode::putfield_a
+            | bytecode::putfield_b
+            | bytecode::putfield_s
+            | bytecode::putfield_i
+            | bytecode::putfield_a_w
+            | bytecode::putfield_b_w
+            | bytecode::putfield_s_w
+            | bytecode::putfield_i_w
+            | bytecode::putfield_a_this
+            | bytecode::putfield_b_this
+            | bytecode::putfield_s_this
+            | bytecode::putfield_i_this => {
+                let (first, index) = match opcode as u8 {
+                    value if value >= bytecode::putfield_a_this as u8 => {
+                        (bytecode::putfield_a_this, u16::from(self.fetch_b()? as u8))
+                    }
+                    value if value >= bytecode::putfield_a_w as u8 => {
+                        (bytecode::putfield_a_w, self.fetch_index()?)
+                    }
+                    _ => (bytecode::putfield_a, u16::from(self.fetch_b()? as u8)),
+                };
+                let type_ = field_type(opcode as u8 - first as u8);
+                self.check_instance_field(index, type_)?;
+                state.pop_type(type_)?;
+                if first == bytecode::putfield_a_this {
+                    state.check_local(0, PrimitiveType::REFERENCE)?;
+                } else {
+                    state.pop_type(PrimitiveType::REFERENCE)?;
+                }
+            }
+            bytecode::invokevirtual | bytecode::invokespecial | bytecode::invokestatic => {
+                let index = self.fetch_index()?;
+                let valid = matches!(
+                    (opcode, self.package.constant(index)?),
+                    (bytecode::invokevirtual, CapConstantPoolEntry::VirtualMethodRef(..))
+                        | (bytecode::invokespecial, CapConstantPoolEntry::SuperMethodRef(..))
+                        | (bytecode::invokespecial, CapConstantPoolEntry::StaticMethodRef(_))
+                        | (bytecode::invokestatic, CapConstantPoolEntry::StaticMethodRef(_))
+                );
+                if !valid {
+                    return Err(VerifyError::InvalidConstantPoolIndex(index));
+                }
+                let signature = self.package.method_signature(index)?;
+                invoke(state, &signature, opcode != bytecode::invokestatic, max_stack)?;
+            }
+            bytecode::invokeinterface => {
+                let nargs = self.fetch_b()? as u8;
+                let index = self.fetch_index()?;
+                let token = self.fetch_b()? as u8;
+                let interface = match self.package.constant(index)? {
+                    CapConstantPoolEntry::ClassRef(interface) => interface,
+                    _ => return Err(VerifyError::InvalidConstantPoolIndex(index)),
+                };
+                let signature = self.package.interface_signature(interface, token)?;
+                if nargs as usize != signature.words() + 1 {
+                    return Err(VerifyError::InvalidOperand);
+                }
+                invoke(state, &signature, true, max_stack)?;
+            }
+            bytecode::new => {
+                let index = self.fetch_index()?;
+                self.check_class(index)?;
+                state.push(WordType::Reference, max_stack)?;
+            }
+            bytecode::newarray => {
+                let atype = self.fetch_b()? as u8;
+                if array_type(atype).is_err() {
+                    return Err(VerifyError::InvalidOperand);
+                }
+                if atype == T_INT && !self.package.int_support {
+                    return Err(VerifyError::UnsupportedBytecode);
+                }
+                state.pop_type(PrimitiveType::SHORT)?;
+                state.push(WordType::Reference, max_stack)?;
+            }
+            bytecode::anewarray => {
+                let index = self.fetch_index()?;
+                self.check_class(index)?;
+                state.pop_type(PrimitiveType::SHORT)?;
+                state.push(WordType::Reference, max_stack)?;
+            }
+            bytecode::arraylength => {
+                state.pop_type(PrimitiveType::REFERENCE)?;
+                state.push(WordType::Short, max_stack)?;
+            }
+            bytecode::athrow => {
+                state.pop_type(PrimitiveType::REFERENCE)?;
+                return Ok(Some(Vec::new()));
+            }
+            bytecode::checkcast | bytecode::instanceof => {
+                let atype = self.fetch_b()? as u8;
+                let index = self.fetch_index()?;
+                match atype {
+                    0 | T_REFERENCE => self.check_class(index)?,
+                    T_BOOLEAN..=T_INT => {}
+                    _ => return Err(VerifyError::InvalidOperand),
+                }
+                if opcode == bytecode::checkcast {
+                    let word = state.peek()?;
+                    if word != WordType::Reference {
+                        return Err(type_error(word, WordType::Reference));
+                    }
+                } else {
+                    state.pop_type(PrimitiveType::REFERENCE)?;
+                    state.push(WordType::Short, max_stack)?;
+                }
+            }
+            _ => return Err(VerifyError::UnrecognizedBytecode(opcode as u8)),
+        }
+        Ok(None)
+    }
+
+    fn load(
+        &self,
+        state: &mut TypeState,
+        index: u8,
+        type_: PrimitiveType,
+    ) -> Result<(), VerifyError> {
+        state.check_local(index, type_)?;
+        state.push_type(type_, self.header.max_stack as usize)
+    }
+
+    fn check_class(&self, index: u16) -> Result<(), VerifyError> {
+        match self.package.constant(index)? {
+            CapConstantPoolEntry::ClassRef(_) => Ok(()),
+            _ => Err(VerifyError::InvalidConstantPoolIndex(index)),
+        }
+    }
+
+    /// the static field referenced by the operand has to be of the type of the opcode
+    fn check_static_field(&mut self, type_: PrimitiveType) -> Result<(), VerifyError> {
+        let index = self.fetch_index()?;
+        match self.package.constant(index)? {
+            CapConstantPoolEntry::StaticFieldRef(_) => check_field_type(self.package, index, type_),
+            _ => Err(VerifyError::InvalidConstantPoolIndex(index)),
+        }
+    }
+
+    fn check_instance_field(&self, index: u16, type_: PrimitiveType) -> Result<(), VerifyError> {
+        match self.package.constant(index)? {
+            CapConstantPoolEntry::InstanceFieldRef(..) => {
+                check_field_type(self.package, index, type_)
+            }
+            _ => Err(VerifyError::InvalidConstantPoolIndex(index)),
+        }
+    }
+}
+
+fn check_field_type(
+    package: &PackageVerifier,
+    index: u16,
+    type_: PrimitiveType,
+) -> Result<(), VerifyError> {
+    let field_type = package.field_type(index)?;
+    if field_type != type_ {
+        return Err(VerifyError::InvalidType(field_type, type_));
+    }
+    Ok(())
+}
+
+/// type of the locals accessed by xload and xstore, given their position (a, s, i)
+fn load_store_type(position: u8) -> PrimitiveType {
+    match position {
+        0 => PrimitiveType::REFERENCE,
+        1 => PrimitiveType::SHORT,
+        _ => PrimitiveType::INTEGER,
+    }
+}
+
+/// offset of the target of a branch, relative to the offset of the branching opcode
+fn branch_target(offset: usize, branch: i16) -> Result<usize, VerifyError> {
+    let target = offset as isize + branch as isize;
+    if target < 0 {
+        return Err(VerifyError::InvalidBranchTarget);
+    }
+    Ok(target as usize)
+}
+
+/// astore also stores the return addresses pushed by jsr
+fn store(state: &mut TypeState, index: u8, type_: PrimitiveType) -> Result<(), VerifyError> {
+    let type_ = match state.peek()? {
+        WordType::ReturnAddress if type_ == PrimitiveType::REFERENCE => {
+            PrimitiveType::RETURNADDRESS
+        }
+        _ => type_,
+    };
+    state.pop_type(type_)?;
+    state.store(index, &locals_of(type_))
+}
+
+fn dup_x(state: &mut TypeState, m: u8, n: u8, max_stack: usize) -> Result<(), VerifyError> {
+    if !(1..=4).contains(&m) || (n != 0 && !(m..=m + 4).contains(&n)) {
+        return Err(VerifyError::InvalidOperand);
+    }
+    let (m, n) = (m as usize, n as usize);
+    state.check_words(m)?;
+    state.check_words(n)?;
+    if state.stack.len() + m > max_stack {
+        return Err(VerifyError::StackOverflow);
+    }
+    let length = state.stack.len();
+    let words = state.stack[length - m..].to_vec();
+    for (index, word) in words.into_iter().enumerate() {
+        state.stack.insert(length - n + index, word);
+    }
+    Ok(())
+}
+
+fn swap_x(state: &mut TypeState, m: u8, n: u8) -> Result<(), VerifyError> {
+    if !(1..=2).contains(&m) || !(1..=2).contains(&n) {
+        return Err(VerifyError::InvalidOperand);
+    }
+    let (m, n) = (m as usize, n as usize);
+    state.check_words(m)?;
+    state.check_words(m + n)?;
+    // the top m words go beneath the n words
+    let length = state.stack.len();
+    state.stack[length - m - n..].rotate_right(m);
+    Ok(())
+}
+
+/// pops the arguments (and 'this' for instance methods), then pushes the result
+fn invoke(
+    state: &mut TypeState,
+    signature: &Signature,
+    instance: bool,
+    max_stack: usize,
+) -> Result<(), VerifyError> {
+    for argument in signature.arguments.iter().rev() {
+        state.pop_type(*argument)?;
+    }
+    if instance {
+        state.pop_type(PrimitiveType::REFERENCE)?;
+    }
+    if let Some(result) = signature.result {
+        state.push_type(result, max_stack)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,452 @@
+extern crate interpreterlib;
+
+use interpreterlib::{constants, context, exceptions, frame, interpreter, jcvmerrors, stack};
+use interpreterlib::bytecodes::*;
+
+use interpreter::{BytecodeData, BytecodeType};
+use jcvmerrors::InterpreterError;
+use stack::StackEntry;
+
+pub fn execute_with_context(ctx: &mut context::Context) {
+    let _result = interpreter::interpreter(ctx);
+}
+
+pub fn execute_bytecode<'a>(data: &'a BytecodeData) -> context::Context<'a> {
+    let mut ctx = context::Context::new(data);
+    // silence result as we don't care here
+    execute_with_context(&mut ctx);
+    ctx
+}
+
+///
+/// utility function pushing value1 and value2 with sspush then executing the given operation
+///
+fn execute_short_operation(bc: bytecode, value1: i16, value2: i16) -> i16 {
+    let datatoexecute: &BytecodeData = &[
+        bytecode::sspush as BytecodeType,
+        (value1 >> 8) as BytecodeType,
+        value1 as BytecodeType,
+        bytecode::sspush as BytecodeType,
+        (value2 >> 8) as BytecodeType,
+        value2 as BytecodeType,
+        bc as BytecodeType,
+    ];
+    let mut ctx = execute_bytecode(datatoexecute);
+
+    let result = ctx.operand_stack
+        .pop_check_type(constants::PrimitiveType::SHORT)
+        .unwrap();
+    // operands shall have been consumed
+    assert!(ctx.operand_stack.top().is_err());
+    result.value
+}
+
+#[test]
+fn opcode_sadd_test() {
+    assert_eq!(execute_short_operation(bytecode::sadd, 0x1234, 0x0101), 0x1335);
+    // overflow wraps around
+    assert_eq!(execute_short_operation(bytecode::sadd, 0x7FFF, 1), -0x8000);
+}
+
+#[test]
+fn opcode_ssub_test() {
+    assert_eq!(execute_short_operation(bytecode::ssub, 10, 3), 7);
+    assert_eq!(execute_short_operation(bytecode::ssub, -0x8000, 1), 0x7FFF);
+}
+
+#[test]
+fn opcode_smul_test() {
+    assert_eq!(execute_short_operation(bytecode::smul, -7, 6), -42);
+    assert_eq!(execute_short_operation(bytecode::smul, 0x4000, 4), 0);
+}
+
+#[test]
+fn opcode_sdiv_test() {
+    assert_eq!(execute_short_operation(bytecode::sdiv, -7, 2), -3);
+    // the only overflowing division returns the dividend
+    assert_eq!(execute_short_operation(bytecode::sdiv, -0x8000, -1), -0x8000);
+}
+
+#[test]
+fn opcode_srem_test() {
+    assert_eq!(execute_short_operation(bytecode::srem, -7, 2), -1);
+    assert_eq!(execute_short_operation(bytecode::srem, 7, -2), 1);
+    assert_eq!(execute_short_operation(bytecode::srem, -0x8000, -1), 0);
+}
+
+///
+/// utility function executing the given operation on value1 and 0, which shall raise
+/// an uncaught ArithmeticException
+///
+fn check_division_by_zero(bc: bytecode, int_operands: bool) {
+    let datatoexecute: &BytecodeData = if int_operands {
+        &[
+            bytecode::iconst_1 as BytecodeType,
+            bytecode::iconst_0 as BytecodeType,
+            bc as BytecodeType,
+        ]
+    } else {
+        &[
+            bytecode::sconst_1 as BytecodeType,
+            bytecode::sconst_0 as BytecodeType,
+            bc as BytecodeType,
+        ]
+    };
+    let mut ctx = context::Context::new(datatoexecute);
+    match interpreter::interpreter(&mut ctx) {
+        Err(InterpreterError::UncaughtException(class, 0)) => {
+            assert_eq!(class, exceptions::ARITHMETIC_EXCEPTION_CLASS)
+        }
+        _ => panic!("an ArithmeticException shall have been raised"),
+    }
+}
+
+/// division by zero shall raise an ArithmeticException
+#[test]
+fn opcode_sdiv_by_zero_test() {
+    check_division_by_zero(bytecode::sdiv, false);
+}
+
+/// remainder by zero shall raise an ArithmeticException
+#[test]
+fn opcode_srem_by_zero_test() {
+    check_division_by_zero(bytecode::srem, false);
+}
+
+#[test]
+fn opcode_sneg_test() {
+    let datatoexecute: &BytecodeData = &[
+        bytecode::sconst_5 as BytecodeType,
+        bytecode::sneg as BytecodeType,
+    ];
+    let ctx = execute_bytecode(datatoexecute);
+    assert_eq!(ctx.operand_stack.top().unwrap().value, -5);
+
+    // negating the minimal value gives the same value
+    let datatoexecute: &BytecodeData = &[
+        bytecode::sspush as BytecodeType,
+        -0x80,
+        0x00,
+        bytecode::sneg as BytecodeType,
+    ];
+    let ctx = execute_bytecode(datatoexecute);
+    assert_eq!(ctx.operand_stack.top().unwrap().value, -0x8000);
+}
+
+#[test]
+fn opcode_sshifts_test() {
+    assert_eq!(execute_short_operation(bytecode::sshl, 0x0101, 4), 0x1010);
+    // only the 5 lowest bits of the shift value are used
+    assert_eq!(execute_short_operation(bytecode::sshl, 1, 0x21), 2);
+    assert_eq!(execute_short_operation(bytecode::sshr, -0x100, 4), -0x10);
+    // the value is sign extended before the logical shift
+    assert_eq!(execute_short_operation(bytecode::sushr, -0x100, 4), -0x10);
+    assert_eq!(execute_short_operation(bytecode::sushr, -0x100, 20), 0x0FFF);
+}
+
+#[test]
+fn opcode_slogic_test() {
+    assert_eq!(execute_short_operation(bytecode::sand, 0x0FF0, 0x3C3C), 0x0C30);
+    assert_eq!(execute_short_operation(bytecode::sor, 0x0FF0, 0x3C3C), 0x3FFC);
+    assert_eq!(execute_short_operation(bytecode::sxor, 0x0FF0, 0x3C3C), 0x33CC);
+}
+
+/// bytes are sign-extended to shorts when loaded, a byte entry is not a short operand
+#[test]
+fn opcode_sadd_byte_operand_test() {
+    let datatoexecute: &BytecodeData = &[
+        bytecode::sconst_2 as BytecodeType,
+        bytecode::sadd as BytecodeType,
+    ];
+    let mut ctx = context::Context::new(datatoexecute);
+    ctx.operand_stack
+        .push(StackEntry::from_values(-3, constants::PrimitiveType::BYTE))
+        .unwrap();
+    assert_eq!(
+        interpreter::interpreter(&mut ctx),
+        Err(InterpreterError::InvalidVariableType(
+            constants::PrimitiveType::BYTE,
+            constants::PrimitiveType::SHORT,
+        ))
+    );
+}
+
+///
+/// Test sinc and sinc_w opcodes from standard specification
+///
+#[test]
+fn opcode_sinc_test() {
+    let idx: BytecodeType = 1;
+    let datatoexecute: &BytecodeData = &[
+        bytecode::sinc as BytecodeType,
+        idx,
+        -2,
+        bytecode::sinc_w as BytecodeType,
+        idx,
+        0x01,
+        0x00,
+    ];
+    let mut ctx = context::Context::new(datatoexecute);
+
+    ctx.frame_stack.push(frame::Frame::new(2));
+    ctx.frame_stack
+        .top_mut()
+        .unwrap()
+        .set_local(
+            idx as i16,
+            StackEntry::from_values(0x7FFF, constants::PrimitiveType::SHORT),
+        )
+        .unwrap();
+
+    execute_with_context(&mut ctx);
+
+    let result = ctx.frame_stack
+        .top()
+        .unwrap()
+        .get_local_check_type(idx as i16, constants::PrimitiveType::SHORT)
+        .unwrap();
+    // 0x7FFF - 2 + 0x100 wraps around
+    assert_eq!(result.value, -0x7F03);
+}
+
+///
+/// utility function pushing value1 and value2 with iipush then executing the given operation
+///
+fn execute_int_operation(bc: bytecode, value1: i32, value2: i32) -> i32 {
+    let datatoexecute: &BytecodeData = &[
+        bytecode::iipush as BytecodeType,
+        (value1 >> 24) as BytecodeType,
+        (value1 >> 16) as BytecodeType,
+        (value1 >> 8) as BytecodeType,
+        value1 as BytecodeType,
+        bytecode::iipush as BytecodeType,
+        (value2 >> 24) as BytecodeType,
+        (value2 >> 16) as BytecodeType,
+        (value2 >> 8) as BytecodeType,
+        value2 as BytecodeType,
+        bc as BytecodeType,
+    ];
+    let mut ctx = execute_bytecode(datatoexecute);
+
+    let result = ctx.operand_stack.ipop().unwrap();
+    // operands shall have been consumed
+    assert!(ctx.operand_stack.top().is_err());
+    result
+}
+
+#[test]
+fn opcode_iadd_isub_imul_test() {
+    assert_eq!(
+        execute_int_operation(bytecode::iadd, 0x0001_FFFF, 1),
+        0x0002_0000
+    );
+    assert_eq!(
+        execute_int_operation(bytecode::iadd, 0x7FFF_FFFF, 1),
+        i32::MIN
+    );
+    assert_eq!(
+        execute_int_operation(bytecode::isub, 0x0002_0000, 1),
+        0x0001_FFFF
+    );
+    assert_eq!(execute_int_operation(bytecode::imul, -0x10000, 0x10), -0x100000);
+}
+
+#[test]
+fn opcode_idiv_irem_test() {
+    assert_eq!(execute_int_operation(bytecode::idiv, -100_000, 7), -14_285);
+    assert_eq!(execute_int_operation(bytecode::irem, -100_000, 7), -5);
+    assert_eq!(
+        execute_int_operation(bytecode::idiv, i32::MIN, -1),
+        i32::MIN
+    );
+}
+
+/// division by zero shall raise an ArithmeticException
+#[test]
+fn opcode_idiv_by_zero_test() {
+    check_division_by_zero(bytecode::idiv, true);
+    check_division_by_zero(bytecode::irem, true);
+}
+
+#[test]
+fn opcode_ishifts_and_logic_test() {
+    assert_eq!(execute_int_operation(bytecode::ishl, 0x1234, 20), 0x2340_0000);
+    assert_eq!(execute_int_operation(bytecode::ishl, 1, 33), 2);
+    assert_eq!(execute_int_operation(bytecode::ishr, -0x10000, 8), -0x100);
+    assert_eq!(
+        execute_int_operation(bytecode::iushr, -0x10000, 8),
+        0x00FF_FF00
+    );
+    assert_eq!(
+        execute_int_operation(bytecode::iand, 0x0F0F_00FF, 0x00FF_0F0F),
+        0x000F_000F
+    );
+    assert_eq!(
+        execute_int_operation(bytecode::ior, 0x0F0F_00FF, 0x00FF_0F0F),
+        0x0FFF_0FFF
+    );
+    assert_eq!(
+        execute_int_operation(bytecode::ixor, 0x0F0F_00FF, 0x00FF_0F0F),
+        0x0FF0_0FF0
+    );
+}
+
+#[test]
+fn opcode_ineg_test() {
+    let datatoexecute: &BytecodeData = &[
+        bytecode::sipush as BytecodeType,
+        0x12,
+        0x34,
+        bytecode::ineg as BytecodeType,
+    ];
+    let mut ctx = execute_bytecode(datatoexecute);
+    assert_eq!(ctx.operand_stack.ipop().unwrap(), -0x1234);
+}
+
+#[test]
+fn opcode_icmp_test() {
+    for &(value1, value2, expected) in &[(0x10000, 0xFFFF, 1), (-1, -1, 0), (-0x10000, 1, -1)] {
+        let datatoexecute: &BytecodeData = &[
+            bytecode::iipush as BytecodeType,
+            (value1 >> 24) as BytecodeType,
+            (value1 >> 16) as BytecodeType,
+            (value1 >> 8) as BytecodeType,
+            value1 as BytecodeType,
+            bytecode::iipush as BytecodeType,
+            (value2 >> 24) as BytecodeType,
+            (value2 >> 16) as BytecodeType,
+            (value2 >> 8) as BytecodeType,
+            value2 as BytecodeType,
+            bytecode::icmp as BytecodeType,
+        ];
+        let mut ctx = execute_bytecode(datatoexecute);
+
+        let result = ctx.operand_stack
+            .pop_check_type(constants::PrimitiveType::SHORT)
+            .unwrap();
+        assert_eq!(result.value, expected);
+        assert!(ctx.operand_stack.top().is_err());
+    }
+}
+
+///
+/// Test the conversion opcodes from standard specification
+///
+#[test]
+fn opcode_conversions_test() {
+    // s2i sign extends the short
+    let datatoexecute: &BytecodeData = &[
+        bytecode::sconst_m1 as BytecodeType,
+        bytecode::s2i as BytecodeType,
+    ];
+    let mut ctx = execute_bytecode(datatoexecute);
+    assert_eq!(ctx.operand_stack.ipop().unwrap(), -1);
+
+    // i2s keeps the 2 LSB
+    let datatoexecute: &BytecodeData = &[
+        bytecode::iipush as BytecodeType,
+        0x12,
+        0x34,
+        0x87_u8 as BytecodeType,
+        0x65,
+        bytecode::i2s as BytecodeType,
+    ];
+    let mut ctx = execute_bytecode(datatoexecute);
+    let result = ctx.operand_stack
+        .pop_check_type(constants::PrimitiveType::SHORT)
+        .unwrap();
+    assert_eq!(result.value as u16, 0x8765);
+
+    // i2b keeps the LSB and sign extends it
+    let datatoexecute: &BytecodeData = &[
+        bytecode::iipush as BytecodeType,
+        0x12,
+        0x34,
+        0x56,
+        0x80_u8 as BytecodeType,
+        bytecode::i2b as BytecodeType,
+    ];
+    let ctx = execute_bytecode(datatoexecute);
+    assert_eq!(ctx.operand_stack.top().unwrap().value, -0x80);
+
+    // s2b keeps the LSB and sign extends it
+    let datatoexecute: &BytecodeData = &[
+        bytecode::sspush as BytecodeType,
+        0x12,
+        0x7F,
+        bytecode::s2b as BytecodeType,
+    ];
+    let ctx = execute_bytecode(datatoexecute);
+    assert_eq!(ctx.operand_stack.top().unwrap().value, 0x7F);
+}
+
+///
+/// Test iinc and iinc_w opcodes from standard specification
+///
+#[test]
+fn opcode_iinc_test() {
+    let idx: BytecodeType = 1;
+    let datatoexecute: &BytecodeData = &[
+        bytecode::iinc as BytecodeType,
+        idx,
+        1,
+        bytecode::iinc_w as BytecodeType,
+        idx,
+        0x01,
+        0x00,
+    ];
+    let mut ctx = context::Context::new(datatoexecute);
+
+    ctx.frame_stack.push(frame::Frame::new(3));
+    {
+        let top_frame = ctx.frame_stack.top_mut().unwrap();
+        top_frame
+            .set_local(
+                idx as i16,
+                StackEntry::from_values(0x0001, constants::PrimitiveType::INTEGER),
+            )
+            .unwrap();
+        top_frame
+            .set_local(
+                (idx + 1) as i16,
+                StackEntry::from_values(-1, constants::PrimitiveType::INTEGER),
+            )
+            .unwrap();
+    }
+
+    execute_with_context(&mut ctx);
+
+    let frame = ctx.frame_stack.top().unwrap();
+    let high = frame
+        .get_local_check_type(idx as i16, constants::PrimitiveType::INTEGER)
+        .unwrap();
+    let low = frame
+        .get_local_check_type((idx + 1) as i16, constants::PrimitiveType::INTEGER)
+        .unwrap();
+    // 0x0001FFFF + 1 + 0x100
+    assert_eq!(high.value, 0x0002);
+    assert_eq!(low.value, 0x0100);
+}
+
+/// integer opcodes are rejected when the optional integer support is disabled
+#[test]
+fn opcode_int_support_disabled_test() {
+    let datatoexecute: &BytecodeData = &[
+        bytecode::sconst_1 as BytecodeType,
+        bytecode::iconst_1 as BytecodeType,
+    ];
+    let mut ctx = context::Context::new(datatoexecute);
+    ctx.int_support = false;
+
+    match interpreter::interpreter(&mut ctx) {
+        Err(InterpreterError::UnsupportedBytecode) => {}
+        _ => panic!("integer bytecode shall not be executed"),
+    }
+
+    // the short constant was pushed before the integer one was rejected
+    let result = ctx.operand_stack
+        .pop_check_type(constants::PrimitiveType::SHORT)
+        .unwrap();
+    assert_eq!(result.value, 1);
+    assert!(ctx.operand_stack.top().is_err());
+}
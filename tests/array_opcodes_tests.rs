@@ -0,0 +1,301 @@
+extern crate interpreterlib;
+
+use interpreterlib::{classes, constants, context, exceptions, frame, interpreter, jcvmerrors,
+                     objects, stack, traits};
+use interpreterlib::bytecodes::*;
+
+use interpreter::{BytecodeData, BytecodeType};
+use classes::{ClassRef, JCVMClass, OBJECT_CLASS};
+use jcvmerrors::InterpreterError;
+use objects::JCVMObject;
+use stack::StackEntry;
+use traits::BufferAccessor;
+
+pub fn execute_with_context(ctx: &mut context::Context) {
+    let _result = interpreter::interpreter(ctx);
+}
+
+///
+/// utility function creating a context with the given objects, whose references are stored
+/// in the locals of the current frame (the first object being in local 0)
+///
+fn context_with_objects(data: &BytecodeData, objects: Vec<JCVMObject>) -> context::Context<'_> {
+    let mut ctx = context::Context::new(data);
+    let mut top_frame = frame::Frame::new(objects.len() as u8);
+    for (index, object) in objects.into_iter().enumerate() {
+        let objectref = ctx.object_manager.add_object(object) as i16;
+        top_frame
+            .set_local(
+                index as i16,
+                StackEntry::from_values(objectref, constants::PrimitiveType::REFERENCE),
+            )
+            .unwrap();
+    }
+    ctx.frame_stack.push(top_frame);
+    ctx
+}
+
+/// the interpreter stops at the end of the bytecode when no exception was raised
+fn check_completed(result: Result<(), InterpreterError>) {
+    match result {
+        Err(InterpreterError::EndOfStream) => {}
+        _ => panic!("the execution shall have completed"),
+    }
+}
+
+fn check_uncaught(result: Result<(), InterpreterError>, expected_class: ClassRef) {
+    match result {
+        Err(InterpreterError::UncaughtException(class, _)) => assert_eq!(class, expected_class),
+        _ => panic!("an exception shall have been raised"),
+    }
+}
+
+///
+/// Test bastore, sastore and iastore opcodes from standard specification
+///
+#[test]
+fn opcode_xastore_test() {
+    let expectations: [(bytecode, constants::PrimitiveType, &[BytecodeType]); 3] = [
+        (
+            bytecode::bastore,
+            constants::PrimitiveType::BYTE,
+            &[bytecode::bspush as BytecodeType, -5],
+        ),
+        (
+            bytecode::sastore,
+            constants::PrimitiveType::SHORT,
+            &[bytecode::sspush as BytecodeType, 0x12, 0x34],
+        ),
+        (
+            bytecode::iastore,
+            constants::PrimitiveType::INTEGER,
+            &[bytecode::iipush as BytecodeType, 0x12, 0x34, 0x56, 0x78],
+        ),
+    ];
+
+    for &(store, type_, push_value) in &expectations {
+        // array[2] = value;
+        let mut datatoexecute = vec![
+            bytecode::aload_0 as BytecodeType,
+            bytecode::sconst_2 as BytecodeType,
+        ];
+        datatoexecute.extend_from_slice(push_value);
+        datatoexecute.push(store as BytecodeType);
+
+        let mut ctx = context_with_objects(
+            &datatoexecute,
+            vec![JCVMObject::new_array(0, 0, type_, 3 * type_.size() as i16, false)],
+        );
+        check_completed(interpreter::interpreter(&mut ctx));
+        assert!(ctx.operand_stack.is_empty());
+
+        let array = ctx.object_manager.get_object(1).unwrap();
+        match type_ {
+            constants::PrimitiveType::BYTE => assert_eq!(array.read_b(2).unwrap(), -5),
+            constants::PrimitiveType::SHORT => assert_eq!(array.read_s(4).unwrap(), 0x1234),
+            _ => assert_eq!(array.read_i(8).unwrap(), 0x1234_5678),
+        }
+    }
+}
+
+/// indexes outside of the array (of 3 shorts) and null arrays raise exceptions
+#[test]
+fn opcode_xastore_exceptions_test() {
+    let cases: [(&BytecodeData, ClassRef); 3] = [
+        (
+            &[
+                bytecode::aload_0 as BytecodeType,
+                bytecode::sconst_m1 as BytecodeType,
+                bytecode::sconst_1 as BytecodeType,
+                bytecode::sastore as BytecodeType,
+            ],
+            exceptions::ARRAY_INDEX_OUT_OF_BOUNDS_EXCEPTION_CLASS,
+        ),
+        (
+            &[
+                bytecode::aload_0 as BytecodeType,
+                bytecode::sconst_3 as BytecodeType,
+                bytecode::sconst_1 as BytecodeType,
+                bytecode::sastore as BytecodeType,
+            ],
+            exceptions::ARRAY_INDEX_OUT_OF_BOUNDS_EXCEPTION_CLASS,
+        ),
+        (
+            &[
+                bytecode::aconst_null as BytecodeType,
+                bytecode::sconst_0 as BytecodeType,
+                bytecode::sconst_1 as BytecodeType,
+                bytecode::sastore as BytecodeType,
+            ],
+            exceptions::NULL_POINTER_EXCEPTION_CLASS,
+        ),
+    ];
+
+    for &(datatoexecute, expected_class) in &cases {
+        let mut ctx = context_with_objects(
+            datatoexecute,
+            vec![JCVMObject::new_array(0, 0, constants::PrimitiveType::SHORT, 6, false)],
+        );
+        check_uncaught(interpreter::interpreter(&mut ctx), expected_class);
+        // the array is left unchanged
+        let array = ctx.object_manager.get_object(1).unwrap();
+        assert!((0..6).all(|offset| array.read_b(offset).unwrap() == 0));
+    }
+}
+
+/// loads outside of the array (of 3 shorts) raise an ArrayIndexOutOfBoundsException
+#[test]
+fn opcode_xaload_exceptions_test() {
+    for index in &[bytecode::sconst_m1, bytecode::sconst_3] {
+        let datatoexecute = [
+            bytecode::aload_0 as BytecodeType,
+            *index as BytecodeType,
+            bytecode::saload as BytecodeType,
+        ];
+        let mut ctx = context_with_objects(
+            &datatoexecute,
+            vec![JCVMObject::new_array(0, 0, constants::PrimitiveType::SHORT, 6, false)],
+        );
+        check_uncaught(
+            interpreter::interpreter(&mut ctx),
+            exceptions::ARRAY_INDEX_OUT_OF_BOUNDS_EXCEPTION_CLASS,
+        );
+    }
+}
+
+///
+/// executes array[0] = value with the array in local 0 and the value in local 1
+/// (null if there is no second object)
+///
+fn execute_aastore(
+    ctx_setup: &dyn Fn(&mut context::Context) -> Vec<JCVMObject>,
+) -> (context::Context<'static>, Result<(), InterpreterError>) {
+    let datatoexecute: &'static BytecodeData = &[
+        bytecode::aload_0 as BytecodeType,
+        bytecode::sconst_0 as BytecodeType,
+        bytecode::aload_1 as BytecodeType,
+        bytecode::aastore as BytecodeType,
+    ];
+    let mut ctx = context::Context::new(datatoexecute);
+    let mut objects = ctx_setup(&mut ctx);
+    let mut top_frame = frame::Frame::new(2);
+    top_frame
+        .set_local(1, StackEntry::from_values(0, constants::PrimitiveType::REFERENCE))
+        .unwrap();
+    for (index, object) in objects.drain(..).enumerate() {
+        let objectref = ctx.object_manager.add_object(object) as i16;
+        top_frame
+            .set_local(
+                index as i16,
+                StackEntry::from_values(objectref, constants::PrimitiveType::REFERENCE),
+            )
+            .unwrap();
+    }
+    ctx.frame_stack.push(top_frame);
+    let result = interpreter::interpreter(&mut ctx);
+    (ctx, result)
+}
+
+///
+/// registers class A, class B extending A and class C, and returns their references
+///
+fn add_classes(ctx: &mut context::Context) -> (ClassRef, ClassRef, ClassRef) {
+    let class_a = ctx.class_manager.add_class(JCVMClass::new(None));
+    let class_b = ctx.class_manager.add_class(JCVMClass::new(Some(class_a)));
+    let class_c = ctx.class_manager.add_class(JCVMClass::new(None));
+    (class_a, class_b, class_c)
+}
+
+fn stored_reference(ctx: &context::Context) -> i16 {
+    ctx.object_manager.get_object(1).unwrap().read_s(0).unwrap()
+}
+
+///
+/// Test aastore opcode from standard specification
+///
+#[test]
+fn opcode_aastore_test() {
+    // instances of subclasses can be stored
+    let (ctx, result) = execute_aastore(&|ctx| {
+        let (class_a, class_b, _) = add_classes(ctx);
+        vec![
+            JCVMObject::new_reference_array(0, 0, class_a, 2, false),
+            JCVMObject::new_instance(0, 0, class_b, 0, false),
+        ]
+    });
+    check_completed(result);
+    assert_eq!(stored_reference(&ctx), 2);
+
+    // arrays can be stored in arrays of Object
+    let (ctx, result) = execute_aastore(&|_| {
+        vec![
+            JCVMObject::new_reference_array(0, 0, OBJECT_CLASS, 2, false),
+            JCVMObject::new_array(0, 0, constants::PrimitiveType::BYTE, 2, false),
+        ]
+    });
+    check_completed(result);
+    assert_eq!(stored_reference(&ctx), 2);
+
+    // null can be stored in any array
+    let (ctx, result) = execute_aastore(&|ctx| {
+        let (_, _, class_c) = add_classes(ctx);
+        vec![JCVMObject::new_reference_array(0, 0, class_c, 2, false)]
+    });
+    check_completed(result);
+    assert_eq!(stored_reference(&ctx), constants::NULL_HANDLE);
+}
+
+/// storing objects that are not assignment compatible raises an ArrayStoreException
+#[test]
+fn opcode_aastore_incompatible_test() {
+    let (ctx, result) = execute_aastore(&|ctx| {
+        let (class_a, _, class_c) = add_classes(ctx);
+        vec![
+            JCVMObject::new_reference_array(0, 0, class_a, 2, false),
+            JCVMObject::new_instance(0, 0, class_c, 0, false),
+        ]
+    });
+    check_uncaught(result, exceptions::ARRAY_STORE_EXCEPTION_CLASS);
+    assert_eq!(stored_reference(&ctx), constants::NULL_HANDLE);
+
+    let (_, result) = execute_aastore(&|ctx| {
+        let (class_a, _, _) = add_classes(ctx);
+        vec![
+            JCVMObject::new_reference_array(0, 0, class_a, 2, false),
+            JCVMObject::new_array(0, 0, constants::PrimitiveType::SHORT, 2, false),
+        ]
+    });
+    check_uncaught(result, exceptions::ARRAY_STORE_EXCEPTION_CLASS);
+}
+
+/// the firewall restricts array stores to the arrays of the current context
+#[test]
+fn opcode_aastore_firewall_test() {
+    let global = constants::ObjectFlags::GLOBAL as u8;
+
+    // arrays owned by another context cannot be written...
+    let (_, result) = execute_aastore(&|ctx| {
+        ctx.current_context = 2;
+        vec![JCVMObject::new_reference_array(1, 0, OBJECT_CLASS, 2, false)]
+    });
+    check_uncaught(result, exceptions::SECURITY_EXCEPTION_CLASS);
+
+    // ... unless they are global, or the JCRE is running
+    for &(current_context, flags) in &[(2, global), (constants::JCRE_CONTEXT, 0)] {
+        let (_, result) = execute_aastore(&|ctx| {
+            ctx.current_context = current_context;
+            vec![JCVMObject::new_reference_array(1, flags, OBJECT_CLASS, 2, false)]
+        });
+        check_completed(result);
+    }
+
+    // references to global arrays cannot be stored
+    let (ctx, result) = execute_aastore(&|_| {
+        vec![
+            JCVMObject::new_reference_array(0, 0, OBJECT_CLASS, 2, false),
+            JCVMObject::new_array(0, global, constants::PrimitiveType::BYTE, 2, false),
+        ]
+    });
+    check_uncaught(result, exceptions::SECURITY_EXCEPTION_CLASS);
+    assert_eq!(stored_reference(&ctx), constants::NULL_HANDLE);
+}
@@ -1,12 +1,14 @@
 extern crate interpreterlib;
 
-use interpreterlib::{constants, context, interpreter};
+use interpreterlib::{constants, context, interpreter, jcvmerrors, stack};
 use interpreterlib::bytecodes::*;
 
 use interpreter::{BytecodeData, BytecodeType};
+use jcvmerrors::InterpreterError;
+use stack::StackEntry;
 
-pub fn execute_with_context(mut ctx: &mut context::Context) {
-    let _result = interpreter::interpreter(&mut ctx);
+pub fn execute_with_context(ctx: &mut context::Context) {
+    let _result = interpreter::interpreter(ctx);
 }
 
 pub fn execute_bytecode<'a>(data: &'a BytecodeData) -> context::Context<'a> {
@@ -16,11 +18,58 @@ pub fn execute_bytecode<'a>(data: &'a BytecodeData) -> context::Context<'a> {
     ctx
 }
 
-/// nop bytecode shall fail (at least for now)
+/// nop does nothing, and opcodes the interpreter does not implement are rejected
 #[test]
-#[should_panic]
 fn nop_test() {
-    execute_bytecode(&[bytecode::nop as i8]);
+    let data: &BytecodeData = &[
+        bytecode::nop as BytecodeType,
+        bytecode::nop as BytecodeType,
+        bytecode::sconst_1 as BytecodeType,
+        bytecode::END as BytecodeType,
+    ];
+    let mut ctx = context::Context::new(data);
+    match interpreter::interpreter(&mut ctx) {
+        Err(InterpreterError::UnsupportedBytecode) => {}
+        _ => panic!("the opcode shall have been rejected"),
+    }
+    assert_eq!(ctx.bytecode_fetcher.opcode_offset(), 3);
+    let entry = ctx.operand_stack
+        .pop_check_type(constants::PrimitiveType::SHORT)
+        .unwrap();
+    assert_eq!(entry.value, 1);
+    assert!(ctx.operand_stack.is_empty());
+}
+
+/// overflows and underflows of the operand stack are reported as errors
+#[test]
+fn stack_overflow_underflow_test() {
+    let mut ctx = context::Context::new(&[bytecode::pop as i8]);
+    match interpreter::interpreter(&mut ctx) {
+        Err(InterpreterError::StackUnderflowError) => {}
+        _ => panic!("stack underflow shall have been reported"),
+    }
+
+    let mut stack = stack::Stack::new(3);
+    stack.spush(1).unwrap();
+    stack.spush(2).unwrap();
+    // integers are pushed entirely or not at all
+    match stack.ipush(3) {
+        Err(InterpreterError::StackOverflowError) => {}
+        _ => panic!("stack overflow shall have been reported"),
+    }
+    stack.spush(3).unwrap();
+    match stack.apush(4) {
+        Err(InterpreterError::StackOverflowError) => {}
+        _ => panic!("stack overflow shall have been reported"),
+    }
+    assert_eq!(stack.spop().unwrap(), 3);
+
+    // the ceiling never goes above the size the stack was created with
+    stack.set_bounds(0, 8);
+    stack.spush(3).unwrap();
+    assert_eq!(stack.spush(4), Err(InterpreterError::StackOverflowError));
+    stack.clear_bounds();
+    assert_eq!(stack.spush(4), Err(InterpreterError::StackOverflowError));
 }
 
 /// make sure the aconst_null pushes a null reference on the stack
@@ -53,8 +102,8 @@ fn opcode_sconst_x_test() {
 #[test]
 fn opcode_iconst_x_test() {
     for x in bytecode::iconst_m1 as u8..bytecode::iconst_5 as u8 {
-        let expected_value1 = ((x as i32) - (bytecode::iconst_0 as i32) >> 16) as i16;
-        let expected_value2 = ((x as i32) - (bytecode::iconst_0 as i32) & 0xFFFF) as i16;
+        let expected_value1 = (((x as i32) - (bytecode::iconst_0 as i32)) >> 16) as i16;
+        let expected_value2 = (((x as i32) - (bytecode::iconst_0 as i32)) & 0xFFFF) as i16;
         let dat = &[x as i8];
         let ctx = execute_bytecode(dat);
 
@@ -182,3 +231,173 @@ fn opcode_iipush_test() {
     assert!(entry1.is_of_type(constants::PrimitiveType::INTEGER));
     assert!(entry2.is_of_type(constants::PrimitiveType::INTEGER));
 }
+
+///
+/// utility function pushing the given words before executing the bytecode
+/// returns the content of the operand stack from bottom to top
+///
+fn execute_with_words(data: &BytecodeData, words: &[StackEntry]) -> Vec<StackEntry> {
+    let mut ctx = context::Context::new(data);
+    for word in words {
+        ctx.operand_stack.push(*word).unwrap();
+    }
+    execute_with_context(&mut ctx);
+
+    let mut result = Vec::new();
+    while ctx.operand_stack.top().is_ok() {
+        result.insert(0, ctx.operand_stack.pop().unwrap());
+    }
+    result
+}
+
+///
+/// compares stack entries based on their values and types
+///
+fn assert_words_eq(obtained: &[StackEntry], expected: &[StackEntry]) {
+    assert_eq!(obtained.len(), expected.len());
+    for (word, expected_word) in obtained.iter().zip(expected.iter()) {
+        assert_eq!(word.value, expected_word.value);
+        for type_ in &[
+            constants::PrimitiveType::REFERENCE,
+            constants::PrimitiveType::SHORT,
+            constants::PrimitiveType::INTEGER,
+        ] {
+            assert_eq!(word.is_of_type(*type_), expected_word.is_of_type(*type_));
+        }
+    }
+}
+
+fn short(value: i16) -> StackEntry {
+    StackEntry::from_values(value, constants::PrimitiveType::SHORT)
+}
+
+fn reference(value: i16) -> StackEntry {
+    StackEntry::from_values(value, constants::PrimitiveType::REFERENCE)
+}
+
+fn int_half(value: i16) -> StackEntry {
+    StackEntry::from_values(value, constants::PrimitiveType::INTEGER)
+}
+
+///
+/// Test pop and pop2 opcodes from standard specification
+///
+#[test]
+fn opcode_pop_pop2_test() {
+    let words = [short(1), reference(2), int_half(3), int_half(4)];
+
+    let result = execute_with_words(&[bytecode::pop as BytecodeType], &words);
+    assert_words_eq(&result, &words[..3]);
+
+    let result = execute_with_words(&[bytecode::pop2 as BytecodeType], &words);
+    assert_words_eq(&result, &words[..2]);
+}
+
+///
+/// Test dup and dup2 opcodes from standard specification
+///
+#[test]
+fn opcode_dup_dup2_test() {
+    let words = [short(1), reference(2), int_half(3), int_half(4)];
+
+    let result = execute_with_words(&[bytecode::dup as BytecodeType], &words[..2]);
+    assert_words_eq(&result, &[short(1), reference(2), reference(2)]);
+
+    // dup2 duplicates both halves of an integer
+    let result = execute_with_words(&[bytecode::dup2 as BytecodeType], &words);
+    assert_words_eq(&result, &[
+        short(1),
+        reference(2),
+        int_half(3),
+        int_half(4),
+        int_half(3),
+        int_half(4),
+    ]);
+}
+
+///
+/// Test dup_x opcode from standard specification
+///
+#[test]
+fn opcode_dup_x_test() {
+    let words = [short(1), reference(2), int_half(3), int_half(4)];
+
+    // m = 1, n = 2: copy of the top word inserted 2 words down
+    let result = execute_with_words(&[bytecode::dup_x as BytecodeType, 0x12], &words);
+    assert_words_eq(&result, &[
+        short(1),
+        reference(2),
+        int_half(4),
+        int_half(3),
+        int_half(4),
+    ]);
+
+    // m = 2, n = 4: copy of the top 2 words inserted at the bottom
+    let result = execute_with_words(&[bytecode::dup_x as BytecodeType, 0x24], &words);
+    assert_words_eq(&result, &[
+        int_half(3),
+        int_half(4),
+        short(1),
+        reference(2),
+        int_half(3),
+        int_half(4),
+    ]);
+
+    // m = 3, n = 0: behaves like a dup of 3 words
+    let result = execute_with_words(&[bytecode::dup_x as BytecodeType, 0x30], &words);
+    assert_words_eq(&result, &[
+        short(1),
+        reference(2),
+        int_half(3),
+        int_half(4),
+        reference(2),
+        int_half(3),
+        int_half(4),
+    ]);
+}
+
+///
+/// Test swap_x opcode from standard specification
+///
+#[test]
+fn opcode_swap_x_test() {
+    let words = [short(1), reference(2), int_half(3), int_half(4)];
+
+    let result = execute_with_words(&[bytecode::swap_x as BytecodeType, 0x11], &words);
+    assert_words_eq(&result, &[short(1), reference(2), int_half(4), int_half(3)]);
+
+    // m = 2, n = 1: the integer is swapped with the reference below it
+    let result = execute_with_words(&[bytecode::swap_x as BytecodeType, 0x21], &words);
+    assert_words_eq(&result, &[short(1), int_half(3), int_half(4), reference(2)]);
+
+    let result = execute_with_words(&[bytecode::swap_x as BytecodeType, 0x22], &words);
+    assert_words_eq(&result, &[int_half(3), int_half(4), short(1), reference(2)]);
+}
+
+/// invalid m and n values are rejected without modifying the stack
+#[test]
+fn opcode_dup_x_swap_x_invalid_operand_test() {
+    let words = [short(1), reference(2), int_half(3), int_half(4)];
+
+    for &(bc, mn) in &[
+        (bytecode::dup_x, 0x01),
+        (bytecode::dup_x, 0x51),
+        (bytecode::dup_x, 0x21),
+        (bytecode::dup_x, 0x17),
+        (bytecode::swap_x, 0x10),
+        (bytecode::swap_x, 0x31),
+        (bytecode::swap_x, 0x13),
+    ] {
+        let datatoexecute: &BytecodeData = &[bc as BytecodeType, mn];
+        let mut ctx = context::Context::new(datatoexecute);
+        for word in &words {
+            ctx.operand_stack.push(*word).unwrap();
+        }
+
+        match interpreter::interpreter(&mut ctx) {
+            Err(InterpreterError::InvalidOperand) => {}
+            _ => panic!("invalid operand shall have been rejected"),
+        }
+        assert_eq!(ctx.operand_stack.top().unwrap().value, 4);
+    }
+}
@@ -0,0 +1,296 @@
+extern crate interpreterlib;
+
+use interpreterlib::{constants, context, frame, interpreter, jcvmerrors, stack};
+use interpreterlib::bytecodes::*;
+
+use interpreter::{BytecodeData, BytecodeType};
+use jcvmerrors::InterpreterError;
+use stack::StackEntry;
+
+pub fn execute_with_context(ctx: &mut context::Context) {
+    let _result = interpreter::interpreter(ctx);
+}
+
+pub fn execute_bytecode<'a>(data: &'a BytecodeData) -> context::Context<'a> {
+    let mut ctx = context::Context::new(data);
+    // silence result as we don't care here
+    execute_with_context(&mut ctx);
+    ctx
+}
+
+///
+/// utility function executing a conditional branch over 2 pushed operands.
+/// When the branch is taken, sconst_1 is skipped and only sconst_2 is pushed
+/// Returns true if the branch was taken
+///
+fn is_branch_taken(bc: bytecode, operands: &[StackEntry]) -> bool {
+    let wide = bc as u8 >= bytecode::ifeq_w as u8;
+    let datatoexecute: &BytecodeData = if wide {
+        &[
+            bc as BytecodeType,
+            0,
+            4,
+            bytecode::sconst_1 as BytecodeType,
+            bytecode::sconst_2 as BytecodeType,
+        ]
+    } else {
+        &[
+            bc as BytecodeType,
+            3,
+            bytecode::sconst_1 as BytecodeType,
+            bytecode::sconst_2 as BytecodeType,
+        ]
+    };
+    let mut ctx = context::Context::new(datatoexecute);
+    for operand in operands {
+        ctx.operand_stack.push(*operand).unwrap();
+    }
+    execute_with_context(&mut ctx);
+
+    let last = ctx.operand_stack.pop().unwrap();
+    assert_eq!(last.value, 2);
+    let taken = ctx.operand_stack.top().is_err();
+    if !taken {
+        assert_eq!(ctx.operand_stack.pop().unwrap().value, 1);
+    }
+    // operands shall have been consumed
+    assert!(ctx.operand_stack.top().is_err());
+    taken
+}
+
+fn short(value: i16) -> StackEntry {
+    StackEntry::from_values(value, constants::PrimitiveType::SHORT)
+}
+
+fn reference(value: i16) -> StackEntry {
+    StackEntry::from_values(value, constants::PrimitiveType::REFERENCE)
+}
+
+///
+/// Test ifeq...ifle opcodes and their wide counterparts from standard specification
+///
+#[test]
+fn opcode_ifxx_test() {
+    let expectations = [
+        (bytecode::ifeq, bytecode::ifeq_w, [false, true, false]),
+        (bytecode::ifne, bytecode::ifne_w, [true, false, true]),
+        (bytecode::iflt, bytecode::iflt_w, [true, false, false]),
+        (bytecode::ifge, bytecode::ifge_w, [false, true, true]),
+        (bytecode::ifgt, bytecode::ifgt_w, [false, false, true]),
+        (bytecode::ifle, bytecode::ifle_w, [true, true, false]),
+    ];
+
+    for &(bc, bc_w, results) in &expectations {
+        for (value, &expected) in [-1, 0, 1].iter().zip(results.iter()) {
+            assert_eq!(is_branch_taken(bc, &[short(*value)]), expected);
+            assert_eq!(is_branch_taken(bc_w, &[short(*value)]), expected);
+        }
+    }
+}
+
+///
+/// Test ifnull and ifnonnull opcodes (and their wide counterparts) from standard specification
+///
+#[test]
+fn opcode_ifnull_ifnonnull_test() {
+    assert!(is_branch_taken(bytecode::ifnull, &[reference(constants::NULL_HANDLE)]));
+    assert!(!is_branch_taken(bytecode::ifnull_w, &[reference(1)]));
+    assert!(!is_branch_taken(bytecode::ifnonnull, &[reference(constants::NULL_HANDLE)]));
+    assert!(is_branch_taken(bytecode::ifnonnull_w, &[reference(1)]));
+}
+
+///
+/// Test if_acmpxx opcodes from standard specification
+///
+#[test]
+fn opcode_if_acmp_test() {
+    assert!(is_branch_taken(bytecode::if_acmpeq, &[reference(3), reference(3)]));
+    assert!(!is_branch_taken(bytecode::if_acmpeq_w, &[reference(3), reference(4)]));
+    assert!(!is_branch_taken(bytecode::if_acmpne, &[reference(3), reference(3)]));
+    assert!(is_branch_taken(bytecode::if_acmpne_w, &[reference(3), reference(4)]));
+}
+
+///
+/// Test if_scmpxx opcodes from standard specification (value1 is pushed first)
+///
+#[test]
+fn opcode_if_scmp_test() {
+    let expectations = [
+        (bytecode::if_scmpeq, bytecode::if_scmpeq_w, [false, true, false]),
+        (bytecode::if_scmpne, bytecode::if_scmpne_w, [true, false, true]),
+        (bytecode::if_scmplt, bytecode::if_scmplt_w, [true, false, false]),
+        (bytecode::if_scmpge, bytecode::if_scmpge_w, [false, true, true]),
+        (bytecode::if_scmpgt, bytecode::if_scmpgt_w, [false, false, true]),
+        (bytecode::if_scmple, bytecode::if_scmple_w, [true, true, false]),
+    ];
+
+    for &(bc, bc_w, results) in &expectations {
+        for (value1, &expected) in [9, 10, 11].iter().zip(results.iter()) {
+            assert_eq!(is_branch_taken(bc, &[short(*value1), short(10)]), expected);
+            assert_eq!(is_branch_taken(bc_w, &[short(*value1), short(10)]), expected);
+        }
+    }
+}
+
+///
+/// Test goto and goto_w with a loop summing 5 + 4 + 3 + 2 + 1 in local 1
+///
+#[test]
+fn opcode_goto_loop_test() {
+    let datatoexecute: &BytecodeData = &[
+        bytecode::goto as BytecodeType,       // 0: jump to the loop condition
+        9,
+        bytecode::sload_0 as BytecodeType,    // 2: local 1 += local 0
+        bytecode::sload_1 as BytecodeType,    // 3
+        bytecode::sadd as BytecodeType,       // 4
+        bytecode::sstore_1 as BytecodeType,   // 5
+        bytecode::sinc as BytecodeType,       // 6: local 0 -= 1
+        0,
+        -1,
+        bytecode::sload_0 as BytecodeType,    // 9: loop while local 0 != 0
+        bytecode::ifne_w as BytecodeType,     // 10
+        -1,
+        -8,
+        bytecode::goto_w as BytecodeType,     // 13: jump over the sconst
+        0,
+        4,
+        bytecode::sconst_m1 as BytecodeType,  // 16
+        bytecode::sload_1 as BytecodeType,    // 17
+    ];
+    let mut ctx = context::Context::new(datatoexecute);
+    ctx.frame_stack.push(frame::Frame::new(2));
+    {
+        let top_frame = ctx.frame_stack.top_mut().unwrap();
+        top_frame.set_local(0, short(5)).unwrap();
+        top_frame.set_local(1, short(0)).unwrap();
+    }
+    execute_with_context(&mut ctx);
+
+    let mut result = ctx.operand_stack
+        .pop_check_type(constants::PrimitiveType::SHORT)
+        .unwrap();
+    assert_eq!(result.value, 15);
+    assert!(ctx.operand_stack.top().is_err());
+
+    // the loop counter reached zero
+    result = ctx.frame_stack
+        .top()
+        .unwrap()
+        .get_local_check_type(0, constants::PrimitiveType::SHORT)
+        .unwrap();
+    assert_eq!(result.value, 0);
+}
+
+/// branches outside of the bytecode are rejected
+#[test]
+fn opcode_goto_out_of_bounds_test() {
+    for &(offset, bounds) in &[(-2, (0, 3)), (2, (0, 3)), (1, (0, 2)), (-1, (1, 3))] {
+        let datatoexecute: &BytecodeData = &[
+            bytecode::sconst_0 as BytecodeType,
+            bytecode::goto as BytecodeType,
+            offset,
+        ];
+        let mut ctx = context::Context::new(datatoexecute);
+        ctx.bytecode_fetcher
+            .set_method_bounds(bounds.0, bounds.1)
+            .unwrap();
+
+        match interpreter::interpreter(&mut ctx) {
+            Err(InterpreterError::InvalidBranchTarget) => {}
+            _ => panic!("branch shall have been rejected"),
+        }
+    }
+}
+
+/// branches are bounded by the code of the method, when its extent is known
+#[test]
+fn opcode_goto_method_bounds_test() {
+    let datatoexecute: &BytecodeData = &[
+        // main, jumping into the code of the next method
+        1,
+        0,
+        bytecode::goto as BytecodeType,
+        6,
+        bytecode::sconst_0 as BytecodeType,
+        bytecode::sreturn as BytecodeType,
+        // next method, at offset 6
+        1,
+        0,
+        bytecode::sconst_1 as BytecodeType,
+        bytecode::sreturn as BytecodeType,
+    ];
+    let mut ctx = context::Context::new(datatoexecute);
+    ctx.method_extents.add_method(0, 6);
+    ctx.method_extents.add_method(6, 10);
+    match interpreter::invoke(&mut ctx, 0) {
+        Err(InterpreterError::InvalidBranchTarget) => {}
+        result => panic!("branch shall have been rejected, got {:?}", result),
+    }
+
+    // methods of unknown extent end at the end of the bytecode
+    let mut ctx = context::Context::new(datatoexecute);
+    interpreter::invoke(&mut ctx, 0).unwrap();
+    assert_eq!(ctx.operand_stack.spop().unwrap(), 1);
+}
+
+///
+/// Test jsr and ret with a subroutine pushing 1, called before pushing 2 and 3
+///
+#[test]
+fn opcode_jsr_ret_test() {
+    let datatoexecute: &BytecodeData = &[
+        bytecode::jsr as BytecodeType,        // 0: call the subroutine
+        0,
+        6,
+        bytecode::sconst_2 as BytecodeType,   // 3
+        bytecode::goto as BytecodeType,       // 4: jump over the subroutine
+        6,
+        bytecode::astore_1 as BytecodeType,   // 6: subroutine, saves the return address
+        bytecode::sconst_1 as BytecodeType,   // 7
+        bytecode::ret as BytecodeType,        // 8
+        1,
+        bytecode::sconst_3 as BytecodeType,   // 10
+    ];
+    let mut ctx = context::Context::new(datatoexecute);
+    ctx.frame_stack.push(frame::Frame::new(2));
+    execute_with_context(&mut ctx);
+
+    for expected in &[3, 2, 1] {
+        assert_eq!(ctx.operand_stack.spop().unwrap(), *expected);
+    }
+    assert!(ctx.operand_stack.top().is_err());
+
+    // the return address is the offset of the opcode following jsr
+    let result = ctx.frame_stack
+        .top()
+        .unwrap()
+        .get_local_check_type(1, constants::PrimitiveType::RETURNADDRESS)
+        .unwrap();
+    assert_eq!(result.value, 3);
+}
+
+/// ret only accepts return addresses pushed by jsr, not shorts
+#[test]
+fn opcode_ret_forged_address_test() {
+    let datatoexecute: &BytecodeData = &[
+        bytecode::ret as BytecodeType,
+        0,
+        bytecode::sconst_1 as BytecodeType,
+    ];
+    let mut ctx = context::Context::new(datatoexecute);
+    ctx.frame_stack.push(frame::Frame::new(1));
+    ctx.frame_stack
+        .top_mut()
+        .unwrap()
+        .set_local(0, short(2))
+        .unwrap();
+
+    match interpreter::interpreter(&mut ctx) {
+        Err(InterpreterError::InvalidVariableType(
+            constants::PrimitiveType::SHORT,
+            constants::PrimitiveType::RETURNADDRESS,
+        )) => {}
+        _ => panic!("ret shall have been rejected"),
+    }
+    assert!(ctx.operand_stack.top().is_err());
+}
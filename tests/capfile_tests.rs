@@ -0,0 +1,391 @@
+extern crate interpreterlib;
+extern crate miniz_oxide;
+
+use interpreterlib::{capcomponents, capfile, constants, jcvmerrors};
+
+use capcomponents::*;
+use capfile::CapFile;
+use jcvmerrors::CapFileError;
+
+const PACKAGE_AID: [u8; 5] = [0xA0, 0x00, 0x00, 0x00, 0x62];
+
+///
+/// Components (tag and info) of a small package defining one applet class A, with:
+/// - a public static method at offset 1 of the Method component
+/// - a static short array {1, 2} and a static short 0x1234
+///
+/// The Directory component is built from the sizes of the others by cap_components
+///
+fn package_components() -> Vec<(u8, Vec<u8>)> {
+    vec![
+        (
+            COMPONENT_HEADER,
+            vec![
+                0xDE, 0xCA, 0xFF, 0xED, 2, 2, ACC_EXPORT | ACC_APPLET, 0, 1, 5, 0xA0, 0x00,
+                0x00, 0x00, 0x62, 3, b'p', b'k', b'g',
+            ],
+        ),
+        (
+            COMPONENT_APPLET,
+            vec![1, 6, 0xA0, 0x00, 0x00, 0x00, 0x62, 0x01, 0x00, 0x01],
+        ),
+        (
+            COMPONENT_IMPORT,
+            vec![1, 0, 1, 7, 0xA0, 0x00, 0x00, 0x00, 0x62, 0x00, 0x01],
+        ),
+        (
+            COMPONENT_CONSTANT_POOL,
+            vec![0, 2, 1, 0x80, 0x00, 0, 6, 0, 0x00, 0x01],
+        ),
+        (
+            COMPONENT_CLASS,
+            vec![0, 0, 0x00, 0x80, 0x00, 1, 0xFF, 0, 0, 1, 0, 0, 0x00, 0x01],
+        ),
+        (COMPONENT_METHOD, vec![0, 0x01, 0x10, 0x7A]),
+        (
+            COMPONENT_STATIC_FIELD,
+            vec![
+                0, 4, 0, 1, 0, 1, 4, 0, 4, 0, 1, 0, 2, 0, 0, 0, 2, 0x12, 0x34,
+            ],
+        ),
+        (COMPONENT_REFERENCE_LOCATION, vec![0, 0, 0, 3, 0xFF, 0x02, 0x04]),
+        (COMPONENT_EXPORT, vec![1, 0, 2, 1, 1, 0, 2, 0, 1]),
+        (
+            COMPONENT_DESCRIPTOR,
+            vec![
+                1, 0, 0x01, 0x00, 0x02, 0, 0, 1, 0, 1, // class A
+                0, 0x09, 0, 0x00, 0x02, 0x80, 0x02, // static field
+                0, 0x09, 0x00, 0x01, 0x00, 35, 0, 1, 0, 0, 0, 0, // static method
+                0, 2, 0xFF, 0xFF, 0, 35, // constant pool types
+                1, 0x10, // ()V
+            ],
+        ),
+        (
+            COMPONENT_DEBUG,
+            vec![
+                0, 2, 0, 3, b'p', b'k', b'g', 0, 1, b'A', 0, 0, 0, 1, // strings, 1 class
+                0, 1, 0, 1, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 1, // class A
+                0, 1, 0, 1, 0, 9, 0, 1, 2, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 10, // method
+            ],
+        ),
+    ]
+}
+
+/// adds the Directory component matching the given components (version 2.2)
+fn cap_components(mut components: Vec<(u8, Vec<u8>)>) -> Vec<(u8, Vec<u8>)> {
+    let directory_size = 12 * 2 + 3 * 2 + 3;
+    let mut directory = Vec::new();
+    for tag in COMPONENT_HEADER..=COMPONENT_DEBUG {
+        let size = match tag {
+            COMPONENT_DIRECTORY => directory_size,
+            _ => components
+                .iter()
+                .find(|component| component.0 == tag)
+                .map_or(0, |component| component.1.len()),
+        };
+        directory.extend_from_slice(&[(size >> 8) as u8, size as u8]);
+    }
+    // image size, array init count and size, import, applet and custom component counts
+    directory.extend_from_slice(&[0, 4, 0, 1, 0, 4, 1, 1, 0]);
+    assert_eq!(directory.len(), directory_size);
+    components.insert(1, (COMPONENT_DIRECTORY, directory));
+    components
+}
+
+/// component file: tag, size and info
+fn component_file(tag: u8, info: &[u8]) -> Vec<u8> {
+    let mut result = vec![tag, (info.len() >> 8) as u8, info.len() as u8];
+    result.extend_from_slice(info);
+    result
+}
+
+/// components concatenated one after the other
+fn concatenate(components: &[(u8, Vec<u8>)]) -> Vec<u8> {
+    components
+        .iter()
+        .flat_map(|&(tag, ref info)| component_file(tag, info))
+        .collect()
+}
+
+fn push_u2(data: &mut Vec<u8>, value: usize) {
+    data.extend_from_slice(&[value as u8, (value >> 8) as u8]);
+}
+
+fn push_u4(data: &mut Vec<u8>, value: usize) {
+    push_u2(data, value & 0xFFFF);
+    push_u2(data, value >> 16);
+}
+
+///
+/// pushes the zip header of a file, local or in the central directory (the CRC not being
+/// checked by the reader)
+///
+fn push_zip_header(
+    header: &mut Vec<u8>,
+    central: bool,
+    name: &str,
+    method: u16,
+    sizes: (usize, usize),
+    local_offset: usize,
+) {
+    push_u4(header, if central { 0x0201_4B50 } else { 0x0403_4B50 });
+    if central {
+        push_u2(header, 20);
+    }
+    for &value in &[20, 0, method as usize, 0, 0] {
+        push_u2(header, value);
+    }
+    for &value in &[0, sizes.0, sizes.1] {
+        push_u4(header, value);
+    }
+    push_u2(header, name.len());
+    push_u2(header, 0);
+    if central {
+        for _ in 0..3 {
+            push_u2(header, 0);
+        }
+        push_u4(header, 0);
+        push_u4(header, local_offset);
+    }
+    header.extend_from_slice(name.as_bytes());
+}
+
+/// builds a zip archive with the given files (name, content, compression method)
+fn zip(files: &[(&str, Vec<u8>, u16)]) -> Vec<u8> {
+    let mut archive = Vec::new();
+    let mut central_directory = Vec::new();
+    for &(name, ref content, method) in files {
+        let data = match method {
+            8 => miniz_oxide::deflate::compress_to_vec(content, 6),
+            _ => content.clone(),
+        };
+        let sizes = (data.len(), content.len());
+        let local_offset = archive.len();
+        push_zip_header(&mut archive, false, name, method, sizes, 0);
+        push_zip_header(&mut central_directory, true, name, method, sizes, local_offset);
+        archive.extend_from_slice(&data);
+    }
+    let central_directory_offset = archive.len();
+    archive.extend_from_slice(&central_directory);
+    push_u4(&mut archive, 0x0605_4B50);
+    for &value in &[0, 0, files.len(), files.len()] {
+        push_u2(&mut archive, value);
+    }
+    push_u4(&mut archive, central_directory.len());
+    push_u4(&mut archive, central_directory_offset);
+    push_u2(&mut archive, 0);
+    archive
+}
+
+/// JAR container of the package, with the manifest and the given compression method
+fn package_jar(components: &[(u8, Vec<u8>)], method: u16) -> Vec<u8> {
+    let names = [
+        "Header", "Directory", "Applet", "Import", "ConstantPool", "Class", "Method",
+        "StaticField", "RefLocation", "Export", "Descriptor", "Debug",
+    ];
+    let mut files = vec![("META-INF/MANIFEST.MF", b"Manifest-Version: 1.0\n".to_vec(), method)];
+    let paths: Vec<String> = components
+        .iter()
+        .map(|&(tag, _)| format!("pkg/javacard/{}.cap", names[tag as usize - 1]))
+        .collect();
+    for (path, &(tag, ref info)) in paths.iter().zip(components) {
+        files.push((path.as_str(), component_file(tag, info), method));
+    }
+    zip(&files)
+}
+
+fn check_package(cap: &CapFile) {
+    assert_eq!(cap.header.package.aid, PACKAGE_AID.to_vec());
+    assert_eq!(cap.header.package_name, Some(b"pkg".to_vec()));
+    assert_eq!(cap.directory.component_size(COMPONENT_METHOD), 4);
+
+    let applet = cap.applet.as_ref().unwrap();
+    assert_eq!(applet.applets[0].install_method_offset, 1);
+    assert_eq!(cap.import.packages[0].major_version, 1);
+    assert_eq!(
+        cap.constant_pool.entries,
+        vec![
+            CapConstantPoolEntry::ClassRef(CapClassRef::External {
+                package_token: 0,
+                class_token: 0,
+            }),
+            CapConstantPoolEntry::StaticMethodRef(CapStaticRef::Internal(1)),
+        ]
+    );
+
+    assert_eq!(cap.class.classes.len(), 1);
+    match cap.class.classes[0] {
+        CapClassInfo::Class {
+            offset,
+            super_class,
+            ref public_virtual_methods,
+            ..
+        } => {
+            assert_eq!(offset, 2);
+            assert!(super_class.is_some());
+            assert_eq!(public_virtual_methods, &vec![1]);
+        }
+        _ => panic!("A shall be a class"),
+    }
+
+    assert!(cap.method.exception_handlers.is_empty());
+    assert_eq!(cap.method.methods, vec![0, 0x01, 0x10, 0x7A]);
+    assert_eq!(cap.static_field.image_size, 4);
+    assert_eq!(
+        cap.static_field.array_init[0].type_,
+        constants::PrimitiveType::SHORT
+    );
+    assert_eq!(cap.static_field.non_default_values, vec![0x12, 0x34]);
+    assert!(cap.reference_location.byte_indices.is_empty());
+    assert_eq!(cap.reference_location.byte2_indices, vec![257, 261]);
+    assert_eq!(cap.export.as_ref().unwrap().classes[0].static_method_offsets, vec![1]);
+
+    let descriptor = cap.descriptor.as_ref().unwrap();
+    assert_eq!(descriptor.classes[0].this_class_ref, CapClassRef::Internal(2));
+    assert_eq!(
+        descriptor.classes[0].fields[0].field_ref,
+        CapFieldRef::Static(CapStaticRef::Internal(2))
+    );
+    assert_eq!(descriptor.classes[0].methods[0].method_offset, 1);
+    assert_eq!(descriptor.constant_pool_types, vec![0xFFFF, 35]);
+    assert_eq!(descriptor.type_desc_offset, 35);
+    assert_eq!(descriptor.type_desc[0].types, vec![0x10]);
+
+    let debug = cap.debug.as_ref().unwrap();
+    assert_eq!(debug.strings[debug.classes[0].name_index as usize], b"A".to_vec());
+    assert_eq!(debug.classes[0].methods[0].line_table[0].source_line, 10);
+}
+
+/// the components can be given concatenated (IJC) or in a JAR container
+#[test]
+fn capfile_parse_test() {
+    let components = cap_components(package_components());
+    check_package(&CapFile::from_bytes(&concatenate(&components)).unwrap());
+    for &method in &[0, 8] {
+        check_package(&CapFile::from_bytes(&package_jar(&components, method)).unwrap());
+    }
+
+    // the optional components may be omitted
+    let components = cap_components(
+        package_components()
+            .into_iter()
+            .filter(|component| component.0 != COMPONENT_APPLET && component.0 != COMPONENT_DEBUG)
+            .collect(),
+    );
+    let mut concatenated = concatenate(&components);
+    // the applet count of the Directory component is the next to last byte
+    let applet_count = components[0].1.len() + 3 + components[1].1.len() + 1;
+    concatenated[applet_count] = 0;
+    let cap = CapFile::from_bytes(&concatenated).unwrap();
+    assert!(cap.applet.is_none());
+    assert!(cap.debug.is_none());
+}
+
+fn check_error(components: &[(u8, Vec<u8>)], expected: CapFileError) {
+    assert_eq!(CapFile::from_bytes(&concatenate(components)), Err(expected));
+}
+
+/// invalid component sets are rejected
+#[test]
+fn capfile_components_errors_test() {
+    let components = cap_components(package_components());
+
+    let mut missing = components.clone();
+    missing.retain(|component| component.0 != COMPONENT_METHOD);
+    check_error(&missing, CapFileError::MissingComponent(COMPONENT_METHOD));
+
+    let mut duplicate = components.clone();
+    duplicate.push(components[3].clone());
+    check_error(&duplicate, CapFileError::DuplicateComponent(components[3].0));
+
+    let mut unknown = components.clone();
+    unknown.push((200, vec![1, 2]));
+    check_error(&unknown, CapFileError::UnknownComponent(200));
+
+    // the size of the components shall match the Directory component
+    let mut resized = components.clone();
+    resized[4].1.push(0);
+    check_error(&resized, CapFileError::InvalidComponentSize(resized[4].0));
+
+    // truncated file
+    let concatenated = concatenate(&components);
+    assert_eq!(
+        CapFile::from_bytes(&concatenated[..concatenated.len() - 1]),
+        Err(CapFileError::UnexpectedEnd(COMPONENT_DEBUG))
+    );
+}
+
+/// invalid component contents are rejected
+#[test]
+fn capfile_content_errors_test() {
+    let mut components = package_components();
+    components[0].1[0] = 0xCA;
+    check_error(
+        &cap_components(components),
+        CapFileError::InvalidMagic(0xCACA_FFED),
+    );
+
+    let mut components = package_components();
+    components[0].1[5] = 3;
+    check_error(
+        &cap_components(components),
+        CapFileError::UnsupportedVersion(3, 2),
+    );
+
+    // unknown constant pool tag
+    let mut components = package_components();
+    components[3].1[2] = 7;
+    check_error(
+        &cap_components(components),
+        CapFileError::InvalidValue(COMPONENT_CONSTANT_POOL),
+    );
+
+    // the image size does not match the static fields
+    let mut components = package_components();
+    components[6].1[1] = 5;
+    check_error(
+        &cap_components(components),
+        CapFileError::InvalidValue(COMPONENT_STATIC_FIELD),
+    );
+
+    // applet count of the Directory component
+    let mut components = package_components();
+    components.retain(|component| component.0 != COMPONENT_APPLET);
+    check_error(
+        &cap_components(components),
+        CapFileError::InvalidValue(COMPONENT_DIRECTORY),
+    );
+
+    // items beyond the declared content
+    let mut components = package_components();
+    components[1].1.push(0);
+    check_error(
+        &cap_components(components),
+        CapFileError::InvalidComponentSize(COMPONENT_APPLET),
+    );
+}
+
+/// invalid JAR containers are rejected
+#[test]
+fn capfile_archive_errors_test() {
+    let components = cap_components(package_components());
+    let jar = package_jar(&components, 12);
+    assert_eq!(
+        CapFile::from_bytes(&jar),
+        Err(CapFileError::UnsupportedCompression(12))
+    );
+
+    let jar = package_jar(&components, 0);
+    assert_eq!(
+        CapFile::from_bytes(&jar[..jar.len() - 4]),
+        Err(CapFileError::InvalidArchive)
+    );
+
+    // entries are not inflated beyond the uncompressed size of the central directory
+    let mut jar = zip(&[("pkg/javacard/Header.cap", vec![0; 4096], 8)]);
+    let mut central_directory = [0; 4];
+    central_directory.copy_from_slice(&jar[jar.len() - 6..jar.len() - 2]);
+    let size_offset = u32::from_le_bytes(central_directory) as usize + 24;
+    jar[size_offset..size_offset + 4].copy_from_slice(&16u32.to_le_bytes());
+    assert_eq!(CapFile::from_bytes(&jar), Err(CapFileError::DecompressionFailed));
+}
@@ -0,0 +1,133 @@
+//! Utilities shared by the integration tests, each test crate using a part of them
+#![allow(dead_code)]
+
+use std::fmt::Debug;
+
+use interpreterlib::capcomponents::{CapClassInfo, CapClassRef, PackageInfo};
+use interpreterlib::classes::ClassRef;
+use interpreterlib::constantpool::ConstantPoolEntry;
+use interpreterlib::constants::PrimitiveType;
+use interpreterlib::context::Context;
+use interpreterlib::interpreter::{BytecodeData, BytecodeType};
+use interpreterlib::jcvmerrors::InterpreterError;
+use interpreterlib::{exceptions, interpreter, natives};
+
+///
+/// utility function appending a method (header and code) to the given bytecode array
+/// and returning the offset of its header
+///
+pub fn add_method(
+    code: &mut Vec<BytecodeType>,
+    max_stack: u8,
+    nargs: u8,
+    max_locals: u8,
+    body: &[BytecodeType],
+) -> usize {
+    let offset = code.len();
+    code.push(max_stack as BytecodeType);
+    code.push(((nargs << 4) | max_locals) as BytecodeType);
+    code.extend_from_slice(body);
+    offset
+}
+
+///
+/// Bytecode array living as long as the test, so that runners can return the context
+/// running it
+///
+pub fn leak_code(code: Vec<BytecodeType>) -> &'static BytecodeData {
+    Box::leak(code.into_boxed_slice())
+}
+
+///
+/// Appends the code of the given native methods to the bytecode array and returns their
+/// offsets, in the same order
+///
+pub fn add_natives(code: &mut Vec<BytecodeType>, ids: &[u8]) -> Vec<usize> {
+    let mut offsets = Vec::new();
+    for id in ids {
+        offsets.push(code.len());
+        code.extend(natives::native_method(*id).unwrap());
+    }
+    offsets
+}
+
+/// adds the entries to the constant pool, the first one getting the next index
+pub fn add_entries(ctx: &mut Context, entries: &[ConstantPoolEntry]) {
+    for entry in entries {
+        ctx.constant_pools.add_entry(*entry);
+    }
+}
+
+///
+/// Invokes the method with the given references as arguments, and returns the value it
+/// returns, which has to be of the given type (if any)
+///
+pub fn invoke_main(
+    ctx: &mut Context,
+    main: usize,
+    arguments: &[i16],
+    result_type: Option<PrimitiveType>,
+) -> Result<i16, InterpreterError> {
+    for argument in arguments {
+        ctx.operand_stack.apush(*argument).unwrap();
+    }
+    interpreter::invoke(ctx, main)?;
+    let result = match result_type {
+        Some(type_) => ctx.operand_stack.pop_check_type(type_)?,
+        None => ctx.operand_stack.pop()?,
+    };
+    Ok(result.value)
+}
+
+///
+/// checks that an exception of the given class, with the given reason code if any, left
+/// the interpreter
+///
+pub fn check_uncaught_exception<T: Debug>(
+    result: Result<T, InterpreterError>,
+    class: ClassRef,
+    reason: Option<i16>,
+) {
+    match result {
+        Err(InterpreterError::UncaughtException(class_ref, r))
+            if class_ref == class && reason.is_none_or(|reason| r == reason) => {}
+        _ => panic!(
+            "an exception of class {} (reason {:?}) shall have been thrown, got {:?}",
+            class, reason, result
+        ),
+    }
+}
+
+/// checks that a SecurityException left the interpreter
+pub fn check_security_exception<T: Debug>(result: Result<T, InterpreterError>) {
+    check_uncaught_exception(result, exceptions::SECURITY_EXCEPTION_CLASS, None);
+}
+
+/// AID of the java.lang package
+pub const JAVA_LANG_AID: [u8; 7] = [0xA0, 0x00, 0x00, 0x00, 0x62, 0x00, 0x01];
+
+pub fn package_info(aid: &[u8], major_version: u8, minor_version: u8) -> PackageInfo {
+    PackageInfo {
+        minor_version,
+        major_version,
+        aid: aid.to_vec(),
+    }
+}
+
+/// class of the Class component with no field and no virtual method
+pub fn class_info(offset: u16, super_class: CapClassRef) -> CapClassInfo {
+    CapClassInfo::Class {
+        offset,
+        flags: 0,
+        super_class: Some(super_class),
+        declared_instance_size: 0,
+        first_reference_token: 0xFF,
+        reference_count: 0,
+        public_method_table_base: 0,
+        package_method_table_base: 0,
+        public_virtual_methods: Vec::new(),
+        package_virtual_methods: Vec::new(),
+        interfaces: Vec::new(),
+        remote_interfaces: None,
+    }
+}
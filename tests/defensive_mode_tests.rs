@@ -0,0 +1,191 @@
+extern crate interpreterlib;
+
+mod common;
+
+use interpreterlib::{constantpool, constants, context, frame, interpreter, jcvmerrors, stack};
+use interpreterlib::bytecodes::*;
+
+use common::add_method;
+use interpreter::BytecodeType;
+use constantpool::ConstantPoolEntry;
+use jcvmerrors::InterpreterError;
+use stack::StackEntry;
+
+///
+/// utility function invoking main, which calls the method at constant pool index 0
+///
+fn invoke_main(
+    code: &[BytecodeType],
+    main: usize,
+    callee: usize,
+    defensive: bool,
+) -> Result<i16, InterpreterError> {
+    let mut ctx = context::Context::new(code);
+    ctx.set_defensive(defensive);
+    ctx.constant_pools
+        .add_entry(ConstantPoolEntry::StaticMethodRef(callee));
+    interpreter::invoke(&mut ctx, main)?;
+    ctx.operand_stack.spop()
+}
+
+/// the defensive mode is enabled by default
+#[test]
+fn defensive_mode_default_test() {
+    let ctx = context::Context::new(&[bytecode::return_ as BytecodeType]);
+    assert!(ctx.is_defensive());
+    assert!(ctx.operand_stack.type_checks());
+}
+
+/// operand types are only checked in defensive mode
+#[test]
+fn defensive_mode_operand_types_test() {
+    let code = [
+        bytecode::aconst_null as BytecodeType,
+        bytecode::sconst_1 as BytecodeType,
+        bytecode::sadd as BytecodeType,
+    ];
+
+    let mut ctx = context::Context::new(&code);
+    match interpreter::interpreter(&mut ctx) {
+        Err(InterpreterError::InvalidVariableType(
+            constants::PrimitiveType::REFERENCE,
+            constants::PrimitiveType::SHORT,
+        )) => {}
+        _ => panic!("the reference shall have been rejected by sadd"),
+    }
+
+    let mut ctx = context::Context::new(&code);
+    ctx.set_defensive(false);
+    let _ = interpreter::interpreter(&mut ctx);
+    assert_eq!(ctx.operand_stack.spop().unwrap(), constants::NULL_HANDLE + 1);
+}
+
+/// local types are only checked in defensive mode, in existing and new frames
+#[test]
+fn defensive_mode_local_types_test() {
+    let code = [bytecode::sload_0 as BytecodeType];
+    let mut ctx = context::Context::new(&code);
+    ctx.frame_stack.push(frame::Frame::new(1));
+    ctx.current_frame_mut()
+        .unwrap()
+        .set_local(
+            0,
+            StackEntry::from_values(3, constants::PrimitiveType::REFERENCE),
+        )
+        .unwrap();
+    ctx.set_defensive(false);
+    let _ = interpreter::interpreter(&mut ctx);
+    assert_eq!(ctx.operand_stack.spop().unwrap(), 3);
+
+    // the reference argument is read as a short by the callee
+    let mut code = Vec::new();
+    let main = add_method(
+        &mut code,
+        1,
+        0,
+        0,
+        &[
+            bytecode::aconst_null as BytecodeType,
+            bytecode::invokestatic as BytecodeType,
+            0,
+            0,
+            bytecode::sreturn as BytecodeType,
+        ],
+    );
+    let callee = add_method(
+        &mut code,
+        1,
+        1,
+        0,
+        &[
+            bytecode::sload_0 as BytecodeType,
+            bytecode::sreturn as BytecodeType,
+        ],
+    );
+
+    match invoke_main(&code, main, callee, true) {
+        Err(InterpreterError::InvalidVariableType(
+            constants::PrimitiveType::REFERENCE,
+            constants::PrimitiveType::SHORT,
+        )) => {}
+        _ => panic!("the reference shall have been rejected by sload_0"),
+    }
+    assert_eq!(
+        invoke_main(&code, main, callee, false).unwrap(),
+        constants::NULL_HANDLE
+    );
+}
+
+/// in defensive mode, a method cannot use the operand stack of its caller
+#[test]
+fn defensive_mode_frame_underflow_test() {
+    let mut code = Vec::new();
+    let main = add_method(
+        &mut code,
+        1,
+        0,
+        0,
+        &[
+            bytecode::sconst_1 as BytecodeType,
+            bytecode::invokestatic as BytecodeType,
+            0,
+            0,
+            bytecode::sreturn as BytecodeType,
+        ],
+    );
+    // the callee takes no argument, but adds the short pushed by main
+    let callee = add_method(
+        &mut code,
+        1,
+        0,
+        0,
+        &[
+            bytecode::sconst_2 as BytecodeType,
+            bytecode::sadd as BytecodeType,
+            bytecode::sreturn as BytecodeType,
+        ],
+    );
+
+    match invoke_main(&code, main, callee, true) {
+        Err(InterpreterError::StackUnderflowError) => {}
+        _ => panic!("the short of the caller shall not have been accessible"),
+    }
+    assert_eq!(invoke_main(&code, main, callee, false).unwrap(), 3);
+}
+
+/// in defensive mode, a method cannot push more than max_stack words
+#[test]
+fn defensive_mode_frame_overflow_test() {
+    let mut code = Vec::new();
+    let main = add_method(
+        &mut code,
+        1,
+        0,
+        0,
+        &[
+            bytecode::invokestatic as BytecodeType,
+            0,
+            0,
+            bytecode::sreturn as BytecodeType,
+        ],
+    );
+    // max_stack is 1 but the callee pushes 2 shorts
+    let callee = add_method(
+        &mut code,
+        1,
+        0,
+        0,
+        &[
+            bytecode::sconst_2 as BytecodeType,
+            bytecode::sconst_3 as BytecodeType,
+            bytecode::sadd as BytecodeType,
+            bytecode::sreturn as BytecodeType,
+        ],
+    );
+
+    match invoke_main(&code, main, callee, true) {
+        Err(InterpreterError::StackOverflowError) => {}
+        _ => panic!("max_stack shall have been enforced"),
+    }
+    assert_eq!(invoke_main(&code, main, callee, false).unwrap(), 5);
+}
@@ -0,0 +1,248 @@
+extern crate interpreterlib;
+
+mod common;
+
+use interpreterlib::{classes, constantpool, constants, context, exceptions, interpreter, jcre,
+                     jcvmerrors, objects, staticfields, traits};
+use interpreterlib::bytecodes::*;
+
+use common::{add_entries, add_method, check_security_exception, check_uncaught_exception,
+             invoke_main, leak_code};
+use interpreter::BytecodeType;
+use classes::{JCVMClass, OBJECT_CLASS};
+use constantpool::ConstantPoolEntry;
+use constants::PrimitiveType;
+use exceptions::ExceptionHandler;
+use jcvmerrors::InterpreterError;
+use objects::JCVMObject;
+use staticfields::{StaticFieldComponent, StaticFieldImage};
+use traits::BufferAccessor;
+
+const APPLET_CONTEXT: i16 = 1;
+
+// constant pool indexes
+const CP_FIELD: u8 = 0;
+const CP_STATIC: u8 = 1;
+
+// references to the objects created by run
+const APPLET: i16 = 1;
+const ARRAY: i16 = 2;
+const APDU: i16 = 3;
+const APDU_BUFFER: i16 = 4;
+const INSTALL_PARAMETERS: i16 = 5;
+
+///
+/// Result of main and state of the virtual machine once it returned
+///
+struct Execution {
+    result: Result<i16, InterpreterError>,
+    // reference held by the field of the applet
+    field: i16,
+}
+
+///
+/// Runs main(applet, array, objectref) in the context of the applet. The applet has a reference
+/// field, its package has a reference static field, and the array is an array of two objects.
+/// The JCRE objects are the APDU object and buffer, and the bArray install parameter.
+/// The exception handler, if any, catches everything thrown between the given offsets of main
+///
+fn run(
+    body: &[BytecodeType],
+    objectref: i16,
+    handler: Option<(usize, usize, usize)>,
+) -> Execution {
+    let mut code = Vec::new();
+    let main = add_method(&mut code, 4, 3, 4, body);
+    let code = leak_code(code);
+
+    let mut ctx = context::Context::new(code);
+    let mut applet_class = JCVMClass::new(None);
+    applet_class.set_declared_instance_size(1);
+    let applet_class = ctx.class_manager.add_class(applet_class);
+
+    let component = StaticFieldComponent {
+        image_size: 2,
+        reference_count: 1,
+        array_init: vec![],
+        default_value_count: 0,
+        non_default_values: vec![],
+    };
+    let image = StaticFieldImage::from_component(
+        &component,
+        APPLET_CONTEXT,
+        &mut ctx.object_manager,
+        &mut ctx.tearing,
+    ).unwrap();
+    let package = ctx.static_fields.add_image(image);
+    add_entries(
+        &mut ctx,
+        &[
+            ConstantPoolEntry::InstanceFieldRef(applet_class, 0),
+            ConstantPoolEntry::StaticFieldRef(package, 0),
+        ],
+    );
+    if let Some((start, length, handler_offset)) = handler {
+        ctx.exception_handlers.push(ExceptionHandler {
+            start_offset: 2 + start,
+            active_length: length,
+            handler_offset: 2 + handler_offset,
+            catch_type_index: None,
+        });
+    }
+
+    let applet = JCVMObject::new_instance(APPLET_CONTEXT, 0, applet_class, 2, true);
+    assert_eq!(ctx.object_manager.add_object(applet) as i16, APPLET);
+    let array = JCVMObject::new_reference_array(APPLET_CONTEXT, 0, OBJECT_CLASS, 4, true);
+    assert_eq!(ctx.object_manager.add_object(array) as i16, ARRAY);
+    let apdu = ctx.jcre.create_apdu(&mut ctx.object_manager, OBJECT_CLASS, 0);
+    assert_eq!(apdu, APDU);
+    assert_eq!(ctx.jcre.apdu_buffer(), APDU_BUFFER);
+    let parameters = jcre::create_install_parameters(&mut ctx.object_manager, &[5, 1, 2, 3])
+        .unwrap();
+    assert_eq!(parameters, INSTALL_PARAMETERS);
+
+    ctx.current_context = APPLET_CONTEXT;
+    let arguments = [APPLET, ARRAY, objectref];
+    let result = invoke_main(&mut ctx, main, &arguments, Some(PrimitiveType::SHORT));
+    let field = ctx.object_manager
+        .get_object(APPLET as usize)
+        .unwrap()
+        .read_s(0)
+        .unwrap();
+    Execution { result, field }
+}
+
+///
+/// body of main storing objectref in the field of the applet, in the static field, then in
+/// the array, and returning 0. The given number of bytes is skipped at the beginning (0 for
+/// the field, 4 for the static field and 8 for the array)
+///
+fn store_body(skipped: usize) -> Vec<BytecodeType> {
+    vec![
+        bytecode::aload_0 as BytecodeType,
+        bytecode::aload_2 as BytecodeType,
+        bytecode::putfield_a as BytecodeType,
+        CP_FIELD as BytecodeType,
+        bytecode::aload_2 as BytecodeType,
+        bytecode::putstatic_a as BytecodeType,
+        0,
+        CP_STATIC as BytecodeType,
+        bytecode::aload_1 as BytecodeType,
+        bytecode::sconst_0 as BytecodeType,
+        bytecode::aload_2 as BytecodeType,
+        bytecode::aastore as BytecodeType,
+        bytecode::sconst_0 as BytecodeType,
+        bytecode::sreturn as BytecodeType,
+    ][skipped..]
+        .to_vec()
+}
+
+/// the JCRE objects have the expected flags
+#[test]
+fn jcre_objects_test() {
+    let mut ctx = context::Context::new(&[]);
+    let apdu = ctx.jcre.create_apdu(&mut ctx.object_manager, OBJECT_CLASS, 0);
+    let apdu = ctx.object_manager.get_object(apdu as usize).unwrap();
+    assert!(apdu.is_entry_point());
+    assert!(apdu.is_temporary());
+    assert_eq!(apdu.owner(), constants::JCRE_CONTEXT);
+
+    let buffer = ctx.object_manager
+        .get_object(ctx.jcre.apdu_buffer() as usize)
+        .unwrap();
+    assert!(buffer.is_array() && buffer.is_global());
+    assert!(!buffer.is_entry_point());
+    assert!(buffer.is_temporary());
+    assert_eq!(buffer.array_length(), jcre::APDU_BUFFER_LENGTH);
+
+    let parameters = jcre::create_install_parameters(&mut ctx.object_manager, &[1, 2]).unwrap();
+    let parameters = ctx.object_manager.get_object(parameters as usize).unwrap();
+    assert!(parameters.is_array() && parameters.is_temporary());
+    assert_eq!(parameters.read_b(1).unwrap(), 2);
+
+    // exceptions are permanent entry points allocated once per class
+    let class_ref = exceptions::ARITHMETIC_EXCEPTION_CLASS;
+    let exception = ctx.jcre.exception(&mut ctx.object_manager, class_ref, 0).unwrap();
+    ctx.object_manager
+        .get_object_mut(exception as usize)
+        .unwrap()
+        .write_s(0, 3)
+        .unwrap();
+    assert_eq!(
+        ctx.jcre.exception(&mut ctx.object_manager, class_ref, 0).unwrap(),
+        exception
+    );
+    let object = ctx.object_manager.get_object(exception as usize).unwrap();
+    assert!(object.is_entry_point());
+    assert!(!object.is_temporary());
+    assert_eq!(object.read_s(0).unwrap(), 0);
+    assert_ne!(
+        ctx.jcre
+            .exception(&mut ctx.object_manager, exceptions::SECURITY_EXCEPTION_CLASS, 0)
+            .unwrap(),
+        exception
+    );
+}
+
+/// references to temporary entry points and global arrays cannot be stored
+#[test]
+fn temporary_references_test() {
+    let execution = run(&store_body(0), constants::NULL_HANDLE, None);
+    assert_eq!(execution.result.unwrap(), 0);
+
+    // the applet stores its own objects
+    let execution = run(&store_body(0), APPLET, None);
+    assert_eq!(execution.result.unwrap(), 0);
+    assert_eq!(execution.field, APPLET);
+
+    for objectref in &[APDU, APDU_BUFFER, INSTALL_PARAMETERS] {
+        // field, static field then array component
+        for skipped in &[0, 4, 8] {
+            let execution = run(&store_body(*skipped), *objectref, None);
+            check_security_exception(execution.result);
+            assert_eq!(execution.field, constants::NULL_HANDLE);
+        }
+        // they can still be held in locals and accessed
+        let body = [
+            bytecode::aload_2 as BytecodeType,
+            bytecode::astore_3 as BytecodeType,
+            bytecode::sconst_1 as BytecodeType,
+            bytecode::sreturn as BytecodeType,
+        ];
+        assert_eq!(run(&body, *objectref, None).result.unwrap(), 1);
+    }
+
+    let body = [
+        bytecode::aload_2 as BytecodeType,
+        bytecode::arraylength as BytecodeType,
+        bytecode::sreturn as BytecodeType,
+    ];
+    assert_eq!(
+        run(&body, APDU_BUFFER, None).result.unwrap(),
+        jcre::APDU_BUFFER_LENGTH
+    );
+}
+
+/// the exceptions thrown by the virtual machine can be stored and rethrown by applets
+#[test]
+fn exception_singleton_test() {
+    let body = [
+        bytecode::sconst_1 as BytecodeType,
+        bytecode::sconst_0 as BytecodeType,
+        bytecode::sdiv as BytecodeType,
+        bytecode::sreturn as BytecodeType,
+        // handler
+        bytecode::astore_3 as BytecodeType,
+        bytecode::aload_0 as BytecodeType,
+        bytecode::aload_3 as BytecodeType,
+        bytecode::putfield_a as BytecodeType,
+        CP_FIELD as BytecodeType,
+        bytecode::aload_3 as BytecodeType,
+        bytecode::athrow as BytecodeType,
+    ];
+    let execution = run(&body, constants::NULL_HANDLE, Some((0, 4, 4)));
+    // the exception is rethrown
+    let class_ref = exceptions::ARITHMETIC_EXCEPTION_CLASS;
+    check_uncaught_exception(execution.result, class_ref, Some(0));
+    assert_ne!(execution.field, constants::NULL_HANDLE);
+}
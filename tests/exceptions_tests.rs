@@ -0,0 +1,321 @@
+extern crate interpreterlib;
+
+mod common;
+
+use interpreterlib::{classes, constantpool, constants, context, exceptions, interpreter, jcvmerrors,
+                     objects, traits};
+use interpreterlib::bytecodes::*;
+
+use common::add_method;
+use interpreter::BytecodeType;
+use classes::JCVMClass;
+use constantpool::ConstantPoolEntry;
+use exceptions::ExceptionHandler;
+use jcvmerrors::InterpreterError;
+use objects::JCVMObject;
+use traits::BufferAccessor;
+
+///
+/// utility function adding a handler for the class registered at the given constant pool
+/// index (None catches everything), offsets being relative to the code of the method
+///
+fn add_handler(
+    ctx: &mut context::Context,
+    method_offset: usize,
+    range: (usize, usize),
+    handler: usize,
+    catch_type_index: Option<u16>,
+) {
+    let code_offset = method_offset + 2;
+    ctx.exception_handlers.push(ExceptionHandler {
+        start_offset: code_offset + range.0,
+        active_length: range.1 - range.0,
+        handler_offset: code_offset + handler,
+        catch_type_index,
+    });
+}
+
+fn assert_uncaught(
+    result: Result<(), InterpreterError>,
+    expected_class: usize,
+    expected_reason: i16,
+) {
+    match result {
+        Err(InterpreterError::UncaughtException(class, reason)) => {
+            assert_eq!(class, expected_class);
+            assert_eq!(reason, expected_reason);
+        }
+        _ => panic!("an exception shall have been raised"),
+    }
+}
+
+///
+/// An exception is caught by a handler of the same method catching one of its superclasses
+/// The handler gets the exception object on top of an empty operand stack
+///
+#[test]
+fn exception_caught_in_method_test() {
+    let mut code = Vec::new();
+    let main = add_method(
+        &mut code,
+        3,
+        0,
+        1,
+        &[
+            bytecode::sconst_3 as BytecodeType, // 0
+            bytecode::sconst_1 as BytecodeType, // 1
+            bytecode::sconst_0 as BytecodeType, // 2
+            bytecode::sdiv as BytecodeType,     // 3
+            bytecode::sreturn as BytecodeType,  // 4
+            bytecode::astore_0 as BytecodeType, // 5: handler
+            bytecode::aload_0 as BytecodeType,  // 6
+            bytecode::areturn as BytecodeType,  // 7
+        ],
+    );
+
+    let mut ctx = context::Context::new(&code);
+    let catch_type = ctx.constant_pools.add_entry(ConstantPoolEntry::ClassRef(
+        exceptions::RUNTIME_EXCEPTION_CLASS,
+    ));
+    add_handler(&mut ctx, main, (0, 4), 5, Some(catch_type));
+
+    interpreter::invoke(&mut ctx, main).unwrap();
+
+    let objectref = ctx.operand_stack
+        .pop_check_type(constants::PrimitiveType::REFERENCE)
+        .unwrap();
+    assert!(ctx.operand_stack.is_empty());
+    assert!(ctx.frame_stack.is_empty());
+
+    let exception = ctx.object_manager
+        .get_object(objectref.value as usize)
+        .unwrap();
+    assert_eq!(
+        exception.class_ref(),
+        Some(exceptions::ARITHMETIC_EXCEPTION_CLASS)
+    );
+}
+
+///
+/// builds main() calling a method dividing by zero, the invocation being at offset 1..4 of
+/// main and the handler at offset 5. Returns the offset of main.
+///
+fn build_unwinding_code(code: &mut Vec<BytecodeType>) -> usize {
+    let main = add_method(
+        code,
+        2,
+        0,
+        0,
+        &[
+            bytecode::sconst_1 as BytecodeType,     // 0
+            bytecode::invokestatic as BytecodeType, // 1
+            0,
+            0,
+            bytecode::sreturn as BytecodeType, // 4
+            bytecode::pop as BytecodeType,     // 5: handler
+            bytecode::bspush as BytecodeType,  // 6
+            7,
+            bytecode::sreturn as BytecodeType, // 8
+        ],
+    );
+    let callee = add_method(
+        code,
+        3,
+        1,
+        0,
+        &[
+            bytecode::sconst_2 as BytecodeType,
+            bytecode::sload_0 as BytecodeType,
+            bytecode::sconst_0 as BytecodeType,
+            bytecode::sdiv as BytecodeType,
+            bytecode::sreturn as BytecodeType,
+        ],
+    );
+    assert_eq!(callee, main + 11);
+    main
+}
+
+///
+/// The frame of a method without handler is discarded and the exception is caught in the caller
+///
+#[test]
+fn exception_caught_in_caller_test() {
+    let mut code = Vec::new();
+    let main = build_unwinding_code(&mut code);
+
+    for catch_type in &[Some(exceptions::ARITHMETIC_EXCEPTION_CLASS), None] {
+        let mut ctx = context::Context::new(&code);
+        ctx.constant_pools
+            .add_entry(ConstantPoolEntry::StaticMethodRef(main + 11));
+        let catch_type_index = catch_type.map(|class| {
+            ctx.constant_pools
+                .add_entry(ConstantPoolEntry::ClassRef(class))
+        });
+        add_handler(&mut ctx, main, (0, 4), 5, catch_type_index);
+
+        interpreter::invoke(&mut ctx, main).unwrap();
+
+        let result = ctx.operand_stack
+            .pop_check_type(constants::PrimitiveType::SHORT)
+            .unwrap();
+        assert_eq!(result.value, 7);
+        assert!(ctx.operand_stack.is_empty());
+        assert!(ctx.frame_stack.is_empty());
+    }
+}
+
+///
+/// Handlers that do not catch the class of the exception (or do not protect the opcode)
+/// are skipped, and the exception leaves the interpreter once all the frames are discarded
+///
+#[test]
+fn exception_uncaught_test() {
+    let mut code = Vec::new();
+    let main = build_unwinding_code(&mut code);
+
+    let mut ctx = context::Context::new(&code);
+    ctx.constant_pools
+        .add_entry(ConstantPoolEntry::StaticMethodRef(main + 11));
+    let catch_type = ctx.constant_pools.add_entry(ConstantPoolEntry::ClassRef(
+        exceptions::NULL_POINTER_EXCEPTION_CLASS,
+    ));
+    add_handler(&mut ctx, main, (0, 4), 5, Some(catch_type));
+    add_handler(&mut ctx, main, (4, 5), 5, None);
+
+    assert_uncaught(
+        interpreter::invoke(&mut ctx, main),
+        exceptions::ARITHMETIC_EXCEPTION_CLASS,
+        0,
+    );
+    assert!(ctx.operand_stack.is_empty());
+    assert!(ctx.frame_stack.is_empty());
+}
+
+///
+/// Test athrow opcode from standard specification: the reason code of the thrown object
+/// (the reason field of CardRuntimeException) is reported if the exception is not caught
+///
+#[test]
+fn opcode_athrow_test() {
+    let mut code = Vec::new();
+    let main = add_method(
+        &mut code,
+        1,
+        1,
+        0,
+        &[
+            bytecode::aload_0 as BytecodeType,
+            bytecode::athrow as BytecodeType,
+        ],
+    );
+
+    for (super_class, reason) in &[
+        (exceptions::CARD_RUNTIME_EXCEPTION_CLASS, 0x6A82),
+        // other exceptions have no reason code, whatever their fields
+        (exceptions::RUNTIME_EXCEPTION_CLASS, 0),
+    ] {
+        let mut ctx = context::Context::new(&code);
+        let mut user_exception = JCVMClass::new(Some(*super_class));
+        user_exception.set_declared_instance_size(1);
+        let user_exception = ctx.class_manager.add_class(user_exception);
+        let size = ctx.class_manager.instance_size(user_exception).unwrap();
+        let mut exception = JCVMObject::new_instance(0, 0, user_exception, size as i16, false);
+        exception.write_s(0, 0x6A82).unwrap();
+        exception.write_s(size - 2, 0x6A83).unwrap();
+        let objectref = ctx.object_manager.add_object(exception);
+        ctx.operand_stack.apush(objectref as i16).unwrap();
+
+        assert_uncaught(interpreter::invoke(&mut ctx, main), user_exception, *reason);
+    }
+
+    // throwing null raises a NullPointerException
+    let mut ctx = context::Context::new(&code);
+    ctx.operand_stack.apush(constants::NULL_HANDLE).unwrap();
+    assert_uncaught(
+        interpreter::invoke(&mut ctx, main),
+        exceptions::NULL_POINTER_EXCEPTION_CLASS,
+        0,
+    );
+
+    // only Throwable objects can be thrown
+    let mut ctx = context::Context::new(&code);
+    let other_class = ctx.class_manager.add_class(JCVMClass::new(None));
+    let objectref = ctx.object_manager
+        .add_object(JCVMObject::new_instance(0, 0, other_class, 0, false));
+    ctx.operand_stack.apush(objectref as i16).unwrap();
+    match interpreter::invoke(&mut ctx, main) {
+        Err(InterpreterError::InvalidClassReference) => {}
+        _ => panic!("athrow shall have been rejected"),
+    }
+}
+
+///
+/// References designating no object raise an exception that the applet can catch, whichever
+/// opcode uses them
+///
+#[test]
+fn exception_invalid_reference_test() {
+    for access in &[
+        &[bytecode::getfield_s as BytecodeType, 0][..],
+        &[bytecode::arraylength as BytecodeType, bytecode::nop as BytecodeType][..],
+        &[bytecode::athrow as BytecodeType, bytecode::nop as BytecodeType][..],
+    ] {
+        let mut body = vec![bytecode::aload_0 as BytecodeType]; // 0
+        body.extend_from_slice(access); // 1
+        body.extend_from_slice(&[
+            bytecode::sreturn as BytecodeType, // 3
+            bytecode::pop as BytecodeType,     // 4: handler
+            bytecode::bspush as BytecodeType,  // 5
+            7,
+            bytecode::sreturn as BytecodeType, // 7
+        ]);
+        let mut code = Vec::new();
+        let main = add_method(&mut code, 1, 1, 0, &body);
+
+        let mut ctx = context::Context::new(&code);
+        let class = ctx.class_manager.add_class(JCVMClass::new(None));
+        ctx.constant_pools
+            .add_entry(ConstantPoolEntry::InstanceFieldRef(class, 0));
+        add_handler(&mut ctx, main, (0, 3), 4, None);
+        ctx.operand_stack.apush(99).unwrap();
+
+        interpreter::invoke(&mut ctx, main).unwrap();
+        assert_eq!(ctx.operand_stack.spop().unwrap(), 7);
+    }
+}
+
+///
+/// The classes of the exceptions thrown by the virtual machine have the references given by
+/// their constants, CardException and CardRuntimeException declaring the reason code
+///
+#[test]
+fn exception_classes_layout_test() {
+    let ctx = context::Context::new(&[]);
+    let classes = &ctx.class_manager;
+    for (class, super_class) in &[
+        (exceptions::THROWABLE_CLASS, classes::OBJECT_CLASS),
+        (exceptions::EXCEPTION_CLASS, exceptions::THROWABLE_CLASS),
+        (exceptions::RUNTIME_EXCEPTION_CLASS, exceptions::EXCEPTION_CLASS),
+        (
+            exceptions::ARRAY_INDEX_OUT_OF_BOUNDS_EXCEPTION_CLASS,
+            exceptions::INDEX_OUT_OF_BOUNDS_EXCEPTION_CLASS,
+        ),
+        (exceptions::ARRAY_STORE_EXCEPTION_CLASS, exceptions::RUNTIME_EXCEPTION_CLASS),
+        (exceptions::CARD_EXCEPTION_CLASS, exceptions::EXCEPTION_CLASS),
+        (exceptions::CARD_RUNTIME_EXCEPTION_CLASS, exceptions::RUNTIME_EXCEPTION_CLASS),
+        (exceptions::TRANSACTION_EXCEPTION_CLASS, exceptions::CARD_RUNTIME_EXCEPTION_CLASS),
+        (exceptions::SYSTEM_EXCEPTION_CLASS, exceptions::CARD_RUNTIME_EXCEPTION_CLASS),
+    ] {
+        assert_eq!(classes.get_class(*class).unwrap().super_class(), Some(*super_class));
+    }
+    assert_eq!(classes.len(), exceptions::SYSTEM_EXCEPTION_CLASS + 1);
+
+    for class in &[exceptions::CARD_EXCEPTION_CLASS, exceptions::SYSTEM_EXCEPTION_CLASS] {
+        assert_eq!(classes.instance_size(*class).unwrap(), 2);
+    }
+    let offset = classes.instance_field_offset(
+        exceptions::CARD_RUNTIME_EXCEPTION_CLASS,
+        exceptions::REASON_FIELD_TOKEN,
+    );
+    assert_eq!(offset.unwrap(), 0);
+}
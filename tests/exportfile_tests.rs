@@ -0,0 +1,521 @@
+extern crate interpreterlib;
+
+mod common;
+
+use interpreterlib::{capcomponents, capfile, classes, exceptions, exportfile, interpreter,
+                     jcvmerrors, linker, staticfields};
+use interpreterlib::bytecodes::*;
+
+use common::{class_info, package_info, JAVA_LANG_AID};
+use interpreter::BytecodeType;
+use capcomponents::*;
+use capfile::CapFile;
+use classes::OBJECT_CLASS;
+use exportfile::{ExportClass, ExportField, ExportFile, ExportMethod, ACC_LIBRARY};
+use jcvmerrors::{CapFileError, ExportFileError};
+use linker::{ExportedClass, Linker};
+use staticfields::StaticFieldComponent;
+
+const LIBRARY_AID: [u8; 6] = [0xA0, 0x00, 0x00, 0x00, 0x01, 0x01];
+
+// access flags of the Descriptor component
+const ACC_PUBLIC: u8 = 0x01;
+const ACC_PRIVATE: u8 = 0x02;
+const ACC_FINAL: u8 = 0x10;
+
+fn export_class(token: u8, name: &str, supers: &[&str]) -> ExportClass {
+    ExportClass {
+        token,
+        access_flags: 0x0001,
+        name: name.to_string(),
+        supers: supers.iter().map(|name| name.to_string()).collect(),
+        interfaces: Vec::new(),
+        fields: Vec::new(),
+        methods: Vec::new(),
+    }
+}
+
+/// export file of java.lang, with java.lang.Object and java.lang.Throwable
+fn java_lang_export() -> ExportFile {
+    let mut object = export_class(0, "java/lang/Object", &[]);
+    object.methods.push(ExportMethod {
+        token: 0,
+        access_flags: 0x0001,
+        name: "equals".to_string(),
+        descriptor: "(Ljava/lang/Object;)Z".to_string(),
+    });
+    let mut throwable = export_class(1, "java/lang/Throwable", &["java/lang/Object"]);
+    throwable.fields.push(ExportField {
+        token: 0,
+        access_flags: 0x0019,
+        name: "MAX_REASON".to_string(),
+        descriptor: "S".to_string(),
+        constant_value: Some(0x7FFF),
+    });
+    ExportFile {
+        minor_version: 1,
+        flags: ACC_LIBRARY,
+        package_name: "java/lang".to_string(),
+        package: package_info(&JAVA_LANG_AID, 1, 0),
+        classes: vec![object, throwable],
+    }
+}
+
+fn method_descriptor(token: u8, access_flags: u8, method_offset: u16) -> MethodDescriptor {
+    MethodDescriptor {
+        token,
+        access_flags,
+        method_offset,
+        type_offset: 0,
+        bytecode_count: 0,
+        exception_handler_count: 0,
+        exception_handler_index: 0,
+    }
+}
+
+fn field_debug(name_index: u16, access_flags: u16, contents: u32) -> FieldDebugInfo {
+    FieldDebugInfo {
+        name_index,
+        descriptor_index: 5,
+        access_flags,
+        contents,
+    }
+}
+
+fn method_debug(name_index: u16, descriptor_index: u16, access_flags: u16, location: u16)
+    -> MethodDebugInfo {
+    MethodDebugInfo {
+        name_index,
+        descriptor_index,
+        access_flags,
+        location,
+        header_size: 2,
+        body_size: 2,
+        variable_table: Vec::new(),
+        line_table: Vec::new(),
+    }
+}
+
+fn class_debug(name_index: u16, location: u16, superclass_name_index: u16) -> ClassDebugInfo {
+    ClassDebugInfo {
+        name_index,
+        access_flags: 0x0001,
+        location,
+        superclass_name_index,
+        source_file_index: 0,
+        interface_name_indexes: Vec::new(),
+        fields: Vec::new(),
+        methods: Vec::new(),
+    }
+}
+
+///
+/// Library package com/example/lib, importing java.lang and defining:
+/// - public class Lib, with the public static field short counter, the public instance field
+///   short value, the private instance field secret, the public static method twice(short),
+///   the public virtual method get() and the private method hidden()
+///   and the constant public static final short LIMIT = -2
+/// - public class Sub extending Lib (at offset 20 of the Class component)
+/// - public class Failure extending java.lang.Throwable (at offset 40 of the Class component)
+///
+fn library_cap() -> CapFile {
+    let strings = [
+        "com/example/lib",
+        "com/example/lib/Lib",
+        "com/example/lib/Sub",
+        "java/lang/Object",
+        "counter",
+        "S",
+        "value",
+        "twice",
+        "(S)S",
+        "get",
+        "()S",
+        "secret",
+        "hidden",
+        "LIMIT",
+        "com/example/lib/Failure",
+        "java/lang/Throwable",
+    ];
+    let mut lib_debug = class_debug(1, 0, 3);
+    lib_debug.fields = vec![
+        field_debug(4, 0x0009, 0),
+        field_debug(6, 0x0001, 0),
+        field_debug(11, 0x0002, 1),
+        field_debug(13, 0x0019, 0xFFFF_FFFE),
+    ];
+    lib_debug.methods = vec![
+        method_debug(7, 8, 0x0009, 1),
+        method_debug(9, 10, 0x0001, 7),
+        method_debug(12, 10, 0x0002, 11),
+    ];
+
+    let object_ref = CapClassRef::External {
+        package_token: 0,
+        class_token: 0,
+    };
+    let lib_descriptor = ClassDescriptor {
+        token: 0,
+        access_flags: ACC_PUBLIC,
+        this_class_ref: CapClassRef::Internal(0),
+        interfaces: Vec::new(),
+        fields: vec![
+            FieldDescriptor {
+                token: 0,
+                access_flags: ACC_PUBLIC | ACC_STATIC,
+                field_ref: CapFieldRef::Static(CapStaticRef::Internal(0)),
+                type_: 0x8003,
+            },
+            FieldDescriptor {
+                token: 0,
+                access_flags: ACC_PUBLIC,
+                field_ref: CapFieldRef::Instance(CapClassRef::Internal(0), 0),
+                type_: 0x8003,
+            },
+            FieldDescriptor {
+                token: 1,
+                access_flags: ACC_PRIVATE,
+                field_ref: CapFieldRef::Instance(CapClassRef::Internal(0), 1),
+                type_: 0x8003,
+            },
+            FieldDescriptor {
+                token: 1,
+                access_flags: ACC_PUBLIC | ACC_STATIC | ACC_FINAL,
+                field_ref: CapFieldRef::Static(CapStaticRef::Internal(0)),
+                type_: 0x8003,
+            },
+        ],
+        methods: vec![
+            method_descriptor(0, ACC_PUBLIC | ACC_STATIC, 1),
+            method_descriptor(1, ACC_PUBLIC, 7),
+            method_descriptor(0x80, ACC_PRIVATE, 11),
+        ],
+    };
+    let sub_descriptor = ClassDescriptor {
+        token: 1,
+        access_flags: ACC_PUBLIC,
+        this_class_ref: CapClassRef::Internal(20),
+        interfaces: Vec::new(),
+        fields: Vec::new(),
+        methods: Vec::new(),
+    };
+    let failure_descriptor = ClassDescriptor {
+        token: 2,
+        access_flags: ACC_PUBLIC,
+        this_class_ref: CapClassRef::Internal(40),
+        interfaces: Vec::new(),
+        fields: Vec::new(),
+        methods: Vec::new(),
+    };
+
+    let methods: Vec<BytecodeType> = vec![
+        0, // no exception handler
+        // twice, at offset 1
+        0x02,
+        0x10,
+        bytecode::sload_0 as BytecodeType,
+        bytecode::dup as BytecodeType,
+        bytecode::sadd as BytecodeType,
+        bytecode::sreturn as BytecodeType,
+        // get, at offset 7
+        0x01,
+        0x10,
+        bytecode::sconst_1 as BytecodeType,
+        bytecode::sreturn as BytecodeType,
+        // hidden, at offset 11
+        0x01,
+        0x10,
+        bytecode::sconst_0 as BytecodeType,
+        bytecode::sreturn as BytecodeType,
+    ];
+
+    CapFile {
+        header: HeaderComponent {
+            minor_version: 2,
+            major_version: 2,
+            flags: ACC_EXPORT,
+            package: package_info(&LIBRARY_AID, 1, 0),
+            package_name: None,
+        },
+        directory: DirectoryComponent {
+            component_sizes: Vec::new(),
+            image_size: 2,
+            array_init_count: 0,
+            array_init_size: 0,
+            import_count: 1,
+            applet_count: 0,
+            custom_components: Vec::new(),
+        },
+        applet: None,
+        import: ImportComponent {
+            packages: vec![package_info(&JAVA_LANG_AID, 1, 0)],
+        },
+        constant_pool: ConstantPoolComponent {
+            entries: Vec::new(),
+        },
+        class: ClassComponent {
+            signature_pool: Vec::new(),
+            classes: vec![
+                class_info(0, object_ref),
+                class_info(20, CapClassRef::Internal(0)),
+                class_info(
+                    40,
+                    CapClassRef::External {
+                        package_token: 0,
+                        class_token: 1,
+                    },
+                ),
+            ],
+        },
+        method: MethodComponent {
+            exception_handlers: Vec::new(),
+            stop_bits: Vec::new(),
+            methods: methods.iter().map(|byte| *byte as u8).collect(),
+        },
+        static_field: StaticFieldComponent {
+            image_size: 2,
+            reference_count: 0,
+            array_init: Vec::new(),
+            default_value_count: 2,
+            non_default_values: Vec::new(),
+        },
+        reference_location: ReferenceLocationComponent {
+            byte_indices: Vec::new(),
+            byte2_indices: Vec::new(),
+        },
+        export: Some(ExportComponent {
+            classes: vec![
+                ClassExportInfo {
+                    class_offset: 0,
+                    static_field_offsets: vec![0],
+                    static_method_offsets: vec![1],
+                },
+                ClassExportInfo {
+                    class_offset: 20,
+                    static_field_offsets: Vec::new(),
+                    static_method_offsets: Vec::new(),
+                },
+                ClassExportInfo {
+                    class_offset: 40,
+                    static_field_offsets: Vec::new(),
+                    static_method_offsets: Vec::new(),
+                },
+            ],
+        }),
+        descriptor: Some(DescriptorComponent {
+            classes: vec![lib_descriptor, sub_descriptor, failure_descriptor],
+            constant_pool_types: Vec::new(),
+            type_desc_offset: 0,
+            type_desc: Vec::new(),
+        }),
+        debug: Some(DebugComponent {
+            strings: strings.iter().map(|string| string.as_bytes().to_vec()).collect(),
+            package_name_index: 0,
+            classes: vec![lib_debug, class_debug(2, 20, 1), class_debug(14, 40, 15)],
+        }),
+        custom_components: Vec::new(),
+    }
+}
+
+/// export files are read back as they were written
+#[test]
+fn exportfile_write_read_test() {
+    let export = java_lang_export();
+    let data = export.write();
+    assert_eq!(&data[..6], &[0x00, 0xFA, 0xCA, 0xDE, 1, 2]);
+    assert_eq!(ExportFile::read(&data), Ok(export.clone()));
+
+    let throwable = export.find_class("java/lang/Throwable").unwrap();
+    assert_eq!(throwable.token, 1);
+    assert_eq!(throwable.field_token("MAX_REASON"), Some(0));
+    let object = export.find_class("java/lang/Object").unwrap();
+    assert_eq!(object.method_token("equals", "(Ljava/lang/Object;)Z"), Some(0));
+    assert_eq!(object.method_token("equals", "()Z"), None);
+}
+
+/// minimal export file written by hand: package without class
+#[test]
+fn exportfile_read_test() {
+    let data = [
+        0x00, 0xFA, 0xCA, 0xDE, 1, 2, // magic and version
+        0, 2, // constant pool
+        1, 0, 3, b'l', b'i', b'b', // Utf8 "lib"
+        13, ACC_LIBRARY, 0, 0, 2, 1, 6, 0xA0, 0x00, 0x00, 0x00, 0x01, 0x01, // Package
+        0, 1, // this_package
+        0, // no class
+    ];
+    let export = ExportFile::read(&data).unwrap();
+    assert_eq!(export.flags, ACC_LIBRARY);
+    assert_eq!(export.package_name, "lib");
+    assert_eq!(export.package, package_info(&LIBRARY_AID, 1, 2));
+    assert!(export.classes.is_empty());
+    assert_eq!(export.write(), data.to_vec());
+
+    // version 2.2 export files are written back with their version
+    let mut data = data.to_vec();
+    data[4] = 2;
+    let export = ExportFile::read(&data).unwrap();
+    assert_eq!(export.minor_version, 2);
+    assert_eq!(export.write(), data);
+
+    // this_package shall designate a Package entry
+    let mut invalid = data.to_vec();
+    invalid[28] = 0;
+    assert_eq!(
+        ExportFile::read(&invalid),
+        Err(ExportFileError::InvalidConstantPoolIndex(0))
+    );
+}
+
+/// malformed export files are rejected
+#[test]
+fn exportfile_read_errors_test() {
+    let data = java_lang_export().write();
+
+    let mut invalid = data.clone();
+    invalid[1] = 0xFB;
+    assert_eq!(
+        ExportFile::read(&invalid),
+        Err(ExportFileError::InvalidMagic(0x00FB_CADE))
+    );
+
+    let mut invalid = data.clone();
+    invalid[5] = 3;
+    assert_eq!(
+        ExportFile::read(&invalid),
+        Err(ExportFileError::UnsupportedVersion(3, 1))
+    );
+
+    assert_eq!(
+        ExportFile::read(&data[..data.len() - 1]),
+        Err(ExportFileError::UnexpectedEnd)
+    );
+
+    let mut invalid = data.clone();
+    invalid.push(0);
+    assert_eq!(ExportFile::read(&invalid), Err(ExportFileError::TrailingData));
+    // other errors of the CAP file reader are kept as is
+    assert_eq!(
+        ExportFileError::from(CapFileError::InvalidValue(0)),
+        ExportFileError::Cap(CapFileError::InvalidValue(0))
+    );
+
+    // unknown tag of the first constant pool entry
+    let mut invalid = data.clone();
+    invalid[8] = 2;
+    assert_eq!(
+        ExportFile::read(&invalid),
+        Err(ExportFileError::InvalidConstant(2))
+    );
+}
+
+/// the export file of a package gets the tokens of its Export and Descriptor components
+#[test]
+fn exportfile_from_cap_test() {
+    let export = ExportFile::from_cap(&library_cap(), &[java_lang_export()]).unwrap();
+    assert_eq!(export.flags, ACC_LIBRARY);
+    assert_eq!(export.package_name, "com/example/lib");
+    assert_eq!(export.package, package_info(&LIBRARY_AID, 1, 0));
+
+    let lib = export.find_class("com/example/lib/Lib").unwrap();
+    assert_eq!(lib.token, 0);
+    assert_eq!(lib.supers, vec!["java/lang/Object".to_string()]);
+    // private members are not exported
+    assert_eq!(lib.fields.len(), 3);
+    assert_eq!(lib.field_token("counter"), Some(0));
+    assert_eq!(lib.field_token("value"), Some(0));
+    assert_eq!(lib.fields[0].access_flags, 0x0009);
+    assert_eq!(lib.fields[0].constant_value, None);
+    // the value of constants comes from the Debug component
+    assert_eq!(lib.field_token("LIMIT"), Some(1));
+    assert_eq!(lib.fields[2].constant_value, Some(-2));
+    assert_eq!(lib.methods.len(), 2);
+    assert_eq!(lib.method_token("twice", "(S)S"), Some(0));
+    assert_eq!(lib.method_token("get", "()S"), Some(1));
+
+    let sub = export.find_class("com/example/lib/Sub").unwrap();
+    assert_eq!(sub.token, 1);
+    assert_eq!(
+        sub.supers,
+        vec!["com/example/lib/Lib".to_string(), "java/lang/Object".to_string()]
+    );
+    // the superclasses of external classes come from the export files of their package
+    let failure = export.find_class("com/example/lib/Failure").unwrap();
+    assert_eq!(
+        failure.supers,
+        vec!["java/lang/Throwable".to_string(), "java/lang/Object".to_string()]
+    );
+
+    assert_eq!(ExportFile::read(&export.write()), Ok(export));
+
+    assert_eq!(
+        ExportFile::from_cap(&library_cap(), &[]),
+        Err(ExportFileError::UnresolvedClass("java/lang/Object".to_string()))
+    );
+    let mut cap = library_cap();
+    cap.debug = None;
+    assert_eq!(
+        ExportFile::from_cap(&cap, &[java_lang_export()]),
+        Err(ExportFileError::MissingComponent(COMPONENT_DEBUG))
+    );
+}
+
+///
+/// packages implemented by the virtual machine are registered from their export file, and
+/// the export files written for loaded packages match the tokens used by the linker
+///
+#[test]
+fn exportfile_linker_test() {
+    let java_lang = ExportFile::read(&java_lang_export().write()).unwrap();
+    let table = java_lang
+        .export_table(|class| {
+            let class_ref = match class.name.as_str() {
+                "java/lang/Object" => OBJECT_CLASS,
+                "java/lang/Throwable" => exceptions::THROWABLE_CLASS,
+                _ => return None,
+            };
+            Some(ExportedClass {
+                class_ref,
+                static_fields: Vec::new(),
+                static_methods: Vec::new(),
+            })
+        })
+        .unwrap();
+    assert_eq!(table.classes[1].class_ref, exceptions::THROWABLE_CLASS);
+
+    let mut linker = Linker::new();
+    linker.add_package(java_lang.package.clone(), table).unwrap();
+    let cap = library_cap();
+    let exports = linker.load(&cap).unwrap().exports.clone().unwrap();
+
+    let export = ExportFile::from_cap(&cap, &[java_lang]).unwrap();
+    let lib = export.find_class("com/example/lib/Lib").unwrap();
+    let twice = lib.method_token("twice", "(S)S").unwrap();
+    // the library code follows the code of java.lang (none)
+    assert_eq!(exports.classes[lib.token as usize].static_methods[twice as usize], 1);
+    assert_eq!(exports.classes[lib.token as usize].static_fields[0], (0, 0));
+}
+
+/// all the exported classes shall be resolved, with unique tokens
+#[test]
+fn exportfile_export_table_errors_test() {
+    let java_lang = java_lang_export();
+    let resolve_all = |_: &ExportClass| Some(ExportedClass::default());
+    assert!(java_lang.export_table(resolve_all).is_ok());
+
+    assert_eq!(
+        java_lang.export_table(|class| if class.token == 0 {
+            Some(ExportedClass::default())
+        } else {
+            None
+        }),
+        Err(ExportFileError::UnresolvedClass("java/lang/Throwable".to_string()))
+    );
+
+    let mut duplicate = java_lang.clone();
+    duplicate.classes[1].token = 0;
+    assert_eq!(
+        duplicate.export_table(resolve_all),
+        Err(ExportFileError::InvalidToken(0))
+    );
+}
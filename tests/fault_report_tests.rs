@@ -0,0 +1,135 @@
+extern crate interpreterlib;
+
+mod common;
+
+use std::error::Error;
+
+use interpreterlib::{constantpool, constants, context, exceptions, faultreport, interpreter,
+                     jcvmerrors};
+use interpreterlib::bytecodes::*;
+
+use common::add_method;
+use interpreter::BytecodeType;
+use constantpool::ConstantPoolEntry;
+use jcvmerrors::InterpreterError;
+
+/// faults are reported with the opcode, the method and the content of the operand stack
+#[test]
+fn fault_report_test() {
+    let code: Vec<BytecodeType> = vec![
+        3, // max_stack
+        1, // nargs = 0, max_locals = 1
+        bytecode::sspush as BytecodeType, // 2
+        0x12,
+        0x34,
+        bytecode::sconst_0 as BytecodeType, // 5
+        bytecode::sload_0 as BytecodeType,  // 6: the local is not initialized
+        bytecode::sreturn as BytecodeType,
+    ];
+    let mut ctx = context::Context::new(&code);
+    let error = interpreter::invoke(&mut ctx, 0).unwrap_err();
+    let report = ctx.fault_report.take().unwrap();
+    assert_eq!(report.error, error);
+
+    match report.error {
+        InterpreterError::InvalidVariableType(
+            constants::PrimitiveType::UNKNOWN,
+            constants::PrimitiveType::SHORT,
+        ) => {}
+        _ => panic!("unexpected error {}", report.error),
+    }
+    assert_eq!(report.opcode, Some(bytecode::sload_0));
+    assert_eq!(report.opcode_offset, 6);
+    assert_eq!(report.current_offset, 7);
+    assert_eq!(report.frame_depth, 1);
+    assert_eq!(report.method.unwrap().method_offset, 0);
+    let stack: Vec<i16> = report.stack.iter().map(|entry| entry.value).collect();
+    assert_eq!(stack, vec![0, 0x1234]);
+
+    assert_eq!(
+        report.to_string(),
+        "InvalidVariableType(UNKNOWN, SHORT) at offset 0x0007 (sload_0 at 0x0006), \
+         frame depth 1, method at 0x0000, operand stack (top first): \
+         [SHORT 0x0000, SHORT 0x1234]"
+    );
+    assert!(report.source().unwrap().is::<InterpreterError>());
+}
+
+/// only the top of the operand stack is recorded, and faults outside of any method
+/// or opcode are reported as such
+#[test]
+fn fault_report_without_method_test() {
+    let mut code = vec![bytecode::sconst_1 as BytecodeType; faultreport::STACK_SNAPSHOT_SIZE + 2];
+    code.push(bytecode::END as BytecodeType);
+    let mut ctx = context::Context::new(&code);
+    interpreter::interpreter(&mut ctx).unwrap_err();
+    let report = ctx.fault_report.take().unwrap();
+
+    assert_eq!(report.opcode, Some(bytecode::END));
+    assert_eq!(report.frame_depth, 0);
+    assert!(report.method.is_none());
+    assert_eq!(report.stack.len(), faultreport::STACK_SNAPSHOT_SIZE);
+
+    let mut ctx = context::Context::new(&[]);
+    interpreter::interpreter(&mut ctx).unwrap_err();
+    let report = ctx.fault_report.take().unwrap();
+    assert!(report.opcode.is_none());
+    assert_eq!(
+        report.to_string(),
+        "EndOfStream at offset 0x0000 (no opcode at 0x0000), frame depth 0, \
+         operand stack (top first): []"
+    );
+}
+
+///
+/// Uncaught exceptions are reported where they were thrown, before the frames are discarded
+///
+#[test]
+fn fault_report_uncaught_exception_test() {
+    let mut code = Vec::new();
+    let main = add_method(
+        &mut code,
+        2,
+        0,
+        0,
+        &[
+            bytecode::sconst_1 as BytecodeType,
+            bytecode::invokestatic as BytecodeType,
+            0,
+            0,
+            bytecode::sreturn as BytecodeType,
+        ],
+    );
+    let callee = add_method(
+        &mut code,
+        3,
+        1,
+        0,
+        &[
+            bytecode::sconst_2 as BytecodeType,
+            bytecode::sload_0 as BytecodeType,
+            bytecode::sconst_0 as BytecodeType,
+            bytecode::sdiv as BytecodeType,
+            bytecode::sreturn as BytecodeType,
+        ],
+    );
+    let mut ctx = context::Context::new(&code);
+    ctx.constant_pools
+        .add_entry(ConstantPoolEntry::StaticMethodRef(callee));
+
+    let error = interpreter::invoke(&mut ctx, main).unwrap_err();
+    assert!(ctx.frame_stack.is_empty());
+    let report = ctx.fault_report.take().unwrap();
+
+    assert_eq!(
+        report.error,
+        InterpreterError::UncaughtException(exceptions::ARITHMETIC_EXCEPTION_CLASS, 0)
+    );
+    assert_eq!(report.error, error);
+    assert_eq!(report.opcode, Some(bytecode::sdiv));
+    assert_eq!(report.opcode_offset, callee + 5);
+    assert_eq!(report.frame_depth, 2);
+    assert_eq!(report.method.unwrap().method_offset, callee);
+    let stack: Vec<i16> = report.stack.iter().map(|entry| entry.value).collect();
+    assert_eq!(stack, vec![2]);
+}
@@ -0,0 +1,273 @@
+extern crate interpreterlib;
+
+mod common;
+
+use interpreterlib::{classes, constantpool, context, exceptions, interpreter, jcvmerrors, objects,
+                     traits};
+use interpreterlib::bytecodes::*;
+
+use common::add_method;
+use interpreter::BytecodeType;
+use classes::JCVMClass;
+use constantpool::ConstantPoolEntry;
+use jcvmerrors::InterpreterError;
+use objects::JCVMObject;
+use traits::BufferAccessor;
+
+///
+/// Registers the classes used by the tests and creates an instance of B:
+/// - A declares a short (token 0) and an int (tokens 1 and 2)
+/// - B extends A and declares a reference (token 0) and a byte (token 1)
+///
+/// The constant pool holds the 4 fields, starting at first_index (previous entries are
+/// filled with useless entries so that wide indexes can be tested)
+/// Returns the reference to the instance and the class A
+///
+fn setup_instance(ctx: &mut context::Context, first_index: u16) -> (i16, usize) {
+    let mut class_a = JCVMClass::new(None);
+    class_a.set_declared_instance_size(3);
+    let class_a_ref = ctx.class_manager.add_class(class_a);
+
+    let mut class_b = JCVMClass::new(Some(class_a_ref));
+    class_b.set_declared_instance_size(2);
+    let class_b_ref = ctx.class_manager.add_class(class_b);
+
+    for _ in 0..first_index {
+        ctx.constant_pools
+            .add_entry(ConstantPoolEntry::ClassRef(class_a_ref));
+    }
+    ctx.constant_pools
+        .add_entry(ConstantPoolEntry::InstanceFieldRef(class_b_ref, 0));
+    ctx.constant_pools
+        .add_entry(ConstantPoolEntry::InstanceFieldRef(class_b_ref, 1));
+    ctx.constant_pools
+        .add_entry(ConstantPoolEntry::InstanceFieldRef(class_a_ref, 0));
+    ctx.constant_pools
+        .add_entry(ConstantPoolEntry::InstanceFieldRef(class_a_ref, 1));
+
+    let size = ctx.class_manager.instance_size(class_b_ref).unwrap();
+    assert_eq!(size, 10);
+    let instance = JCVMObject::new_instance(0, 0, class_b_ref, size as i16, false);
+    (ctx.object_manager.add_object(instance) as i16, class_a_ref)
+}
+
+///
+/// pushes the value to store in a field of the given type (reference, byte, short, int)
+///
+fn push_value(code: &mut Vec<BytecodeType>, position: u8) {
+    match position {
+        0 => code.extend_from_slice(&[bytecode::aload_0 as BytecodeType]),
+        1 => code.extend_from_slice(&[bytecode::bspush as BytecodeType, -2]),
+        2 => code.extend_from_slice(&[bytecode::sspush as BytecodeType, 0x12, 0x34]),
+        _ => code.extend_from_slice(&[
+            bytecode::iipush as BytecodeType,
+            0x01,
+            0x02,
+            0x03,
+            0x04,
+        ]),
+    }
+}
+
+///
+/// executes obj.field = value; return obj.field; with the given opcodes and returns
+/// the method result (2 words for integers) and the content of the object
+///
+fn execute_field_access(
+    position: u8,
+    putfield: bytecode,
+    getfield: bytecode,
+) -> (Vec<i16>, Vec<i8>) {
+    let this = putfield as u8 >= bytecode::putfield_a_this as u8;
+    let wide = !this && putfield as u8 >= bytecode::putfield_a_w as u8;
+    let first_index: u16 = if wide { 0x100 } else { 0 };
+    let index = first_index + u16::from(position);
+
+    let mut body = Vec::new();
+    if !this {
+        body.push(bytecode::aload_0 as BytecodeType);
+    }
+    push_value(&mut body, position);
+    for &opcode in &[putfield, getfield] {
+        if !this && opcode == getfield {
+            body.push(bytecode::aload_0 as BytecodeType);
+        }
+        body.push(opcode as BytecodeType);
+        if wide {
+            body.push((index >> 8) as BytecodeType);
+        }
+        body.push(index as BytecodeType);
+    }
+    body.push(match position {
+        0 => bytecode::areturn as BytecodeType,
+        3 => bytecode::ireturn as BytecodeType,
+        _ => bytecode::sreturn as BytecodeType,
+    });
+
+    let mut code = Vec::new();
+    let method = add_method(&mut code, 6, 1, 0, &body);
+
+    let mut ctx = context::Context::new(&code);
+    let (objectref, _) = setup_instance(&mut ctx, first_index);
+    ctx.operand_stack.apush(objectref).unwrap();
+    interpreter::invoke(&mut ctx, method).unwrap();
+
+    let mut result = Vec::new();
+    while !ctx.operand_stack.is_empty() {
+        result.push(ctx.operand_stack.pop().unwrap().value);
+    }
+    let object = ctx.object_manager.get_object(objectref as usize).unwrap();
+    let content = (0..object.length() as usize)
+        .map(|offset| object.read_b(offset).unwrap())
+        .collect();
+    (result, content)
+}
+
+///
+/// checks the getfield/putfield opcodes of the group starting with the given ones, for all types
+///
+fn check_field_opcodes(first_putfield: bytecode, first_getfield: bytecode) {
+    for position in 0..4 {
+        let putfield = bytecode::from(first_putfield as u8 + position).unwrap();
+        let getfield = bytecode::from(first_getfield as u8 + position).unwrap();
+        let (result, content) = execute_field_access(position, putfield, getfield);
+
+        // A fields come first, then B fields
+        match position {
+            // reference (B token 0), the object references itself
+            0 => {
+                assert_eq!(result, vec![1]);
+                assert_eq!(&content[6..8], &[0, 1]);
+            }
+            // byte (B token 1)
+            1 => {
+                assert_eq!(result, vec![-2]);
+                assert_eq!(content[8], -2);
+            }
+            // short (A token 0)
+            2 => {
+                assert_eq!(result, vec![0x1234]);
+                assert_eq!(&content[0..2], &[0x12, 0x34]);
+            }
+            // int (A tokens 1 and 2)
+            _ => {
+                assert_eq!(result, vec![0x0102, 0x0304]);
+                assert_eq!(&content[2..6], &[1, 2, 3, 4]);
+            }
+        }
+    }
+}
+
+///
+/// Test getfield_<t> and putfield_<t> opcodes from standard specification
+///
+#[test]
+fn opcode_getfield_putfield_test() {
+    check_field_opcodes(bytecode::putfield_a, bytecode::getfield_a);
+}
+
+///
+/// Test getfield_<t>_w and putfield_<t>_w opcodes from standard specification
+///
+#[test]
+fn opcode_getfield_putfield_w_test() {
+    check_field_opcodes(bytecode::putfield_a_w, bytecode::getfield_a_w);
+}
+
+///
+/// Test getfield_<t>_this and putfield_<t>_this opcodes from standard specification
+///
+#[test]
+fn opcode_getfield_putfield_this_test() {
+    check_field_opcodes(bytecode::putfield_a_this, bytecode::getfield_a_this);
+}
+
+/// byte fields are sign-extended to shorts when loaded
+#[test]
+fn opcode_getfield_b_sign_extension_test() {
+    let mut code = Vec::new();
+    let method = add_method(
+        &mut code,
+        2,
+        1,
+        0,
+        &[
+            bytecode::aload_0 as BytecodeType,
+            bytecode::bspush as BytecodeType,
+            -2,
+            bytecode::putfield_b as BytecodeType,
+            1,
+            bytecode::aload_0 as BytecodeType,
+            bytecode::getfield_b as BytecodeType,
+            1,
+            // the value can be stored as a short
+            bytecode::sstore_0 as BytecodeType,
+            bytecode::sload_0 as BytecodeType,
+            bytecode::sreturn as BytecodeType,
+        ],
+    );
+
+    let mut ctx = context::Context::new(&code);
+    let (objectref, _) = setup_instance(&mut ctx, 0);
+    ctx.operand_stack.apush(objectref).unwrap();
+    interpreter::invoke(&mut ctx, method).unwrap();
+    assert_eq!(ctx.operand_stack.spop().unwrap(), -2);
+}
+
+/// accessing a field through a null reference raises a NullPointerException
+#[test]
+fn opcode_getfield_null_test() {
+    let mut code = Vec::new();
+    let method = add_method(
+        &mut code,
+        1,
+        0,
+        0,
+        &[
+            bytecode::aconst_null as BytecodeType,
+            bytecode::getfield_s as BytecodeType,
+            2,
+            bytecode::sreturn as BytecodeType,
+        ],
+    );
+
+    let mut ctx = context::Context::new(&code);
+    setup_instance(&mut ctx, 0);
+    match interpreter::invoke(&mut ctx, method) {
+        Err(InterpreterError::UncaughtException(class, _)) => {
+            assert_eq!(class, exceptions::NULL_POINTER_EXCEPTION_CLASS)
+        }
+        _ => panic!("a NullPointerException shall have been raised"),
+    }
+}
+
+/// fields that are not declared by the class, or objects of another class, are rejected
+#[test]
+fn opcode_getfield_unresolved_test() {
+    let code: Vec<BytecodeType> = vec![bytecode::getfield_s as BytecodeType, 4];
+
+    // token 3 of A does not exist
+    let mut ctx = context::Context::new(&code);
+    let (objectref, class_a) = setup_instance(&mut ctx, 0);
+    ctx.constant_pools
+        .add_entry(ConstantPoolEntry::InstanceFieldRef(class_a, 3));
+    ctx.operand_stack.apush(objectref).unwrap();
+    match interpreter::interpreter(&mut ctx) {
+        Err(InterpreterError::UnresolvedField) => {}
+        _ => panic!("field access shall have been rejected"),
+    }
+
+    // the object is not an instance of A
+    let mut ctx = context::Context::new(&code);
+    let (_, class_a) = setup_instance(&mut ctx, 0);
+    ctx.constant_pools
+        .add_entry(ConstantPoolEntry::InstanceFieldRef(class_a, 0));
+    let other_class = ctx.class_manager.add_class(JCVMClass::new(None));
+    let objectref = ctx.object_manager
+        .add_object(JCVMObject::new_instance(0, 0, other_class, 6, false));
+    ctx.operand_stack.apush(objectref as i16).unwrap();
+    match interpreter::interpreter(&mut ctx) {
+        Err(InterpreterError::InvalidClassReference) => {}
+        _ => panic!("field access shall have been rejected"),
+    }
+}
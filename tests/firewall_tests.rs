@@ -0,0 +1,280 @@
+extern crate interpreterlib;
+
+mod common;
+
+use interpreterlib::{capcomponents, classes, constantpool, constants, context, exceptions,
+                     interpreter, jcvmerrors, objects};
+use interpreterlib::bytecodes::*;
+
+use common::{add_entries, add_method, check_security_exception, check_uncaught_exception,
+             invoke_main, leak_code};
+use interpreter::BytecodeType;
+use classes::{ImplementedInterface, JCVMClass, VirtualMethodTable, ACC_INTERFACE};
+use constantpool::ConstantPoolEntry;
+use constants::PrimitiveType;
+use jcvmerrors::InterpreterError;
+use objects::JCVMObject;
+
+// contexts of the applets
+const SERVER_CONTEXT: i16 = 1;
+const CLIENT_CONTEXT: i16 = 2;
+
+// constant pool indexes
+const CP_FIELD: u8 = 0;
+const CP_METHOD: u8 = 1;
+const CP_SHAREABLE: u8 = 2;
+const CP_NOT_SHAREABLE: u8 = 3;
+const CP_SERVER: u8 = 4;
+
+// references to the objects created by run
+const SERVER: i16 = 1;
+const ENTRY_POINT: i16 = 2;
+const ARRAY: i16 = 3;
+const GLOBAL_ARRAY: i16 = 4;
+const EXCEPTION: i16 = 5;
+
+///
+/// Runs main(objectref) in the given context and returns the short it returns.
+/// The server class has a short field and a method returning 7, which implements a method
+/// of a shareable interface and of an interface that is not shareable. The objects are:
+/// - an instance of the server class owned by the server applet
+/// - an instance of the server class which is a JCRE entry point
+/// - a short array owned by the server applet, and a global byte array
+/// - an exception owned by the server applet
+///
+fn run(
+    body: &[BytecodeType],
+    objectref: i16,
+    current_context: i16,
+) -> Result<i16, InterpreterError> {
+    let mut code = Vec::new();
+    let main = add_method(&mut code, 2, 1, 0, body);
+    let get = add_method(
+        &mut code,
+        1,
+        1,
+        0,
+        &[
+            bytecode::bspush as BytecodeType,
+            7,
+            bytecode::sreturn as BytecodeType,
+        ],
+    );
+    let code = leak_code(code);
+
+    let mut ctx = context::Context::new(code);
+    let mut shareable = JCVMClass::new_interface(&[]);
+    shareable.set_flags(ACC_INTERFACE | capcomponents::ACC_SHAREABLE);
+    let shareable = ctx.class_manager.add_class(shareable);
+    let not_shareable = ctx.class_manager.add_class(JCVMClass::new_interface(&[]));
+
+    let mut server = JCVMClass::new(None);
+    server.set_declared_instance_size(1);
+    server.set_public_methods(VirtualMethodTable::new(0, vec![get]));
+    for interface in &[shareable, not_shareable] {
+        server.add_interface(ImplementedInterface {
+            interface: *interface,
+            method_tokens: vec![0],
+        });
+    }
+    let server = ctx.class_manager.add_class(server);
+
+    add_entries(
+        &mut ctx,
+        &[
+            ConstantPoolEntry::InstanceFieldRef(server, 0),
+            ConstantPoolEntry::VirtualMethodRef(server, 0),
+            ConstantPoolEntry::ClassRef(shareable),
+            ConstantPoolEntry::ClassRef(not_shareable),
+            ConstantPoolEntry::ClassRef(server),
+        ],
+    );
+
+    let global = constants::ObjectFlags::GLOBAL as u8;
+    let objects = vec![
+        JCVMObject::new_instance(SERVER_CONTEXT, 0, server, 2, false),
+        JCVMObject::new_instance(constants::JCRE_CONTEXT, global, server, 2, false),
+        JCVMObject::new_array(SERVER_CONTEXT, 0, constants::PrimitiveType::SHORT, 4, false),
+        JCVMObject::new_array(
+            constants::JCRE_CONTEXT,
+            global,
+            constants::PrimitiveType::BYTE,
+            2,
+            false,
+        ),
+        JCVMObject::new_instance(
+            SERVER_CONTEXT,
+            0,
+            exceptions::RUNTIME_EXCEPTION_CLASS,
+            2,
+            false,
+        ),
+    ];
+    for object in objects {
+        ctx.object_manager.add_object(object);
+    }
+
+    ctx.current_context = current_context;
+    invoke_main(&mut ctx, main, &[objectref], Some(PrimitiveType::SHORT))
+}
+
+/// fields can only be accessed by the owner of the object, or by the JCRE
+#[test]
+fn firewall_field_access_test() {
+    let body = [
+        bytecode::aload_0 as BytecodeType,
+        bytecode::getfield_s as BytecodeType,
+        CP_FIELD as BytecodeType,
+        bytecode::sreturn as BytecodeType,
+    ];
+    assert_eq!(run(&body, SERVER, SERVER_CONTEXT).unwrap(), 0);
+    assert_eq!(run(&body, SERVER, constants::JCRE_CONTEXT).unwrap(), 0);
+    check_security_exception(run(&body, SERVER, CLIENT_CONTEXT));
+    // the fields of entry points are protected as well
+    check_security_exception(run(&body, ENTRY_POINT, CLIENT_CONTEXT));
+
+    let body = [
+        bytecode::aload_0 as BytecodeType,
+        bytecode::sconst_1 as BytecodeType,
+        bytecode::putfield_s as BytecodeType,
+        CP_FIELD as BytecodeType,
+        bytecode::sconst_0 as BytecodeType,
+        bytecode::sreturn as BytecodeType,
+    ];
+    assert_eq!(run(&body, SERVER, SERVER_CONTEXT).unwrap(), 0);
+    check_security_exception(run(&body, SERVER, CLIENT_CONTEXT));
+}
+
+/// arrays can only be accessed by their owner, or by the JCRE, unless they are global
+#[test]
+fn firewall_array_access_test() {
+    let body = [
+        bytecode::aload_0 as BytecodeType,
+        bytecode::arraylength as BytecodeType,
+        bytecode::sreturn as BytecodeType,
+    ];
+    assert_eq!(run(&body, ARRAY, SERVER_CONTEXT).unwrap(), 2);
+    assert_eq!(run(&body, GLOBAL_ARRAY, CLIENT_CONTEXT).unwrap(), 2);
+    check_security_exception(run(&body, ARRAY, CLIENT_CONTEXT));
+
+    let body = [
+        bytecode::aload_0 as BytecodeType,
+        bytecode::sconst_1 as BytecodeType,
+        bytecode::saload as BytecodeType,
+        bytecode::sreturn as BytecodeType,
+    ];
+    assert_eq!(run(&body, ARRAY, constants::JCRE_CONTEXT).unwrap(), 0);
+    check_security_exception(run(&body, ARRAY, CLIENT_CONTEXT));
+
+    let body = [
+        bytecode::aload_0 as BytecodeType,
+        bytecode::sconst_1 as BytecodeType,
+        bytecode::baload as BytecodeType,
+        bytecode::sreturn as BytecodeType,
+    ];
+    assert_eq!(run(&body, GLOBAL_ARRAY, CLIENT_CONTEXT).unwrap(), 0);
+}
+
+/// methods of other contexts can only be invoked on entry points or through
+/// shareable interfaces
+#[test]
+fn firewall_invocation_test() {
+    let body = [
+        bytecode::aload_0 as BytecodeType,
+        bytecode::invokevirtual as BytecodeType,
+        0,
+        CP_METHOD as BytecodeType,
+        bytecode::sreturn as BytecodeType,
+    ];
+    assert_eq!(run(&body, SERVER, SERVER_CONTEXT).unwrap(), 7);
+    assert_eq!(run(&body, ENTRY_POINT, CLIENT_CONTEXT).unwrap(), 7);
+    check_security_exception(run(&body, SERVER, CLIENT_CONTEXT));
+
+    let interface_body = |index: u8| {
+        [
+            bytecode::aload_0 as BytecodeType,
+            bytecode::invokeinterface as BytecodeType,
+            1,
+            0,
+            index as BytecodeType,
+            0,
+            bytecode::sreturn as BytecodeType,
+        ]
+    };
+    assert_eq!(
+        run(&interface_body(CP_SHAREABLE), SERVER, CLIENT_CONTEXT).unwrap(),
+        7
+    );
+    assert_eq!(
+        run(&interface_body(CP_NOT_SHAREABLE), SERVER, SERVER_CONTEXT).unwrap(),
+        7
+    );
+    check_security_exception(run(&interface_body(CP_NOT_SHAREABLE), SERVER, CLIENT_CONTEXT));
+}
+
+/// exceptions of other contexts cannot be thrown
+#[test]
+fn firewall_athrow_test() {
+    let body = [
+        bytecode::aload_0 as BytecodeType,
+        bytecode::athrow as BytecodeType,
+    ];
+    check_uncaught_exception(
+        run(&body, EXCEPTION, SERVER_CONTEXT),
+        exceptions::RUNTIME_EXCEPTION_CLASS,
+        None,
+    );
+    check_security_exception(run(&body, EXCEPTION, CLIENT_CONTEXT));
+}
+
+/// objects of other contexts can only be cast to shareable interfaces,
+/// unless they are entry points or global arrays
+#[test]
+fn firewall_cast_test() {
+    let instanceof_body = |atype: u8, index: u8| {
+        [
+            bytecode::aload_0 as BytecodeType,
+            bytecode::instanceof as BytecodeType,
+            atype as BytecodeType,
+            0,
+            index as BytecodeType,
+            bytecode::sreturn as BytecodeType,
+        ]
+    };
+    assert_eq!(
+        run(&instanceof_body(0, CP_SHAREABLE), SERVER, CLIENT_CONTEXT).unwrap(),
+        1
+    );
+    assert_eq!(
+        run(&instanceof_body(0, CP_SERVER), ENTRY_POINT, CLIENT_CONTEXT).unwrap(),
+        1
+    );
+    assert_eq!(
+        run(&instanceof_body(constants::T_BYTE, 0), GLOBAL_ARRAY, CLIENT_CONTEXT).unwrap(),
+        1
+    );
+    check_security_exception(run(&instanceof_body(0, CP_SERVER), SERVER, CLIENT_CONTEXT));
+    check_security_exception(run(
+        &instanceof_body(constants::T_SHORT, 0),
+        ARRAY,
+        CLIENT_CONTEXT,
+    ));
+
+    let checkcast_body = |index: u8| {
+        [
+            bytecode::aload_0 as BytecodeType,
+            bytecode::checkcast as BytecodeType,
+            0,
+            0,
+            index as BytecodeType,
+            bytecode::pop as BytecodeType,
+            bytecode::sconst_1 as BytecodeType,
+            bytecode::sreturn as BytecodeType,
+        ]
+    };
+    assert_eq!(
+        run(&checkcast_body(CP_SHAREABLE), SERVER, CLIENT_CONTEXT).unwrap(),
+        1
+    );
+    check_security_exception(run(&checkcast_body(CP_NOT_SHAREABLE), SERVER, CLIENT_CONTEXT));
+}
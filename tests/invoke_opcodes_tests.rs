@@ -0,0 +1,431 @@
+extern crate interpreterlib;
+
+mod common;
+
+use interpreterlib::{classes, constantpool, constants, context, interpreter, jcvmerrors, objects};
+use interpreterlib::bytecodes::*;
+
+use common::add_method;
+use interpreter::BytecodeType;
+use classes::{ImplementedInterface, JCVMClass, VirtualMethodTable};
+use constantpool::ConstantPoolEntry;
+use jcvmerrors::InterpreterError;
+use objects::JCVMObject;
+
+///
+/// Test invokestatic and sreturn: sub(2, 3)
+///
+#[test]
+fn opcode_invokestatic_test() {
+    let mut code = Vec::new();
+    let main = add_method(
+        &mut code,
+        2,
+        0,
+        0,
+        &[
+            bytecode::sconst_2 as BytecodeType,
+            bytecode::sconst_3 as BytecodeType,
+            bytecode::invokestatic as BytecodeType,
+            0,
+            0,
+            // the result is doubled once back in the caller
+            bytecode::dup as BytecodeType,
+            bytecode::sadd as BytecodeType,
+            bytecode::sreturn as BytecodeType,
+        ],
+    );
+    let sub = add_method(
+        &mut code,
+        2,
+        2,
+        0,
+        &[
+            bytecode::sload_0 as BytecodeType,
+            bytecode::sload_1 as BytecodeType,
+            bytecode::ssub as BytecodeType,
+            bytecode::sreturn as BytecodeType,
+        ],
+    );
+
+    let mut ctx = context::Context::new(&code);
+    ctx.constant_pools
+        .add_entry(ConstantPoolEntry::StaticMethodRef(sub));
+
+    interpreter::invoke(&mut ctx, main).unwrap();
+
+    // (2 - 3) * 2
+    let result = ctx.operand_stack
+        .pop_check_type(constants::PrimitiveType::SHORT)
+        .unwrap();
+    assert_eq!(result.value, -2);
+    assert!(ctx.operand_stack.is_empty());
+    assert!(ctx.frame_stack.is_empty());
+}
+
+///
+/// Test integer arguments passing and ireturn: compute(s, i) returns i - s
+///
+#[test]
+fn opcode_invokestatic_int_arguments_test() {
+    let mut code = Vec::new();
+    let main = add_method(
+        &mut code,
+        3,
+        0,
+        0,
+        &[
+            bytecode::sconst_5 as BytecodeType,
+            bytecode::iipush as BytecodeType,
+            0x00,
+            0x01,
+            0x00,
+            0x00,
+            bytecode::invokestatic as BytecodeType,
+            0,
+            0,
+            bytecode::ireturn as BytecodeType,
+        ],
+    );
+    let compute = add_method(
+        &mut code,
+        4,
+        3,
+        0,
+        &[
+            bytecode::iload_1 as BytecodeType,
+            bytecode::sload_0 as BytecodeType,
+            bytecode::s2i as BytecodeType,
+            bytecode::isub as BytecodeType,
+            bytecode::ireturn as BytecodeType,
+        ],
+    );
+
+    let mut ctx = context::Context::new(&code);
+    ctx.constant_pools
+        .add_entry(ConstantPoolEntry::StaticMethodRef(compute));
+
+    interpreter::invoke(&mut ctx, main).unwrap();
+
+    assert_eq!(ctx.operand_stack.ipop().unwrap(), 0xFFFB);
+    assert!(ctx.operand_stack.is_empty());
+}
+
+///
+/// Test recursive calls with a factorial (extended method header)
+///
+#[test]
+fn opcode_invokestatic_recursion_test() {
+    let mut code = Vec::new();
+    // extended header: flags, max_stack, nargs, max_locals
+    let factorial = code.len();
+    code.extend_from_slice(&[(0x8 << 4) as BytecodeType, 3, 1, 0]);
+    code.extend_from_slice(&[
+        bytecode::sload_0 as BytecodeType,  // 0
+        bytecode::sconst_1 as BytecodeType, // 1
+        bytecode::if_scmpgt as BytecodeType, // 2: recursion if n > 1
+        4,
+        bytecode::sconst_1 as BytecodeType, // 4
+        bytecode::sreturn as BytecodeType,  // 5
+        bytecode::sload_0 as BytecodeType,  // 6
+        bytecode::sload_0 as BytecodeType,  // 7
+        bytecode::sconst_1 as BytecodeType, // 8
+        bytecode::ssub as BytecodeType,     // 9
+        bytecode::invokestatic as BytecodeType, // 10
+        0,
+        0,
+        bytecode::smul as BytecodeType, // 13
+        bytecode::sreturn as BytecodeType,
+    ]);
+
+    let mut ctx = context::Context::new(&code);
+    ctx.constant_pools
+        .add_entry(ConstantPoolEntry::StaticMethodRef(factorial));
+    ctx.operand_stack.spush(6).unwrap();
+
+    interpreter::invoke(&mut ctx, factorial).unwrap();
+
+    let result = ctx.operand_stack
+        .pop_check_type(constants::PrimitiveType::SHORT)
+        .unwrap();
+    assert_eq!(result.value, 720);
+    assert!(ctx.operand_stack.is_empty());
+}
+
+///
+/// return discards what the method left on its operand stack and execution continues
+/// in the caller right after the invocation
+///
+#[test]
+fn opcode_return_test() {
+    let mut code = Vec::new();
+    let main = add_method(
+        &mut code,
+        2,
+        0,
+        0,
+        &[
+            bytecode::sconst_1 as BytecodeType,
+            bytecode::invokestatic as BytecodeType,
+            0,
+            0,
+            bytecode::sconst_2 as BytecodeType,
+            bytecode::sadd as BytecodeType,
+            bytecode::sreturn as BytecodeType,
+        ],
+    );
+    let callee = add_method(
+        &mut code,
+        2,
+        0,
+        0,
+        &[
+            bytecode::sconst_4 as BytecodeType,
+            bytecode::sconst_5 as BytecodeType,
+            bytecode::return_ as BytecodeType,
+        ],
+    );
+
+    let mut ctx = context::Context::new(&code);
+    ctx.constant_pools
+        .add_entry(ConstantPoolEntry::StaticMethodRef(callee));
+
+    interpreter::invoke(&mut ctx, main).unwrap();
+
+    let result = ctx.operand_stack
+        .pop_check_type(constants::PrimitiveType::SHORT)
+        .unwrap();
+    assert_eq!(result.value, 3);
+    assert!(ctx.operand_stack.is_empty());
+}
+
+///
+/// Offsets of the methods of the hierarchy built by build_hierarchy
+///
+struct Hierarchy {
+    main: usize,
+    a_get: usize,
+    a_id: usize,
+    b_get: usize,
+}
+
+///
+/// Builds the methods of a hierarchy where B extends A and implements I:
+/// - A.get() (public token 0) returns 1, A.id() (package token 0) returns 10
+/// - B.get() overrides A.get() and returns 2
+///
+/// main(obj) is built from the given body
+///
+fn build_hierarchy(code: &mut Vec<BytecodeType>, main_body: &[BytecodeType]) -> Hierarchy {
+    let main = add_method(code, 3, 1, 0, main_body);
+    let a_get = add_method(
+        code,
+        1,
+        1,
+        0,
+        &[
+            bytecode::sconst_1 as BytecodeType,
+            bytecode::sreturn as BytecodeType,
+        ],
+    );
+    let a_id = add_method(
+        code,
+        1,
+        1,
+        0,
+        &[
+            bytecode::bspush as BytecodeType,
+            10,
+            bytecode::sreturn as BytecodeType,
+        ],
+    );
+    let b_get = add_method(
+        code,
+        1,
+        1,
+        0,
+        &[
+            bytecode::sconst_2 as BytecodeType,
+            bytecode::sreturn as BytecodeType,
+        ],
+    );
+    Hierarchy {
+        main,
+        a_get,
+        a_id,
+        b_get,
+    }
+}
+
+///
+/// registers the classes A, B and the interface I, returning their references
+///
+fn register_hierarchy(ctx: &mut context::Context, methods: &Hierarchy) -> (usize, usize, usize) {
+    let interface = ctx.class_manager.add_class(JCVMClass::new(None));
+
+    let mut class_a = JCVMClass::new(None);
+    class_a.set_public_methods(VirtualMethodTable::new(0, vec![methods.a_get]));
+    class_a.set_package_methods(VirtualMethodTable::new(0, vec![methods.a_id]));
+    let class_a_ref = ctx.class_manager.add_class(class_a);
+
+    let mut class_b = JCVMClass::new(Some(class_a_ref));
+    class_b.set_public_methods(VirtualMethodTable::new(0, vec![methods.b_get]));
+    class_b.add_interface(ImplementedInterface {
+        interface,
+        method_tokens: vec![0, 0x80],
+    });
+    let class_b_ref = ctx.class_manager.add_class(class_b);
+
+    (class_a_ref, class_b_ref, interface)
+}
+
+///
+/// runs main(obj) where obj is an instance of the given class and returns the short result
+///
+fn run_main(ctx: &mut context::Context, main: usize, class_ref: usize) -> i16 {
+    let handle = ctx.object_manager
+        .add_object(JCVMObject::new_instance(0, 0, class_ref, 0, false));
+    ctx.operand_stack.apush(handle as i16).unwrap();
+
+    interpreter::invoke(ctx, main).unwrap();
+
+    let result = ctx.operand_stack
+        .pop_check_type(constants::PrimitiveType::SHORT)
+        .unwrap();
+    assert!(ctx.operand_stack.is_empty());
+    assert!(ctx.frame_stack.is_empty());
+    result.value
+}
+
+const INVOKEVIRTUAL_MAIN: [BytecodeType; 5] = [
+    bytecode::aload_0 as BytecodeType,
+    bytecode::invokevirtual as BytecodeType,
+    0,
+    0,
+    bytecode::sreturn as BytecodeType,
+];
+
+///
+/// Test invokevirtual opcode from standard specification
+///
+#[test]
+fn opcode_invokevirtual_test() {
+    let mut code = Vec::new();
+    let methods = build_hierarchy(&mut code, &INVOKEVIRTUAL_MAIN);
+
+    // the method is selected from the class of the object
+    for &(use_b, expected) in &[(false, 1), (true, 2)] {
+        let mut ctx = context::Context::new(&code);
+        let (class_a, class_b, _) = register_hierarchy(&mut ctx, &methods);
+        ctx.constant_pools
+            .add_entry(ConstantPoolEntry::VirtualMethodRef(class_a, 0));
+
+        let object_class = if use_b { class_b } else { class_a };
+        assert_eq!(run_main(&mut ctx, methods.main, object_class), expected);
+    }
+
+    // package visible methods are inherited as well
+    let mut ctx = context::Context::new(&code);
+    let (_, class_b, _) = register_hierarchy(&mut ctx, &methods);
+    ctx.constant_pools
+        .add_entry(ConstantPoolEntry::VirtualMethodRef(class_b, 0x80));
+    assert_eq!(run_main(&mut ctx, methods.main, class_b), 10);
+}
+
+///
+/// Test invokespecial opcode (private methods and super calls) from standard specification
+///
+#[test]
+fn opcode_invokespecial_test() {
+    let mut code = Vec::new();
+    let methods = build_hierarchy(
+        &mut code,
+        &[
+            bytecode::aload_0 as BytecodeType,
+            bytecode::invokespecial as BytecodeType,
+            0,
+            0,
+            bytecode::aload_0 as BytecodeType,
+            bytecode::invokespecial as BytecodeType,
+            0,
+            1,
+            bytecode::sadd as BytecodeType,
+            bytecode::sreturn as BytecodeType,
+        ],
+    );
+
+    let mut ctx = context::Context::new(&code);
+    let (_, class_b, _) = register_hierarchy(&mut ctx, &methods);
+    // super.get() from B calls A.get()
+    ctx.constant_pools
+        .add_entry(ConstantPoolEntry::SuperMethodRef(class_b, 0));
+    // direct call to B.get()
+    ctx.constant_pools
+        .add_entry(ConstantPoolEntry::StaticMethodRef(methods.b_get));
+
+    assert_eq!(run_main(&mut ctx, methods.main, class_b), 3);
+}
+
+///
+/// Test invokeinterface opcode from standard specification
+///
+#[test]
+fn opcode_invokeinterface_test() {
+    let mut code = Vec::new();
+    let methods = build_hierarchy(
+        &mut code,
+        &[
+            bytecode::aload_0 as BytecodeType,
+            bytecode::invokeinterface as BytecodeType,
+            1,
+            0,
+            0,
+            0,
+            bytecode::aload_0 as BytecodeType,
+            bytecode::invokeinterface as BytecodeType,
+            1,
+            0,
+            0,
+            1,
+            bytecode::smul as BytecodeType,
+            bytecode::sreturn as BytecodeType,
+        ],
+    );
+
+    let mut ctx = context::Context::new(&code);
+    let (_, class_b, interface) = register_hierarchy(&mut ctx, &methods);
+    ctx.constant_pools
+        .add_entry(ConstantPoolEntry::ClassRef(interface));
+
+    // B.get() * A.id()
+    assert_eq!(run_main(&mut ctx, methods.main, class_b), 20);
+}
+
+/// invocations that cannot be resolved are reported as errors
+#[test]
+fn opcode_invoke_unresolved_test() {
+    let mut code = Vec::new();
+    let methods = build_hierarchy(&mut code, &INVOKEVIRTUAL_MAIN);
+
+    // wrong kind of constant pool entry
+    let mut ctx = context::Context::new(&code);
+    register_hierarchy(&mut ctx, &methods);
+    ctx.constant_pools
+        .add_entry(ConstantPoolEntry::StaticMethodRef(methods.a_get));
+    ctx.operand_stack.apush(0).unwrap();
+    match interpreter::invoke(&mut ctx, methods.main) {
+        Err(InterpreterError::InvalidConstantPoolEntry) => {}
+        _ => panic!("invocation shall have been rejected"),
+    }
+
+    // unknown token
+    let mut ctx = context::Context::new(&code);
+    let (class_a, _, _) = register_hierarchy(&mut ctx, &methods);
+    ctx.constant_pools
+        .add_entry(ConstantPoolEntry::VirtualMethodRef(class_a, 5));
+    ctx.operand_stack.apush(0).unwrap();
+    match interpreter::invoke(&mut ctx, methods.main) {
+        Err(InterpreterError::UnresolvedMethod) => {}
+        _ => panic!("invocation shall have been rejected"),
+    }
+}
@@ -0,0 +1,434 @@
+extern crate interpreterlib;
+
+mod common;
+
+use interpreterlib::{capcomponents, capfile, classes, constants, context, interpreter, jcvmerrors,
+                     linker, staticfields, traits};
+use interpreterlib::bytecodes::*;
+
+use common::{class_info, package_info, JAVA_LANG_AID};
+use interpreter::BytecodeType;
+use capcomponents::*;
+use capfile::CapFile;
+use classes::OBJECT_CLASS;
+use jcvmerrors::LinkError;
+use linker::{ExportTable, ExportedClass, Linker};
+use staticfields::StaticFieldComponent;
+use traits::BufferAccessor;
+
+const LIBRARY_AID: [u8; 6] = [0xA0, 0x00, 0x00, 0x00, 0x01, 0x01];
+const APPLET_PACKAGE_AID: [u8; 6] = [0xA0, 0x00, 0x00, 0x00, 0x01, 0x02];
+
+// package tokens of the imports of the applet package
+const JAVA_LANG_TOKEN: u8 = 0;
+const LIBRARY_TOKEN: u8 = 1;
+
+///
+/// utility function building a CAP file from the components used by the linker, with a
+/// static field image of one short (the Directory component is left empty)
+///
+fn cap_file(
+    package: PackageInfo,
+    imports: Vec<PackageInfo>,
+    entries: Vec<CapConstantPoolEntry>,
+    classes: Vec<CapClassInfo>,
+    methods: &[BytecodeType],
+) -> CapFile {
+    CapFile {
+        header: HeaderComponent {
+            minor_version: 2,
+            major_version: 2,
+            flags: 0,
+            package,
+            package_name: None,
+        },
+        directory: DirectoryComponent {
+            component_sizes: Vec::new(),
+            image_size: 2,
+            array_init_count: 0,
+            array_init_size: 0,
+            import_count: imports.len() as u8,
+            applet_count: 0,
+            custom_components: Vec::new(),
+        },
+        applet: None,
+        import: ImportComponent { packages: imports },
+        constant_pool: ConstantPoolComponent { entries },
+        class: ClassComponent {
+            signature_pool: Vec::new(),
+            classes,
+        },
+        method: MethodComponent {
+            exception_handlers: Vec::new(),
+            stop_bits: Vec::new(),
+            methods: methods.iter().map(|byte| *byte as u8).collect(),
+        },
+        static_field: StaticFieldComponent {
+            image_size: 2,
+            reference_count: 0,
+            array_init: Vec::new(),
+            default_value_count: 2,
+            non_default_values: Vec::new(),
+        },
+        reference_location: ReferenceLocationComponent {
+            byte_indices: Vec::new(),
+            byte2_indices: Vec::new(),
+        },
+        export: None,
+        descriptor: None,
+        debug: None,
+        custom_components: Vec::new(),
+    }
+}
+
+///
+/// Library package version 1.2, exporting class Lib (token 0) with:
+/// - static field 0: short initialized to 5
+/// - static method 0: short twice(short)
+///
+fn library_cap() -> CapFile {
+    let mut cap = cap_file(
+        package_info(&LIBRARY_AID, 1, 2),
+        vec![package_info(&JAVA_LANG_AID, 1, 0)],
+        Vec::new(),
+        vec![class_info(
+            0,
+            CapClassRef::External {
+                package_token: 0,
+                class_token: 0,
+            },
+        )],
+        &[
+            0, // no exception handler
+            // twice, at offset 1
+            0x02,
+            0x10,
+            bytecode::sload_0 as BytecodeType,
+            bytecode::dup as BytecodeType,
+            bytecode::sadd as BytecodeType,
+            bytecode::sreturn as BytecodeType,
+        ],
+    );
+    cap.static_field.default_value_count = 0;
+    cap.static_field.non_default_values = vec![0x00, 0x05];
+    cap.export = Some(ExportComponent {
+        classes: vec![ClassExportInfo {
+            class_offset: 0,
+            static_field_offsets: vec![0],
+            static_method_offsets: vec![1],
+        }],
+    });
+    cap
+}
+
+// offset of the main method of the applet package in its Method component
+const MAIN_OFFSET: usize = 1;
+
+///
+/// Applet package importing the library (with the given version and class token), whose
+/// main method computes field = Lib.twice(Lib.field) + 1 and returns it.
+/// It defines class App extending Lib.
+///
+fn applet_cap(library_major: u8, library_minor: u8, library_class: u8) -> CapFile {
+    let library_ref = |token| CapStaticRef::External {
+        package_token: LIBRARY_TOKEN,
+        class_token: library_class,
+        token,
+    };
+    let mut cap = cap_file(
+        package_info(&APPLET_PACKAGE_AID, 1, 0),
+        vec![
+            package_info(&JAVA_LANG_AID, 1, 0),
+            package_info(&LIBRARY_AID, library_major, library_minor),
+        ],
+        vec![
+            CapConstantPoolEntry::StaticFieldRef(library_ref(0)),
+            CapConstantPoolEntry::StaticMethodRef(library_ref(0)),
+            CapConstantPoolEntry::StaticFieldRef(CapStaticRef::Internal(0)),
+            CapConstantPoolEntry::StaticMethodRef(CapStaticRef::Internal(17)),
+        ],
+        vec![class_info(
+            0,
+            CapClassRef::External {
+                package_token: LIBRARY_TOKEN,
+                class_token: library_class,
+            },
+        )],
+        &[
+            0, // no exception handler
+            // main, at offset 1
+            0x02,
+            0x00,
+            bytecode::getstatic_s as BytecodeType,
+            0,
+            0, // 4
+            bytecode::invokestatic as BytecodeType,
+            0,
+            1, // 7
+            bytecode::invokestatic as BytecodeType,
+            0,
+            3, // 10
+            bytecode::dup as BytecodeType,
+            bytecode::putstatic_s as BytecodeType,
+            0,
+            2, // 14
+            bytecode::sreturn as BytecodeType,
+            // increment, at offset 17
+            0x02,
+            0x10,
+            bytecode::sload_0 as BytecodeType,
+            bytecode::sconst_1 as BytecodeType,
+            bytecode::sadd as BytecodeType,
+            bytecode::sreturn as BytecodeType,
+        ],
+    );
+    cap.reference_location.byte2_indices = vec![4, 7, 10, 14];
+    cap
+}
+
+/// linker where java.lang is registered, its class 0 being java.lang.Object
+fn linker_with_java_lang() -> Linker {
+    let mut linker = Linker::new();
+    linker
+        .add_package(
+            package_info(&JAVA_LANG_AID, 1, 0),
+            ExportTable {
+                classes: vec![ExportedClass {
+                    class_ref: OBJECT_CLASS,
+                    static_fields: Vec::new(),
+                    static_methods: Vec::new(),
+                }],
+            },
+        )
+        .unwrap();
+    linker
+}
+
+/// packages are linked against the packages they import, and can be executed once installed
+#[test]
+fn linker_link_test() {
+    let mut linker = linker_with_java_lang();
+    let library_class = {
+        let library = linker.load(&library_cap()).unwrap();
+        assert_eq!(library.static_fields, Some(0));
+        library.exports.as_ref().unwrap().classes[0].class_ref
+    };
+    let main = {
+        let package = linker.load(&applet_cap(1, 1, 0)).unwrap();
+        assert_eq!(package.static_fields, Some(1));
+        assert!(package.exports.is_none());
+        // the code of the applet package follows the one of the library
+        7 + MAIN_OFFSET
+    };
+    assert_eq!(linker.packages().len(), 3);
+
+    let code = linker.code().to_vec();
+    let mut ctx = context::Context::new(&code);
+    linker.install(&mut ctx);
+
+    // App extends Lib which extends Object
+    let app_class = ctx.class_manager.len() - 1;
+    let lib = ctx.class_manager.get_class(library_class).unwrap();
+    assert_eq!(lib.super_class(), Some(OBJECT_CLASS));
+    assert!(ctx.class_manager
+        .is_subclass_of(app_class, library_class)
+        .unwrap());
+
+    interpreter::invoke(&mut ctx, main).unwrap();
+    let result = ctx.operand_stack
+        .pop_check_type(constants::PrimitiveType::SHORT)
+        .unwrap();
+    assert_eq!(result.value, 11);
+    assert_eq!(ctx.static_fields.get_image(1).unwrap().read_s(0).unwrap(), 11);
+    assert_eq!(ctx.static_fields.get_image(0).unwrap().read_s(0).unwrap(), 5);
+}
+
+///
+/// the code of the methods listed by the Descriptor component ends after their bytecode count,
+/// the code of the other methods known to the linker at the next method
+///
+#[test]
+fn linker_method_extents_test() {
+    let mut linker = linker_with_java_lang();
+    linker.load(&library_cap()).unwrap();
+    let mut applet = applet_cap(1, 1, 0);
+    applet.descriptor = Some(DescriptorComponent {
+        classes: vec![ClassDescriptor {
+            token: 0,
+            access_flags: 0,
+            this_class_ref: CapClassRef::Internal(0),
+            interfaces: Vec::new(),
+            fields: Vec::new(),
+            methods: vec![MethodDescriptor {
+                token: 0,
+                access_flags: 0,
+                method_offset: MAIN_OFFSET as u16,
+                type_offset: 0,
+                bytecode_count: 14,
+                exception_handler_count: 0,
+                exception_handler_index: 0,
+            }],
+        }],
+        constant_pool_types: Vec::new(),
+        type_desc_offset: 0,
+        type_desc: Vec::new(),
+    });
+    linker.load(&applet).unwrap();
+
+    let code = linker.code().to_vec();
+    let mut ctx = context::Context::new(&code);
+    linker.install(&mut ctx);
+    // twice, exported by the library
+    assert_eq!(ctx.method_extents.code_end(1), Some(7));
+    // main, whose code follows its 2 bytes header
+    assert_eq!(ctx.method_extents.code_end(7 + MAIN_OFFSET), Some(7 + 17));
+    // increment, referenced by the constant pool of the applet package
+    assert_eq!(ctx.method_extents.code_end(7 + 17), Some(7 + 23));
+    assert_eq!(ctx.method_extents.len(), 3);
+}
+
+/// imports are resolved by AID, and have to be binary compatible with the loaded packages
+#[test]
+fn linker_import_errors_test() {
+    let mut linker = linker_with_java_lang();
+    assert_eq!(
+        linker.load(&applet_cap(1, 0, 0)),
+        Err(LinkError::UnresolvedImport(LIBRARY_AID.to_vec()))
+    );
+
+    linker.load(&library_cap()).unwrap();
+    assert_eq!(
+        linker.load(&library_cap()),
+        Err(LinkError::DuplicatePackage(LIBRARY_AID.to_vec()))
+    );
+    for &(major, minor) in &[(1, 3), (2, 0)] {
+        assert_eq!(
+            linker.load(&applet_cap(major, minor, 0)),
+            Err(LinkError::VersionMismatch(LIBRARY_AID.to_vec(), major, minor))
+        );
+    }
+}
+
+/// references to elements that do not exist are rejected, leaving the linker unchanged
+#[test]
+fn linker_reference_errors_test() {
+    let mut linker = linker_with_java_lang();
+    linker.load(&library_cap()).unwrap();
+    let code_size = linker.code().len();
+
+    assert_eq!(
+        linker.load(&applet_cap(1, 0, 1)),
+        Err(LinkError::UnresolvedToken(LIBRARY_TOKEN, 1))
+    );
+
+    let mut cap = applet_cap(1, 0, 0);
+    cap.class.classes[0] = class_info(0, CapClassRef::Internal(5));
+    assert_eq!(linker.load(&cap), Err(LinkError::InvalidReference(5)));
+
+    let mut cap = applet_cap(1, 0, 0);
+    cap.constant_pool.entries[2] = CapConstantPoolEntry::StaticFieldRef(CapStaticRef::Internal(2));
+    assert_eq!(linker.load(&cap), Err(LinkError::InvalidReference(2)));
+
+    // the code shall only reference the constant pool of the package
+    let mut cap = applet_cap(1, 0, 0);
+    cap.reference_location.byte2_indices[0] = 12;
+    assert_eq!(linker.load(&cap), Err(LinkError::InvalidReference(12)));
+
+    assert_eq!(linker.packages().len(), 2);
+    assert_eq!(linker.code().len(), code_size);
+
+    // the failed attempts did not prevent the package from being loaded
+    linker.load(&applet_cap(1, 0, 0)).unwrap();
+    let code = linker.code().to_vec();
+    let mut ctx = context::Context::new(&code);
+    linker.install(&mut ctx);
+    interpreter::invoke(&mut ctx, code_size + MAIN_OFFSET).unwrap();
+    assert_eq!(ctx.operand_stack.spop().unwrap(), 11);
+}
+
+/// package tokens refer to the imports in their order in the Import component
+#[test]
+fn linker_import_token_test() {
+    let mut linker = linker_with_java_lang();
+    linker.load(&library_cap()).unwrap();
+
+    let mut cap = applet_cap(1, 0, 0);
+    cap.class.classes[0] = class_info(
+        0,
+        CapClassRef::External {
+            package_token: JAVA_LANG_TOKEN,
+            class_token: 0,
+        },
+    );
+    linker.load(&cap).unwrap();
+
+    let code = linker.code().to_vec();
+    let mut ctx = context::Context::new(&code);
+    linker.install(&mut ctx);
+    let app_class = ctx.class_manager.len() - 1;
+    assert_eq!(
+        ctx.class_manager.get_class(app_class).unwrap().super_class(),
+        Some(OBJECT_CLASS)
+    );
+}
+
+///
+/// each package has its own constant pool: the code of the applet package uses its 1-byte
+/// indexes whatever the size of the constant pool of the library
+///
+#[test]
+fn linker_constant_pools_test() {
+    let object_ref = CapClassRef::External {
+        package_token: JAVA_LANG_TOKEN,
+        class_token: 0,
+    };
+    let mut linker = linker_with_java_lang();
+    let mut library = library_cap();
+    library.constant_pool.entries = vec![CapConstantPoolEntry::ClassRef(object_ref); 300];
+    linker.load(&library).unwrap();
+    let code_size = linker.code().len();
+
+    // main creates an App, whose short field 0 is set to 7, and returns the field
+    let mut cap = cap_file(
+        package_info(&APPLET_PACKAGE_AID, 1, 0),
+        vec![package_info(&JAVA_LANG_AID, 1, 0)],
+        vec![
+            CapConstantPoolEntry::ClassRef(CapClassRef::Internal(0)),
+            CapConstantPoolEntry::InstanceFieldRef(CapClassRef::Internal(0), 0),
+        ],
+        vec![class_info(0, object_ref)],
+        &[
+            0, // no exception handler
+            // main, at offset 1
+            0x03,
+            0x00,
+            bytecode::new as BytecodeType,
+            0,
+            0, // 4
+            bytecode::dup as BytecodeType,
+            bytecode::bspush as BytecodeType,
+            7,
+            bytecode::putfield_s as BytecodeType,
+            1, // 10
+            bytecode::getfield_s as BytecodeType,
+            1, // 12
+            bytecode::sreturn as BytecodeType,
+        ],
+    );
+    if let CapClassInfo::Class {
+        ref mut declared_instance_size,
+        ..
+    } = cap.class.classes[0]
+    {
+        *declared_instance_size = 1;
+    }
+    cap.reference_location.byte_indices = vec![10, 12];
+    cap.reference_location.byte2_indices = vec![4];
+    linker.load(&cap).unwrap();
+
+    let code = linker.code().to_vec();
+    let mut ctx = context::Context::new(&code);
+    linker.install(&mut ctx);
+    interpreter::invoke(&mut ctx, code_size + MAIN_OFFSET).unwrap();
+    assert_eq!(ctx.operand_stack.spop().unwrap(), 7);
+}
@@ -8,8 +8,8 @@ use stack::{StackElementType, StackEntry};
 use objects::JCVMObject;
 use traits::BufferAccessor;
 
-pub fn execute_with_context(mut ctx: &mut context::Context) {
-    let _result = interpreter::interpreter(&mut ctx);
+pub fn execute_with_context(ctx: &mut context::Context) {
+    let _result = interpreter::interpreter(ctx);
 }
 
 pub fn execute_bytecode<'a>(data: &'a BytecodeData) -> context::Context<'a> {
@@ -30,7 +30,7 @@ fn opcode_aload_test() {
     let datatoexecute: &BytecodeData = &[bytecode::aload as BytecodeType, idx];
     let mut ctx = context::Context::new(datatoexecute);
     let exp_value = StackEntry::from_values(
-        (0xA55A as u16) as StackElementType,
+        0xA55A_u16 as StackElementType,
         constants::PrimitiveType::REFERENCE,
     );
 
@@ -60,7 +60,7 @@ fn opcode_sload_test() {
     let datatoexecute: &BytecodeData = &[bytecode::sload as BytecodeType, idx];
     let mut ctx = context::Context::new(datatoexecute);
     let exp_value = StackEntry::from_values(
-        (0xA55A as u16) as StackElementType,
+        0xA55A_u16 as StackElementType,
         constants::PrimitiveType::SHORT,
     );
 
@@ -136,7 +136,7 @@ fn opcode_xload_x_unittest(bc: bytecode, idx: u8, type_: constants::PrimitiveTyp
     // we voluntarily don't  use offset 0 to make sure we pick the right value
     let datatoexecute: &BytecodeData = &[bc as BytecodeType];
     let mut ctx = context::Context::new(datatoexecute);
-    let exp_value = StackEntry::from_values((0xA55A as u16) as StackElementType, type_);
+    let exp_value = StackEntry::from_values(0xA55A_u16 as StackElementType, type_);
 
     // create a new frame
     ctx.frame_stack.push(frame::Frame::new(idx + 1));
@@ -194,7 +194,7 @@ fn opcode_iload_x_tests() {
         let datatoexecute: &BytecodeData = &[curbc as BytecodeType];
         let mut ctx = context::Context::new(datatoexecute);
         let mut exp_value = StackEntry::from_values(
-            (0xA55A as u16) as StackElementType,
+            0xA55A_u16 as StackElementType,
             constants::PrimitiveType::INTEGER,
         );
         let idx = curbc - (bytecode::iload_0 as u8);
@@ -247,7 +247,7 @@ fn opcode_xaload_x_tests(bc: bytecode, type_: constants::PrimitiveType) {
             let size_one_entry = constants::SHORT_SIZE;
             // add a specific value that we will be able to check afterwards
             created_array
-                .write_s(((idx as usize) * size_one_entry), exp_value as i16)
+                .write_s((idx as usize) * size_one_entry, exp_value as i16)
                 .unwrap();
         }
 
@@ -276,16 +276,16 @@ fn opcode_xaload_x_tests(bc: bytecode, type_: constants::PrimitiveType) {
     let refidx = ctx.object_manager.add_object(created_array);
 
     // now prepare the stack with approriate content
-    // first, the index
-    ctx.operand_stack.push(StackEntry::from_values(
-        idx,
-        constants::PrimitiveType::SHORT,
-    ));
-    // next, the arrayref
+    // first, the arrayref
     ctx.operand_stack.push(StackEntry::from_values(
         refidx as i16,
         constants::PrimitiveType::REFERENCE,
-    ));
+    )).unwrap();
+    // next, the index
+    ctx.operand_stack.push(StackEntry::from_values(
+        idx,
+        constants::PrimitiveType::SHORT,
+    )).unwrap();
     // execute code
     execute_with_context(&mut ctx);
 
@@ -301,14 +301,17 @@ fn opcode_xaload_x_tests(bc: bytecode, type_: constants::PrimitiveType) {
         }
 
         constants::PrimitiveType::BYTE => {
-            // now check that we have an entry with the given value on the stack
-            let result = ctx.operand_stack.pop_check_type(type_).unwrap();
+            // now check that we have an entry with the given value on the stack, sign-extended
+            // to a short
+            let result = ctx.operand_stack
+                .pop_check_type(constants::PrimitiveType::SHORT)
+                .unwrap();
 
             println!(
                 "obtained:{:04X}, expected: {:04X}",
-                result.value as u8, exp_value as u8
+                result.value as u16, exp_value as i8 as u16
             );
-            assert_eq!(result.value as u8, exp_value as u8);
+            assert_eq!(result.value, i16::from(exp_value as i8));
         }
 
         constants::PrimitiveType::INTEGER => {
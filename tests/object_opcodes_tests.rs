@@ -0,0 +1,223 @@
+extern crate interpreterlib;
+
+use interpreterlib::{classes, constantpool, constants, context, exceptions, interpreter, jcvmerrors,
+                     traits};
+use interpreterlib::bytecodes::*;
+
+use interpreter::{BytecodeData, BytecodeType};
+use classes::JCVMClass;
+use constantpool::ConstantPoolEntry;
+use jcvmerrors::InterpreterError;
+use traits::HasType;
+
+pub fn execute_with_context(ctx: &mut context::Context) {
+    let _result = interpreter::interpreter(ctx);
+}
+
+///
+/// utility function creating a context where the constant pool entry 0 is a class
+/// declaring 3 field slots
+///
+fn context_with_class(data: &BytecodeData) -> context::Context<'_> {
+    let mut ctx = context::Context::new(data);
+    let mut class = JCVMClass::new(None);
+    class.set_declared_instance_size(3);
+    let class_ref = ctx.class_manager.add_class(class);
+    ctx.constant_pools
+        .add_entry(ConstantPoolEntry::ClassRef(class_ref));
+    ctx
+}
+
+///
+/// Test new opcode from standard specification
+///
+#[test]
+fn opcode_new_test() {
+    let mut ctx = context_with_class(&[bytecode::new as BytecodeType, 0, 0]);
+    ctx.current_context = 3;
+    execute_with_context(&mut ctx);
+
+    let objectref = ctx.operand_stack
+        .pop_check_type(constants::PrimitiveType::REFERENCE)
+        .unwrap();
+    assert!(ctx.operand_stack.is_empty());
+
+    let object = ctx.object_manager
+        .get_object(objectref.value as usize)
+        .unwrap();
+    assert!(!object.is_array());
+    assert!(object.class_ref().is_some());
+    assert_eq!(object.length(), 6);
+    assert_eq!(object.owner(), 3);
+    assert!(object.is_persistent());
+}
+
+///
+/// Test newarray and arraylength opcodes from standard specification
+///
+#[test]
+fn opcode_newarray_arraylength_test() {
+    let expectations = [
+        (constants::T_BOOLEAN, constants::PrimitiveType::BYTE, 5),
+        (constants::T_BYTE, constants::PrimitiveType::BYTE, 5),
+        (constants::T_SHORT, constants::PrimitiveType::SHORT, 10),
+        (constants::T_INT, constants::PrimitiveType::INTEGER, 20),
+    ];
+
+    for &(atype, type_, size) in &expectations {
+        let datatoexecute: &BytecodeData = &[
+            bytecode::sconst_5 as BytecodeType,
+            bytecode::newarray as BytecodeType,
+            atype as BytecodeType,
+            bytecode::dup as BytecodeType,
+            bytecode::arraylength as BytecodeType,
+        ];
+        let mut ctx = context::Context::new(datatoexecute);
+        ctx.current_context = 2;
+        execute_with_context(&mut ctx);
+
+        assert_eq!(ctx.operand_stack.spop().unwrap(), 5);
+        let arrayref = ctx.operand_stack
+            .pop_check_type(constants::PrimitiveType::REFERENCE)
+            .unwrap();
+        assert!(ctx.operand_stack.is_empty());
+
+        let array = ctx.object_manager
+            .get_object(arrayref.value as usize)
+            .unwrap();
+        assert!(array.is_array());
+        assert!(array.is_of_type(type_));
+        assert_eq!(array.length(), size);
+        assert_eq!(array.owner(), 2);
+    }
+}
+
+///
+/// Test anewarray opcode from standard specification
+///
+#[test]
+fn opcode_anewarray_test() {
+    let mut ctx = context_with_class(&[
+        bytecode::sconst_3 as BytecodeType,
+        bytecode::anewarray as BytecodeType,
+        0,
+        0,
+        bytecode::dup as BytecodeType,
+        bytecode::arraylength as BytecodeType,
+    ]);
+    execute_with_context(&mut ctx);
+
+    assert_eq!(ctx.operand_stack.spop().unwrap(), 3);
+    let arrayref = ctx.operand_stack
+        .pop_check_type(constants::PrimitiveType::REFERENCE)
+        .unwrap();
+    let array = ctx.object_manager
+        .get_object(arrayref.value as usize)
+        .unwrap();
+    assert!(array.is_array());
+    assert!(array.is_of_type(constants::PrimitiveType::REFERENCE));
+    assert_eq!(array.length(), 6);
+    // arrays are not instances of the class of their elements
+    assert!(array.class_ref().is_none());
+    assert!(array.component_class().is_some());
+}
+
+/// negative counts raise a NegativeArraySizeException, null references a NullPointerException
+#[test]
+fn opcode_newarray_exceptions_test() {
+    let cases: [(&BytecodeData, usize); 3] = [
+        (
+            &[
+                bytecode::sconst_m1 as BytecodeType,
+                bytecode::newarray as BytecodeType,
+                constants::T_BYTE as BytecodeType,
+            ],
+            exceptions::NEGATIVE_ARRAY_SIZE_EXCEPTION_CLASS,
+        ),
+        (
+            &[
+                bytecode::sconst_m1 as BytecodeType,
+                bytecode::anewarray as BytecodeType,
+                0,
+                0,
+            ],
+            exceptions::NEGATIVE_ARRAY_SIZE_EXCEPTION_CLASS,
+        ),
+        (
+            &[
+                bytecode::aconst_null as BytecodeType,
+                bytecode::arraylength as BytecodeType,
+            ],
+            exceptions::NULL_POINTER_EXCEPTION_CLASS,
+        ),
+    ];
+
+    for &(datatoexecute, expected_class) in &cases {
+        let mut ctx = context_with_class(datatoexecute);
+        match interpreter::interpreter(&mut ctx) {
+            Err(InterpreterError::UncaughtException(class, _)) => assert_eq!(class, expected_class),
+            _ => panic!("an exception shall have been raised"),
+        }
+    }
+}
+
+/// objects larger than i16::MAX bytes raise a SystemException with the NO_RESOURCE reason
+#[test]
+fn opcode_new_no_resource_test() {
+    let check_no_resource = |ctx: &mut context::Context| match interpreter::interpreter(ctx) {
+        Err(InterpreterError::UncaughtException(class, reason)) => {
+            assert_eq!(class, exceptions::SYSTEM_EXCEPTION_CLASS);
+            assert_eq!(reason, exceptions::NO_RESOURCE);
+        }
+        _ => panic!("a SystemException shall have been raised"),
+    };
+
+    // 65 classes of 255 field slots
+    let mut ctx = context::Context::new(&[bytecode::new as BytecodeType, 0, 0]);
+    let mut class_ref = None;
+    for _ in 0..65 {
+        let mut class = JCVMClass::new(class_ref);
+        class.set_declared_instance_size(255);
+        class_ref = Some(ctx.class_manager.add_class(class));
+    }
+    ctx.constant_pools
+        .add_entry(ConstantPoolEntry::ClassRef(class_ref.unwrap()));
+    check_no_resource(&mut ctx);
+
+    // 16384 shorts
+    let mut ctx = context::Context::new(&[
+        bytecode::sspush as BytecodeType,
+        0x40,
+        0,
+        bytecode::newarray as BytecodeType,
+        constants::T_SHORT as BytecodeType,
+    ]);
+    check_no_resource(&mut ctx);
+}
+
+/// unknown array types, and int arrays without the integer support, are rejected
+#[test]
+fn opcode_newarray_invalid_type_test() {
+    let datatoexecute: &BytecodeData = &[
+        bytecode::sconst_1 as BytecodeType,
+        bytecode::newarray as BytecodeType,
+        9,
+    ];
+    let mut ctx = context::Context::new(datatoexecute);
+    match interpreter::interpreter(&mut ctx) {
+        Err(InterpreterError::InvalidOperand) => {}
+        _ => panic!("newarray shall have been rejected"),
+    }
+
+    let datatoexecute: &BytecodeData = &[
+        bytecode::sconst_1 as BytecodeType,
+        bytecode::newarray as BytecodeType,
+        constants::T_INT as BytecodeType,
+    ];
+    let mut ctx = context::Context::new(datatoexecute);
+    ctx.int_support = false;
+    match interpreter::interpreter(&mut ctx) {
+        Err(InterpreterError::UnsupportedBytecode) => {}
+        _ => panic!("newarray shall have been rejected"),
+    }
+}
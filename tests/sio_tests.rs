@@ -0,0 +1,394 @@
+extern crate interpreterlib;
+
+mod common;
+
+use interpreterlib::{capcomponents, classes, constantpool, constants, context, exceptions,
+                     interpreter, jcvmerrors, natives, objects, traits};
+use interpreterlib::bytecodes::*;
+
+use common::{add_entries, add_method, add_natives, check_uncaught_exception, invoke_main,
+             leak_code};
+use interpreter::BytecodeType;
+use classes::{ImplementedInterface, JCVMClass, VirtualMethodTable, ACC_INTERFACE};
+use constantpool::ConstantPoolEntry;
+use exceptions::ExceptionHandler;
+use jcvmerrors::InterpreterError;
+use objects::JCVMObject;
+use traits::BufferAccessor;
+
+// contexts of the applets
+const SERVER_CONTEXT: i16 = 1;
+const CLIENT_CONTEXT: i16 = 2;
+
+const SERVER_AID: [u8; 6] = [0xA0, 0x00, 0x00, 0x00, 0x01, 0x01];
+const CLIENT_AID: [u8; 6] = [0xA0, 0x00, 0x00, 0x00, 0x02, 0x01];
+// other applet of the package of the client, registered first
+const OTHER_AID: [u8; 6] = [0xA0, 0x00, 0x00, 0x00, 0x02, 0x02];
+// other applet of the package of the server, registered first
+const OTHER_SERVER_AID: [u8; 6] = [0xA0, 0x00, 0x00, 0x00, 0x01, 0x02];
+
+// constant pool indexes
+const CP_GET_SIO: u8 = 0;
+const CP_PREVIOUS_CONTEXT: u8 = 1;
+const CP_LOYALTY: u8 = 2;
+const CP_BALANCE: u8 = 3;
+const CP_SERVER: u8 = 4;
+
+// tokens of the methods of the loyalty interface
+const POINTS: u8 = 0;
+const CALLER: u8 = 1;
+const FAIL: u8 = 2;
+const STOP: u8 = 3;
+
+///
+/// Result of a client method and state of the virtual machine once it returned
+///
+struct Execution {
+    result: Result<i16, InterpreterError>,
+    current_context: i16,
+    current_applet: i16,
+    frame_depth: usize,
+    server: i16,
+    server_aid: i16,
+    client_aid: i16,
+}
+
+///
+/// Runs main(serverAID) in the context of the client applet. The server applet (a loyalty
+/// applet with a balance of 10 points) returns itself as shareable interface object when the
+/// parameter is 0, null when it is 1, a new instance of its class when it is 3 and the AID of
+/// the client otherwise. Its shareable interface has the methods:
+/// - short points(short): returns the balance plus the given points
+/// - AID caller(): returns JCSystem.getPreviousContextAID()
+/// - short fail(): throws an ArithmeticException
+/// - void stop(): executes an opcode the interpreter does not implement, so that the state of
+///   the virtual machine is left as it was in the server
+///
+/// The exception handler, if any, is given by its range and handler offsets in main
+///
+fn run(main_body: &[BytecodeType], handler: Option<(usize, usize, usize)>) -> Execution {
+    let mut code = Vec::new();
+    let natives = add_natives(
+        &mut code,
+        &[natives::GET_APPLET_SHAREABLE_INTERFACE_OBJECT, natives::GET_PREVIOUS_CONTEXT_AID],
+    );
+
+    let get_sio = add_method(
+        &mut code,
+        2,
+        3,
+        0,
+        &[
+            bytecode::sload_2 as BytecodeType,
+            bytecode::ifne as BytecodeType,
+            4,
+            bytecode::aload_0 as BytecodeType,
+            bytecode::areturn as BytecodeType,
+            bytecode::sload_2 as BytecodeType,
+            bytecode::sconst_1 as BytecodeType,
+            bytecode::if_scmpne as BytecodeType,
+            4,
+            bytecode::aconst_null as BytecodeType,
+            bytecode::areturn as BytecodeType,
+            bytecode::sload_2 as BytecodeType,
+            bytecode::sconst_3 as BytecodeType,
+            bytecode::if_scmpne as BytecodeType,
+            6,
+            bytecode::new as BytecodeType,
+            0,
+            CP_SERVER as BytecodeType,
+            bytecode::areturn as BytecodeType,
+            bytecode::aload_1 as BytecodeType,
+            bytecode::areturn as BytecodeType,
+        ],
+    );
+    let points = add_method(
+        &mut code,
+        2,
+        2,
+        0,
+        &[
+            bytecode::getfield_s_this as BytecodeType,
+            CP_BALANCE as BytecodeType,
+            bytecode::sload_1 as BytecodeType,
+            bytecode::sadd as BytecodeType,
+            bytecode::sreturn as BytecodeType,
+        ],
+    );
+    let caller = add_method(
+        &mut code,
+        1,
+        1,
+        0,
+        &[
+            bytecode::invokestatic as BytecodeType,
+            0,
+            CP_PREVIOUS_CONTEXT as BytecodeType,
+            bytecode::areturn as BytecodeType,
+        ],
+    );
+    let fail = add_method(
+        &mut code,
+        2,
+        1,
+        0,
+        &[
+            bytecode::sconst_1 as BytecodeType,
+            bytecode::sconst_0 as BytecodeType,
+            bytecode::sdiv as BytecodeType,
+            bytecode::sreturn as BytecodeType,
+        ],
+    );
+    let stop = add_method(&mut code, 0, 1, 0, &[bytecode::END as BytecodeType]);
+    let main = add_method(&mut code, 3, 1, 0, main_body);
+    let code = leak_code(code);
+
+    let mut ctx = context::Context::new(code);
+    let mut loyalty = JCVMClass::new_interface(&[]);
+    loyalty.set_flags(ACC_INTERFACE | capcomponents::ACC_SHAREABLE);
+    let loyalty = ctx.class_manager.add_class(loyalty);
+
+    let mut server_class = JCVMClass::new(None);
+    server_class.set_declared_instance_size(1);
+    server_class.set_public_methods(VirtualMethodTable::new(
+        0,
+        vec![get_sio, get_sio, get_sio, points, caller, fail, stop],
+    ));
+    server_class.add_interface(ImplementedInterface {
+        interface: loyalty,
+        method_tokens: vec![3, 4, 5, 6],
+    });
+    let server_class = ctx.class_manager.add_class(server_class);
+    let client_class = ctx.class_manager.add_class(JCVMClass::new(None));
+
+    add_entries(
+        &mut ctx,
+        &[
+            ConstantPoolEntry::StaticMethodRef(natives[0]),
+            ConstantPoolEntry::StaticMethodRef(natives[1]),
+            ConstantPoolEntry::ClassRef(loyalty),
+            ConstantPoolEntry::InstanceFieldRef(server_class, 0),
+            ConstantPoolEntry::ClassRef(server_class),
+        ],
+    );
+    if let Some((start, length, handler_offset)) = handler {
+        ctx.exception_handlers.push(ExceptionHandler {
+            start_offset: main + 2 + start,
+            active_length: length,
+            handler_offset: main + 2 + handler_offset,
+            catch_type_index: None,
+        });
+    }
+
+    let other_server = JCVMObject::new_instance(SERVER_CONTEXT, 0, server_class, 2, true);
+    let other_server = ctx.object_manager.add_object(other_server) as i16;
+    let mut server = JCVMObject::new_instance(SERVER_CONTEXT, 0, server_class, 2, true);
+    server.write_s(0, 10).unwrap();
+    let server = ctx.object_manager.add_object(server) as i16;
+    let other = JCVMObject::new_instance(CLIENT_CONTEXT, 0, client_class, 0, true);
+    let other = ctx.object_manager.add_object(other) as i16;
+    let client = JCVMObject::new_instance(CLIENT_CONTEXT, 0, client_class, 0, true);
+    let client = ctx.object_manager.add_object(client) as i16;
+    ctx.applet_manager
+        .register(&mut ctx.object_manager, &mut ctx.tearing, &OTHER_SERVER_AID, other_server)
+        .unwrap();
+    let server_aid = ctx.applet_manager
+        .register(&mut ctx.object_manager, &mut ctx.tearing, &SERVER_AID, server)
+        .unwrap();
+    ctx.applet_manager
+        .register(&mut ctx.object_manager, &mut ctx.tearing, &OTHER_AID, other)
+        .unwrap();
+    let client_aid = ctx.applet_manager
+        .register(&mut ctx.object_manager, &mut ctx.tearing, &CLIENT_AID, client)
+        .unwrap();
+
+    ctx.current_context = CLIENT_CONTEXT;
+    ctx.current_applet = client;
+    let result = invoke_main(&mut ctx, main, &[server_aid], None);
+    Execution {
+        result,
+        current_context: ctx.current_context,
+        current_applet: ctx.current_applet,
+        frame_depth: ctx.frame_stack.depth(),
+        server,
+        server_aid,
+        client_aid,
+    }
+}
+
+///
+/// body of main calling JCSystem.getAppletShareableInterfaceObject(serverAID, parameter)
+/// followed by the given code
+///
+fn with_sio(parameter: BytecodeType, code: &[BytecodeType]) -> Vec<BytecodeType> {
+    let mut body = vec![
+        bytecode::aload_0 as BytecodeType,
+        bytecode::bspush as BytecodeType,
+        parameter,
+        bytecode::invokestatic as BytecodeType,
+        0,
+        CP_GET_SIO as BytecodeType,
+    ];
+    body.extend_from_slice(code);
+    body
+}
+
+/// the server applet is invoked in its own context through its shareable interface object
+#[test]
+fn sio_invocation_test() {
+    let execution = run(
+        &with_sio(
+            0,
+            &[
+                bytecode::bspush as BytecodeType,
+                5,
+                bytecode::invokeinterface as BytecodeType,
+                2,
+                0,
+                CP_LOYALTY as BytecodeType,
+                POINTS as BytecodeType,
+                bytecode::sreturn as BytecodeType,
+            ],
+        ),
+        None,
+    );
+    // the balance can only be read in the context of the server
+    assert_eq!(execution.result.unwrap(), 15);
+    assert_eq!(execution.current_context, CLIENT_CONTEXT);
+    assert_eq!(execution.frame_depth, 0);
+
+    // the server applet decides which object is returned
+    let execution = run(&with_sio(1, &[bytecode::areturn as BytecodeType]), None);
+    assert_eq!(execution.result.unwrap(), constants::NULL_HANDLE);
+
+    // the server is given the AID of the active applet, not of the first one of its context
+    let execution = run(&with_sio(2, &[bytecode::areturn as BytecodeType]), None);
+    assert_eq!(execution.result.unwrap(), execution.client_aid);
+
+    // null is returned for unknown applets
+    let mut body = with_sio(0, &[bytecode::areturn as BytecodeType]);
+    body[0] = bytecode::aconst_null as BytecodeType;
+    let execution = run(&body, None);
+    assert_eq!(execution.result.unwrap(), constants::NULL_HANDLE);
+
+    // the parameter is a byte
+    let mut body = with_sio(0, &[bytecode::areturn as BytecodeType]);
+    body[1] = bytecode::aconst_null as BytecodeType;
+    body[2] = bytecode::nop as BytecodeType;
+    let execution = run(&body, None);
+    assert_eq!(
+        execution.result,
+        Err(InterpreterError::InvalidVariableType(
+            constants::PrimitiveType::REFERENCE,
+            constants::PrimitiveType::SHORT,
+        ))
+    );
+}
+
+/// the previous context is the one of the caller of the shareable interface object
+#[test]
+fn sio_previous_context_test() {
+    let execution = run(
+        &with_sio(
+            0,
+            &[
+                bytecode::invokeinterface as BytecodeType,
+                1,
+                0,
+                CP_LOYALTY as BytecodeType,
+                CALLER as BytecodeType,
+                bytecode::areturn as BytecodeType,
+            ],
+        ),
+        None,
+    );
+    assert_eq!(execution.result.unwrap(), execution.client_aid);
+    assert_ne!(execution.client_aid, execution.server_aid);
+
+    // no context switch happened in the client, the JCRE was the previous context
+    let execution = run(
+        &[
+            bytecode::invokestatic as BytecodeType,
+            0,
+            CP_PREVIOUS_CONTEXT as BytecodeType,
+            bytecode::areturn as BytecodeType,
+        ],
+        None,
+    );
+    assert_eq!(execution.result.unwrap(), constants::NULL_HANDLE);
+}
+
+/// the context of the client is restored when an exception leaves the server
+#[test]
+fn sio_exception_test() {
+    let body = with_sio(
+        0,
+        &[
+            bytecode::invokeinterface as BytecodeType,
+            1,
+            0,
+            CP_LOYALTY as BytecodeType,
+            FAIL as BytecodeType,
+            bytecode::sreturn as BytecodeType,
+            // handler
+            bytecode::pop as BytecodeType,
+            bytecode::sconst_m1 as BytecodeType,
+            bytecode::sreturn as BytecodeType,
+        ],
+    );
+    let execution = run(&body, Some((6, 5, 12)));
+    assert_eq!(execution.result.unwrap(), -1);
+    assert_eq!(execution.current_context, CLIENT_CONTEXT);
+
+    let execution = run(&body, None);
+    // the exception is not caught
+    check_uncaught_exception(execution.result, exceptions::ARITHMETIC_EXCEPTION_CLASS, None);
+    assert_eq!(execution.current_context, CLIENT_CONTEXT);
+    assert_eq!(execution.frame_depth, 0);
+}
+
+///
+/// The applet owning the shareable interface object becomes active when it is invoked (not
+/// the first applet of its context), be it the server applet itself or an object it created
+///
+#[test]
+fn sio_active_applet_test() {
+    for parameter in &[0, 3] {
+        let execution = run(
+            &with_sio(
+                *parameter,
+                &[
+                    bytecode::invokeinterface as BytecodeType,
+                    1,
+                    0,
+                    CP_LOYALTY as BytecodeType,
+                    STOP as BytecodeType,
+                    bytecode::return_ as BytecodeType,
+                ],
+            ),
+            None,
+        );
+        assert_eq!(execution.result, Err(InterpreterError::UnsupportedBytecode));
+        assert_eq!(execution.current_context, SERVER_CONTEXT);
+        assert_eq!(execution.current_applet, execution.server);
+    }
+
+    // the object created by the server is not the server applet
+    let execution = run(
+        &with_sio(
+            3,
+            &[
+                bytecode::bspush as BytecodeType,
+                5,
+                bytecode::invokeinterface as BytecodeType,
+                2,
+                0,
+                CP_LOYALTY as BytecodeType,
+                POINTS as BytecodeType,
+                bytecode::sreturn as BytecodeType,
+            ],
+        ),
+        None,
+    );
+    assert_eq!(execution.result.unwrap(), 5);
+}
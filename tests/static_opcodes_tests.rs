@@ -0,0 +1,186 @@
+extern crate interpreterlib;
+
+use interpreterlib::{constantpool, constants, context, interpreter, jcvmerrors, staticfields,
+                     traits};
+use interpreterlib::bytecodes::*;
+
+use interpreter::{BytecodeData, BytecodeType};
+use constantpool::ConstantPoolEntry;
+use jcvmerrors::InterpreterError;
+use staticfields::{ArrayInit, StaticFieldComponent, StaticFieldImage};
+use traits::{BufferAccessor, HasType};
+
+///
+/// Static Field component used by the tests (12 bytes):
+/// - offset 0: short array {1, 2, 3}
+/// - offset 2: null reference
+/// - offset 4: byte and int initialized to 0 (offsets 4 and 5)
+/// - offset 9: byte 0x7F and short 0x1234 (offsets 9 and 10)
+///
+fn static_field_component() -> StaticFieldComponent {
+    StaticFieldComponent {
+        image_size: 12,
+        reference_count: 2,
+        array_init: vec![ArrayInit {
+            type_: constants::PrimitiveType::SHORT,
+            values: vec![0, 1, 0, 2, 0, 3],
+        }],
+        default_value_count: 5,
+        non_default_values: vec![0x7F, 0x12, 0x34],
+    }
+}
+
+///
+/// executes the given code with the static fields of the component, the constant pool
+/// entry i being the static field at offset i
+///
+fn execute_with_statics(
+    data: &BytecodeData,
+) -> (context::Context<'_>, Result<(), InterpreterError>) {
+    let mut ctx = context::Context::new(data);
+    let image = StaticFieldImage::from_component(
+        &static_field_component(),
+        0,
+        &mut ctx.object_manager,
+        &mut ctx.tearing,
+    ).unwrap();
+    let package = ctx.static_fields.add_image(image);
+    for offset in 0..12 {
+        ctx.constant_pools
+            .add_entry(ConstantPoolEntry::StaticFieldRef(package, offset));
+    }
+    let result = interpreter::interpreter(&mut ctx);
+    (ctx, result)
+}
+
+/// static field images are initialized from the Static Field component
+#[test]
+fn static_field_image_test() {
+    let (ctx, _) = execute_with_statics(&[]);
+    let image = ctx.static_fields.get_image(0).unwrap();
+    assert_eq!(image.len(), 12);
+
+    // the initialized array is allocated and referenced from the image
+    let arrayref = image.read_s(0).unwrap();
+    let array = ctx.object_manager.get_object(arrayref as usize).unwrap();
+    assert!(array.is_array());
+    assert!(array.is_of_type(constants::PrimitiveType::SHORT));
+    assert_eq!(array.read_s(4).unwrap(), 3);
+
+    assert_eq!(image.read_s(2).unwrap(), constants::NULL_HANDLE);
+    assert_eq!(image.read_b(4).unwrap(), 0);
+    assert_eq!(image.read_i(5).unwrap(), 0);
+    assert_eq!(image.read_b(9).unwrap(), 0x7F);
+    assert_eq!(image.read_s(10).unwrap(), 0x1234);
+}
+
+///
+/// Test getstatic_a, getstatic_b, getstatic_s and getstatic_i opcodes from standard specification
+///
+#[test]
+fn opcode_getstatic_test() {
+    let (mut ctx, _) = execute_with_statics(&[
+        bytecode::getstatic_a as BytecodeType,
+        0,
+        0,
+        bytecode::getstatic_b as BytecodeType,
+        0,
+        9,
+        bytecode::getstatic_s as BytecodeType,
+        0,
+        10,
+        bytecode::getstatic_i as BytecodeType,
+        0,
+        5,
+    ]);
+
+    assert_eq!(ctx.operand_stack.ipop().unwrap(), 0);
+    assert_eq!(ctx.operand_stack.spop().unwrap(), 0x1234);
+    // the byte is sign-extended to a short
+    assert_eq!(ctx.operand_stack.spop().unwrap(), 0x7F);
+    let arrayref = ctx.operand_stack
+        .pop_check_type(constants::PrimitiveType::REFERENCE)
+        .unwrap();
+    assert_eq!(arrayref.value, 1);
+    assert!(ctx.operand_stack.is_empty());
+}
+
+///
+/// Test putstatic_a, putstatic_b, putstatic_s and putstatic_i opcodes from standard specification
+///
+#[test]
+fn opcode_putstatic_test() {
+    let (ctx, _) = execute_with_statics(&[
+        bytecode::getstatic_a as BytecodeType,
+        0,
+        0,
+        bytecode::putstatic_a as BytecodeType,
+        0,
+        2,
+        bytecode::bspush as BytecodeType,
+        -3,
+        bytecode::putstatic_b as BytecodeType,
+        0,
+        4,
+        bytecode::iipush as BytecodeType,
+        0x12,
+        0x34,
+        0x56,
+        0x78,
+        bytecode::putstatic_i as BytecodeType,
+        0,
+        5,
+        // shorts stored in byte fields are truncated
+        bytecode::sspush as BytecodeType,
+        0x01,
+        0x02,
+        bytecode::putstatic_b as BytecodeType,
+        0,
+        9,
+        bytecode::sconst_m1 as BytecodeType,
+        bytecode::putstatic_s as BytecodeType,
+        0,
+        10,
+    ]);
+    assert!(ctx.operand_stack.is_empty());
+
+    let image = ctx.static_fields.get_image(0).unwrap();
+    assert_eq!(image.read_s(2).unwrap(), 1);
+    assert_eq!(image.read_b(4).unwrap(), -3);
+    assert_eq!(image.read_i(5).unwrap(), 0x1234_5678);
+    assert_eq!(image.read_b(9).unwrap(), 0x02);
+    assert_eq!(image.read_s(10).unwrap(), -1);
+}
+
+/// byte static fields are sign-extended to shorts when loaded
+#[test]
+fn opcode_getstatic_b_sign_extension_test() {
+    let (mut ctx, _) = execute_with_statics(&[
+        bytecode::bspush as BytecodeType,
+        -3,
+        bytecode::putstatic_b as BytecodeType,
+        0,
+        4,
+        bytecode::getstatic_b as BytecodeType,
+        0,
+        4,
+    ]);
+    assert_eq!(ctx.operand_stack.spop().unwrap(), -3);
+    assert!(ctx.operand_stack.is_empty());
+}
+
+/// static fields outside of the image or unresolved references are rejected
+#[test]
+fn opcode_getstatic_invalid_test() {
+    let (_, result) = execute_with_statics(&[bytecode::getstatic_i as BytecodeType, 0, 10]);
+    match result {
+        Err(InterpreterError::IndexOutOfBound) => {}
+        _ => panic!("static field access shall have been rejected"),
+    }
+
+    let (_, result) = execute_with_statics(&[bytecode::getstatic_s as BytecodeType, 0, 12]);
+    match result {
+        Err(InterpreterError::InvalidConstantPoolEntry) => {}
+        _ => panic!("static field access shall have been rejected"),
+    }
+}
@@ -1,15 +1,14 @@
 extern crate interpreterlib;
 
-use interpreterlib::{constants, context, frame, interpreter, objects, stack, traits};
+use interpreterlib::{constants, context, frame, interpreter, jcvmerrors, stack};
 use interpreterlib::bytecodes::*;
 
 use interpreter::{BytecodeData, BytecodeType};
+use jcvmerrors::InterpreterError;
 use stack::{StackElementType, StackEntry};
-use objects::JCVMObject;
-use traits::BufferAccessor;
 
-pub fn execute_with_context(mut ctx: &mut context::Context) {
-    let _result = interpreter::interpreter(&mut ctx);
+pub fn execute_with_context(ctx: &mut context::Context) {
+    let _result = interpreter::interpreter(ctx);
 }
 
 pub fn execute_bytecode<'a>(data: &'a BytecodeData) -> context::Context<'a> {
@@ -29,10 +28,10 @@ fn opcode_xstore_test(curbytecode: bytecode, type_: constants::PrimitiveType, id
     // create context
     let mut ctx = context::Context::new(datatoexecute);
     // prepare expected value
-    let exp_value = StackEntry::from_values((0xA55A as u16) as StackElementType, type_);
+    let exp_value = StackEntry::from_values(0xA55A_u16 as StackElementType, type_);
 
-    ctx.operand_stack.push(exp_value);
-    ctx.operand_stack.push(exp_value);
+    ctx.operand_stack.push(exp_value).unwrap();
+    ctx.operand_stack.push(exp_value).unwrap();
 
     ctx.frame_stack.push(frame::Frame::new(idx + 3));
 
@@ -61,6 +60,35 @@ fn opcode_xstore_test(curbytecode: bytecode, type_: constants::PrimitiveType, id
     }
 }
 
+///
+/// checks that xstore rejects values of the wrong type, without modifying the locals
+///
+fn opcode_xstore_bad_type_test(
+    curbytecode: bytecode,
+    type_: constants::PrimitiveType,
+    expected_type: constants::PrimitiveType,
+) {
+    let datatoexecute: &BytecodeData = &[curbytecode as BytecodeType, 1];
+    let mut ctx = context::Context::new(datatoexecute);
+    let value = StackEntry::from_values(0xA55A_u16 as StackElementType, type_);
+    ctx.operand_stack.push(value).unwrap();
+    ctx.operand_stack.push(value).unwrap();
+    ctx.frame_stack.push(frame::Frame::new(4));
+
+    match interpreter::interpreter(&mut ctx) {
+        Err(InterpreterError::InvalidVariableType(found, expected)) => {
+            assert_eq!(found, type_);
+            assert_eq!(expected, expected_type);
+        }
+        _ => panic!("xstore shall have been rejected"),
+    }
+    assert!(ctx.frame_stack
+        .top()
+        .unwrap()
+        .get_local_check_type(1, type_)
+        .is_err());
+}
+
 ///
 /// Test sload opcode from standard specification
 ///
@@ -69,11 +97,14 @@ fn opcode_astore_test_nominal() {
     opcode_xstore_test(bytecode::astore, constants::PrimitiveType::REFERENCE, 1);
 }
 
-#[should_panic]
 #[test]
 fn opcode_astore_test_bad_type() {
     // bad entry types in operand stack should raise an error
-    opcode_xstore_test(bytecode::astore, constants::PrimitiveType::SHORT, 1);
+    opcode_xstore_bad_type_test(
+        bytecode::astore,
+        constants::PrimitiveType::SHORT,
+        constants::PrimitiveType::REFERENCE,
+    );
 }
 
 #[test]
@@ -82,10 +113,13 @@ fn opcode_sstore_test_nominal() {
 }
 
 #[test]
-#[should_panic]
 fn opcode_sstore_test_bad_type() {
     // bad entry types in operand stack should raise an error
-    opcode_xstore_test(bytecode::sstore, constants::PrimitiveType::REFERENCE, 1);
+    opcode_xstore_bad_type_test(
+        bytecode::sstore,
+        constants::PrimitiveType::REFERENCE,
+        constants::PrimitiveType::SHORT,
+    );
 }
 
 #[test]
@@ -94,10 +128,13 @@ fn opcode_istore_test_nominal() {
 }
 
 #[test]
-#[should_panic]
 fn opcode_istore_test_bad_type() {
     // bad entry types in operand stack should raise an error
-    opcode_xstore_test(bytecode::istore, constants::PrimitiveType::SHORT, 1);
+    opcode_xstore_bad_type_test(
+        bytecode::istore,
+        constants::PrimitiveType::SHORT,
+        constants::PrimitiveType::INTEGER,
+    );
 }
 
 ///
@@ -110,10 +147,10 @@ fn opcode_xstore_x_test(curbytecode: bytecode, type_: constants::PrimitiveType,
     // create context
     let mut ctx = context::Context::new(datatoexecute);
     // prepare expected value
-    let exp_value = StackEntry::from_values((0xA55A as u16) as StackElementType, type_);
+    let exp_value = StackEntry::from_values(0xA55A_u16 as StackElementType, type_);
 
-    ctx.operand_stack.push(exp_value);
-    ctx.operand_stack.push(exp_value);
+    ctx.operand_stack.push(exp_value).unwrap();
+    ctx.operand_stack.push(exp_value).unwrap();
 
     ctx.frame_stack.push(frame::Frame::new(idx + 3));
 
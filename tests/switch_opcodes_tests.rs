@@ -0,0 +1,197 @@
+extern crate interpreterlib;
+
+use interpreterlib::{constants, context, interpreter, jcvmerrors, stack};
+use interpreterlib::bytecodes::*;
+
+use interpreter::BytecodeType;
+use jcvmerrors::InterpreterError;
+use stack::StackEntry;
+
+pub fn execute_with_context(ctx: &mut context::Context) {
+    let _result = interpreter::interpreter(ctx);
+}
+
+///
+/// The switch targets are consecutive bspush of 1, 2, 3 and 9 (default) at the end of the code.
+/// Executing from one target pushes all the following values, so the first
+/// value pushed identifies the target of the switch.
+///
+const TARGETS: [BytecodeType; 8] = [
+    bytecode::bspush as BytecodeType,
+    1,
+    bytecode::bspush as BytecodeType,
+    2,
+    bytecode::bspush as BytecodeType,
+    3,
+    bytecode::bspush as BytecodeType,
+    9,
+];
+
+fn push_short(code: &mut Vec<BytecodeType>, value: i16) {
+    code.push((value >> 8) as BytecodeType);
+    code.push(value as BytecodeType);
+}
+
+fn push_int(code: &mut Vec<BytecodeType>, value: i32) {
+    code.push((value >> 24) as BytecodeType);
+    code.push((value >> 16) as BytecodeType);
+    code.push((value >> 8) as BytecodeType);
+    code.push(value as BytecodeType);
+}
+
+///
+/// executes the given switch code with the key pushed on the stack and returns the target reached
+///
+fn execute_switch(code: &[BytecodeType], key: StackEntry, int_key: Option<i32>) -> i16 {
+    let mut ctx = context::Context::new(code);
+    match int_key {
+        Some(value) => ctx.operand_stack.ipush(value).unwrap(),
+        None => ctx.operand_stack.push(key).unwrap(),
+    }
+    execute_with_context(&mut ctx);
+
+    let mut first_pushed = None;
+    while ctx.operand_stack.top().is_ok() {
+        first_pushed = Some(ctx.operand_stack.spop().unwrap());
+    }
+    first_pushed.unwrap()
+}
+
+///
+/// builds a tableswitch for [low; high] where each index jumps to the matching TARGETS entry
+///
+fn build_tableswitch(bc: bytecode, low: i32, high: i32) -> Vec<BytecodeType> {
+    let int_keys = bc == bytecode::itableswitch;
+    let count = (high - low + 1) as i16;
+    let header_size = 1 + 2 + if int_keys { 8 } else { 4 };
+    let targets_offset = header_size + 2 * count;
+
+    let mut code = vec![bc as BytecodeType];
+    // default is the last target
+    push_short(&mut code, targets_offset + 6);
+    if int_keys {
+        push_int(&mut code, low);
+        push_int(&mut code, high);
+    } else {
+        push_short(&mut code, low as i16);
+        push_short(&mut code, high as i16);
+    }
+    for i in 0..count {
+        push_short(&mut code, targets_offset + 2 * i);
+    }
+    code.extend_from_slice(&TARGETS);
+    code
+}
+
+///
+/// builds a lookupswitch matching the given keys to the consecutive TARGETS entries
+///
+fn build_lookupswitch(bc: bytecode, keys: &[i32]) -> Vec<BytecodeType> {
+    let int_keys = bc == bytecode::ilookupswitch;
+    let pair_size = if int_keys { 6 } else { 4 };
+    let targets_offset = 1 + 2 + 2 + pair_size * keys.len() as i16;
+
+    let mut code = vec![bc as BytecodeType];
+    push_short(&mut code, targets_offset + 6);
+    push_short(&mut code, keys.len() as i16);
+    for (i, key) in keys.iter().enumerate() {
+        if int_keys {
+            push_int(&mut code, *key);
+        } else {
+            push_short(&mut code, *key as i16);
+        }
+        push_short(&mut code, targets_offset + 2 * i as i16);
+    }
+    code.extend_from_slice(&TARGETS);
+    code
+}
+
+fn short(value: i16) -> StackEntry {
+    StackEntry::from_values(value, constants::PrimitiveType::SHORT)
+}
+
+///
+/// Test stableswitch opcode from standard specification
+///
+#[test]
+fn opcode_stableswitch_test() {
+    let code = build_tableswitch(bytecode::stableswitch, -1, 1);
+    assert_eq!(execute_switch(&code, short(-1), None), 1);
+    assert_eq!(execute_switch(&code, short(0), None), 2);
+    assert_eq!(execute_switch(&code, short(1), None), 3);
+    assert_eq!(execute_switch(&code, short(-2), None), 9);
+    assert_eq!(execute_switch(&code, short(2), None), 9);
+}
+
+///
+/// Test itableswitch opcode from standard specification
+///
+#[test]
+fn opcode_itableswitch_test() {
+    let code = build_tableswitch(bytecode::itableswitch, 0x10000, 0x10002);
+    assert_eq!(execute_switch(&code, short(0), Some(0x10000)), 1);
+    assert_eq!(execute_switch(&code, short(0), Some(0x10002)), 3);
+    assert_eq!(execute_switch(&code, short(0), Some(0x0002)), 9);
+}
+
+///
+/// Test slookupswitch opcode from standard specification
+///
+#[test]
+fn opcode_slookupswitch_test() {
+    let code = build_lookupswitch(bytecode::slookupswitch, &[-0x100, 0x20, 0x7FFF]);
+    assert_eq!(execute_switch(&code, short(-0x100), None), 1);
+    assert_eq!(execute_switch(&code, short(0x20), None), 2);
+    assert_eq!(execute_switch(&code, short(0x7FFF), None), 3);
+    assert_eq!(execute_switch(&code, short(0x21), None), 9);
+
+    // without any pair, the default branch is always taken
+    let code = build_lookupswitch(bytecode::slookupswitch, &[]);
+    assert_eq!(execute_switch(&code, short(0), None), 9);
+}
+
+///
+/// Test ilookupswitch opcode from standard specification
+///
+#[test]
+fn opcode_ilookupswitch_test() {
+    let code = build_lookupswitch(bytecode::ilookupswitch, &[-0x10000, 0x10000, 0x20000]);
+    assert_eq!(execute_switch(&code, short(0), Some(-0x10000)), 1);
+    assert_eq!(execute_switch(&code, short(0), Some(0x20000)), 3);
+    assert_eq!(execute_switch(&code, short(0), Some(0)), 9);
+}
+
+/// malformed switch tables are reported as errors
+#[test]
+fn opcode_switch_malformed_test() {
+    let check_rejected = |code: &[BytecodeType], expected_error: fn(&InterpreterError) -> bool| {
+        let mut ctx = context::Context::new(code);
+        ctx.operand_stack.spush(0).unwrap();
+        match interpreter::interpreter(&mut ctx) {
+            Err(ref e) if expected_error(e) => {}
+            _ => panic!("switch table shall have been rejected"),
+        }
+    };
+
+    // low > high
+    let mut code = vec![bytecode::stableswitch as BytecodeType];
+    push_short(&mut code, 7);
+    push_short(&mut code, 1);
+    push_short(&mut code, 0);
+    check_rejected(&code, |e| matches!(*e, InterpreterError::MalformedSwitchTable));
+
+    // truncated table
+    let mut code = build_tableswitch(bytecode::stableswitch, 0, 3);
+    code.truncate(10);
+    check_rejected(&code, |e| matches!(*e, InterpreterError::EndOfStream));
+
+    // unsorted matches
+    let code = build_lookupswitch(bytecode::slookupswitch, &[2, 1]);
+    check_rejected(&code, |e| matches!(*e, InterpreterError::MalformedSwitchTable));
+
+    // branch outside of the code
+    let mut code = vec![bytecode::slookupswitch as BytecodeType];
+    push_short(&mut code, 0x100);
+    push_short(&mut code, 0);
+    check_rejected(&code, |e| matches!(*e, InterpreterError::InvalidBranchTarget));
+}
@@ -0,0 +1,308 @@
+extern crate interpreterlib;
+
+mod common;
+
+use interpreterlib::{classes, constantpool, constants, context, interpreter, jcvmerrors,
+                     natives, objects, staticfields, tearing, traits, transactions};
+use interpreterlib::bytecodes::*;
+
+use common::{add_method, add_natives, invoke_main, leak_code};
+use interpreter::BytecodeType;
+use classes::JCVMClass;
+use constants::PrimitiveType;
+use constantpool::ConstantPoolEntry;
+use jcvmerrors::InterpreterError;
+use objects::JCVMObject;
+use staticfields::{ArrayInit, StaticFieldComponent, StaticFieldImage};
+use traits::BufferAccessor;
+use transactions::Location;
+
+// constant pool indexes
+const CP_BEGIN: u8 = 0;
+const CP_COMMIT: u8 = 1;
+const CP_ABORT: u8 = 2;
+const CP_BALANCE: u8 = 3;
+const CP_COUNTER: u8 = 4;
+
+// references to the objects created by the card
+const WALLET: i16 = 1;
+const SAVINGS: i16 = 2;
+const TRANSIENT: i16 = 3;
+
+// the balance of the wallet and the savings always add up to this amount
+const TOTAL: i16 = 100;
+const COUNTER: i32 = 0x0102_0304;
+
+///
+/// Card holding a wallet (persistent object with a short balance of 60 and an int counter
+/// of 0), savings (persistent short array whose element 0 is 40) and a transient short array
+///
+struct Card {
+    ctx: context::Context<'static>,
+    main: usize,
+}
+
+///
+/// main(wallet, savings, transient): the given code followed by the update of the int counter
+/// of the wallet (outside of any transaction), returning 0
+///
+fn card(body: &[BytecodeType]) -> Card {
+    let mut code = Vec::new();
+    let natives_offsets = add_natives(
+        &mut code,
+        &[
+            natives::BEGIN_TRANSACTION,
+            natives::COMMIT_TRANSACTION,
+            natives::ABORT_TRANSACTION,
+        ],
+    );
+    let main = add_method(&mut code, 5, 3, 0, body);
+    code.extend_from_slice(&[
+        bytecode::aload_0 as BytecodeType,
+        bytecode::iipush as BytecodeType,
+        (COUNTER >> 24) as BytecodeType,
+        (COUNTER >> 16) as BytecodeType,
+        (COUNTER >> 8) as BytecodeType,
+        COUNTER as BytecodeType,
+        bytecode::putfield_i as BytecodeType,
+        CP_COUNTER as BytecodeType,
+        bytecode::sconst_0 as BytecodeType,
+        bytecode::sreturn as BytecodeType,
+    ]);
+    let code = leak_code(code);
+
+    let mut ctx = context::Context::new(code);
+    let mut class = JCVMClass::new(None);
+    class.set_declared_instance_size(3);
+    let class = ctx.class_manager.add_class(class);
+    for offset in natives_offsets {
+        ctx.constant_pools
+            .add_entry(ConstantPoolEntry::StaticMethodRef(offset));
+    }
+    ctx.constant_pools
+        .add_entry(ConstantPoolEntry::InstanceFieldRef(class, 0));
+    ctx.constant_pools
+        .add_entry(ConstantPoolEntry::InstanceFieldRef(class, 1));
+
+    let mut wallet = JCVMObject::new_instance(0, 0, class, 6, true);
+    wallet.write_s(0, 60).unwrap();
+    ctx.object_manager.add_object(wallet);
+    let mut savings = JCVMObject::new_array(0, 0, PrimitiveType::SHORT, 2, true);
+    savings.write_s(0, TOTAL - 60).unwrap();
+    ctx.object_manager.add_object(savings);
+    let mut transient = JCVMObject::new_array(0, 0, PrimitiveType::SHORT, 2, false);
+    transient.write_s(0, 1).unwrap();
+    ctx.object_manager.add_object(transient);
+    Card { ctx, main }
+}
+
+impl Card {
+    fn run(&mut self) -> Result<i16, InterpreterError> {
+        let arguments = [WALLET, SAVINGS, TRANSIENT];
+        invoke_main(&mut self.ctx, self.main, &arguments, Some(PrimitiveType::SHORT))
+    }
+
+    fn read(&self, objectref: i16) -> ObjectState {
+        let object = self.ctx.object_manager.get_object(objectref as usize).unwrap();
+        ObjectState {
+            short: object.read_s(0).unwrap(),
+            int: object.read_i(2).ok(),
+        }
+    }
+}
+
+/// values of an object once the card returned
+struct ObjectState {
+    // first short of the object
+    short: i16,
+    // int at offset 2, if any
+    int: Option<i32>,
+}
+
+///
+/// transfers 5 from the wallet to the savings in a transaction, which is committed or aborted
+/// with the given native method
+///
+fn transfer(end: u8) -> Vec<BytecodeType> {
+    vec![
+        bytecode::invokestatic as BytecodeType,
+        0,
+        CP_BEGIN as BytecodeType,
+        // wallet.balance -= 5
+        bytecode::aload_0 as BytecodeType,
+        bytecode::aload_0 as BytecodeType,
+        bytecode::getfield_s as BytecodeType,
+        CP_BALANCE as BytecodeType,
+        bytecode::sconst_5 as BytecodeType,
+        bytecode::ssub as BytecodeType,
+        bytecode::putfield_s as BytecodeType,
+        CP_BALANCE as BytecodeType,
+        // savings[0] += 5
+        bytecode::aload_1 as BytecodeType,
+        bytecode::sconst_0 as BytecodeType,
+        bytecode::aload_1 as BytecodeType,
+        bytecode::sconst_0 as BytecodeType,
+        bytecode::saload as BytecodeType,
+        bytecode::sconst_5 as BytecodeType,
+        bytecode::sadd as BytecodeType,
+        bytecode::sastore as BytecodeType,
+        // transient[0] = 2
+        bytecode::aload_2 as BytecodeType,
+        bytecode::sconst_0 as BytecodeType,
+        bytecode::sconst_2 as BytecodeType,
+        bytecode::sastore as BytecodeType,
+        bytecode::invokestatic as BytecodeType,
+        0,
+        end as BytecodeType,
+    ]
+}
+
+///
+/// Tears the card after each of the persistent writes done by the given code, resets it and
+/// checks the persistent state with the given function (called with the number of writes
+/// done before the card was torn). Returns the number of persistent writes of the code
+///
+fn sweep(body: &[BytecodeType], check: &dyn Fn(&Card, usize)) -> usize {
+    let mut reference = card(body);
+    assert_eq!(reference.run().unwrap(), 0);
+    let writes = reference.ctx.tearing.persistent_writes();
+
+    for torn_after in 0..writes {
+        let mut card = card(body);
+        card.ctx.tearing.arm(torn_after);
+        match card.run() {
+            Err(InterpreterError::CardTorn) => {}
+            result => panic!("the card shall have been torn, got {:?}", result),
+        }
+        assert!(card.ctx.tearing.is_torn());
+        tearing::reset(&mut card.ctx).unwrap();
+        assert!(!card.ctx.tearing.is_torn());
+        assert_eq!(card.ctx.frame_stack.depth(), 0);
+        assert_eq!(card.read(TRANSIENT).short, 0);
+        assert_eq!(card.read(WALLET).short + card.read(SAVINGS).short, TOTAL);
+        check(&card, torn_after);
+
+        // the card works again once reset
+        assert_eq!(card.run().unwrap(), 0);
+    }
+
+    // the card is not torn when the code does fewer writes than expected
+    let mut card = card(body);
+    card.ctx.tearing.arm(writes);
+    assert_eq!(card.run().unwrap(), 0);
+    writes
+}
+
+///
+/// writes are only counted for persistent objects and static fields, and fail once the card
+/// is torn. Each context has its own simulation
+///
+#[test]
+fn tearing_hook_test() {
+    let mut ctx = context::Context::new(&[]);
+    let mut objects = Vec::new();
+    for persistent in &[true, false] {
+        let array = JCVMObject::new_array(0, 0, PrimitiveType::BYTE, 4, *persistent);
+        objects.push(Location::Object(ctx.object_manager.add_object(array) as i16));
+    }
+    let (persistent, transient) = (objects[0], objects[1]);
+    let component = StaticFieldComponent {
+        image_size: 2,
+        reference_count: 0,
+        array_init: vec![],
+        default_value_count: 2,
+        non_default_values: vec![],
+    };
+    let image =
+        StaticFieldImage::from_component(&component, 0, &mut ctx.object_manager, &mut ctx.tearing)
+            .unwrap();
+    let statics = Location::Static(ctx.static_fields.add_image(image));
+
+    ctx.tearing.arm(4);
+    transactions::write(&mut ctx, persistent, 0, PrimitiveType::SHORT, 0x0102).unwrap();
+    transactions::write(&mut ctx, transient, 0, PrimitiveType::INTEGER, 1).unwrap();
+    transactions::write(&mut ctx, statics, 0, PrimitiveType::SHORT, 3).unwrap();
+    assert_eq!(ctx.tearing.persistent_writes(), 4);
+    assert!(!ctx.tearing.is_torn());
+    assert_eq!(context::Context::new(&[]).tearing.persistent_writes(), 0);
+
+    match transactions::write(&mut ctx, persistent, 2, PrimitiveType::BYTE, 3) {
+        Err(InterpreterError::CardTorn) => {}
+        result => panic!("the card shall have been torn, got {:?}", result),
+    }
+    assert!(ctx.tearing.is_torn());
+    assert!(transactions::write(&mut ctx, statics, 1, PrimitiveType::BYTE, 3).is_err());
+    let object = ctx.object_manager.get_object(1).unwrap();
+    assert_eq!(object.read_i(0).unwrap(), 0x0102_0000);
+
+    ctx.tearing.disarm();
+    transactions::write(&mut ctx, persistent, 2, PrimitiveType::BYTE, 3).unwrap();
+    assert_eq!(ctx.tearing.persistent_writes(), 1);
+}
+
+///
+/// the initial values of static field images and the AID objects of applets are persistent
+/// writes as well
+///
+#[test]
+fn tearing_initialization_test() {
+    let component = StaticFieldComponent {
+        image_size: 3,
+        reference_count: 1,
+        array_init: vec![ArrayInit {
+            type_: PrimitiveType::BYTE,
+            values: vec![1, 2],
+        }],
+        default_value_count: 0,
+        non_default_values: vec![5],
+    };
+    let mut ctx = context::Context::new(&[]);
+    // the array, the reference to it, then the byte field
+    StaticFieldImage::from_component(&component, 0, &mut ctx.object_manager, &mut ctx.tearing)
+        .unwrap();
+    assert_eq!(ctx.tearing.persistent_writes(), 5);
+
+    ctx.tearing.arm(4);
+    let result =
+        StaticFieldImage::from_component(&component, 0, &mut ctx.object_manager, &mut ctx.tearing);
+    assert_eq!(result.err(), Some(InterpreterError::CardTorn));
+
+    ctx.tearing.disarm();
+    let applet = JCVMObject::new_instance(1, 0, classes::OBJECT_CLASS, 0, true);
+    let applet = ctx.object_manager.add_object(applet) as i16;
+    ctx.applet_manager
+        .register(&mut ctx.object_manager, &mut ctx.tearing, &[1, 2, 3, 4, 5], applet)
+        .unwrap();
+    assert_eq!(ctx.tearing.persistent_writes(), 5);
+
+    ctx.tearing.arm(0);
+    let result = ctx.applet_manager
+        .register(&mut ctx.object_manager, &mut ctx.tearing, &[1, 2, 3], applet);
+    assert_eq!(result, Err(InterpreterError::CardTorn));
+}
+
+/// the transaction is undone when the card is torn before it is committed, and single
+/// updates are atomic
+#[test]
+fn tearing_commit_test() {
+    let writes = sweep(&transfer(CP_COMMIT), &|card, torn_after| {
+        let committed = torn_after >= 4;
+        assert_eq!(card.read(WALLET).short, if committed { 55 } else { 60 });
+        // the counter is written after the transaction
+        assert_eq!(card.read(WALLET).int, Some(0));
+    });
+    // 2 shorts in the transaction, then the int counter
+    assert_eq!(writes, 8);
+}
+
+/// an abort interrupted by tearing is resumed by the recovery
+#[test]
+fn tearing_abort_test() {
+    let writes = sweep(&transfer(CP_ABORT), &|card, _| {
+        assert_eq!(card.read(WALLET).short, 60);
+        assert_eq!(card.read(SAVINGS).short, TOTAL - 60);
+        assert_eq!(card.read(WALLET).int, Some(0));
+    });
+    // the values restored by the abort are written as well
+    assert_eq!(writes, 12);
+}
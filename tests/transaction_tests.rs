@@ -0,0 +1,287 @@
+extern crate interpreterlib;
+
+mod common;
+
+use interpreterlib::{classes, constantpool, constants, context, exceptions, interpreter,
+                     jcvmerrors, natives, objects, staticfields, traits, transactions};
+use interpreterlib::bytecodes::*;
+
+use common::{add_entries, add_method, add_natives, check_uncaught_exception, invoke_main,
+             leak_code};
+use interpreter::BytecodeType;
+use classes::JCVMClass;
+use constantpool::ConstantPoolEntry;
+use jcvmerrors::InterpreterError;
+use objects::JCVMObject;
+use staticfields::{StaticFieldComponent, StaticFieldImage};
+use traits::BufferAccessor;
+
+// constant pool indexes
+const CP_BEGIN: u8 = 0;
+const CP_COMMIT: u8 = 1;
+const CP_ABORT: u8 = 2;
+const CP_DEPTH: u8 = 3;
+const CP_UNUSED: u8 = 4;
+const CP_FIELD: u8 = 5;
+const CP_INT_FIELD: u8 = 6;
+const CP_STATIC: u8 = 7;
+
+// references to the objects created by run
+const OBJECT: i16 = 1;
+const ARRAY: i16 = 2;
+const TRANSIENT_ARRAY: i16 = 3;
+
+///
+/// Result of main and values of the fields and array elements once it returned
+///
+struct Execution {
+    result: Result<i16, InterpreterError>,
+    field: i16,
+    int_field: i32,
+    static_field: i16,
+    element: i16,
+    transient_element: i16,
+    depth: i8,
+}
+
+///
+/// Runs main(object, array, transientArray) with a commit buffer of the given capacity.
+/// The object is persistent, has a short field initialized to 1 and an int field initialized
+/// to 2, and the static field is initialized to 3. The element 0 of the persistent short array
+/// is initialized to 4, the element 0 of the transient one to 5
+///
+fn run(body: &[BytecodeType], capacity: usize) -> Execution {
+    let mut code = Vec::new();
+    let natives_offsets = add_natives(
+        &mut code,
+        &[
+            natives::BEGIN_TRANSACTION,
+            natives::COMMIT_TRANSACTION,
+            natives::ABORT_TRANSACTION,
+            natives::GET_TRANSACTION_DEPTH,
+            natives::GET_UNUSED_COMMIT_CAPACITY,
+        ],
+    );
+    let main = add_method(&mut code, 4, 3, 0, body);
+    let code = leak_code(code);
+
+    let mut ctx = context::Context::new(code);
+    ctx.transaction.set_capacity(capacity);
+    let mut class = JCVMClass::new(None);
+    class.set_declared_instance_size(3);
+    let class = ctx.class_manager.add_class(class);
+    let component = StaticFieldComponent {
+        image_size: 2,
+        reference_count: 0,
+        array_init: vec![],
+        default_value_count: 0,
+        non_default_values: vec![0, 3],
+    };
+    let image =
+        StaticFieldImage::from_component(&component, 0, &mut ctx.object_manager, &mut ctx.tearing)
+            .unwrap();
+    let package = ctx.static_fields.add_image(image);
+
+    for offset in natives_offsets {
+        ctx.constant_pools
+            .add_entry(ConstantPoolEntry::StaticMethodRef(offset));
+    }
+    add_entries(
+        &mut ctx,
+        &[
+            ConstantPoolEntry::InstanceFieldRef(class, 0),
+            ConstantPoolEntry::InstanceFieldRef(class, 1),
+            ConstantPoolEntry::StaticFieldRef(package, 0),
+        ],
+    );
+
+    let mut object = JCVMObject::new_instance(0, 0, class, 6, true);
+    object.write_s(0, 1).unwrap();
+    object.write_i(2, 2).unwrap();
+    ctx.object_manager.add_object(object);
+    let mut array = JCVMObject::new_array(0, 0, constants::PrimitiveType::SHORT, 4, true);
+    array.write_s(0, 4).unwrap();
+    ctx.object_manager.add_object(array);
+    let mut array = JCVMObject::new_array(0, 0, constants::PrimitiveType::SHORT, 4, false);
+    array.write_s(0, 5).unwrap();
+    ctx.object_manager.add_object(array);
+
+    let arguments = [OBJECT, ARRAY, TRANSIENT_ARRAY];
+    let result = invoke_main(&mut ctx, main, &arguments, Some(constants::PrimitiveType::SHORT));
+    let read_s = |ctx: &context::Context, objectref: i16, offset: usize| {
+        ctx.object_manager
+            .get_object(objectref as usize)
+            .unwrap()
+            .read_s(offset)
+            .unwrap()
+    };
+    Execution {
+        result,
+        field: read_s(&ctx, OBJECT, 0),
+        int_field: ctx.object_manager
+            .get_object(OBJECT as usize)
+            .unwrap()
+            .read_i(2)
+            .unwrap(),
+        static_field: ctx.static_fields.get_image(package).unwrap().read_s(0).unwrap(),
+        element: read_s(&ctx, ARRAY, 0),
+        transient_element: read_s(&ctx, TRANSIENT_ARRAY, 0),
+        depth: ctx.transaction.depth(),
+    }
+}
+
+/// invocation of the native method at the given constant pool index
+fn native(index: u8) -> [BytecodeType; 3] {
+    [bytecode::invokestatic as BytecodeType, 0, index as BytecodeType]
+}
+
+///
+/// code writing 10 to the short field, 11 to the int field, 12 to the static field, 13 to the
+/// element of the persistent array and 14 to the element of the transient array
+///
+fn updates() -> Vec<BytecodeType> {
+    vec![
+        bytecode::aload_0 as BytecodeType,
+        bytecode::bspush as BytecodeType,
+        10,
+        bytecode::putfield_s as BytecodeType,
+        CP_FIELD as BytecodeType,
+        bytecode::aload_0 as BytecodeType,
+        bytecode::bipush as BytecodeType,
+        11,
+        bytecode::putfield_i as BytecodeType,
+        CP_INT_FIELD as BytecodeType,
+        bytecode::bspush as BytecodeType,
+        12,
+        bytecode::putstatic_s as BytecodeType,
+        0,
+        CP_STATIC as BytecodeType,
+        bytecode::aload_1 as BytecodeType,
+        bytecode::sconst_0 as BytecodeType,
+        bytecode::bspush as BytecodeType,
+        13,
+        bytecode::sastore as BytecodeType,
+        bytecode::aload_2 as BytecodeType,
+        bytecode::sconst_0 as BytecodeType,
+        bytecode::bspush as BytecodeType,
+        14,
+        bytecode::sastore as BytecodeType,
+    ]
+}
+
+/// body of main made of the given parts, returning the transaction depth
+fn body(parts: &[&[BytecodeType]]) -> Vec<BytecodeType> {
+    let mut body: Vec<BytecodeType> = parts.concat();
+    body.extend_from_slice(&native(CP_DEPTH));
+    body.push(bytecode::sreturn as BytecodeType);
+    body
+}
+
+fn check_transaction_exception(result: Result<i16, InterpreterError>, reason: i16) {
+    check_uncaught_exception(result, exceptions::TRANSACTION_EXCEPTION_CLASS, Some(reason));
+}
+
+/// the updates of a committed transaction are kept
+#[test]
+fn transaction_commit_test() {
+    let execution = run(
+        &body(&[&native(CP_BEGIN), &updates(), &native(CP_COMMIT)]),
+        transactions::DEFAULT_COMMIT_CAPACITY,
+    );
+    assert_eq!(execution.result.unwrap(), 0);
+    assert_eq!(execution.field, 10);
+    assert_eq!(execution.int_field, 11);
+    assert_eq!(execution.static_field, 12);
+    assert_eq!(execution.element, 13);
+    assert_eq!(execution.transient_element, 14);
+
+    // the updates are applied in place: they are visible before the commit
+    let execution = run(
+        &body(&[&native(CP_BEGIN), &updates()]),
+        transactions::DEFAULT_COMMIT_CAPACITY,
+    );
+    assert_eq!(execution.result.unwrap(), 1);
+    assert_eq!(execution.field, 10);
+    assert_eq!(execution.depth, 1);
+}
+
+/// the updates of an aborted transaction are undone, except for transient objects
+#[test]
+fn transaction_abort_test() {
+    // the short field is updated twice
+    let execution = run(
+        &body(&[&native(CP_BEGIN), &updates(), &updates()[..5], &native(CP_ABORT)]),
+        transactions::DEFAULT_COMMIT_CAPACITY,
+    );
+    assert_eq!(execution.result.unwrap(), 0);
+    assert_eq!(execution.field, 1);
+    assert_eq!(execution.int_field, 2);
+    assert_eq!(execution.static_field, 3);
+    assert_eq!(execution.element, 4);
+    assert_eq!(execution.transient_element, 14);
+
+    // updates outside of the transaction are not undone
+    let execution = run(
+        &body(&[&updates(), &native(CP_BEGIN), &native(CP_ABORT)]),
+        transactions::DEFAULT_COMMIT_CAPACITY,
+    );
+    assert_eq!(execution.field, 10);
+    assert_eq!(execution.element, 13);
+}
+
+/// the commit buffer holds the values of the persistent locations updated by the transaction
+#[test]
+fn transaction_capacity_test() {
+    let unused = [
+        native(CP_UNUSED).to_vec(),
+        vec![bytecode::sreturn as BytecodeType],
+    ].concat();
+    let execution = run(&[&native(CP_BEGIN)[..], &unused].concat(), 100);
+    assert_eq!(execution.result.unwrap(), 100);
+
+    // the transient array is not recorded
+    let execution = run(&[&native(CP_BEGIN)[..], &updates(), &unused].concat(), 100);
+    let used = 2 + 4 + 2 + 2 + 4 * transactions::ENTRY_OVERHEAD;
+    assert_eq!(execution.result.unwrap(), 100 - used as i16);
+
+    // nothing is used outside of transactions
+    let execution = run(&[updates(), unused].concat(), 100);
+    assert_eq!(execution.result.unwrap(), 100);
+
+    // the int field does not fit: the short field is updated, and the transaction goes on
+    let capacity = 2 + 4 + 2 * transactions::ENTRY_OVERHEAD - 1;
+    let execution = run(&body(&[&native(CP_BEGIN), &updates()]), capacity);
+    check_transaction_exception(execution.result, transactions::BUFFER_FULL);
+    assert_eq!(execution.field, 10);
+    assert_eq!(execution.int_field, 2);
+    assert_eq!(execution.depth, 1);
+}
+
+/// transactions cannot be nested, and can only be ended when in progress
+#[test]
+fn transaction_state_test() {
+    let capacity = transactions::DEFAULT_COMMIT_CAPACITY;
+    let execution = run(&body(&[&native(CP_BEGIN), &native(CP_BEGIN)]), capacity);
+    check_transaction_exception(execution.result, transactions::IN_PROGRESS);
+    assert_eq!(execution.depth, 1);
+
+    let execution = run(&body(&[&native(CP_COMMIT)]), capacity);
+    check_transaction_exception(execution.result, transactions::NOT_IN_PROGRESS);
+    let execution = run(&body(&[&native(CP_ABORT)]), capacity);
+    check_transaction_exception(execution.result, transactions::NOT_IN_PROGRESS);
+
+    let execution = run(&body(&[]), capacity);
+    assert_eq!(execution.result.unwrap(), 0);
+}
+
+/// the transaction depth is pushed as a short
+#[test]
+fn transaction_depth_test() {
+    let mut ctx = context::Context::new(&[]);
+    ctx.transaction.begin().unwrap();
+    natives::invoke_native(&mut ctx, natives::GET_TRANSACTION_DEPTH).unwrap();
+    let depth = ctx.operand_stack
+        .pop_check_type(constants::PrimitiveType::SHORT)
+        .unwrap();
+    assert_eq!(depth.value, 1);
+}
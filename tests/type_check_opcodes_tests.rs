@@ -0,0 +1,171 @@
+extern crate interpreterlib;
+
+mod common;
+
+use interpreterlib::{classes, constantpool, constants, context, exceptions, interpreter, jcvmerrors,
+                     objects};
+use interpreterlib::bytecodes::*;
+
+use common::{check_uncaught_exception, leak_code};
+use interpreter::BytecodeType;
+use classes::{ImplementedInterface, JCVMClass, OBJECT_CLASS};
+use constantpool::ConstantPoolEntry;
+use jcvmerrors::InterpreterError;
+use objects::JCVMObject;
+
+// constant pool indexes of the classes of the hierarchy
+const CP_A: u8 = 0;
+const CP_B: u8 = 1;
+const CP_C: u8 = 2;
+const CP_I: u8 = 3;
+const CP_J: u8 = 4;
+const CP_OBJECT: u8 = 5;
+
+// references to the objects created by setup_hierarchy
+const OBJ_A: i16 = 1;
+const OBJ_B: i16 = 2;
+const SHORT_ARRAY: i16 = 3;
+const B_ARRAY: i16 = 4;
+
+///
+/// Registers the hierarchy used by the tests and creates one object of each kind:
+/// - interface J extends interface I
+/// - class A implements J, class B extends A, class C is not related to them
+///
+fn setup_hierarchy(ctx: &mut context::Context) {
+    let interface_i = ctx.class_manager.add_class(JCVMClass::new_interface(&[]));
+    let interface_j = ctx.class_manager
+        .add_class(JCVMClass::new_interface(&[interface_i]));
+
+    let mut class_a = JCVMClass::new(None);
+    class_a.add_interface(ImplementedInterface {
+        interface: interface_j,
+        method_tokens: Vec::new(),
+    });
+    let class_a_ref = ctx.class_manager.add_class(class_a);
+    let class_b_ref = ctx.class_manager
+        .add_class(JCVMClass::new(Some(class_a_ref)));
+    let class_c_ref = ctx.class_manager.add_class(JCVMClass::new(None));
+
+    for class_ref in &[
+        class_a_ref,
+        class_b_ref,
+        class_c_ref,
+        interface_i,
+        interface_j,
+        OBJECT_CLASS,
+    ] {
+        ctx.constant_pools
+            .add_entry(ConstantPoolEntry::ClassRef(*class_ref));
+    }
+
+    let objects = vec![
+        JCVMObject::new_instance(0, 0, class_a_ref, 0, false),
+        JCVMObject::new_instance(0, 0, class_b_ref, 0, false),
+        JCVMObject::new_array(0, 0, constants::PrimitiveType::SHORT, 4, false),
+        JCVMObject::new_reference_array(0, 0, class_b_ref, 4, false),
+    ];
+    for object in objects {
+        ctx.object_manager.add_object(object);
+    }
+}
+
+///
+/// utility function executing the given opcode (checkcast or instanceof) on objectref
+///
+fn execute_type_check(
+    bc: bytecode,
+    objectref: i16,
+    atype: u8,
+    index: u8,
+) -> (context::Context<'static>, Result<(), InterpreterError>) {
+    let datatoexecute = leak_code(vec![
+        bc as BytecodeType,
+        atype as BytecodeType,
+        0,
+        index as BytecodeType,
+    ]);
+    let mut ctx = context::Context::new(datatoexecute);
+    setup_hierarchy(&mut ctx);
+    ctx.operand_stack.apush(objectref).unwrap();
+    let result = interpreter::interpreter(&mut ctx);
+    (ctx, result)
+}
+
+fn is_instance_of(objectref: i16, atype: u8, index: u8) -> bool {
+    let (mut ctx, _) = execute_type_check(bytecode::instanceof, objectref, atype, index);
+    let result = ctx.operand_stack.spop().unwrap();
+    assert!(ctx.operand_stack.is_empty());
+    result == 1
+}
+
+///
+/// Test instanceof opcode from standard specification with classes and interfaces
+///
+#[test]
+fn opcode_instanceof_class_test() {
+    assert!(is_instance_of(OBJ_B, 0, CP_A));
+    assert!(is_instance_of(OBJ_B, 0, CP_B));
+    assert!(!is_instance_of(OBJ_B, 0, CP_C));
+    assert!(is_instance_of(OBJ_B, 0, CP_OBJECT));
+    assert!(!is_instance_of(OBJ_A, 0, CP_B));
+
+    // interfaces are implemented by subclasses, superinterfaces by implementing classes
+    assert!(is_instance_of(OBJ_B, 0, CP_J));
+    assert!(is_instance_of(OBJ_B, 0, CP_I));
+    assert!(is_instance_of(OBJ_A, 0, CP_I));
+
+    // null is not an instance of anything
+    assert!(!is_instance_of(constants::NULL_HANDLE, 0, CP_OBJECT));
+}
+
+///
+/// Test instanceof opcode from standard specification with arrays
+///
+#[test]
+fn opcode_instanceof_array_test() {
+    assert!(is_instance_of(SHORT_ARRAY, constants::T_SHORT, 0));
+    assert!(!is_instance_of(SHORT_ARRAY, constants::T_BYTE, 0));
+    assert!(!is_instance_of(SHORT_ARRAY, constants::T_INT, 0));
+    assert!(!is_instance_of(SHORT_ARRAY, constants::T_REFERENCE, CP_OBJECT));
+    assert!(is_instance_of(SHORT_ARRAY, 0, CP_OBJECT));
+    assert!(!is_instance_of(SHORT_ARRAY, 0, CP_A));
+
+    assert!(is_instance_of(B_ARRAY, constants::T_REFERENCE, CP_A));
+    assert!(is_instance_of(B_ARRAY, constants::T_REFERENCE, CP_I));
+    assert!(is_instance_of(B_ARRAY, constants::T_REFERENCE, CP_OBJECT));
+    assert!(!is_instance_of(B_ARRAY, constants::T_REFERENCE, CP_C));
+    assert!(!is_instance_of(B_ARRAY, constants::T_SHORT, 0));
+
+    // objects are not arrays
+    assert!(!is_instance_of(OBJ_B, constants::T_REFERENCE, CP_B));
+    assert!(!is_instance_of(OBJ_B, constants::T_SHORT, 0));
+}
+
+///
+/// Test checkcast opcode from standard specification
+///
+#[test]
+fn opcode_checkcast_test() {
+    // successful casts leave the reference on the stack
+    for &(objectref, atype, index) in &[
+        (OBJ_B, 0, CP_I),
+        (B_ARRAY, constants::T_REFERENCE, CP_A),
+        (constants::NULL_HANDLE, 0, CP_C),
+    ] {
+        let (mut ctx, _) = execute_type_check(bytecode::checkcast, objectref, atype, index);
+        let result = ctx.operand_stack
+            .pop_check_type(constants::PrimitiveType::REFERENCE)
+            .unwrap();
+        assert_eq!(result.value, objectref);
+        assert!(ctx.operand_stack.is_empty());
+    }
+
+    for &(objectref, atype, index) in &[
+        (OBJ_A, 0, CP_B),
+        (SHORT_ARRAY, constants::T_BYTE, 0),
+    ] {
+        let result = execute_type_check(bytecode::checkcast, objectref, atype, index).1;
+        check_uncaught_exception(result, exceptions::CLASS_CAST_EXCEPTION_CLASS, None);
+    }
+}
@@ -0,0 +1,546 @@
+extern crate interpreterlib;
+
+mod common;
+
+use interpreterlib::{capcomponents, capfile, classes, constants, exceptions, exportfile,
+                     interpreter, jcvmerrors, linker, staticfields, verifier};
+use interpreterlib::bytecodes::*;
+
+use common::{package_info, JAVA_LANG_AID};
+use interpreter::BytecodeType;
+use capcomponents::*;
+use capfile::CapFile;
+use classes::OBJECT_CLASS;
+use constants::PrimitiveType;
+use exceptions::ExceptionHandler;
+use exportfile::{ExportClass, ExportFile, ExportMethod, ACC_LIBRARY};
+use jcvmerrors::{LinkError, VerifyError};
+use linker::{ExportTable, ExportedClass, Linker};
+use staticfields::StaticFieldComponent;
+use verifier::{verify, VerificationError};
+
+const PACKAGE_AID: [u8; 6] = [0xA0, 0x00, 0x00, 0x00, 0x01, 0x01];
+
+// access flags of the methods in the Descriptor component
+const ACC_PUBLIC_STATIC: u8 = 0x09;
+
+// offsets of the type descriptors in the Descriptor component
+const TYPE_OFFSET: u16 = 10;
+const TYPE_SHORT_SHORT: u16 = TYPE_OFFSET; // (S)S
+const TYPE_SHORT: u16 = TYPE_OFFSET + 2; // S, ()S
+const TYPE_VOID: u16 = TYPE_OFFSET + 4; // ()V
+const TYPE_INT_INT: u16 = TYPE_OFFSET + 6; // (I)I
+
+// offsets of the methods in the Method component
+const TWICE: u16 = 1;
+const MAIN: u16 = 7;
+const INCREMENT: u16 = 27;
+const SIZE: u16 = 33;
+
+fn type_descriptor(nibbles: &[u8]) -> TypeDescriptor {
+    TypeDescriptor {
+        nibble_count: nibbles.len() as u8,
+        types: nibbles
+            .chunks(2)
+            .map(|pair| pair[0] << 4 | pair.get(1).cloned().unwrap_or(0))
+            .collect(),
+    }
+}
+
+fn method_descriptor(
+    token: u8,
+    method_offset: u16,
+    type_offset: u16,
+    bytecode_count: u16,
+) -> MethodDescriptor {
+    MethodDescriptor {
+        token,
+        access_flags: ACC_PUBLIC_STATIC,
+        method_offset,
+        type_offset,
+        bytecode_count,
+        exception_handler_count: 0,
+        exception_handler_index: 0,
+    }
+}
+
+///
+/// Package using the integer instruction set, with a class whose static methods are:
+/// - short twice(short)
+/// - void main(): calls twice with a static field, then counts down to zero in a loop
+///   protected by an exception handler
+/// - int increment(int)
+/// - short size(): calls the interface method short size() of java.lang class token 1
+///
+fn valid_cap() -> CapFile {
+    let methods: Vec<BytecodeType> = vec![
+        0, // no exception handler
+        // twice, at offset 1
+        0x02,
+        0x10,
+        bytecode::sload_0 as BytecodeType,
+        bytecode::dup as BytecodeType,
+        bytecode::sadd as BytecodeType,
+        bytecode::sreturn as BytecodeType,
+        // main, at offset 7
+        0x03,
+        0x01,
+        bytecode::getstatic_s as BytecodeType,
+        0,
+        1, // 9
+        bytecode::invokestatic as BytecodeType,
+        0,
+        0, // 12
+        bytecode::sstore_0 as BytecodeType, // 15
+        bytecode::sload_0 as BytecodeType, // 16
+        bytecode::ifeq as BytecodeType,
+        7, // 17
+        bytecode::sinc as BytecodeType,
+        0,
+        -1, // 19
+        bytecode::goto as BytecodeType,
+        -6, // 22
+        bytecode::return_ as BytecodeType, // 24
+        // handler, at offset 25
+        bytecode::pop as BytecodeType,
+        bytecode::return_ as BytecodeType,
+        // increment, at offset 27
+        0x04,
+        0x20,
+        bytecode::iload_0 as BytecodeType,
+        bytecode::iconst_1 as BytecodeType,
+        bytecode::iadd as BytecodeType,
+        bytecode::ireturn as BytecodeType,
+        // size, at offset 33
+        0x01,
+        0x00,
+        bytecode::aconst_null as BytecodeType, // 35
+        bytecode::invokeinterface as BytecodeType,
+        1,
+        0,
+        3,
+        0, // 36
+        bytecode::sreturn as BytecodeType, // 41
+    ];
+
+    let mut main = method_descriptor(1, MAIN, TYPE_VOID, 18);
+    main.exception_handler_count = 1;
+
+    CapFile {
+        header: HeaderComponent {
+            minor_version: 2,
+            major_version: 2,
+            flags: ACC_INT,
+            package: package_info(&PACKAGE_AID, 1, 0),
+            package_name: None,
+        },
+        directory: DirectoryComponent {
+            component_sizes: Vec::new(),
+            image_size: 2,
+            array_init_count: 0,
+            array_init_size: 0,
+            import_count: 1,
+            applet_count: 0,
+            custom_components: Vec::new(),
+        },
+        applet: None,
+        import: ImportComponent {
+            packages: vec![package_info(&JAVA_LANG_AID, 1, 0)],
+        },
+        constant_pool: ConstantPoolComponent {
+            entries: vec![
+                CapConstantPoolEntry::StaticMethodRef(CapStaticRef::Internal(TWICE)),
+                CapConstantPoolEntry::StaticFieldRef(CapStaticRef::Internal(0)),
+                CapConstantPoolEntry::ClassRef(CapClassRef::External {
+                    package_token: 0,
+                    class_token: 0,
+                }),
+                CapConstantPoolEntry::ClassRef(CapClassRef::External {
+                    package_token: 0,
+                    class_token: 1,
+                }),
+            ],
+        },
+        class: ClassComponent {
+            signature_pool: Vec::new(),
+            classes: Vec::new(),
+        },
+        method: MethodComponent {
+            exception_handlers: vec![ExceptionHandler {
+                start_offset: 9,
+                active_length: 15,
+                handler_offset: 25,
+                catch_type_index: Some(2),
+            }],
+            stop_bits: vec![true],
+            methods: methods.iter().map(|byte| *byte as u8).collect(),
+        },
+        static_field: StaticFieldComponent {
+            image_size: 2,
+            reference_count: 0,
+            array_init: Vec::new(),
+            default_value_count: 2,
+            non_default_values: Vec::new(),
+        },
+        reference_location: ReferenceLocationComponent {
+            byte_indices: Vec::new(),
+            byte2_indices: vec![10, 13, 38],
+        },
+        export: None,
+        descriptor: Some(DescriptorComponent {
+            classes: vec![ClassDescriptor {
+                token: 0,
+                access_flags: 0x01,
+                this_class_ref: CapClassRef::Internal(0),
+                interfaces: Vec::new(),
+                fields: Vec::new(),
+                methods: vec![
+                    method_descriptor(0, TWICE, TYPE_SHORT_SHORT, 4),
+                    main,
+                    method_descriptor(2, INCREMENT, TYPE_INT_INT, 4),
+                    method_descriptor(3, SIZE, TYPE_SHORT, 7),
+                ],
+            }],
+            constant_pool_types: vec![TYPE_SHORT_SHORT, TYPE_SHORT, 0xFFFF, 0xFFFF],
+            type_desc_offset: TYPE_OFFSET,
+            type_desc: vec![
+                type_descriptor(&[0x4, 0x4]),
+                type_descriptor(&[0x4]),
+                type_descriptor(&[0x1]),
+                type_descriptor(&[0x5, 0x5]),
+            ],
+        }),
+        debug: None,
+        custom_components: Vec::new(),
+    }
+}
+
+/// export file of java.lang, whose class token 1 is an interface with the method short size()
+fn java_lang_export(descriptor: &str) -> ExportFile {
+    ExportFile {
+        minor_version: 1,
+        flags: ACC_LIBRARY,
+        package_name: "java/lang".to_string(),
+        package: package_info(&JAVA_LANG_AID, 1, 0),
+        classes: vec![ExportClass {
+            token: 1,
+            access_flags: 0x0601,
+            name: "java/lang/Sizeable".to_string(),
+            supers: Vec::new(),
+            interfaces: Vec::new(),
+            fields: Vec::new(),
+            methods: vec![ExportMethod {
+                token: 0,
+                access_flags: 0x0401,
+                name: "size".to_string(),
+                descriptor: descriptor.to_string(),
+            }],
+        }],
+    }
+}
+
+/// verifies the CAP file with the valid java.lang export file
+fn verify_cap(cap: &CapFile) -> Result<(), VerificationError> {
+    verify(cap, &[java_lang_export("()S")])
+}
+
+/// error expected at the given opcode of the given method
+fn error_at(
+    error: VerifyError,
+    method_offset: u16,
+    opcode_offset: usize,
+    opcode: bytecode,
+) -> Result<(), VerificationError> {
+    Err(VerificationError {
+        error,
+        method_offset: Some(method_offset),
+        opcode_offset: Some(opcode_offset),
+        opcode: Some(opcode),
+    })
+}
+
+/// error expected in the header or the handlers of the given method
+fn error_in(error: VerifyError, method_offset: u16) -> Result<(), VerificationError> {
+    Err(VerificationError {
+        error,
+        method_offset: Some(method_offset),
+        opcode_offset: None,
+        opcode: None,
+    })
+}
+
+/// valid packages are accepted
+#[test]
+fn verifier_valid_package_test() {
+    assert_eq!(verify_cap(&valid_cap()), Ok(()));
+}
+
+/// the types of the operands have to match the instructions
+#[test]
+fn verifier_type_errors_test() {
+    // sadd on a reference
+    let mut cap = valid_cap();
+    cap.method.methods[4] = bytecode::aconst_null as u8;
+    let result = verify_cap(&cap);
+    assert_eq!(
+        result,
+        error_at(
+            VerifyError::InvalidType(PrimitiveType::REFERENCE, PrimitiveType::SHORT),
+            TWICE,
+            5,
+            bytecode::sadd,
+        )
+    );
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "InvalidType(REFERENCE, SHORT) in method at 0x0001 (sadd at 0x0005)"
+    );
+
+    // uninitialized local
+    let mut cap = valid_cap();
+    cap.method.methods[15] = bytecode::pop as u8;
+    assert_eq!(
+        verify_cap(&cap),
+        error_at(
+            VerifyError::InvalidType(PrimitiveType::UNKNOWN, PrimitiveType::SHORT),
+            MAIN,
+            16,
+            bytecode::sload_0,
+        )
+    );
+
+    // the static field is a short
+    let mut cap = valid_cap();
+    cap.method.methods[9] = bytecode::getstatic_a as u8;
+    assert_eq!(
+        verify_cap(&cap),
+        error_at(
+            VerifyError::InvalidType(PrimitiveType::SHORT, PrimitiveType::REFERENCE),
+            MAIN,
+            9,
+            bytecode::getstatic_a,
+        )
+    );
+
+    // the returned type has to match the signature
+    let mut cap = valid_cap();
+    cap.method.methods[32] = bytecode::sreturn as u8;
+    assert_eq!(
+        verify_cap(&cap),
+        error_at(VerifyError::InvalidReturn, INCREMENT, 32, bytecode::sreturn)
+    );
+}
+
+/// array loads pop the index, then the array reference (size returns array[0])
+#[test]
+fn verifier_array_load_test() {
+    let array_load = |first: bytecode, second: bytecode| {
+        let mut cap = valid_cap();
+        cap.method.methods[33] = 0x02;
+        let body = [first, second, bytecode::baload, bytecode::sreturn];
+        for (offset, opcode) in body.iter().enumerate() {
+            cap.method.methods[35 + offset] = *opcode as u8;
+        }
+        cap.reference_location.byte2_indices.pop();
+        verify_cap(&cap)
+    };
+    // order of the converter
+    assert_eq!(array_load(bytecode::aconst_null, bytecode::sconst_0), Ok(()));
+    assert_eq!(
+        array_load(bytecode::sconst_0, bytecode::aconst_null),
+        error_at(
+            VerifyError::InvalidType(PrimitiveType::REFERENCE, PrimitiveType::SHORT),
+            SIZE,
+            37,
+            bytecode::baload,
+        )
+    );
+}
+
+/// integers are made of two words which cannot be separated
+#[test]
+fn verifier_integer_pairing_test() {
+    let mut cap = valid_cap();
+    cap.method.methods[31] = bytecode::pop as u8;
+    assert_eq!(
+        verify_cap(&cap),
+        error_at(VerifyError::SplitInteger, INCREMENT, 31, bytecode::pop)
+    );
+
+    let mut cap = valid_cap();
+    cap.method.methods[31] = bytecode::sadd as u8;
+    assert_eq!(
+        verify_cap(&cap),
+        error_at(
+            VerifyError::InvalidType(PrimitiveType::INTEGER, PrimitiveType::SHORT),
+            INCREMENT,
+            31,
+            bytecode::sadd,
+        )
+    );
+
+    // the integer instruction set has to be enabled in the Header component
+    let mut cap = valid_cap();
+    cap.header.flags = 0;
+    assert_eq!(
+        verify_cap(&cap),
+        error_at(VerifyError::UnsupportedBytecode, INCREMENT, 29, bytecode::iload_0)
+    );
+}
+
+/// the operand stack stays between 0 and max_stack, with the same height on all the paths
+#[test]
+fn verifier_stack_errors_test() {
+    let mut cap = valid_cap();
+    cap.method.methods[1] = 0x01;
+    assert_eq!(
+        verify_cap(&cap),
+        error_at(VerifyError::StackOverflow, TWICE, 4, bytecode::dup)
+    );
+
+    let mut cap = valid_cap();
+    cap.method.methods[3] = bytecode::pop as u8;
+    assert_eq!(
+        verify_cap(&cap),
+        error_at(VerifyError::StackUnderflow, TWICE, 3, bytecode::pop)
+    );
+
+    // the loop pushes a short at each iteration
+    let mut cap = valid_cap();
+    cap.method.methods[19] = bytecode::sconst_0 as u8;
+    cap.method.methods[20] = bytecode::nop as u8;
+    cap.method.methods[21] = bytecode::nop as u8;
+    assert_eq!(
+        verify_cap(&cap),
+        error_at(VerifyError::StackHeightMismatch(0, 1), MAIN, 22, bytecode::goto)
+    );
+}
+
+/// branches and handlers have to target instructions of the method
+#[test]
+fn verifier_control_flow_errors_test() {
+    // operand of ifeq
+    let mut cap = valid_cap();
+    cap.method.methods[23] = -4i8 as u8;
+    assert_eq!(
+        verify_cap(&cap),
+        error_at(VerifyError::InvalidBranchTarget, MAIN, 22, bytecode::goto)
+    );
+
+    let mut cap = valid_cap();
+    cap.method.methods[6] = bytecode::nop as u8;
+    assert_eq!(
+        verify_cap(&cap),
+        error_at(VerifyError::FallOffEnd, TWICE, 6, bytecode::nop)
+    );
+
+    // the protected range ends in the middle of goto
+    let mut cap = valid_cap();
+    cap.method.exception_handlers[0].active_length = 14;
+    assert_eq!(
+        verify_cap(&cap),
+        error_in(VerifyError::InvalidExceptionHandler(0), MAIN)
+    );
+
+    // the operand of invokestatic goes past the end of the method
+    let mut cap = valid_cap();
+    cap.descriptor.as_mut().unwrap().classes[0].methods[1].bytecode_count = 5;
+    assert_eq!(
+        verify_cap(&cap),
+        error_at(VerifyError::TruncatedInstruction, MAIN, 12, bytecode::invokestatic)
+    );
+}
+
+/// headers, locals and constant pool entries have to match the Descriptor component
+#[test]
+fn verifier_descriptor_errors_test() {
+    // twice takes one argument
+    let mut cap = valid_cap();
+    cap.method.methods[2] = 0x20;
+    assert_eq!(
+        verify_cap(&cap),
+        error_in(VerifyError::InvalidMethodHeader, TWICE)
+    );
+
+    let mut cap = valid_cap();
+    cap.method.methods[3] = bytecode::sload_1 as u8;
+    assert_eq!(
+        verify_cap(&cap),
+        error_at(VerifyError::InvalidLocalIndex(1), TWICE, 3, bytecode::sload_1)
+    );
+
+    // static field instead of static method
+    let mut cap = valid_cap();
+    cap.method.methods[14] = 1;
+    assert_eq!(
+        verify_cap(&cap),
+        error_at(
+            VerifyError::InvalidConstantPoolIndex(1),
+            MAIN,
+            12,
+            bytecode::invokestatic,
+        )
+    );
+
+    let mut cap = valid_cap();
+    cap.descriptor = None;
+    assert_eq!(
+        verify_cap(&cap),
+        Err(VerificationError {
+            error: VerifyError::MissingComponent(COMPONENT_DESCRIPTOR),
+            method_offset: None,
+            opcode_offset: None,
+            opcode: None,
+        })
+    );
+}
+
+/// methods of external interfaces are found in the export files of the imported packages
+#[test]
+fn verifier_interface_test() {
+    let cap = valid_cap();
+    assert_eq!(
+        verify(&cap, &[]),
+        error_at(VerifyError::UnresolvedMethod, SIZE, 36, bytecode::invokeinterface)
+    );
+    // nargs does not match the signature of the method
+    assert_eq!(
+        verify(&cap, &[java_lang_export("(S)S")]),
+        error_at(VerifyError::InvalidOperand, SIZE, 36, bytecode::invokeinterface)
+    );
+    assert_eq!(
+        verify(&cap, &[java_lang_export("()V")]),
+        error_at(VerifyError::StackUnderflow, SIZE, 41, bytecode::sreturn)
+    );
+}
+
+/// the linker only loads the packages accepted by the verifier
+#[test]
+fn verifier_load_test() {
+    let mut linker = Linker::new();
+    let class = ExportedClass {
+        class_ref: OBJECT_CLASS,
+        static_fields: Vec::new(),
+        static_methods: Vec::new(),
+    };
+    linker
+        .add_package(
+            package_info(&JAVA_LANG_AID, 1, 0),
+            ExportTable {
+                classes: vec![class.clone(), class],
+            },
+        )
+        .unwrap();
+    let imports = [java_lang_export("()S")];
+
+    let mut cap = valid_cap();
+    cap.method.methods[4] = bytecode::aconst_null as u8;
+    match linker.load_verified(&cap, &imports) {
+        Err(LinkError::Verification(error)) => assert_eq!(Err(error), verify_cap(&cap)),
+        result => panic!("the package shall have been rejected, got {:?}", result),
+    }
+    assert_eq!(linker.packages().len(), 1);
+
+    linker.load_verified(&valid_cap(), &imports).unwrap();
+    assert!(linker.find_package(&PACKAGE_AID).is_some());
+}